@@ -0,0 +1,35 @@
+//! Throughput of the token-processing hot path, per transform.
+//!
+//! Offline: tokens come from a fixed input and are sent to a closed web
+//! channel, so no provider or network is involved.  `--bench-transforms`
+//! prints a quick tokens/sec table over the same path.
+//!
+//! ```sh
+//! cargo bench --bench transforms
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use every_other_token::transform_bench::{bench_chunks, bench_interceptor, process_chunks};
+use every_other_token::transforms::Transform;
+
+const WORDS: usize = 4_096;
+
+fn process_content_logprob(c: &mut Criterion) {
+    let chunks = bench_chunks(WORDS);
+    let mut group = c.benchmark_group("process_content_logprob");
+    group.throughput(Throughput::Elements(WORDS as u64));
+    for transform in Transform::all() {
+        let name = transform.name();
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || bench_interceptor(transform.clone()),
+                |mut interceptor| process_chunks(&mut interceptor, &chunks),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, process_content_logprob);
+criterion_main!(benches);
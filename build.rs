@@ -1,9 +1,27 @@
-//! Build script: tracks changes to the embedded web UI.
+//! Build script: tracks changes to the embedded web UI and records build metadata.
 //!
 //! The web UI is a single embedded HTML file at `static/index.html`,
 //! loaded at compile time by `include_str!` in `src/web.rs`.
 //! Cargo will recompile when this file changes.
+//!
+//! The short git commit hash is exported as `EOT_GIT_COMMIT` for
+//! `build_info::GIT_COMMIT`; builds outside a git checkout get `"unknown"`.
+
+use std::process::Command;
 
 fn main() {
     println!("cargo:rerun-if-changed=static/index.html");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=EOT_GIT_COMMIT={commit}");
 }
@@ -0,0 +1,262 @@
+//! Structured A/B experiment export (`POST /api/ab/export`).
+//!
+//! The web UI's Experiment mode streams one prompt under two system prompts
+//! (`/ab-stream`).  [`AbExport::build`] turns the two token streams into a
+//! citable record: both system prompts, per-arm statistics, the tokens of
+//! both arms aligned by a longest-common-subsequence match over their
+//! original texts (the same [`crate::token_align`] match `/diff-stream`
+//! reports), the resulting similarity, and which arm won on the chosen
+//! [`WinCriterion`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::token_align::{align, match_pct};
+use crate::TokenEvent;
+
+/// Longest arm accepted for alignment.  The LCS table is `a × b` cells.
+pub const MAX_ARM_TOKENS: usize = 4096;
+
+/// What decides the winning arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WinCriterion {
+    /// Lower mean perplexity wins.
+    #[default]
+    Perplexity,
+    /// Higher mean confidence wins.
+    Confidence,
+}
+
+impl WinCriterion {
+    /// Parse `"perplexity"` or `"confidence"`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_lowercase().as_str() {
+            "perplexity" => Ok(WinCriterion::Perplexity),
+            "confidence" => Ok(WinCriterion::Confidence),
+            other => Err(format!("unknown criterion '{}' (expected perplexity or confidence)", other)),
+        }
+    }
+}
+
+/// Request body for `POST /api/ab/export`: what the UI saw on each side.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AbExportRequest {
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub system_a: String,
+    #[serde(default)]
+    pub system_b: String,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub transform: Option<String>,
+    pub tokens_a: Vec<TokenEvent>,
+    pub tokens_b: Vec<TokenEvent>,
+    /// `"perplexity"` (default) or `"confidence"`.
+    #[serde(default)]
+    pub criterion: Option<String>,
+    /// The stream's `experiment_result` event, kept verbatim when present.
+    #[serde(default)]
+    pub significance: Option<serde_json::Value>,
+}
+
+/// Summary statistics for one arm.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArmStats {
+    pub system_prompt: String,
+    pub token_count: usize,
+    pub transformed_count: usize,
+    /// `None` when the arm reported no perplexities.
+    pub mean_perplexity: Option<f64>,
+    /// `None` when the arm reported no confidences.
+    pub mean_confidence: Option<f64>,
+}
+
+impl ArmStats {
+    fn from_tokens(system_prompt: &str, tokens: &[TokenEvent]) -> Self {
+        let mean = |values: Vec<f64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        ArmStats {
+            system_prompt: system_prompt.to_string(),
+            token_count: tokens.len(),
+            transformed_count: tokens.iter().filter(|t| t.transformed).count(),
+            mean_perplexity: mean(
+                tokens
+                    .iter()
+                    .filter_map(|t| t.perplexity.map(f64::from))
+                    .filter(|p| p.is_finite())
+                    .collect(),
+            ),
+            mean_confidence: mean(tokens.iter().filter_map(|t| t.confidence.map(f64::from)).collect()),
+        }
+    }
+}
+
+/// One row of the alignment.  A token present on only one side has `None`
+/// for the other; `matched` rows have equal original texts.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlignedPair {
+    pub a_index: Option<usize>,
+    pub b_index: Option<usize>,
+    pub a: Option<String>,
+    pub b: Option<String>,
+    pub matched: bool,
+}
+
+/// The exported experiment.  Fields serialize in declaration order, summary
+/// first and the long `aligned` array last.
+#[derive(Debug, Clone, Serialize)]
+pub struct AbExport {
+    pub version: &'static str,
+    pub prompt: String,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub transform: Option<String>,
+    pub criterion: WinCriterion,
+    /// `"a"`, `"b"`, or `None` for a tie or when an arm lacks the metric.
+    pub winner: Option<&'static str>,
+    /// Matched tokens as a percentage of the longer arm, to one decimal.
+    pub similarity_pct: f64,
+    pub matched: usize,
+    pub arm_a: ArmStats,
+    pub arm_b: ArmStats,
+    pub significance: Option<serde_json::Value>,
+    pub aligned: Vec<AlignedPair>,
+}
+
+impl AbExport {
+    /// Build the export for `req`.
+    ///
+    /// # Errors
+    /// Returns a message for an unknown criterion or an arm longer than
+    /// [`MAX_ARM_TOKENS`].
+    pub fn build(req: AbExportRequest) -> Result<Self, String> {
+        let criterion = match req.criterion.as_deref() {
+            Some(s) if !s.trim().is_empty() => WinCriterion::parse(s)?,
+            _ => WinCriterion::default(),
+        };
+        if req.tokens_a.len().max(req.tokens_b.len()) > MAX_ARM_TOKENS {
+            return Err(format!("each arm is limited to {} tokens", MAX_ARM_TOKENS));
+        }
+        let originals = |tokens: &[TokenEvent]| -> Vec<String> {
+            tokens.iter().map(|t| t.original.trim().to_string()).collect()
+        };
+        let (a, b) = (originals(&req.tokens_a), originals(&req.tokens_b));
+        let aligned: Vec<AlignedPair> = align(&a, &b)
+            .into_iter()
+            .map(|(ai, bi)| AlignedPair {
+                a_index: ai,
+                b_index: bi,
+                a: ai.map(|i| req.tokens_a[i].original.clone()),
+                b: bi.map(|i| req.tokens_b[i].original.clone()),
+                matched: ai.is_some() && bi.is_some(),
+            })
+            .collect();
+        let matched = aligned.iter().filter(|p| p.matched).count();
+        let similarity_pct = match_pct(matched, a.len(), b.len());
+        let arm_a = ArmStats::from_tokens(&req.system_a, &req.tokens_a);
+        let arm_b = ArmStats::from_tokens(&req.system_b, &req.tokens_b);
+        Ok(AbExport {
+            version: crate::build_info::VERSION,
+            prompt: req.prompt,
+            provider: req.provider,
+            model: req.model,
+            transform: req.transform,
+            criterion,
+            winner: winner(criterion, &arm_a, &arm_b),
+            similarity_pct,
+            matched,
+            arm_a,
+            arm_b,
+            significance: req.significance,
+            aligned,
+        })
+    }
+}
+
+fn winner(criterion: WinCriterion, a: &ArmStats, b: &ArmStats) -> Option<&'static str> {
+    let (a, b, lower_wins) = match criterion {
+        WinCriterion::Perplexity => (a.mean_perplexity?, b.mean_perplexity?, true),
+        WinCriterion::Confidence => (a.mean_confidence?, b.mean_confidence?, false),
+    };
+    if a == b {
+        None
+    } else if (a < b) == lower_wins {
+        Some("a")
+    } else {
+        Some("b")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(original: &str, perplexity: f32, confidence: f32) -> TokenEvent {
+        serde_json::from_value(serde_json::json!({
+            "text": original, "original": original, "index": 0, "transformed": false,
+            "importance": 0.5, "perplexity": perplexity, "confidence": confidence,
+        }))
+        .expect("token")
+    }
+
+    fn request() -> AbExportRequest {
+        AbExportRequest {
+            prompt: "Tell me about robots".to_string(),
+            system_a: "You are a poet.".to_string(),
+            system_b: "Be precise.".to_string(),
+            tokens_a: vec![token("Robots", 2.0, 0.6), token(" dream", 4.0, 0.4), token(" softly", 3.0, 0.5)],
+            tokens_b: vec![token("Robots", 1.5, 0.8), token(" compute", 1.5, 0.8), token(" softly", 1.5, 0.8)],
+            ..AbExportRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_export_has_both_arms_and_aligned_pairs() {
+        let export = AbExport::build(request()).expect("export");
+        assert_eq!(export.arm_a.system_prompt, "You are a poet.");
+        assert_eq!(export.arm_a.token_count, 3);
+        assert_eq!(export.arm_a.mean_perplexity, Some(3.0));
+        assert_eq!(export.arm_b.system_prompt, "Be precise.");
+        assert_eq!(export.arm_b.mean_perplexity, Some(1.5));
+        assert_eq!(export.matched, 2);
+        assert_eq!(export.similarity_pct, 66.7);
+        let rows: Vec<(Option<&str>, Option<&str>)> =
+            export.aligned.iter().map(|p| (p.a.as_deref(), p.b.as_deref())).collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Some("Robots"), Some("Robots")),
+                (Some(" dream"), None),
+                (None, Some(" compute")),
+                (Some(" softly"), Some(" softly")),
+            ]
+        );
+        assert_eq!(export.winner, Some("b"));
+
+        let json = serde_json::to_value(&export).unwrap();
+        assert_eq!(json["arm_a"]["token_count"], 3);
+        assert_eq!(json["arm_b"]["mean_confidence"].as_f64().map(|c| (c * 10.0).round()), Some(8.0));
+        assert_eq!(json["aligned"].as_array().map(Vec::len), Some(4));
+        assert_eq!(json["criterion"], "perplexity");
+    }
+
+    #[test]
+    fn test_winner_follows_criterion() {
+        let mut req = request();
+        req.criterion = Some("confidence".to_string());
+        assert_eq!(AbExport::build(req).unwrap().winner, Some("b"));
+
+        let mut req = request();
+        req.tokens_b = req.tokens_a.clone();
+        assert_eq!(AbExport::build(req).unwrap().winner, None, "a tie has no winner");
+
+        let mut req = request();
+        req.criterion = Some("vibes".to_string());
+        assert!(AbExport::build(req).is_err());
+    }
+}
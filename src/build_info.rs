@@ -0,0 +1,65 @@
+//! Build metadata: crate version, git commit, and enabled feature flags.
+//!
+//! Served by `GET /api/version` and used wherever the version appears in
+//! user-facing text (CLI `--version`, research citations, the web UI footer)
+//! so there is a single source of truth.
+
+/// Crate version from `Cargo.toml`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short git commit hash captured by `build.rs`, or `"unknown"` when the crate
+/// was built outside a git checkout.
+pub const GIT_COMMIT: &str = env!("EOT_GIT_COMMIT");
+
+/// Cargo feature flags compiled into this binary, in `Cargo.toml` order.
+pub fn enabled_features() -> Vec<&'static str> {
+    let flags = [
+        ("sqlite-log", cfg!(feature = "sqlite-log")),
+        ("wasm", cfg!(feature = "wasm")),
+        ("self-tune", cfg!(feature = "self-tune")),
+        ("self-modify", cfg!(feature = "self-modify")),
+        ("intelligence", cfg!(feature = "intelligence")),
+        ("evolution", cfg!(feature = "evolution")),
+        ("self-improving", cfg!(feature = "self-improving")),
+        ("helix-bridge", cfg!(feature = "helix-bridge")),
+        ("redis-backing", cfg!(feature = "redis-backing")),
+    ];
+    flags.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect()
+}
+
+/// JSON body for `GET /api/version`.
+pub fn version_json() -> serde_json::Value {
+    serde_json::json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": VERSION,
+        "git_commit": GIT_COMMIT,
+        "features": enabled_features(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_matches_cargo_package() {
+        assert_eq!(VERSION, env!("CARGO_PKG_VERSION"));
+        assert!(!GIT_COMMIT.is_empty());
+    }
+
+    #[test]
+    fn test_version_json_reports_cargo_package_version() {
+        let v = version_json();
+        assert_eq!(v["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(v["name"], "every-other-token");
+        assert_eq!(v["git_commit"], GIT_COMMIT);
+        assert!(v["features"].is_array());
+    }
+
+    #[test]
+    fn test_enabled_features_reflect_cfg() {
+        let features = enabled_features();
+        assert_eq!(features.contains(&"self-tune"), cfg!(feature = "self-tune"));
+        assert_eq!(features.contains(&"sqlite-log"), cfg!(feature = "sqlite-log"));
+    }
+}
@@ -4,25 +4,28 @@
 //! ([`resolve_model`], [`validate_model`], [`parse_rate_range`], [`apply_template`])
 //! are kept here rather than in `main.rs` so they can be unit-tested in isolation.
 
+use crate::preprocess::{PreprocessStage, StageKind};
 use crate::providers::Provider;
+use crate::transforms::{NoiseConfig, ReplacementDictionary, Transform, TransformOptions};
 use clap::Parser;
+use std::collections::HashMap;
 
 #[derive(Parser)]
 #[command(name = "every-other-token")]
-#[command(version = "4.0.0")]
+#[command(version = crate::build_info::VERSION)]
 #[command(about = "A real-time token stream mutator for LLM interpretability research")]
 pub struct Args {
     /// Input prompt to send to the LLM (optional when using --web)
     #[arg(default_value = "")]
     pub prompt: String,
 
-    /// Transformation type (reverse, uppercase, mock, noise)
+    /// Transformation type (reverse, uppercase, mock, noise, spongebob, chaos, ...)
     #[arg(default_value = "reverse")]
     pub transform: String,
 
-    /// Model name (e.g. gpt-4, claude-sonnet-4-20250514)
-    #[arg(default_value = "gpt-3.5-turbo")]
-    pub model: String,
+    /// Model name (e.g. gpt-4, claude-sonnet-4-6); defaults to the provider's
+    /// default model
+    pub model: Option<String>,
 
     /// LLM provider: openai or anthropic
     #[arg(long, value_enum, default_value = "openai")]
@@ -48,6 +51,15 @@ pub struct Args {
     #[arg(long, default_value = "8888")]
     pub port: u16,
 
+    /// Follow collaboration room CODE read-only in the terminal, rendering
+    /// its live token stream (honours --heatmap, --visual, --show-original).
+    #[arg(long, value_name = "CODE")]
+    pub watch: Option<String>,
+
+    /// Base URL of the `--web` server hosting the room for `--watch`.
+    #[arg(long, value_name = "URL", default_value = "http://localhost:8888")]
+    pub watch_url: String,
+
     /// Enable headless research mode — runs N times and outputs JSON stats
     #[arg(long)]
     pub research: bool,
@@ -60,6 +72,21 @@ pub struct Args {
     #[arg(long, default_value = "research_output.json")]
     pub output: String,
 
+    /// Research mode: experiment name recorded in the output and citation.
+    /// A relative `--output` is written under `experiments/<NAME>/`.
+    #[arg(long, value_name = "NAME", value_parser = parse_tag)]
+    pub tag: Option<String>,
+
+    /// Research mode: free-form notes recorded in the output and citation.
+    #[arg(long)]
+    pub notes: Option<String>,
+
+    /// Research mode: run the prompt `--runs` times at each of these
+    /// comma-separated temperatures (e.g. `0.0,0.5,1.0`) and compare them.
+    /// `--output` receives one session per temperature plus the comparison.
+    #[arg(long, value_name = "T,T,...", value_delimiter = ',', value_parser = parse_temperature)]
+    pub temp_sweep: Vec<f32>,
+
     /// System prompt A for A/B experiment mode
     #[arg(long)]
     pub system_a: Option<String>,
@@ -68,6 +95,21 @@ pub struct Args {
     #[arg(long, default_value = "5")]
     pub top_logprobs: u8,
 
+    /// Don't request log probabilities from OpenAI: faster, smaller responses,
+    /// but no confidence, perplexity, or alternatives.
+    #[arg(long)]
+    pub no_logprobs: bool,
+
+    /// Fail instead of retrying without logprobs when an OpenAI model
+    /// rejects them with HTTP 400.
+    #[arg(long)]
+    pub no_logprobs_fallback: bool,
+
+    /// Fail the stream with an error when a provider SSE `data:` line cannot
+    /// be parsed, instead of logging, counting and skipping it.
+    #[arg(long)]
+    pub strict_parsing: bool,
+
     /// System prompt B for A/B experiment mode
     #[arg(long)]
     pub system_b: Option<String>,
@@ -100,6 +142,12 @@ pub struct Args {
     #[arg(long)]
     pub replay: Option<String>,
 
+    /// Re-render a session exported from the web UI (Export JSON) or
+    /// /api/session/export in the terminal, honouring --heatmap, --visual,
+    /// --show-original and --color-by.  No API call is made.
+    #[arg(long, value_name = "PATH")]
+    pub replay_export: Option<String>,
+
     /// Fraction of tokens to intercept and transform (0.0–1.0, default 0.5).
     /// At 0.5 every other token is transformed; at 0.3 roughly one in three.
     /// Uses a deterministic Bresenham spread so results are reproducible when
@@ -116,6 +164,11 @@ pub struct Args {
     #[arg(long)]
     pub seed: Option<u64>,
 
+    /// Index of the first token, for continuing an earlier session's
+    /// numbering (e.g. `--json-stream` output of a later turn).
+    #[arg(long, value_name = "N", default_value = "0")]
+    pub start_index: usize,
+
     /// Path to SQLite experiment log database (requires sqlite-log feature)
     #[arg(long)]
     pub log_db: Option<String>,
@@ -149,6 +202,25 @@ pub struct Args {
     #[arg(long)]
     pub helix_url: Option<String>,
 
+    /// Redis URL for sharing collaboration rooms across instances (e.g. redis://127.0.0.1/).
+    ///
+    /// When set in --web mode, room state is mirrored to Redis and room
+    /// broadcasts are relayed over pub/sub, so participants connected to
+    /// different EOT instances can join the same room.
+    #[cfg(feature = "redis-backing")]
+    #[arg(long)]
+    pub redis_url: Option<String>,
+
+    /// Spend ceiling in USD for budget-aware model selection.
+    ///
+    /// When set, estimated spend is tracked across every stream of the run
+    /// (all research runs, sweep temperatures, or web requests) and, once it
+    /// crosses the warn threshold, the model is downgraded to a cheaper
+    /// alternative (e.g. gpt-4o → gpt-4o-mini) before streaming.  Requires the
+    /// `self-tune` feature; rejected otherwise.
+    #[arg(long, value_name = "USD")]
+    pub budget_usd: Option<f64>,
+
     /// Rate range for stochastic experiments, e.g. "0.3-0.7". When set, the
     /// interceptor randomly picks a rate in [min, max] for each run.
     /// Overrides --rate when provided. Format: "MIN-MAX" (e.g. "0.2-0.8").
@@ -165,6 +237,35 @@ pub struct Args {
     #[arg(long)]
     pub template: Option<String>,
 
+    /// Prompt template variable, substituted for `{NAME}` in the prompt
+    /// (repeatable).  Example: --var topic=robots --var style=noir
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = crate::prompt_template::parse_var)]
+    pub vars: Vec<(String, String)>,
+
+    /// JSON object file of prompt template variables, e.g. {"topic": "robots"}.
+    /// `--var` values take precedence.
+    #[arg(long, value_name = "PATH")]
+    pub vars_file: Option<String>,
+
+    /// Comma-separated, ordered prompt preprocessing stages applied before
+    /// sending: template (substitute --var / --vars-file), truncate (to
+    /// --preprocess-max-chars), style (prepend --style-instruction) and
+    /// orchestrator (MCP enrichment).  Research output records the
+    /// resulting `effective_prompt` when it differs from the prompt.
+    /// Example: --preprocess template,style
+    #[arg(long, value_name = "STAGES", value_delimiter = ',', value_parser = crate::preprocess::StageKind::parse)]
+    pub preprocess: Vec<crate::preprocess::StageKind>,
+
+    /// Character budget for the `truncate` preprocess stage
+    /// (default: --max-prompt-chars).
+    #[arg(long, value_name = "N")]
+    pub preprocess_max_chars: Option<usize>,
+
+    /// Instruction the `style` preprocess stage puts ahead of the prompt,
+    /// e.g. "Answer in the voice of a ship's captain."
+    #[arg(long, value_name = "TEXT")]
+    pub style_instruction: Option<String>,
+
     /// Only transform tokens whose API confidence is below this threshold.
     /// Tokens with confidence >= threshold are passed through unchanged.
     /// When no confidence data is available (Anthropic), falls back to rate-based selection.
@@ -172,6 +273,30 @@ pub struct Args {
     #[arg(long)]
     pub min_confidence: Option<f64>,
 
+    /// Smooth heatmap colours with an exponential moving average of the
+    /// token importance; ALPHA in (0, 1] weights the newest token (lower is
+    /// smoother).  Exports keep the raw importance.
+    #[arg(long, value_name = "ALPHA", value_parser = parse_smoothing_alpha)]
+    pub importance_smoothing: Option<f64>,
+
+    /// Which tokens to transform: "alternating" (by --rate, the default) or
+    /// "importance:T" to transform every token whose importance exceeds T,
+    /// regardless of position.
+    #[arg(long, value_name = "SELECTOR", default_value = "alternating", value_parser = crate::TransformSelector::parse)]
+    pub select: crate::TransformSelector,
+
+    /// Only transform selected tokens of one heuristic part of speech:
+    /// "content" (non-stopwords), "stopword", "noun", "verb", "adjective" or
+    /// "adverb".  Classes come from a built-in stopword list and English
+    /// suffixes, not a tagger.  Combine with --rate 1 to transform every match.
+    #[arg(long, value_name = "CLASS", value_parser = crate::PosFilter::parse)]
+    pub pos_filter: Option<crate::PosFilter>,
+
+    /// Unit the alternating selector steps over: "token" (default) or
+    /// "sentence" to transform every other sentence wholesale.
+    #[arg(long, value_name = "UNIT", default_value = "token", value_parser = crate::Granularity::parse)]
+    pub granularity: crate::Granularity,
+
     /// Output format for research mode: "json" (default), "jsonl" (one JSON object per line).
     #[arg(long, default_value = "json")]
     pub format: String,
@@ -181,10 +306,86 @@ pub struct Args {
     #[arg(long, default_value = "5")]
     pub collapse_window: usize,
 
+    /// Serve JSON-RPC `tools/call` `infer` requests on --port as an MCP
+    /// worker, returning the prompt's transformed response.
+    #[arg(long)]
+    pub mcp_server: bool,
+
     /// Base URL for the MCP orchestrator pipeline (default: http://localhost:3000).
     #[arg(long, default_value = "http://localhost:3000")]
     pub orchestrator_url: String,
 
+    /// With --orchestrator, print the original vs enriched prompt diff to
+    /// stderr before streaming, and record the enriched prompt in research output.
+    #[arg(long)]
+    pub show_enriched: bool,
+
+    /// Characters the noise transform draws from (default "*+~@#$%").
+    #[arg(long, value_name = "CHARS")]
+    pub noise_chars: Option<String>,
+
+    /// Number of noise characters appended to each transformed token.
+    #[arg(long, value_name = "N", default_value = "1")]
+    pub noise_count: usize,
+
+    /// Combining marks the zalgo transform stacks on each character (1-32).
+    #[arg(long, value_name = "N", default_value = "3", value_parser = clap::value_parser!(u64).range(1..=32))]
+    pub zalgo_intensity: u64,
+
+    /// Buffer provider deltas to whole words so a word split across deltas
+    /// (e.g. "wor" + "ld") is emitted and transformed as one token.
+    #[arg(long)]
+    pub word_boundaries: bool,
+
+    /// Context-aware transform applied to selected tokens instead of
+    /// --transform: "stutter" repeats a token (`fox-fox`) after a short one.
+    #[arg(long, value_name = "NAME", value_parser = ["stutter"])]
+    pub window_transform: Option<String>,
+
+    /// When terminal output is flushed: "token" (every token, default),
+    /// "chunk" (once per provider chunk), or N (every N tokens).  Coarser
+    /// policies speed up piping large outputs.
+    #[arg(long, value_name = "POLICY", default_value = "token", value_parser = crate::FlushPolicy::parse)]
+    pub flush_policy: crate::FlushPolicy,
+
+    /// Unicode normalization of each emitted token's text: "nfc", "nfd", or
+    /// "none" (default) to emit text exactly as transformed.
+    #[arg(long, value_name = "FORM", default_value = "none", value_parser = crate::Normalization::parse)]
+    pub normalize: crate::Normalization,
+
+    /// With --normalize, also normalize each token's original text.
+    #[arg(long)]
+    pub normalize_original: bool,
+
+    /// Show each transformed token as one inline mapping instead of just its
+    /// transformed text, "original→transformed" by default.  An optional
+    /// template may use {original} and {text}, e.g. "[{original}|{text}]".
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        num_args = 0..=1,
+        default_missing_value = crate::DEFAULT_INLINE_MAPPING,
+        value_parser = crate::parse_inline_mapping
+    )]
+    pub inline_mapping: Option<String>,
+
+    /// Show the whitespace between tokens as single spaces and collapse
+    /// whitespace runs inside transformed text (e.g. from --noise-chars or
+    /// an --inline-mapping template), for cleaner reading and export.
+    /// Originals are unchanged.
+    #[arg(long)]
+    pub collapse_whitespace: bool,
+
+    /// Log every raw SSE line from the provider to stderr before parsing, to
+    /// diagnose streaming format changes.
+    #[arg(long)]
+    pub debug_raw: bool,
+
+    /// Write the --debug-raw log to this file instead of stderr (implies
+    /// --debug-raw).
+    #[arg(long, value_name = "PATH")]
+    pub debug_raw_file: Option<String>,
+
     /// Maximum API retry attempts on 429/5xx errors (default: 3).
     #[arg(long, default_value = "3")]
     pub max_retries: u32,
@@ -199,6 +400,15 @@ pub struct Args {
     #[arg(long)]
     pub synonym_file: Option<String>,
 
+    /// JSON file of `{"from": "to"}` token replacements for the `dictionary` transform.
+    /// Tokens not in the map pass through unchanged.
+    #[arg(long, value_name = "PATH")]
+    pub dict: Option<String>,
+
+    /// Match `--dict` entries case-insensitively.
+    #[arg(long)]
+    pub dict_ignore_case: bool,
+
     /// Optional API key required for /api/ endpoints in web UI mode.
     /// When set, requests to /api/* must include `Authorization: Bearer <key>`.
     #[arg(long)]
@@ -213,6 +423,12 @@ pub struct Args {
     #[arg(long, default_value = "120")]
     pub timeout: u64,
 
+    /// Extra provider request header in "Key: Value" form (repeatable), e.g.
+    /// `--header "OpenAI-Organization: org-123"` or `--header "anthropic-beta: ..."`.
+    /// `Authorization` and `x-api-key` are rejected.
+    #[arg(long = "header", value_name = "KEY: VALUE", value_parser = crate::providers::parse_extra_header)]
+    pub headers: Vec<(String, String)>,
+
     /// Export per-run timeseries data to a CSV file at this path.
     /// Columns: run,token_index,confidence,perplexity
     #[arg(long)]
@@ -226,6 +442,21 @@ pub struct Args {
     #[arg(long)]
     pub list_models: Option<String>,
 
+    /// List every transform with a one-line description and an example, then exit.
+    #[arg(long)]
+    pub list_transforms: bool,
+
+    /// Time every transform over a fixed offline input and print a
+    /// tokens/sec table, then exit.  No API key or network is needed.
+    #[arg(long)]
+    pub bench_transforms: bool,
+
+    /// Check the setup and exit: API keys, provider reachability (via each
+    /// provider's models endpoint), default models and compiled-in features.
+    /// Exits non-zero when the `--provider` check fails.
+    #[arg(long, visible_alias = "check")]
+    pub doctor: bool,
+
     /// Validate configuration (print resolved values and exit).
     #[arg(long)]
     pub validate_config: bool,
@@ -236,6 +467,120 @@ pub struct Args {
     #[arg(long, default_value = "1000")]
     pub sse_buffer_size: usize,
 
+    /// Reconnection delay, in milliseconds, sent to browsers as the SSE
+    /// `retry:` field at the start of every web stream.  0 omits the field
+    /// and leaves the browser default.
+    #[arg(long, value_name = "MS", default_value_t = crate::web::DEFAULT_SSE_RETRY_MS)]
+    pub sse_retry_ms: u64,
+
+    /// Web UI: `/stream` token events per SSE frame.  Above 1, consecutive
+    /// events are sent as one JSON array when the stream is running ahead of
+    /// the socket, cutting framing overhead on fast streams.  1 sends every
+    /// event on its own.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub sse_batch: u64,
+
+    /// In terminal mode, print each transformed token followed by its original
+    /// in dim brackets, e.g. `dlrow[world]`.
+    #[arg(long)]
+    pub show_original: bool,
+
+    /// Also apply the transform to every other word of the prompt before it is
+    /// sent, to study how the model handles perturbed input.  Research output
+    /// records the original `prompt` alongside the `transformed_prompt`.
+    #[arg(long)]
+    pub transform_prompt: bool,
+
+    /// Terminal token colour source: "importance" (the --heatmap / --visual
+    /// colouring, default) or "confidence" (green/yellow/red by model
+    /// confidence, falling back to importance when a provider reports none).
+    #[arg(long, value_enum, value_name = "SOURCE", default_value = "importance")]
+    pub color_by: crate::render::ColorBy,
+
+    /// Disable ANSI colors in terminal output (the NO_COLOR env var also works).
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Score heuristic token importance without random jitter, so heatmaps are
+    /// reproducible across runs (pairs with --seed).
+    #[arg(long)]
+    pub deterministic_importance: bool,
+
+    /// Tee the transformed response text (plain, no colors) to this file while
+    /// still displaying it. Unlike --log-db / --export-logprobs this is the
+    /// human-readable text, not per-token records.
+    #[arg(long, value_name = "PATH")]
+    pub tee: Option<String>,
+
+    /// Reject prompts longer than this many characters (CLI and web endpoints).
+    /// A safety and cost guard against accidental multi-megabyte prompts.
+    #[arg(long, default_value_t = crate::DEFAULT_MAX_PROMPT_CHARS, value_name = "N")]
+    pub max_prompt_chars: usize,
+
+    /// Pace `/stream` token emission in the web UI to at most N tokens/sec.
+    /// Smooths bursty output for demos; tokens are delayed, never dropped, and
+    /// the provider stream is still consumed at full speed. Disabled by default.
+    #[arg(long, value_name = "N")]
+    pub render_rate: Option<f64>,
+
+    /// Web UI teaching aid: render each transformed token as its original
+    /// first, then morph it into the transformed text after MS milliseconds.
+    #[arg(long, value_name = "MS")]
+    pub reveal_delay: Option<u64>,
+
+    /// Web UI: reveal `/stream` tokens one word at a time (default) or one
+    /// character at a time for a typewriter effect.  Transforms still apply
+    /// to whole words.
+    #[arg(long, value_enum, default_value = "word")]
+    pub emit_granularity: crate::web::EmitGranularity,
+
+    /// Web UI: keep only the most recent N token spans in each view so long
+    /// generations stay responsive.  Every token is still kept for export.
+    /// 0 keeps every span.
+    #[arg(long, value_name = "N", default_value_t = crate::web::DEFAULT_RENDER_WINDOW)]
+    pub render_window: usize,
+
+    /// Web UI: how to show each token's top alternatives (from logprobs):
+    /// listed with their probabilities in the hover tooltip (default), as an
+    /// inline mini bar chart after the token, or not at all.
+    #[arg(long, value_enum, default_value = "tooltip")]
+    pub alt_display: crate::web::AltDisplay,
+
+    /// Web UI: seconds a disconnected collaborator stays "away" (dimmed in
+    /// the sidebar) before they are removed from the room.  Reconnecting with
+    /// the same browser within the window resumes their seat.  0 removes
+    /// participants immediately.
+    #[arg(long, value_name = "SECS", default_value_t = crate::collab::DEFAULT_RECONNECT_GRACE_SECS)]
+    pub reconnect_grace: u64,
+
+    /// Web UI: longest collaborator display name, in characters.  Names set
+    /// with `set_name` also have HTML tags and control characters removed.
+    #[arg(long, value_name = "N", default_value_t = crate::collab::DEFAULT_MAX_NAME_CHARS)]
+    pub max_name_chars: usize,
+
+    /// Web UI: events kept in a room recording before the oldest are dropped
+    /// (clients are sent `record_truncated`).  Bounds memory for long sessions.
+    #[arg(long, value_name = "N", default_value_t = crate::collab::DEFAULT_RECORDING_CAP)]
+    pub max_recording_events: usize,
+
+    /// Web UI: at most N upstream provider requests in flight at once across
+    /// `/stream`, `/batch-stream`, `/diff-stream` and `/ab-stream`.  Further
+    /// interceptors wait for a free slot and the client is sent a `queued`
+    /// event.  Unlimited by default.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_concurrency: Option<u32>,
+
+    /// Web UI: at most N connections handled at once.  Each open connection,
+    /// including long-lived SSE streams and WebSockets, holds a slot; beyond
+    /// the cap new connections get `503 Service Unavailable`.  Independent of
+    /// `--max-concurrency`, which bounds provider calls.
+    #[arg(long, value_name = "N", default_value_t = crate::web::DEFAULT_MAX_CONNECTIONS, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_connections: u32,
+
+    /// Web UI: pending-connection queue length passed to `listen(2)`.
+    #[arg(long, value_name = "N", default_value_t = crate::web::DEFAULT_LISTEN_BACKLOG, value_parser = clap::value_parser!(u32).range(1..))]
+    pub listen_backlog: u32,
+
     /// Path to a JSONL file for batch research mode. Each line must be JSON:
     /// {"prompt": "...", "model": "gpt-4o", "transforms": ["drop_every_other"]}
     /// Results are saved to batch_results_<timestamp>.jsonl.
@@ -306,21 +651,212 @@ pub struct Args {
     pub vocab_stats: bool,
 }
 
-/// Select the appropriate default model for the given provider when the user
-/// hasn't explicitly chosen one (i.e. the model is still the OpenAI default).
-pub fn resolve_model(provider: &Provider, model: &str) -> String {
-    match provider {
-        Provider::Anthropic if model == "gpt-3.5-turbo" => "claude-sonnet-4-6".to_string(),
-        Provider::Mock => "mock-fixture-v1".to_string(),
-        _ => model.to_string(),
+/// Build the [`Transform`] named by `args.transform`, loading the `--dict`
+/// mapping when the `dictionary` transform is selected.
+///
+/// # Errors
+///
+/// Returns `Err(String)` for an unknown transform, a `dictionary` transform
+/// without `--dict`, an unreadable mapping file, or `--dict` given alongside a
+/// different transform.
+pub fn resolve_transform(args: &Args) -> Result<Transform, String> {
+    if args.transform.trim().eq_ignore_ascii_case("dictionary") {
+        let path = args
+            .dict
+            .as_deref()
+            .ok_or("the dictionary transform needs a mapping file (--dict PATH)")?;
+        let dict = ReplacementDictionary::from_file(path, !args.dict_ignore_case)?;
+        return Ok(Transform::Dictionary(dict));
+    }
+    if args.dict.is_some() {
+        return Err(format!(
+            "--dict is only used with the dictionary transform (got '{}')",
+            args.transform
+        ));
+    }
+    Transform::from_str_loose(&args.transform)
+}
+
+/// The prompt actually sent when `--transform-prompt` is set: `prompt` with
+/// `transform` applied offline to every other word (see
+/// [`crate::transforms::transform_text`]).  Returns `None` when the flag is off.
+///
+/// # Errors
+///
+/// Returns `Err(String)` when [`resolve_transform_options`] does.
+pub fn transform_prompt(args: &Args, prompt: &str, transform: &Transform) -> Result<Option<String>, String> {
+    if !args.transform_prompt {
+        return Ok(None);
+    }
+    let options = resolve_transform_options(args)?;
+    Ok(Some(crate::transforms::transform_text(prompt, transform, 2, &options).0))
+}
+
+/// Build the noise transform settings from `--noise-chars` and `--noise-count`.
+///
+/// # Errors
+///
+/// Returns `Err(String)` for an empty `--noise-chars` or a zero `--noise-count`.
+pub fn resolve_noise_config(args: &Args) -> Result<NoiseConfig, String> {
+    match &args.noise_chars {
+        Some(chars) => NoiseConfig::new(chars, args.noise_count),
+        None => {
+            let default: String = crate::transforms::NOISE_CHARS.iter().collect();
+            NoiseConfig::new(&default, args.noise_count)
+        }
+    }
+}
+
+/// Build the [`TransformOptions`] shared by every interceptor of a run from
+/// `--noise-chars`, `--noise-count` and `--zalgo-intensity`.
+///
+/// # Errors
+///
+/// Returns `Err(String)` when [`resolve_noise_config`] does.
+pub fn resolve_transform_options(args: &Args) -> Result<TransformOptions, String> {
+    Ok(TransformOptions {
+        noise: resolve_noise_config(args)?,
+        zalgo_intensity: args.zalgo_intensity as usize,
+    })
+}
+
+/// Collect prompt template variables from `--vars-file` and `--var`, with
+/// `--var` overriding the file.  Empty when neither is given, in which case
+/// prompts are sent without template substitution.
+pub fn resolve_template_vars(args: &Args) -> Result<HashMap<String, String>, String> {
+    let mut vars = match &args.vars_file {
+        Some(path) => crate::prompt_template::load_vars_file(path)?,
+        None => HashMap::new(),
+    };
+    vars.extend(args.vars.iter().cloned());
+    Ok(vars)
+}
+
+/// Build the `--preprocess` pipeline, in the order the stages were given.
+///
+/// # Errors
+///
+/// Returns `Err(String)` when `style` is listed without `--style-instruction`
+/// or the `--vars-file` for `template` cannot be read.
+pub fn preprocess_stages(args: &Args) -> Result<Vec<PreprocessStage>, String> {
+    args.preprocess
+        .iter()
+        .map(|kind| {
+            Ok(match kind {
+                StageKind::Template => PreprocessStage::Custom(Box::new(crate::preprocess::TemplateVars {
+                    vars: resolve_template_vars(args)?,
+                })),
+                StageKind::Truncate => PreprocessStage::Custom(Box::new(crate::preprocess::Truncate {
+                    max_chars: args.preprocess_max_chars.unwrap_or(args.max_prompt_chars),
+                })),
+                StageKind::Style => {
+                    let instruction = args
+                        .style_instruction
+                        .clone()
+                        .filter(|s| !s.trim().is_empty())
+                        .ok_or("--preprocess style requires --style-instruction")?;
+                    PreprocessStage::Custom(Box::new(crate::preprocess::StyleInstruction { instruction }))
+                }
+                StageKind::Orchestrator => PreprocessStage::Orchestrator,
+            })
+        })
+        .collect()
+}
+
+/// The `--budget-usd` cost router, shared by every interceptor of one run so
+/// spend accumulates across streams.
+///
+/// Cloning shares the same router.  Empty when `--budget-usd` is unset.
+#[derive(Clone, Default)]
+pub struct SharedBudget {
+    #[cfg(feature = "self-tune")]
+    router: Option<std::sync::Arc<crate::self_tune::cost::CostRouter>>,
+}
+
+impl std::fmt::Debug for SharedBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedBudget").field("enabled", &self.is_enabled()).finish()
+    }
+}
+
+impl SharedBudget {
+    /// Build the router for `args.budget_usd`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(String)` when `--budget-usd` is set but the binary was
+    /// built without the `self-tune` feature, or the ceiling is not positive.
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        let Some(budget) = args.budget_usd else {
+            return Ok(Self::default());
+        };
+        if !budget.is_finite() || budget <= 0.0 {
+            return Err(format!("--budget-usd must be a positive amount, got {budget}"));
+        }
+        #[cfg(feature = "self-tune")]
+        {
+            use crate::self_tune::cost::{BudgetConfig, CostRouter, ModelDowngradeTable};
+            let router = CostRouter::new(
+                BudgetConfig {
+                    ceiling_usd: budget,
+                    ..BudgetConfig::default()
+                },
+                ModelDowngradeTable::default(),
+            )
+            .with_default_prices();
+            Ok(Self {
+                router: Some(std::sync::Arc::new(router)),
+            })
+        }
+        #[cfg(not(feature = "self-tune"))]
+        {
+            Err("--budget-usd requires a build with the `self-tune` feature".to_string())
+        }
+    }
+
+    /// Whether a router is configured.
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "self-tune")]
+        {
+            self.router.is_some()
+        }
+        #[cfg(not(feature = "self-tune"))]
+        {
+            false
+        }
+    }
+
+    /// Attach the shared router (if any) to `interceptor`.
+    pub fn attach(&self, interceptor: crate::TokenInterceptor) -> crate::TokenInterceptor {
+        #[cfg(feature = "self-tune")]
+        if let Some(router) = &self.router {
+            return interceptor.with_cost_router(router.clone());
+        }
+        interceptor
+    }
+
+    /// The shared router, for inspecting pressure.
+    #[cfg(feature = "self-tune")]
+    pub fn router(&self) -> Option<&std::sync::Arc<crate::self_tune::cost::CostRouter>> {
+        self.router.as_ref()
+    }
+}
+
+/// The model to request from `provider`: `model` when one was given, else
+/// [`Provider::default_model`].  The mock provider always uses its fixture.
+pub fn resolve_model(provider: &Provider, model: Option<&str>) -> String {
+    match (provider, model.filter(|m| !m.is_empty())) {
+        (Provider::Mock, _) | (_, None) => provider.default_model().to_string(),
+        (_, Some(model)) => model.to_string(),
     }
 }
 
 /// Known-good model identifiers for basic validation (#18).
 ///
 /// This list is non-exhaustive — new models are released regularly.
-/// An unknown model string produces a warning, not an error.
-const KNOWN_OPENAI_MODELS: &[&str] = &[
+/// An unknown model string produces a warning, not an error.  The OpenAI
+/// list doubles as the web UI's fallback model list (see [`crate::model_list`]).
+pub const KNOWN_OPENAI_MODELS: &[&str] = &[
     "gpt-3.5-turbo",
     "gpt-3.5-turbo-0125",
     "gpt-4",
@@ -362,6 +898,43 @@ pub fn validate_model(provider: &Provider, model: &str) {
     }
 }
 
+/// Parse the `--importance-smoothing` EMA weight, which must lie in `(0.0, 1.0]`.
+pub fn parse_smoothing_alpha(s: &str) -> Result<f64, String> {
+    let alpha: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid smoothing alpha '{}'", s))?;
+    if !(alpha > 0.0 && alpha <= 1.0) {
+        return Err(format!("smoothing alpha {} is outside (0.0, 1.0]", alpha));
+    }
+    Ok(alpha)
+}
+
+/// Parse a `--tag` experiment name.  It becomes a directory name, so only
+/// ASCII letters, digits, `-`, `_` and `.` are allowed.
+pub fn parse_tag(s: &str) -> Result<String, String> {
+    let tag = s.trim();
+    if tag.is_empty() || tag.starts_with('.') {
+        return Err(format!("invalid tag '{}'", s));
+    }
+    if let Some(c) = tag.chars().find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))) {
+        return Err(format!("invalid character '{}' in tag '{}'", c, s));
+    }
+    Ok(tag.to_string())
+}
+
+/// Parse one `--temp-sweep` temperature, which must lie in `[0.0, 2.0]`.
+pub fn parse_temperature(s: &str) -> Result<f32, String> {
+    let t: f32 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid temperature '{}'", s))?;
+    if !(0.0..=2.0).contains(&t) {
+        return Err(format!("temperature {} is outside 0.0..=2.0", t));
+    }
+    Ok(t)
+}
+
 /// Parse "MIN-MAX" rate range string. Returns (min, max) or None on error.
 ///
 /// Uses `rfind('-')` to locate the separator so that scientific-notation
@@ -393,17 +966,36 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_resolve_model_anthropic_default_swap() {
+    fn test_resolve_model_omitted_uses_provider_default() {
+        assert_eq!(resolve_model(&Provider::Anthropic, None), "claude-sonnet-4-6");
+        assert_eq!(resolve_model(&Provider::Openai, None), "gpt-3.5-turbo");
+        assert_eq!(resolve_model(&Provider::Mock, Some("gpt-4")), "mock-fixture-v1");
+    }
+
+    #[test]
+    fn test_provider_default_models_are_known_for_their_provider() {
+        assert!(KNOWN_OPENAI_MODELS.contains(&Provider::Openai.default_model()));
+        assert!(KNOWN_ANTHROPIC_MODELS.contains(&Provider::Anthropic.default_model()));
+        assert!(!KNOWN_ANTHROPIC_MODELS.contains(&Provider::Openai.default_model()));
+        assert!(!KNOWN_OPENAI_MODELS.contains(&Provider::Anthropic.default_model()));
+    }
+
+    #[test]
+    fn test_cli_without_model_picks_provider_default() {
+        let args = Args::parse_from(["eot", "hello", "--provider", "anthropic"]);
+        assert_eq!(args.model, None);
         assert_eq!(
-            resolve_model(&Provider::Anthropic, "gpt-3.5-turbo"),
+            resolve_model(&args.provider, args.model.as_deref()),
             "claude-sonnet-4-6"
         );
+        let args = Args::parse_from(["eot", "hello", "reverse", "gpt-4", "--provider", "anthropic"]);
+        assert_eq!(resolve_model(&args.provider, args.model.as_deref()), "gpt-4");
     }
 
     #[test]
     fn test_resolve_model_anthropic_explicit_model_kept() {
         assert_eq!(
-            resolve_model(&Provider::Anthropic, "claude-haiku-4-5-20251001"),
+            resolve_model(&Provider::Anthropic, Some("claude-haiku-4-5-20251001")),
             "claude-haiku-4-5-20251001"
         );
     }
@@ -411,14 +1003,44 @@ mod tests {
     #[test]
     fn test_resolve_model_openai_default_kept() {
         assert_eq!(
-            resolve_model(&Provider::Openai, "gpt-3.5-turbo"),
+            resolve_model(&Provider::Openai, Some("gpt-3.5-turbo")),
             "gpt-3.5-turbo"
         );
     }
 
+    #[test]
+    fn test_transform_prompt_perturbs_every_other_word_only_when_set() {
+        let args = Args::parse_from(["eot", "p", "uppercase"]);
+        assert_eq!(transform_prompt(&args, "one two three four", &Transform::Uppercase), Ok(None));
+        let args = Args::parse_from(["eot", "p", "uppercase", "--transform-prompt"]);
+        assert_eq!(
+            transform_prompt(&args, "one two three four", &Transform::Uppercase),
+            Ok(Some("one TWO three FOUR".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_temp_sweep_parses_comma_separated_temperatures() {
+        let args = Args::parse_from(["eot", "p", "--research", "--temp-sweep", "0.0,0.5,1.0"]);
+        assert_eq!(args.temp_sweep, vec![0.0, 0.5, 1.0]);
+        assert!(Args::parse_from(["eot", "p"]).temp_sweep.is_empty());
+        assert!(Args::try_parse_from(["eot", "p", "--temp-sweep", "0.5,2.5"]).is_err());
+        assert!(parse_temperature("warm").is_err());
+    }
+
+    #[test]
+    fn test_tag_must_be_a_safe_directory_name() {
+        let args = Args::parse_from(["eot", "p", "--research", "--tag", "ablation-3", "--notes", "warm cache"]);
+        assert_eq!(args.tag.as_deref(), Some("ablation-3"));
+        assert_eq!(args.notes.as_deref(), Some("warm cache"));
+        assert!(Args::try_parse_from(["eot", "p", "--tag", "../up"]).is_err());
+        assert!(parse_tag("a/b").is_err());
+        assert!(parse_tag(" ").is_err());
+    }
+
     #[test]
     fn test_resolve_model_openai_explicit_model_kept() {
-        assert_eq!(resolve_model(&Provider::Openai, "gpt-4"), "gpt-4");
+        assert_eq!(resolve_model(&Provider::Openai, Some("gpt-4")), "gpt-4");
     }
 
     #[test]
@@ -426,7 +1048,7 @@ mod tests {
         let args = Args::parse_from(["eot", "hello world"]);
         assert_eq!(args.prompt, "hello world");
         assert_eq!(args.transform, "reverse");
-        assert_eq!(args.model, "gpt-3.5-turbo");
+        assert_eq!(args.model, None);
         assert_eq!(args.provider, Provider::Openai);
         assert!(!args.visual);
         assert!(!args.heatmap);
@@ -454,7 +1076,7 @@ mod tests {
         ]);
         assert_eq!(args.prompt, "test prompt");
         assert_eq!(args.transform, "uppercase");
-        assert_eq!(args.model, "gpt-4");
+        assert_eq!(args.model.as_deref(), Some("gpt-4"));
         assert_eq!(args.provider, Provider::Anthropic);
         assert!(args.visual);
         assert!(args.heatmap);
@@ -603,6 +1225,124 @@ mod tests {
         assert!(args.helix_url.is_none());
     }
 
+    #[test]
+    fn test_args_show_original_and_no_color() {
+        let args = Args::parse_from(["eot", "prompt"]);
+        assert!(!args.show_original && !args.no_color);
+        let args = Args::parse_from(["eot", "prompt", "--show-original", "--no-color"]);
+        assert!(args.show_original && args.no_color);
+    }
+
+    #[test]
+    fn test_args_tee() {
+        assert!(Args::parse_from(["eot", "prompt"]).tee.is_none());
+        let args = Args::parse_from(["eot", "prompt", "--tee", "out.txt"]);
+        assert_eq!(args.tee.as_deref(), Some("out.txt"));
+    }
+
+    #[test]
+    fn test_args_max_prompt_chars() {
+        assert_eq!(Args::parse_from(["eot", "prompt"]).max_prompt_chars, crate::DEFAULT_MAX_PROMPT_CHARS);
+        let args = Args::parse_from(["eot", "prompt", "--max-prompt-chars", "500"]);
+        assert_eq!(args.max_prompt_chars, 500);
+    }
+
+    #[test]
+    fn test_args_render_rate() {
+        assert!(Args::parse_from(["eot", "prompt"]).render_rate.is_none());
+        let args = Args::parse_from(["eot", "prompt", "--render-rate", "30"]);
+        assert_eq!(args.render_rate, Some(30.0));
+    }
+
+    #[test]
+    fn test_args_no_logprobs() {
+        assert!(!Args::parse_from(["eot", "prompt"]).no_logprobs);
+        assert!(Args::parse_from(["eot", "prompt", "--no-logprobs"]).no_logprobs);
+    }
+
+    #[test]
+    fn test_args_reveal_delay() {
+        assert!(Args::parse_from(["eot", "prompt"]).reveal_delay.is_none());
+        let args = Args::parse_from(["eot", "prompt", "--reveal-delay", "400"]);
+        assert_eq!(args.reveal_delay, Some(400));
+    }
+
+    #[test]
+    fn test_args_header_repeatable() {
+        let args = Args::parse_from([
+            "eot",
+            "prompt",
+            "--header",
+            "OpenAI-Organization: org-1",
+            "--header",
+            "anthropic-beta: x",
+        ]);
+        assert_eq!(
+            args.headers,
+            vec![
+                ("OpenAI-Organization".to_string(), "org-1".to_string()),
+                ("anthropic-beta".to_string(), "x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_args_header_rejects_authorization() {
+        assert!(Args::try_parse_from(["eot", "prompt", "--header", "Authorization: Bearer x"]).is_err());
+    }
+
+    #[cfg(feature = "redis-backing")]
+    #[test]
+    fn test_args_redis_url_default_none() {
+        let args = Args::parse_from(["eot", "prompt"]);
+        assert!(args.redis_url.is_none());
+    }
+
+    #[cfg(feature = "redis-backing")]
+    #[test]
+    fn test_args_redis_url_set() {
+        let args = Args::parse_from(["eot", "prompt", "--redis-url", "redis://127.0.0.1/"]);
+        assert_eq!(args.redis_url.as_deref(), Some("redis://127.0.0.1/"));
+    }
+
+    #[test]
+    fn test_args_budget_usd_parses() {
+        let args = Args::parse_from(["eot", "prompt", "--budget-usd", "2.5"]);
+        assert_eq!(args.budget_usd, Some(2.5));
+    }
+
+    #[test]
+    fn test_shared_budget_unset_is_disabled() {
+        let args = Args::parse_from(["eot", "prompt"]);
+        assert!(!SharedBudget::from_args(&args).expect("no budget").is_enabled());
+    }
+
+    #[test]
+    fn test_shared_budget_rejects_non_positive() {
+        let args = Args::parse_from(["eot", "prompt", "--budget-usd", "0"]);
+        assert!(SharedBudget::from_args(&args).is_err());
+    }
+
+    #[cfg(not(feature = "self-tune"))]
+    #[test]
+    fn test_shared_budget_requires_self_tune() {
+        let args = Args::parse_from(["eot", "prompt", "--budget-usd", "2.5"]);
+        let err = SharedBudget::from_args(&args).unwrap_err();
+        assert!(err.contains("self-tune"), "{err}");
+    }
+
+    #[cfg(feature = "self-tune")]
+    #[test]
+    fn test_shared_budget_clones_share_one_router() {
+        let args = Args::parse_from(["eot", "prompt", "--budget-usd", "2.5"]);
+        let budget = SharedBudget::from_args(&args).expect("budget");
+        let copy = budget.clone();
+        assert!(std::sync::Arc::ptr_eq(
+            budget.router().expect("router"),
+            copy.router().expect("router")
+        ));
+    }
+
     #[cfg(feature = "helix-bridge")]
     #[test]
     fn test_args_helix_url_set() {
@@ -752,6 +1492,143 @@ mod tests {
         assert_eq!(args.orchestrator_url, "http://localhost:3000");
     }
 
+    #[test]
+    fn test_resolve_template_vars_var_overrides_file() {
+        let path = std::env::temp_dir().join(format!("eot_cli_vars_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"topic": "cats", "style": "noir"}"#).unwrap();
+        let args = Args::parse_from([
+            "eot",
+            "About {topic} in {style}",
+            "--vars-file",
+            path.to_str().unwrap(),
+            "--var",
+            "topic=robots",
+        ]);
+        let vars = resolve_template_vars(&args).expect("vars");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(vars["topic"], "robots");
+        assert_eq!(vars["style"], "noir");
+        assert!(resolve_template_vars(&Args::parse_from(["eot", "p"])).unwrap().is_empty());
+        assert!(Args::try_parse_from(["eot", "p", "--var", "novalue"]).is_err());
+    }
+
+    #[test]
+    fn test_preprocess_stages_follow_flag_order() {
+        let args = Args::parse_from([
+            "eot", "p", "--preprocess", "style,truncate,orchestrator", "--style-instruction", "Be brief.",
+            "--preprocess-max-chars", "8",
+        ]);
+        let stages = preprocess_stages(&args).expect("stages");
+        let names: Vec<&str> = stages.iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["style", "truncate", "orchestrator"]);
+        assert!(Args::try_parse_from(["eot", "p", "--preprocess", "summarize"]).is_err());
+        let no_style = Args::parse_from(["eot", "p", "--preprocess", "style"]);
+        assert!(preprocess_stages(&no_style).unwrap_err().contains("--style-instruction"));
+        assert!(preprocess_stages(&Args::parse_from(["eot", "p"])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_noise_config() {
+        let args = Args::parse_from(["eot", "prompt", "--noise-chars", "!?.", "--noise-count", "2"]);
+        let config = resolve_noise_config(&args).expect("valid");
+        assert_eq!(config.chars(), &['!', '?', '.']);
+        assert_eq!(config.count(), 2);
+        assert_eq!(resolve_noise_config(&Args::parse_from(["eot", "prompt"])), Ok(NoiseConfig::default()));
+        let empty = Args::parse_from(["eot", "prompt", "--noise-chars", ""]);
+        assert!(resolve_noise_config(&empty).is_err());
+        let zero = Args::parse_from(["eot", "prompt", "--noise-count", "0"]);
+        assert!(resolve_noise_config(&zero).is_err());
+    }
+
+    #[test]
+    fn test_args_importance_smoothing() {
+        assert_eq!(Args::parse_from(["eot", "prompt"]).importance_smoothing, None);
+        let args = Args::parse_from(["eot", "prompt", "--importance-smoothing", "0.3"]);
+        assert_eq!(args.importance_smoothing, Some(0.3));
+        for bad in ["0", "1.5", "x"] {
+            assert!(Args::try_parse_from(["eot", "prompt", "--importance-smoothing", bad]).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_args_granularity() {
+        assert_eq!(Args::parse_from(["eot", "prompt"]).granularity, crate::Granularity::Token);
+        let args = Args::parse_from(["eot", "prompt", "--granularity", "sentence"]);
+        assert_eq!(args.granularity, crate::Granularity::Sentence);
+        assert!(Args::try_parse_from(["eot", "prompt", "--granularity", "paragraph"]).is_err());
+    }
+
+    #[test]
+    fn test_args_zalgo_intensity() {
+        assert_eq!(Args::parse_from(["eot", "prompt"]).zalgo_intensity, 3);
+        assert_eq!(Args::parse_from(["eot", "prompt", "--zalgo-intensity", "8"]).zalgo_intensity, 8);
+        assert!(Args::try_parse_from(["eot", "prompt", "--zalgo-intensity", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_args_mcp_server() {
+        assert!(!Args::parse_from(["eot", "prompt"]).mcp_server);
+        let args = Args::parse_from(["eot", "--mcp-server", "--port", "3000"]);
+        assert!(args.mcp_server && args.port == 3000);
+    }
+
+    #[test]
+    fn test_args_select() {
+        assert_eq!(Args::parse_from(["eot", "prompt"]).select, crate::TransformSelector::Alternating);
+        let args = Args::parse_from(["eot", "prompt", "--select", "importance:0.6"]);
+        assert_eq!(args.select, crate::TransformSelector::ImportanceAbove(0.6));
+        assert!(Args::try_parse_from(["eot", "prompt", "--select", "importance"]).is_err());
+    }
+
+    #[test]
+    fn test_args_watch() {
+        let args = Args::parse_from(["eot", "--watch", "SWIFT-LION-42", "--watch-url", "http://10.0.0.5:9000"]);
+        assert_eq!(args.watch.as_deref(), Some("SWIFT-LION-42"));
+        assert_eq!(args.watch_url, "http://10.0.0.5:9000");
+        assert_eq!(Args::parse_from(["eot", "prompt"]).watch_url, "http://localhost:8888");
+    }
+
+    #[test]
+    fn test_args_debug_raw() {
+        let args = Args::parse_from(["eot", "prompt"]);
+        assert!(!args.debug_raw);
+        assert_eq!(args.debug_raw_file, None);
+        let args = Args::parse_from(["eot", "prompt", "--debug-raw", "--debug-raw-file", "raw.log"]);
+        assert!(args.debug_raw);
+        assert_eq!(args.debug_raw_file.as_deref(), Some("raw.log"));
+    }
+
+    #[test]
+    fn test_args_inline_mapping() {
+        assert_eq!(Args::parse_from(["eot", "prompt"]).inline_mapping, None);
+        let args = Args::parse_from(["eot", "prompt", "--inline-mapping"]);
+        assert_eq!(args.inline_mapping.as_deref(), Some(crate::DEFAULT_INLINE_MAPPING));
+        let args = Args::parse_from(["eot", "prompt", "--inline-mapping", "[{original}|{text}]"]);
+        assert_eq!(args.inline_mapping.as_deref(), Some("[{original}|{text}]"));
+        assert!(Args::try_parse_from(["eot", "prompt", "--inline-mapping", "no placeholders"]).is_err());
+    }
+
+    #[test]
+    fn test_args_flush_policy() {
+        assert_eq!(Args::parse_from(["eot", "prompt"]).flush_policy, crate::FlushPolicy::Token);
+        let args = Args::parse_from(["eot", "prompt", "--flush-policy", "32"]);
+        assert_eq!(args.flush_policy, crate::FlushPolicy::Every(32));
+        assert!(Args::try_parse_from(["eot", "prompt", "--flush-policy", "never"]).is_err());
+    }
+
+    #[test]
+    fn test_args_word_boundaries() {
+        assert!(!Args::parse_from(["eot", "prompt"]).word_boundaries);
+        assert!(Args::parse_from(["eot", "prompt", "--word-boundaries"]).word_boundaries);
+    }
+
+    #[test]
+    fn test_args_show_enriched() {
+        assert!(!Args::parse_from(["eot", "prompt"]).show_enriched);
+        let args = Args::parse_from(["eot", "prompt", "--orchestrator", "--show-enriched"]);
+        assert!(args.orchestrator && args.show_enriched);
+    }
+
     #[test]
     fn test_args_orchestrator_url_custom() {
         let args = Args::parse_from([
@@ -827,6 +1704,13 @@ mod tests {
         assert_eq!(args.list_models.as_deref(), Some("all"));
     }
 
+    #[test]
+    fn test_doctor_flag_and_check_alias() {
+        assert!(Args::parse_from(["eot", "--doctor"]).doctor);
+        assert!(Args::parse_from(["eot", "--check"]).doctor);
+        assert!(!Args::parse_from(["eot", "prompt"]).doctor);
+    }
+
     // -- Item 17: --json-schema flag --
     #[test]
     fn test_json_schema_flag_outputs_valid_json() {
@@ -859,4 +1743,20 @@ mod tests {
         let args = Args::parse_from(["eot", "prompt", "--export-timeseries", "out.csv"]);
         assert_eq!(args.export_timeseries.as_deref(), Some("out.csv"));
     }
+
+    #[test]
+    fn test_resolve_transform_loads_dict() {
+        let mut file = tempfile::NamedTempFile::new().expect("tempfile");
+        std::io::Write::write_all(&mut file, br#"{"Cat": "dog"}"#).expect("write");
+        let path = file.path().to_str().expect("utf-8 path");
+
+        let args = Args::parse_from(["eot", "prompt", "dictionary", "--dict", path, "--dict-ignore-case"]);
+        let transform = resolve_transform(&args).expect("dictionary transform");
+        assert_eq!(transform.apply(" cat"), " dog");
+
+        let args = Args::parse_from(["eot", "prompt", "dictionary"]);
+        assert!(resolve_transform(&args).is_err());
+        let args = Args::parse_from(["eot", "prompt", "reverse", "--dict", path]);
+        assert!(resolve_transform(&args).is_err());
+    }
 }
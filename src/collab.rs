@@ -1,7 +1,8 @@
 //! Multiplayer collaboration: room state, participant management, WebSocket handling.
 //!
 //! ## Design
-//! - RoomStore: Arc<Mutex<HashMap<String, Room>>> — shared across all connections
+//! - RoomStore: Arc<Mutex<HashMap<String, Room>>> plus an optional backend link —
+//!   shared across all connections
 //! - Each Room has a broadcast channel (tokio::sync::broadcast) for real-time fan-out
 //! - Each WS client subscribes to the room's broadcast sender
 //! - Messages are serde_json::Value for flexibility
@@ -12,17 +13,65 @@
 //! 3. Guests open /join/CODE in browser → connects to WS /ws/CODE → gets guest role
 //! 4. Host starts a stream → token events broadcast to all participants
 //! 5. Any participant edits a token → surgery event broadcast to all
-//! 6. Participants can chat and vote on transforms
+//! 6. Participants can chat, vote on transforms and annotate tokens
+//! 7. Any participant can switch the transform (`set_transform`); the server
+//!    re-renders the retained tokens from their originals for everyone
+//!
+//! ## Multi-instance rooms
+//! A store made with [`new_room_store_with_backend`] mirrors its rooms to a
+//! [`RoomBackend`] shared with other web-server instances.  Every mutation
+//! queues a revisioned snapshot and every broadcast is queued for publishing;
+//! a writer thread performs that I/O so the store lock is never held across
+//! it.  Events arriving from other instances are applied with
+//! [`deliver_remote`] (see [`spawn_remote_relay`]).
 
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use tokio_tungstenite::tungstenite::Message as WsMessage;
 
-/// Shared room store: room code → Room.
-pub type RoomStore = Arc<Mutex<HashMap<String, Room>>>;
+/// Shared room store: room code → Room, plus the backend its rooms are
+/// mirrored to, if any.  Clones share the same rooms.
+#[derive(Clone)]
+pub struct RoomStore {
+    rooms: Arc<Mutex<HashMap<String, Room>>>,
+    sync: Option<RoomSync>,
+    /// Settings of the parameterised transforms used when a room switches
+    /// transform (see [`set_room_transform`]).
+    transform_options: crate::transforms::TransformOptions,
+}
+
+impl RoomStore {
+    /// Use `options` when re-rendering a room's tokens for a new transform.
+    pub fn with_transform_options(mut self, options: crate::transforms::TransformOptions) -> Self {
+        self.transform_options = options;
+        self
+    }
+
+    /// Lock the room map.
+    pub fn lock(&self) -> std::sync::LockResult<std::sync::MutexGuard<'_, HashMap<String, Room>>> {
+        self.rooms.lock()
+    }
+
+    /// The backend this store's rooms are mirrored to, if any.
+    pub fn backend(&self) -> Option<&Arc<dyn RoomBackend>> {
+        self.sync.as_ref().map(|sync| &sync.backend)
+    }
+
+    /// Block until every save and publish queued so far has reached the
+    /// backend.  Returns at once for a store without a backend.
+    pub fn flush_backend(&self) {
+        if let Some(sync) = &self.sync {
+            let (tx, rx) = std::sync::mpsc::channel();
+            sync.send(SyncOp::Flush(tx));
+            let _ = rx.recv();
+        }
+    }
+}
 
 /// Idle TTL for rooms: rooms not mutated in this many milliseconds are eligible for eviction.
 const ROOM_IDLE_TTL_MS: u64 = 3_600_000;
@@ -30,8 +79,37 @@ const ROOM_IDLE_TTL_MS: u64 = 3_600_000;
 /// Timeout for rooms with no active WebSocket connections: 30 minutes.
 const ROOM_ABANDONED_TIMEOUT_MS: u64 = 30 * 60 * 1_000;
 
-/// Maximum number of events stored in a room's recording buffer.
-const DEFAULT_RECORDING_CAP: usize = 10_000;
+/// Window during which a repeated `/room/create` carrying the same
+/// idempotency key returns the room created by the first request.
+pub const ROOM_IDEMPOTENCY_TTL_MS: u64 = 30_000;
+
+/// Default for `--max-recording-events`: events kept in a room's recording
+/// buffer before the oldest are dropped.
+pub const DEFAULT_RECORDING_CAP: usize = 10_000;
+
+/// Maximum number of tokens retained per room for session search.
+const SESSION_TOKEN_CAP: usize = 10_000;
+
+/// Maximum number of stream tokens held while a room is paused; beyond it
+/// the oldest held token is dropped (it stays in the session tokens).
+const HELD_TOKEN_CAP: usize = SESSION_TOKEN_CAP;
+
+/// Maximum number of entries retained in a room's audit log.
+pub const AUDIT_LOG_CAP: usize = 5_000;
+
+/// Maximum number of simultaneous participants in one room.
+pub const MAX_ROOM_PARTICIPANTS: usize = 32;
+
+/// Maximum number of token annotations kept per room; further notes are rejected.
+pub const MAX_ROOM_ANNOTATIONS: usize = 500;
+
+/// Default for `--max-name-chars`: longest participant display name kept by
+/// `set_name`, in characters.
+pub const DEFAULT_MAX_NAME_CHARS: usize = 32;
+
+/// Default for `--reconnect-grace`: how long a disconnected participant with
+/// a stable client identifier stays "away" before `participant_leave`.
+pub const DEFAULT_RECONNECT_GRACE_SECS: u64 = 10;
 
 /// Adjectives used for memorable room code generation.
 const CODE_ADJECTIVES: &[&str] = &[
@@ -45,6 +123,20 @@ const CODE_NOUNS: &[&str] = &[
     "FISH", "HARE", "KITE", "LYNX", "MOLE", "NEWT", "PUMA", "SWAN", "TOAD", "WREN",
 ];
 
+/// Adjectives used for generated participant names.
+const NAME_ADJECTIVES: &[&str] = &[
+    "Amber", "Bold", "Brisk", "Calm", "Clever", "Cosmic", "Daring", "Eager", "Fuzzy", "Gentle",
+    "Happy", "Jolly", "Kind", "Lucky", "Merry", "Nimble", "Plucky", "Quick", "Quiet", "Rapid",
+    "Shiny", "Silent", "Sunny", "Swift", "Tidy", "Vivid", "Witty", "Zesty",
+];
+
+/// Animals used for generated participant names.
+const NAME_ANIMALS: &[&str] = &[
+    "Badger", "Beaver", "Bison", "Falcon", "Ferret", "Gecko", "Heron", "Koala", "Lemur", "Llama",
+    "Lynx", "Marten", "Moose", "Narwhal", "Ocelot", "Otter", "Panda", "Puffin", "Quokka", "Raven",
+    "Robin", "Seal", "Sloth", "Stoat", "Tapir", "Walrus", "Wombat", "Yak",
+];
+
 /// Colors assigned to participants in round-robin order.
 pub const PARTICIPANT_COLORS: &[&str] = &[
     "#58a6ff", "#f0883e", "#a371f7", "#3fb950", "#e3b341", "#f85149",
@@ -58,6 +150,9 @@ pub struct Participant {
     pub color: String,
     pub joined_at_ms: u64,
     pub is_host: bool,
+    /// Disconnected but still within the reconnection grace period.
+    #[serde(default)]
+    pub away: bool,
 }
 
 /// A token-level surgery edit applied by a participant.
@@ -84,6 +179,61 @@ pub struct ChatMessage {
     pub timestamp_ms: u64,
 }
 
+/// A note attached to a token by a participant.  Unlike a [`SurgeryEdit`] it
+/// leaves the token text unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenAnnotation {
+    pub id: String,
+    /// Logical stream index of the annotated token.
+    pub token_index: usize,
+    pub note: String,
+    pub author_id: String,
+    pub author_name: String,
+    pub author_color: String,
+    pub timestamp_ms: u64,
+}
+
+/// Why a participant could not join a room.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    /// No room with this code exists.
+    NotFound(String),
+    /// The room already has [`MAX_ROOM_PARTICIPANTS`] participants.
+    Full,
+    /// The host has locked the room against new guests.
+    Locked,
+    /// The room store is unavailable (poisoned lock).
+    Internal,
+}
+
+impl JoinError {
+    /// WebSocket close code sent when rejecting a connection for this reason.
+    ///
+    /// Application codes live in the 4000–4999 range and mirror the closest
+    /// HTTP status: 4004 not found, 4029 full, 4003 locked.
+    pub fn close_code(&self) -> u16 {
+        match self {
+            JoinError::NotFound(_) => 4004,
+            JoinError::Full => 4029,
+            JoinError::Locked => 4003,
+            JoinError::Internal => 1011,
+        }
+    }
+}
+
+impl std::fmt::Display for JoinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JoinError::NotFound(code) => write!(f, "Room '{}' not found", code),
+            JoinError::Full => write!(f, "Room is full ({} participants)", MAX_ROOM_PARTICIPANTS),
+            JoinError::Locked => write!(f, "Room is locked by the host"),
+            JoinError::Internal => write!(f, "internal: lock poisoned"),
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
 /// A recorded room event with a relative timestamp.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordedEvent {
@@ -91,6 +241,30 @@ pub struct RecordedEvent {
     pub payload: serde_json::Value,
 }
 
+/// One entry of a room's audit log: what happened, when, and who did it.
+///
+/// `event` is one of `join`, `reconnect`, `leave`, `rename`, `surgery`,
+/// `chat`, `annotate`, `vote`, `transform_lock`, `set_transform`, `room_lock`
+/// or `pause`; `detail` carries the event-specific fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Wall-clock ms timestamp of the action.
+    pub timestamp_ms: u64,
+    pub event: String,
+    /// Participant `id` of whoever performed the action, when known.
+    pub actor_id: Option<String>,
+    pub detail: serde_json::Value,
+}
+
+/// Why a room's audit log or archive could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditError {
+    /// No room with this code exists.
+    NotFound,
+    /// The supplied host token does not match the room's.
+    Forbidden,
+}
+
 /// An active collaboration room shared by one host and zero or more guests.
 ///
 /// Rooms are created via [`create_room`] and stored in a [`RoomStore`].
@@ -109,6 +283,9 @@ pub struct Room {
     pub surgery_log: Vec<SurgeryEdit>,
     /// Ordered log of all chat messages sent during the session.
     pub chat_log: Vec<ChatMessage>,
+    /// Token annotations in the order they were added, capped at
+    /// [`MAX_ROOM_ANNOTATIONS`].
+    pub annotations: Vec<TokenAnnotation>,
     /// Transform vote tallies: transform name → (upvotes, downvotes).
     pub votes: HashMap<String, (u32, u32)>,
     /// Whether the room is currently recording events to `recorded_events`.
@@ -130,6 +307,361 @@ pub struct Room {
     /// Wall-clock ms timestamp when the last WebSocket connection disconnected.
     /// None means a WS connection is still active or one has never connected.
     pub last_ws_disconnect_ms: Option<u64>,
+    /// Transform forced on every stream in this room, set by the host.
+    pub locked_transform: Option<String>,
+    /// When `true`, new guests are rejected with [`JoinError::Locked`].
+    pub joins_locked: bool,
+    /// Tokens of the room's most recent stream, oldest first, for session search.
+    pub session_tokens: std::collections::VecDeque<serde_json::Value>,
+    /// When `true`, stream tokens are held in `held_tokens` instead of being
+    /// broadcast; set by the host with `{"type":"pause"}`.
+    pub paused: bool,
+    /// Stream tokens buffered while `paused`, oldest first, capped at
+    /// [`HELD_TOKEN_CAP`] and cleared when a new stream starts.
+    pub held_tokens: std::collections::VecDeque<serde_json::Value>,
+    /// Stable client identifier → assigned color, so a participant who
+    /// reconnects with the same identifier keeps their color.  Saved to the
+    /// backend but not included in the state snapshot sent to clients.
+    pub client_colors: HashMap<String, String>,
+    /// Stable client identifier → participant `id`, so a reconnect within the
+    /// grace period resumes the same participant.  Local only.
+    pub client_participants: HashMap<String, String>,
+    /// Participant `id` → departure number for participants currently away;
+    /// see [`mark_away`].  Local only.
+    pub away_since: HashMap<String, u64>,
+    /// Number of departures so far, used to number `away_since` entries.
+    pub departures: u64,
+    /// Client-supplied idempotency key of the create request that made this
+    /// room; see [`create_room_idempotent`].  Local only.
+    pub idempotency_key: Option<String>,
+    /// Ordered audit log of room actions, capped at [`AUDIT_LOG_CAP`].  Local only.
+    pub audit_log: std::collections::VecDeque<AuditEntry>,
+    /// Secret returned to the room's creator; required to read `audit_log`.
+    /// Empty for rooms hydrated from a backend, which disables audit access
+    /// on that instance.  Local only.
+    pub host_token: String,
+    /// Revision of the room's backend snapshot: bumped on every write and
+    /// raised to match events from other instances; see [`RoomBackend::save_room`].
+    pub revision: u64,
+}
+
+impl Room {
+    /// Append an entry to the audit log, evicting the oldest beyond the cap.
+    fn audit(&mut self, event: &str, actor_id: Option<&str>, detail: serde_json::Value) {
+        if self.audit_log.len() >= AUDIT_LOG_CAP {
+            self.audit_log.pop_front();
+        }
+        self.audit_log.push_back(AuditEntry {
+            timestamp_ms: now_ms(),
+            event: event.to_string(),
+            actor_id: actor_id.map(str::to_string),
+            detail,
+        });
+    }
+
+    /// Log a surgery edit and rewrite the token it targets.
+    ///
+    /// The target is the buffered token whose logical `index` field equals
+    /// `edit.token_index`, not the token at that buffer position: session
+    /// tokens are capped from the front, and late joiners only ever see a
+    /// suffix of the stream, so positions drift while logical indices do not.
+    fn record_surgery(&mut self, edit: SurgeryEdit) {
+        let target = edit.token_index as u64;
+        for token in self.session_tokens.iter_mut().chain(self.held_tokens.iter_mut()) {
+            if token.get("index").and_then(|v| v.as_u64()) == Some(target) {
+                token["text"] = serde_json::Value::String(edit.new_text.clone());
+            }
+        }
+        self.surgery_log.push(edit);
+    }
+
+    /// Re-render every token the stream transformed by applying `transform`
+    /// to its `original`, in the retained and held tokens alike.  Tokens the
+    /// stream left untouched keep their text.
+    fn retransform(&mut self, transform: &crate::transforms::Transform, options: &crate::transforms::TransformOptions) {
+        for token in self.session_tokens.iter_mut().chain(self.held_tokens.iter_mut()) {
+            if token.get("transformed").and_then(|v| v.as_bool()) != Some(true) {
+                continue;
+            }
+            let Some(original) = token.get("original").and_then(|v| v.as_str()).map(str::to_string) else {
+                continue;
+            };
+            let (text, chaos_label) = crate::transforms::retransform_token(&original, transform, options);
+            token["text"] = serde_json::Value::String(text);
+            token["chaos_label"] = serde_json::json!(chaos_label);
+        }
+    }
+
+    /// Participant `id` of the host, for auditing host-only actions.
+    fn host_actor(&self) -> Option<String> {
+        (!self.host_id.is_empty()).then(|| self.host_id.clone())
+    }
+
+    /// Send `msg` to local subscribers and queue it for other instances
+    /// when the store has a backend.
+    ///
+    /// Returns `false` when the room has no local subscribers.
+    fn fan_out(&self, sync: Option<&RoomSync>, msg: serde_json::Value) -> bool {
+        if let Some(sync) = sync {
+            sync.publish(self, msg.clone());
+        }
+        self.broadcast_tx.send(msg).is_ok()
+    }
+
+    /// Resume a paused room: announce it, then flush the held tokens in order.
+    fn resume(&mut self, sync: Option<&RoomSync>) {
+        self.paused = false;
+        self.fan_out(sync, serde_json::json!({"type": "stream_pause", "paused": false}));
+        while let Some(token) = self.held_tokens.pop_front() {
+            self.fan_out(sync, token);
+        }
+    }
+
+    /// Retain a streamed token for session search, evicting the oldest
+    /// beyond the per-room cap.
+    fn retain_session_token(&mut self, token: serde_json::Value) {
+        if self.session_tokens.len() >= SESSION_TOKEN_CAP {
+            self.session_tokens.pop_front();
+        }
+        self.session_tokens.push_back(token);
+    }
+
+    /// Queue the room's current state, under the next revision, for the
+    /// store's backend, if any.
+    fn persist(&mut self, sync: Option<&RoomSync>) {
+        let Some(sync) = sync else { return };
+        self.revision += 1;
+        let mut snapshot = snapshot_of(self);
+        snapshot["client_colors"] = serde_json::json!(self.client_colors);
+        snapshot["revision"] = serde_json::json!(self.revision);
+        sync.send(SyncOp::Save {
+            code: self.code.clone(),
+            revision: self.revision,
+            snapshot,
+        });
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Backend writer
+// ---------------------------------------------------------------------------
+
+/// Backend I/O queued by a [`RoomStore`] for its writer thread.
+enum SyncOp {
+    Save {
+        code: String,
+        revision: u64,
+        snapshot: serde_json::Value,
+    },
+    Publish {
+        code: String,
+        revision: u64,
+        msg: serde_json::Value,
+    },
+    /// Acknowledged once every operation queued before it is done.
+    Flush(std::sync::mpsc::Sender<()>),
+}
+
+/// A store's link to its backend.  Rooms only enqueue [`SyncOp`]s, which is
+/// cheap under the store lock; a dedicated thread performs the blocking I/O
+/// in order.
+#[derive(Clone)]
+struct RoomSync {
+    backend: Arc<dyn RoomBackend>,
+    tx: std::sync::mpsc::Sender<SyncOp>,
+}
+
+impl RoomSync {
+    fn start(backend: Arc<dyn RoomBackend>) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let writer = Arc::clone(&backend);
+        if let Err(e) = std::thread::Builder::new()
+            .name("eot-room-sync".to_string())
+            .spawn(move || run_sync_writer(writer.as_ref(), rx))
+        {
+            tracing::warn!(err = %e, "room backend writer failed to start; rooms stay local");
+        }
+        Self { backend, tx }
+    }
+
+    fn send(&self, op: SyncOp) {
+        // Only fails once the writer is gone, when there is nowhere to write.
+        let _ = self.tx.send(op);
+    }
+
+    /// Queue `msg` for other instances, tagged with the room's revision.
+    fn publish(&self, room: &Room, msg: serde_json::Value) {
+        self.send(SyncOp::Publish {
+            code: room.code.clone(),
+            revision: room.revision,
+            msg,
+        });
+    }
+}
+
+/// Apply queued operations to `backend` until every [`RoomSync`] is dropped.
+///
+/// Operations are taken in batches.  Within a batch only the newest snapshot
+/// of each room is saved, and a room's pending snapshot is saved before any
+/// of its broadcasts is published so another instance hydrating on that
+/// broadcast sees the state it describes.
+fn run_sync_writer(backend: &dyn RoomBackend, rx: std::sync::mpsc::Receiver<SyncOp>) {
+    let save = |code: &str, (revision, snapshot): (u64, serde_json::Value)| {
+        match backend.save_room(code, revision, &snapshot) {
+            Ok(true) => {}
+            Ok(false) => tracing::debug!(room_code = %code, revision, "stale room snapshot not saved"),
+            Err(e) => tracing::warn!(room_code = %code, err = %e, "room backend save failed"),
+        }
+    };
+    while let Ok(first) = rx.recv() {
+        let mut pending: HashMap<String, (u64, serde_json::Value)> = HashMap::new();
+        for op in std::iter::once(first).chain(rx.try_iter()) {
+            match op {
+                SyncOp::Save { code, revision, snapshot } => {
+                    pending.insert(code, (revision, snapshot));
+                }
+                SyncOp::Publish { code, revision, msg } => {
+                    if let Some(snapshot) = pending.remove(&code) {
+                        save(&code, snapshot);
+                    }
+                    if let Err(e) = backend.publish(&code, revision, &msg) {
+                        tracing::warn!(room_code = %code, err = %e, "room backend publish failed");
+                    }
+                }
+                SyncOp::Flush(done) => {
+                    for (code, snapshot) in pending.drain() {
+                        save(&code, snapshot);
+                    }
+                    let _ = done.send(());
+                }
+            }
+        }
+        for (code, snapshot) in pending {
+            save(&code, snapshot);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Room backends
+// ---------------------------------------------------------------------------
+
+/// A broadcast published by another instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteEvent {
+    pub code: String,
+    /// Revision of the room on the publishing instance when it was sent.
+    pub revision: u64,
+    pub msg: serde_json::Value,
+}
+
+/// Shared persistence and cross-instance fan-out for rooms.
+///
+/// The in-process [`RoomStore`] stays the source of truth for connected
+/// clients; a backend lets several web-server instances see the same rooms.
+/// Saves and publishes come from the store's writer thread and may block.
+pub trait RoomBackend: Send + Sync {
+    /// Persist the JSON state snapshot of room `code` taken at `revision`.
+    ///
+    /// A snapshot older than the stored one is refused with `Ok(false)`, so a
+    /// delayed or lagging writer cannot roll the room back.
+    fn save_room(&self, code: &str, revision: u64, snapshot: &serde_json::Value) -> Result<bool, String>;
+    /// Load the snapshot of room `code`, or `None` if no instance has saved it.
+    fn load_room(&self, code: &str) -> Result<Option<serde_json::Value>, String>;
+    /// Publish a broadcast message for room `code`, sent at `revision`, to
+    /// every other instance.
+    fn publish(&self, code: &str, revision: u64, msg: &serde_json::Value) -> Result<(), String>;
+    /// Subscribe to the events published by other instances.
+    fn subscribe(&self) -> Result<tokio::sync::mpsc::UnboundedReceiver<RemoteEvent>, String>;
+}
+
+/// Shared state behind every handle of an [`InMemoryRoomBackend`].
+#[derive(Default)]
+struct InMemoryBackendState {
+    rooms: HashMap<String, (u64, serde_json::Value)>,
+    subscribers: Vec<(String, tokio::sync::mpsc::UnboundedSender<RemoteEvent>)>,
+}
+
+/// Process-local [`RoomBackend`].
+///
+/// Each handle obtained from [`InMemoryRoomBackend::handle`] behaves like a
+/// separate server instance sharing the same storage, which makes it a
+/// stand-in for Redis in tests and single-binary deployments.
+pub struct InMemoryRoomBackend {
+    instance_id: String,
+    state: Arc<Mutex<InMemoryBackendState>>,
+}
+
+impl InMemoryRoomBackend {
+    /// Create a new backend with empty storage.
+    pub fn new() -> Self {
+        Self {
+            instance_id: uuid::Uuid::new_v4().to_string(),
+            state: Arc::new(Mutex::new(InMemoryBackendState::default())),
+        }
+    }
+
+    /// Return another handle onto the same storage with its own instance id.
+    pub fn handle(&self) -> Self {
+        Self {
+            instance_id: uuid::Uuid::new_v4().to_string(),
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl Default for InMemoryRoomBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RoomBackend for InMemoryRoomBackend {
+    fn save_room(&self, code: &str, revision: u64, snapshot: &serde_json::Value) -> Result<bool, String> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "internal: lock poisoned".to_string())?;
+        if state.rooms.get(code).is_some_and(|(stored, _)| *stored > revision) {
+            return Ok(false);
+        }
+        state.rooms.insert(code.to_string(), (revision, snapshot.clone()));
+        Ok(true)
+    }
+
+    fn load_room(&self, code: &str) -> Result<Option<serde_json::Value>, String> {
+        let state = self
+            .state
+            .lock()
+            .map_err(|_| "internal: lock poisoned".to_string())?;
+        Ok(state.rooms.get(code).map(|(_, snapshot)| snapshot.clone()))
+    }
+
+    fn publish(&self, code: &str, revision: u64, msg: &serde_json::Value) -> Result<(), String> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "internal: lock poisoned".to_string())?;
+        let event = RemoteEvent {
+            code: code.to_string(),
+            revision,
+            msg: msg.clone(),
+        };
+        state
+            .subscribers
+            .retain(|(origin, tx)| origin == &self.instance_id || tx.send(event.clone()).is_ok());
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<tokio::sync::mpsc::UnboundedReceiver<RemoteEvent>, String> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "internal: lock poisoned".to_string())?;
+        state.subscribers.push((self.instance_id.clone(), tx));
+        Ok(rx)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -138,7 +670,24 @@ pub struct Room {
 
 /// Create a new empty RoomStore.
 pub fn new_room_store() -> RoomStore {
-    Arc::new(Mutex::new(HashMap::new()))
+    RoomStore {
+        rooms: Arc::new(Mutex::new(HashMap::new())),
+        sync: None,
+        transform_options: Default::default(),
+    }
+}
+
+/// Create a new empty RoomStore whose rooms are mirrored to `backend`.
+///
+/// Every room created in it writes its state through to the backend and
+/// publishes its broadcasts to other instances sharing the backend; rooms
+/// those instances created are loaded on demand with [`hydrate_room`].
+pub fn new_room_store_with_backend(backend: Arc<dyn RoomBackend>) -> RoomStore {
+    RoomStore {
+        rooms: Arc::new(Mutex::new(HashMap::new())),
+        sync: Some(RoomSync::start(backend)),
+        transform_options: Default::default(),
+    }
 }
 
 /// Generate a memorable uppercase room code in the format `ADJ-NOUN-NN`.
@@ -156,6 +705,17 @@ pub fn generate_code() -> String {
     format!("{}-{}-{}", adj, noun, num)
 }
 
+/// Generate a friendly participant name in the form `AdjectiveAnimal`.
+///
+/// Example: `CalmOtter`
+pub fn generate_participant_name() -> String {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let adj = NAME_ADJECTIVES[rng.gen_range(0..NAME_ADJECTIVES.len())];
+    let animal = NAME_ANIMALS[rng.gen_range(0..NAME_ANIMALS.len())];
+    format!("{}{}", adj, animal)
+}
+
 /// Truncate `s` to at most `max_bytes` bytes while respecting UTF-8 char boundaries.
 ///
 /// Slicing a `&str` at an arbitrary byte offset panics when that offset falls
@@ -172,6 +732,34 @@ fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
     &s[..end]
 }
 
+/// Clean a client-supplied display name for `set_name`.
+///
+/// Drops `<...>` tags and any stray angle brackets, removes control
+/// characters, collapses whitespace and keeps at most `max_chars` characters.
+/// Returns `None` when nothing printable is left, so the participant keeps
+/// their current name.
+pub fn sanitize_participant_name(raw: &str, max_chars: usize) -> Option<String> {
+    let mut stripped = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(open) = rest.find('<') {
+        stripped.push_str(&rest[..open]);
+        rest = match rest[open..].find('>') {
+            Some(close) => &rest[open + close + 1..],
+            None => &rest[open + 1..],
+        };
+    }
+    stripped.push_str(rest);
+    let cleaned = stripped
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('>', "");
+    let name: String = cleaned.trim().chars().take(max_chars).collect();
+    let name = name.trim_end();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
 /// Current Unix epoch in milliseconds.
 pub fn now_ms() -> u64 {
     SystemTime::now()
@@ -186,15 +774,55 @@ pub fn now_ms() -> u64 {
 
 /// Create a new room in the store, returning its 6-char code.
 pub fn create_room(store: &RoomStore) -> String {
+    let mut room = new_room();
+    room.persist(store.sync.as_ref());
+    let code = room.code.clone();
+    if let Ok(mut guard) = store.lock() {
+        guard.insert(code.clone(), room);
+    }
+    code
+}
+
+/// Create a room, or return the existing one if a room was created with the
+/// same `key` within [`ROOM_IDEMPOTENCY_TTL_MS`].
+///
+/// Lets a client retry or double-submit `/room/create` without orphaning a
+/// second room.  With `key == None` this is [`create_room`].
+pub fn create_room_idempotent(store: &RoomStore, key: Option<&str>) -> String {
+    let Some(key) = key else {
+        return create_room(store);
+    };
+    // Hold the lock across lookup and insert so two concurrent requests with
+    // the same key cannot both miss.  A poisoned lock still holds the rooms,
+    // so recover it rather than minting a duplicate for a known key.
+    let mut guard = store.lock().unwrap_or_else(|e| e.into_inner());
+    let now = now_ms();
+    if let Some(room) = guard.values().find(|r| {
+        r.idempotency_key.as_deref() == Some(key)
+            && now.saturating_sub(r.created_at_ms) < ROOM_IDEMPOTENCY_TTL_MS
+    }) {
+        return room.code.clone();
+    }
+    let mut room = new_room();
+    room.idempotency_key = Some(key.to_string());
+    room.persist(store.sync.as_ref());
+    let code = room.code.clone();
+    guard.insert(code.clone(), room);
+    code
+}
+
+/// A fresh, empty room with a newly generated code.
+fn new_room() -> Room {
     let (tx, _rx) = tokio::sync::broadcast::channel(256);
     let code = generate_code();
-    let room = Room {
-        code: code.clone(),
+    Room {
+        code,
         host_id: String::new(),
         participants: Vec::new(),
         token_count: 0,
         surgery_log: Vec::new(),
         chat_log: Vec::new(),
+        annotations: Vec::new(),
         votes: HashMap::new(),
         is_recording: false,
         recording_start_ms: None,
@@ -205,11 +833,199 @@ pub fn create_room(store: &RoomStore) -> String {
         broadcast_tx: tx,
         active_ws_count: 0,
         last_ws_disconnect_ms: None,
+        locked_transform: None,
+        joins_locked: false,
+        session_tokens: std::collections::VecDeque::new(),
+        paused: false,
+        held_tokens: std::collections::VecDeque::new(),
+        client_colors: HashMap::new(),
+        client_participants: HashMap::new(),
+        away_since: HashMap::new(),
+        departures: 0,
+        idempotency_key: None,
+        audit_log: std::collections::VecDeque::new(),
+        host_token: uuid::Uuid::new_v4().to_string(),
+        revision: 0,
+    }
+}
+
+/// Make room `code` available locally, loading it from the store's backend
+/// if another instance created it.
+///
+/// Returns `true` if the room exists in `store` after the call.
+pub fn hydrate_room(store: &RoomStore, code: &str) -> bool {
+    if room_exists(store, code) {
+        return true;
+    }
+    let Some(backend) = store.backend() else {
+        return false;
+    };
+    let snapshot = match backend.load_room(code) {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return false,
+        Err(e) => {
+            tracing::warn!(room_code = %code, err = %e, "room backend load failed");
+            return false;
+        }
+    };
+    let field = |name: &str| snapshot.get(name).cloned().unwrap_or(serde_json::Value::Null);
+    let (tx, _rx) = tokio::sync::broadcast::channel(256);
+    let room = Room {
+        code: code.to_string(),
+        host_id: serde_json::from_value(field("host_id")).unwrap_or_default(),
+        participants: serde_json::from_value(field("participants")).unwrap_or_default(),
+        token_count: serde_json::from_value(field("token_count")).unwrap_or_default(),
+        surgery_log: serde_json::from_value(field("surgery_log")).unwrap_or_default(),
+        chat_log: serde_json::from_value(field("chat_log")).unwrap_or_default(),
+        annotations: serde_json::from_value(field("annotations")).unwrap_or_default(),
+        votes: serde_json::from_value(field("votes")).unwrap_or_default(),
+        is_recording: false,
+        recording_start_ms: None,
+        recorded_events: Vec::new(),
+        created_at_ms: serde_json::from_value(field("created_at_ms")).unwrap_or_else(|_| now_ms()),
+        last_activity_ms: now_ms(),
+        recording_cap: DEFAULT_RECORDING_CAP,
+        broadcast_tx: tx,
+        active_ws_count: 0,
+        last_ws_disconnect_ms: None,
+        locked_transform: serde_json::from_value(field("locked_transform")).unwrap_or_default(),
+        joins_locked: serde_json::from_value(field("joins_locked")).unwrap_or_default(),
+        session_tokens: std::collections::VecDeque::new(),
+        paused: serde_json::from_value(field("paused")).unwrap_or_default(),
+        held_tokens: std::collections::VecDeque::new(),
+        client_colors: serde_json::from_value(field("client_colors")).unwrap_or_default(),
+        client_participants: HashMap::new(),
+        away_since: HashMap::new(),
+        departures: 0,
+        idempotency_key: None,
+        audit_log: std::collections::VecDeque::new(),
+        host_token: String::new(),
+        revision: serde_json::from_value(field("revision")).unwrap_or_default(),
+    };
+    match store.lock() {
+        Ok(mut guard) => {
+            guard.entry(code.to_string()).or_insert(room);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Subscribe to the store's backend and apply every event other instances
+/// publish with [`deliver_remote`], from a spawned task.
+///
+/// A no-op for a store without a backend.  Must be called from within a
+/// tokio runtime.
+pub fn spawn_remote_relay(store: &RoomStore) -> Result<(), String> {
+    let Some(backend) = store.backend() else {
+        return Ok(());
     };
+    let mut remote_rx = backend.subscribe()?;
+    let store = store.clone();
+    tokio::spawn(async move {
+        while let Some(event) = remote_rx.recv().await {
+            deliver_remote(&store, event);
+        }
+    });
+    Ok(())
+}
+
+/// Apply a broadcast published by another instance to the local copy of its
+/// room and forward it to local subscribers without re-publishing.
+///
+/// Stream tokens are retained for session search like local ones (the
+/// publishing instance already held them while the room was paused), and a
+/// `session_reset` clears them without reaching clients.  Events that change
+/// room state re-save the snapshot above the sender's revision, so the newest
+/// snapshot in the backend carries every instance's changes.
+///
+/// Rooms that have not been hydrated on this instance are ignored.
+pub fn deliver_remote(store: &RoomStore, event: RemoteEvent) {
+    let RemoteEvent { code, revision, msg } = event;
     if let Ok(mut guard) = store.lock() {
-        guard.insert(code.clone(), room);
+        if let Some(room) = guard.get_mut(&code) {
+            room.revision = room.revision.max(revision);
+            let mut changed = true;
+            match msg.get("type").and_then(|v| v.as_str()) {
+                Some("surgery") => {
+                    if let Some(edit) = msg.get("edit").and_then(|e| SurgeryEdit::deserialize(e).ok()) {
+                        room.record_surgery(edit);
+                    }
+                }
+                Some("chat") => {
+                    if let Some(chat) = msg.get("message").and_then(|m| ChatMessage::deserialize(m).ok()) {
+                        room.chat_log.push(chat);
+                    }
+                }
+                Some("annotation") => {
+                    if let Some(note) = msg.get("annotation").and_then(|a| TokenAnnotation::deserialize(a).ok()) {
+                        if room.annotations.len() < MAX_ROOM_ANNOTATIONS {
+                            room.annotations.push(note);
+                        }
+                    }
+                }
+                Some("participant_join") => {
+                    if let Some(p) = msg.get("participant").and_then(|p| Participant::deserialize(p).ok()) {
+                        if !room.participants.iter().any(|q| q.id == p.id) {
+                            room.participants.push(p);
+                        }
+                    }
+                }
+                Some("participant_update") => {
+                    if let Some(p) = msg.get("participant").and_then(|p| Participant::deserialize(p).ok()) {
+                        if let Some(existing) = room.participants.iter_mut().find(|q| q.id == p.id) {
+                            *existing = p;
+                        }
+                    }
+                }
+                Some("participant_leave") => {
+                    if let Some(id) = msg.get("participant_id").and_then(|v| v.as_str()) {
+                        room.participants.retain(|p| p.id != id);
+                    }
+                }
+                Some("room_lock") => {
+                    room.joins_locked = msg.get("locked").and_then(|v| v.as_bool()).unwrap_or(false);
+                }
+                Some("stream_pause") => {
+                    room.paused = msg.get("paused").and_then(|v| v.as_bool()).unwrap_or(false);
+                }
+                Some("transform_lock") => {
+                    room.locked_transform = msg
+                        .get("transform")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string);
+                }
+                Some("set_transform") => {
+                    if let Some(tokens) = msg.get("tokens").and_then(|v| v.as_array()) {
+                        room.session_tokens = tokens.iter().cloned().collect();
+                    }
+                }
+                Some("vote_update") => {
+                    let transform = msg.get("transform").and_then(|v| v.as_str());
+                    let up = msg.get("up").and_then(|v| v.as_u64());
+                    let down = msg.get("down").and_then(|v| v.as_u64());
+                    if let (Some(t), Some(up), Some(down)) = (transform, up, down) {
+                        room.votes.insert(t.to_string(), (up as u32, down as u32));
+                    }
+                }
+                Some("session_reset") => {
+                    room.session_tokens.clear();
+                    room.held_tokens.clear();
+                    return;
+                }
+                None if msg.get("index").is_some() && msg["is_error"] != true => {
+                    room.retain_session_token(msg.clone());
+                    changed = false;
+                }
+                _ => changed = false,
+            }
+            room.last_activity_ms = now_ms();
+            if changed {
+                room.persist(store.sync.as_ref());
+            }
+            let _ = room.broadcast_tx.send(msg);
+        }
     }
-    code
 }
 
 /// Add a participant to a room.
@@ -226,26 +1042,82 @@ pub fn join_room(
         Participant,
         tokio::sync::broadcast::Receiver<serde_json::Value>,
     ),
-    String,
+    JoinError,
 > {
-    let mut guard = store
-        .lock()
-        .map_err(|_| "internal: lock poisoned".to_string())?;
+    join_room_as(store, code, name, is_host, None)
+}
+
+/// Join a room like [`join_room`], keying color assignment off `client_id`.
+///
+/// The first join with a given identifier gets the first free palette color
+/// (see [`next_free_color`]); later joins with the same identifier (e.g. after a reconnect) get the same color
+/// for the rest of the room's lifetime.  An empty `name` is replaced by a
+/// [`generate_participant_name`] not already used in the room.
+pub fn join_room_as(
+    store: &RoomStore,
+    code: &str,
+    name: &str,
+    is_host: bool,
+    client_id: Option<&str>,
+) -> Result<
+    (
+        Participant,
+        tokio::sync::broadcast::Receiver<serde_json::Value>,
+    ),
+    JoinError,
+> {
+    let mut guard = store.lock().map_err(|_| JoinError::Internal)?;
 
     let room = guard
         .get_mut(code)
-        .ok_or_else(|| format!("Room '{}' not found", code))?;
+        .ok_or_else(|| JoinError::NotFound(code.to_string()))?;
+
+    if room.participants.len() >= MAX_ROOM_PARTICIPANTS {
+        return Err(JoinError::Full);
+    }
+    if room.joins_locked && !is_host {
+        return Err(JoinError::Locked);
+    }
+
+    let next_color = || next_free_color(room);
+    let color = match client_id {
+        Some(id) => match room.client_colors.get(id) {
+            Some(color) => color.clone(),
+            None => {
+                let color = next_color();
+                room.client_colors.insert(id.to_string(), color.clone());
+                color
+            }
+        },
+        None => next_color(),
+    };
 
-    let color_idx = room.participants.len() % PARTICIPANT_COLORS.len();
-    let color = PARTICIPANT_COLORS[color_idx].to_string();
+    let name = if name.is_empty() {
+        // A handful of retries keeps names unique in practice; a clash after
+        // that is harmless since participants are keyed by id.
+        let mut generated = generate_participant_name();
+        for _ in 0..8 {
+            if !room.participants.iter().any(|p| p.name == generated) {
+                break;
+            }
+            generated = generate_participant_name();
+        }
+        generated
+    } else {
+        name.to_string()
+    };
 
     let participant = Participant {
         id: uuid::Uuid::new_v4().to_string(),
-        name: name.to_string(),
+        name,
         color,
         joined_at_ms: now_ms(),
         is_host,
+        away: false,
     };
+    if let Some(id) = client_id {
+        room.client_participants.insert(id.to_string(), participant.id.clone());
+    }
 
     if is_host && room.host_id.is_empty() {
         room.host_id = participant.id.clone();
@@ -253,12 +1125,132 @@ pub fn join_room(
 
     let rx = room.broadcast_tx.subscribe();
     room.participants.push(participant.clone());
+    room.audit(
+        "join",
+        Some(&participant.id),
+        serde_json::json!({"name": participant.name, "is_host": is_host}),
+    );
+    room.persist(store.sync.as_ref());
 
     Ok((participant, rx))
 }
 
-/// Remove a participant from a room.
-///
+/// The first palette color neither worn by a current participant nor kept for
+/// a client identifier that may reconnect; once the palette is exhausted,
+/// the first color no current participant wears, then round-robin.
+fn next_free_color(room: &Room) -> String {
+    let worn = |c: &&str| room.participants.iter().any(|p| p.color == *c);
+    let kept = |c: &&str| room.client_colors.values().any(|k| k == c);
+    PARTICIPANT_COLORS
+        .iter()
+        .find(|c| !worn(c) && !kept(c))
+        .or_else(|| PARTICIPANT_COLORS.iter().find(|c| !worn(c)))
+        .unwrap_or(&PARTICIPANT_COLORS[room.participants.len() % PARTICIPANT_COLORS.len()])
+        .to_string()
+}
+
+/// Resume the away participant that connected with `client_id`, if it is
+/// still within its reconnection grace period (see [`mark_away`]).
+///
+/// Returns the participant (no longer away) and a fresh broadcast receiver,
+/// or `None` if there is no such participant and the caller should join
+/// normally.
+pub fn rejoin_room(
+    store: &RoomStore,
+    code: &str,
+    client_id: &str,
+) -> Option<(Participant, tokio::sync::broadcast::Receiver<serde_json::Value>)> {
+    let mut guard = store.lock().ok()?;
+    let room = guard.get_mut(code)?;
+    let id = room.client_participants.get(client_id)?.clone();
+    room.away_since.remove(&id)?;
+    let participant = room.participants.iter_mut().find(|p| p.id == id)?;
+    participant.away = false;
+    let participant = participant.clone();
+    room.audit("reconnect", Some(&id), serde_json::json!({}));
+    room.persist(store.sync.as_ref());
+    Some((participant, room.broadcast_tx.subscribe()))
+}
+
+/// Mark a disconnected participant as away, keeping their seat for the
+/// reconnection grace period.
+///
+/// Returns the updated participant and a departure number to pass to
+/// [`leave_if_still_away`] once the grace period ends, or `None` if the
+/// participant is not in the room.
+pub fn mark_away(store: &RoomStore, code: &str, participant_id: &str) -> Option<(Participant, u64)> {
+    let mut guard = store.lock().ok()?;
+    let room = guard.get_mut(code)?;
+    let participant = room.participants.iter_mut().find(|p| p.id == participant_id)?;
+    participant.away = true;
+    let participant = participant.clone();
+    room.departures += 1;
+    let departure = room.departures;
+    room.away_since.insert(participant_id.to_string(), departure);
+    room.persist(store.sync.as_ref());
+    Some((participant, departure))
+}
+
+/// Remove a participant whose grace period has ended, unless they reconnected
+/// (or reconnected and left again) since departure number `departure`.
+///
+/// Returns `true` if the participant was removed.
+pub fn leave_if_still_away(store: &RoomStore, code: &str, participant_id: &str, departure: u64) -> bool {
+    {
+        let Ok(mut guard) = store.lock() else {
+            return false;
+        };
+        let Some(room) = guard.get_mut(code) else {
+            return false;
+        };
+        if room.away_since.get(participant_id) != Some(&departure) {
+            return false;
+        }
+        room.away_since.remove(participant_id);
+        room.client_participants.retain(|_, id| id != participant_id);
+    }
+    leave_room(store, code, participant_id).is_some()
+}
+
+/// Handle a closed WebSocket for `participant`.
+///
+/// With a stable client identifier and a non-zero `grace`, the participant is
+/// shown as away (`participant_update`) and only removed, with
+/// `participant_leave`, if they have not reconnected when `grace` elapses.
+/// Otherwise they leave immediately.
+pub async fn depart(
+    store: RoomStore,
+    code: String,
+    participant: Participant,
+    reconnectable: bool,
+    grace: std::time::Duration,
+) {
+    let leave_msg = serde_json::json!({
+        "type": "participant_leave",
+        "participant_id": participant.id,
+        "name": participant.name,
+    });
+    if !reconnectable || grace.is_zero() {
+        if leave_room(&store, &code, &participant.id).is_some() {
+            broadcast(&store, &code, leave_msg);
+        }
+        return;
+    }
+    let Some((away, departure)) = mark_away(&store, &code, &participant.id) else {
+        return;
+    };
+    broadcast(&store, &code, serde_json::json!({"type": "participant_update", "participant": away}));
+    tokio::time::sleep(grace).await;
+    if leave_if_still_away(&store, &code, &participant.id, departure) {
+        broadcast(&store, &code, leave_msg);
+    }
+}
+
+/// Remove a participant from a room.
+///
+/// When the host leaves a paused room nobody is left to resume it, so the
+/// room resumes and flushes its held tokens.
+///
 /// Returns the room's broadcast sender (so the caller can broadcast the leave
 /// event) or `None` if the room or participant was not found.
 pub fn leave_room(
@@ -268,7 +1260,16 @@ pub fn leave_room(
 ) -> Option<tokio::sync::broadcast::Sender<serde_json::Value>> {
     let mut guard = store.lock().ok()?;
     let room = guard.get_mut(code)?;
+    let before = room.participants.len();
     room.participants.retain(|p| p.id != participant_id);
+    if room.participants.len() != before {
+        room.audit("leave", Some(participant_id), serde_json::json!({}));
+        if room.paused && participant_id == room.host_id {
+            room.audit("pause", Some(participant_id), serde_json::json!({"paused": false}));
+            room.resume(store.sync.as_ref());
+        }
+    }
+    room.persist(store.sync.as_ref());
     Some(room.broadcast_tx.clone())
 }
 
@@ -277,18 +1278,53 @@ pub fn leave_room(
 ///
 /// Silently does nothing if the room does not exist or the store lock is
 /// poisoned.  Lagging receivers that have fallen behind will have their
-/// oldest unread messages overwritten (tokio broadcast semantics).
+/// oldest unread messages overwritten (tokio broadcast semantics).  Rooms
+/// with a backend also publish the message to other instances.
 pub fn broadcast(store: &RoomStore, code: &str, msg: serde_json::Value) {
     if let Ok(guard) = store.lock() {
         if let Some(room) = guard.get(code) {
-            if let Err(_) = room.broadcast_tx.send(msg) {
+            if !room.fan_out(store.sync.as_ref(), msg) {
                 tracing::debug!(room_code = %code, "broadcast dropped: no active subscribers");
             }
         }
     }
 }
 
+/// Minimum spacing between cursor messages relayed for one connection;
+/// faster hover updates are dropped.
+pub const CURSOR_MIN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Relay a participant's hovered token (`None` when the pointer left the
+/// stream) to the room, tagged with their color and name.
+///
+/// Cursors are ephemeral: nothing is stored on the room, persisted, or
+/// captured by an active recording.  The sender's own connection skips the
+/// echo (see [`is_own_cursor`]).
+pub fn broadcast_cursor(store: &RoomStore, code: &str, participant_id: &str, token_index: Option<usize>) {
+    let (color, name) = get_participant_info(store, code, participant_id);
+    broadcast(
+        store,
+        code,
+        serde_json::json!({
+            "type": "cursor",
+            "participant_id": participant_id,
+            "color": color,
+            "name": name,
+            "token_index": token_index,
+        }),
+    );
+}
+
+/// True when `msg` is a cursor broadcast originating from `participant_id`.
+pub fn is_own_cursor(msg: &serde_json::Value, participant_id: &str) -> bool {
+    msg.get("type").and_then(|t| t.as_str()) == Some("cursor")
+        && msg.get("participant_id").and_then(|p| p.as_str()) == Some(participant_id)
+}
+
 /// Record and broadcast a surgery edit.
+///
+/// `edit.token_index` is the token's logical stream index (its `index` field),
+/// which is what both the retained session tokens and clients match on.
 pub fn apply_surgery(store: &RoomStore, code: &str, edit: SurgeryEdit) {
     if let Ok(mut guard) = store.lock() {
         if let Some(room) = guard.get_mut(code) {
@@ -296,9 +1332,19 @@ pub fn apply_surgery(store: &RoomStore, code: &str, edit: SurgeryEdit) {
                 "type": "surgery",
                 "edit": edit,
             });
-            room.surgery_log.push(edit);
+            room.audit(
+                "surgery",
+                Some(&edit.editor_id),
+                serde_json::json!({
+                    "token_index": edit.token_index,
+                    "old_text": edit.old_text,
+                    "new_text": edit.new_text,
+                }),
+            );
+            room.record_surgery(edit);
             room.last_activity_ms = now_ms();
-            let _ = room.broadcast_tx.send(msg);
+            room.persist(store.sync.as_ref());
+            room.fan_out(store.sync.as_ref(), msg);
         }
     }
 }
@@ -311,18 +1357,65 @@ pub fn add_chat(store: &RoomStore, code: &str, msg: ChatMessage) {
                 "type": "chat",
                 "message": msg,
             });
+            room.audit(
+                "chat",
+                Some(&msg.author_id),
+                serde_json::json!({"text": msg.text, "token_index": msg.token_index}),
+            );
             room.chat_log.push(msg);
             room.last_activity_ms = now_ms();
-            let _ = room.broadcast_tx.send(broadcast_msg);
+            room.persist(store.sync.as_ref());
+            room.fan_out(store.sync.as_ref(), broadcast_msg);
         }
     }
 }
 
+/// Record and broadcast a token annotation as an `annotation` event.
+///
+/// Returns `false` without broadcasting if the room does not exist or already
+/// holds [`MAX_ROOM_ANNOTATIONS`] annotations.
+pub fn add_annotation(store: &RoomStore, code: &str, annotation: TokenAnnotation) -> bool {
+    let Ok(mut guard) = store.lock() else {
+        return false;
+    };
+    let Some(room) = guard.get_mut(code) else {
+        return false;
+    };
+    if room.annotations.len() >= MAX_ROOM_ANNOTATIONS {
+        return false;
+    }
+    let msg = serde_json::json!({
+        "type": "annotation",
+        "annotation": annotation,
+    });
+    room.audit(
+        "annotate",
+        Some(&annotation.author_id),
+        serde_json::json!({"token_index": annotation.token_index, "note": annotation.note}),
+    );
+    room.annotations.push(annotation);
+    room.last_activity_ms = now_ms();
+    room.persist(store.sync.as_ref());
+    room.fan_out(store.sync.as_ref(), msg);
+    true
+}
+
 /// Cast a vote for a transform.
 ///
 /// `dir` must be `"up"` or `"down"`. Returns the updated `(up, down)` counts,
 /// or `None` if the room was not found.
 pub fn vote(store: &RoomStore, code: &str, transform: &str, dir: &str) -> Option<(u32, u32)> {
+    vote_as(store, code, transform, dir, None)
+}
+
+/// Cast a vote like [`vote`], attributing it to `voter_id` in the audit log.
+pub fn vote_as(
+    store: &RoomStore,
+    code: &str,
+    transform: &str,
+    dir: &str,
+    voter_id: Option<&str>,
+) -> Option<(u32, u32)> {
     let mut guard = store.lock().ok()?;
     let room = guard.get_mut(code)?;
     let entry = room.votes.entry(transform.to_string()).or_insert((0, 0));
@@ -331,30 +1424,316 @@ pub fn vote(store: &RoomStore, code: &str, transform: &str, dir: &str) -> Option
         "down" => entry.1 = entry.1.saturating_add(1),
         _ => {}
     }
+    let counts = *entry;
+    room.audit("vote", voter_id, serde_json::json!({"transform": transform, "dir": dir}));
     room.last_activity_ms = now_ms();
-    Some(*entry)
+    room.persist(store.sync.as_ref());
+    Some(counts)
 }
 
 /// Snapshot the room state as a JSON value.
 pub fn room_state_snapshot(store: &RoomStore, code: &str) -> serde_json::Value {
     if let Ok(guard) = store.lock() {
         if let Some(room) = guard.get(code) {
-            return serde_json::json!({
-                "code": room.code,
-                "host_id": room.host_id,
-                "participants": room.participants,
-                "token_count": room.token_count,
-                "surgery_log": room.surgery_log,
-                "chat_log": room.chat_log,
-                "votes": room.votes,
-                "is_recording": room.is_recording,
-                "created_at_ms": room.created_at_ms,
-            });
+            return snapshot_of(room);
         }
     }
     serde_json::Value::Null
 }
 
+/// Build the JSON state snapshot shared by [`room_state_snapshot`] and backends.
+fn snapshot_of(room: &Room) -> serde_json::Value {
+    serde_json::json!({
+        "code": room.code,
+        "host_id": room.host_id,
+        "participants": room.participants,
+        "token_count": room.token_count,
+        "surgery_log": room.surgery_log,
+        "chat_log": room.chat_log,
+        "annotations": room.annotations,
+        "votes": room.votes,
+        "is_recording": room.is_recording,
+        "created_at_ms": room.created_at_ms,
+        "locked_transform": room.locked_transform,
+        "joins_locked": room.joins_locked,
+        "paused": room.paused,
+    })
+}
+
+/// Lock the transform used by every stream in room `code`, or clear the lock
+/// with `None`, and broadcast a `transform_lock` event.
+///
+/// Returns `false` if the room does not exist.
+pub fn set_transform_lock(store: &RoomStore, code: &str, transform: Option<String>) -> bool {
+    if let Ok(mut guard) = store.lock() {
+        if let Some(room) = guard.get_mut(code) {
+            room.locked_transform = transform.clone();
+            let host = room.host_actor();
+            room.audit("transform_lock", host.as_deref(), serde_json::json!({"transform": transform}));
+            room.last_activity_ms = now_ms();
+            room.persist(store.sync.as_ref());
+            room.fan_out(store.sync.as_ref(), serde_json::json!({
+                "type": "transform_lock",
+                "transform": transform,
+            }));
+            return true;
+        }
+    }
+    false
+}
+
+/// Re-render the retained stream of room `code` with `transform` and send the
+/// room a `set_transform` event carrying the re-rendered tokens, so every
+/// participant sees the new transform without another model call.
+///
+/// Returns the re-rendered session tokens, or `None` if the room does not
+/// exist.
+pub fn set_room_transform(
+    store: &RoomStore,
+    code: &str,
+    transform: &crate::transforms::Transform,
+    actor_id: Option<&str>,
+) -> Option<Vec<serde_json::Value>> {
+    let mut guard = store.lock().ok()?;
+    let room = guard.get_mut(code)?;
+    room.retransform(transform, &store.transform_options);
+    let name = transform.name();
+    room.audit("set_transform", actor_id, serde_json::json!({"transform": name}));
+    room.last_activity_ms = now_ms();
+    room.persist(store.sync.as_ref());
+    let tokens: Vec<serde_json::Value> = room.session_tokens.iter().cloned().collect();
+    room.fan_out(store.sync.as_ref(), serde_json::json!({
+        "type": "set_transform",
+        "transform": name,
+        "tokens": tokens,
+    }));
+    Some(tokens)
+}
+
+/// Lock or unlock room `code` against new guests and broadcast a `room_lock`
+/// event.  Participants already in the room are unaffected.
+///
+/// Returns `false` if the room does not exist.
+pub fn set_room_locked(store: &RoomStore, code: &str, locked: bool) -> bool {
+    if let Ok(mut guard) = store.lock() {
+        if let Some(room) = guard.get_mut(code) {
+            room.joins_locked = locked;
+            let host = room.host_actor();
+            room.audit("room_lock", host.as_deref(), serde_json::json!({"locked": locked}));
+            room.last_activity_ms = now_ms();
+            room.persist(store.sync.as_ref());
+            room.fan_out(store.sync.as_ref(), serde_json::json!({"type": "room_lock", "locked": locked}));
+            return true;
+        }
+    }
+    false
+}
+
+/// Pause or resume token fan-out in room `code` and broadcast a
+/// `stream_pause` event so clients can show a "paused by host" indicator.
+///
+/// While paused, [`broadcast_token`] holds tokens instead of sending them;
+/// resuming flushes the held tokens in their original order right after the
+/// `stream_pause` event.  Returns `false` if the room does not exist.
+pub fn set_room_paused(store: &RoomStore, code: &str, paused: bool) -> bool {
+    if let Ok(mut guard) = store.lock() {
+        if let Some(room) = guard.get_mut(code) {
+            room.paused = paused;
+            let host = room.host_actor();
+            room.audit("pause", host.as_deref(), serde_json::json!({"paused": paused}));
+            room.last_activity_ms = now_ms();
+            room.persist(store.sync.as_ref());
+            if paused {
+                room.fan_out(store.sync.as_ref(), serde_json::json!({"type": "stream_pause", "paused": true}));
+            } else {
+                room.resume(store.sync.as_ref());
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// Broadcast a stream token to room `code`, or hold it until the host resumes
+/// if the room is paused.  At [`HELD_TOKEN_CAP`] held tokens the oldest is
+/// dropped.
+pub fn broadcast_token(store: &RoomStore, code: &str, token: serde_json::Value) {
+    if let Ok(mut guard) = store.lock() {
+        if let Some(room) = guard.get_mut(code) {
+            if room.paused {
+                if room.held_tokens.len() >= HELD_TOKEN_CAP {
+                    room.held_tokens.pop_front();
+                    tracing::debug!(room_code = %code, "paused room hold queue full; dropped oldest token");
+                }
+                room.held_tokens.push_back(token);
+            } else if !room.fan_out(store.sync.as_ref(), token) {
+                tracing::debug!(room_code = %code, "broadcast dropped: no active subscribers");
+            }
+        }
+    }
+}
+
+/// The transform locked for room `code`, if any.
+pub fn locked_transform(store: &RoomStore, code: &str) -> Option<String> {
+    store
+        .lock()
+        .ok()?
+        .get(code)
+        .and_then(|room| room.locked_transform.clone())
+}
+
+/// Drop the retained session tokens of room `code`, and any tokens held
+/// while paused (called when a new stream starts).  Other instances are told
+/// to do the same with a `session_reset` event.
+pub fn clear_session_tokens(store: &RoomStore, code: &str) {
+    if let Ok(mut guard) = store.lock() {
+        if let Some(room) = guard.get_mut(code) {
+            room.session_tokens.clear();
+            room.held_tokens.clear();
+            if let Some(sync) = &store.sync {
+                sync.publish(room, serde_json::json!({"type": "session_reset"}));
+            }
+        }
+    }
+}
+
+/// Retain a streamed token for session search, evicting the oldest beyond
+/// the per-room cap.
+pub fn record_session_token(store: &RoomStore, code: &str, token: serde_json::Value) {
+    if let Ok(mut guard) = store.lock() {
+        if let Some(room) = guard.get_mut(code) {
+            room.retain_session_token(token);
+        }
+    }
+}
+
+/// `true` if room `code` exists in `store`.
+pub fn room_exists(store: &RoomStore, code: &str) -> bool {
+    store.lock().map(|guard| guard.contains_key(code)).unwrap_or(false)
+}
+
+/// Copy of the retained session tokens of room `code`, or `None` if the room
+/// does not exist.
+pub fn session_tokens(store: &RoomStore, code: &str) -> Option<Vec<serde_json::Value>> {
+    let guard = store.lock().ok()?;
+    guard
+        .get(code)
+        .map(|room| room.session_tokens.iter().cloned().collect())
+}
+
+/// The host token of room `code`, handed to the room's creator so it can
+/// read the audit log.  `None` if the room does not exist.
+pub fn host_token(store: &RoomStore, code: &str) -> Option<String> {
+    store.lock().ok()?.get(code).map(|room| room.host_token.clone())
+}
+
+/// Copy of the audit log of room `code`, oldest first.
+///
+/// `host_token` must match the token returned by [`host_token`] for the
+/// room; the comparison is constant-time.
+pub fn audit_log(store: &RoomStore, code: &str, host_token: &str) -> Result<Vec<AuditEntry>, AuditError> {
+    let guard = store.lock().map_err(|_| AuditError::NotFound)?;
+    let room = guard.get(code).ok_or(AuditError::NotFound)?;
+    authorize_host(room, host_token)?;
+    Ok(room.audit_log.iter().cloned().collect())
+}
+
+/// Check `host_token` against the room's in constant time.
+fn authorize_host(room: &Room, host_token: &str) -> Result<(), AuditError> {
+    use subtle::ConstantTimeEq;
+    let authorized = !room.host_token.is_empty()
+        && bool::from(room.host_token.as_bytes().ct_eq(host_token.as_bytes()));
+    if authorized {
+        Ok(())
+    } else {
+        Err(AuditError::Forbidden)
+    }
+}
+
+/// A participant's presence in a [`room_archive`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchivedParticipant {
+    pub id: String,
+    pub name: String,
+    /// Known only for participants still in the room.
+    pub color: Option<String>,
+    pub is_host: bool,
+    pub joined_at_ms: u64,
+    /// `None` while the participant is still in the room.
+    pub left_at_ms: Option<u64>,
+}
+
+/// Everything room `code` accumulated, as one JSON document for
+/// `GET /api/room/CODE/archive`: the streamed tokens, the current
+/// recording, surgery history, chat transcript, annotations, vote tallies
+/// and every participant with their join and leave times.
+///
+/// Departed participants come from the audit log, so only those within its
+/// last [`AUDIT_LOG_CAP`] entries appear.  Requires the room's host token,
+/// like [`audit_log`].
+pub fn room_archive(store: &RoomStore, code: &str, host_token: &str) -> Result<serde_json::Value, AuditError> {
+    let guard = store.lock().map_err(|_| AuditError::NotFound)?;
+    let room = guard.get(code).ok_or(AuditError::NotFound)?;
+    authorize_host(room, host_token)?;
+
+    let mut participants: Vec<ArchivedParticipant> = Vec::new();
+    for entry in &room.audit_log {
+        let Some(id) = entry.actor_id.as_deref() else { continue };
+        match entry.event.as_str() {
+            "join" => participants.push(ArchivedParticipant {
+                id: id.to_string(),
+                name: entry.detail.get("name").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                color: None,
+                is_host: entry.detail.get("is_host").and_then(|v| v.as_bool()).unwrap_or(false),
+                joined_at_ms: entry.timestamp_ms,
+                left_at_ms: None,
+            }),
+            "leave" => {
+                if let Some(p) = participants.iter_mut().rev().find(|p| p.id == id && p.left_at_ms.is_none()) {
+                    p.left_at_ms = Some(entry.timestamp_ms);
+                }
+            }
+            _ => {}
+        }
+    }
+    for current in &room.participants {
+        match participants.iter_mut().rev().find(|p| p.id == current.id && p.left_at_ms.is_none()) {
+            Some(p) => {
+                p.name = current.name.clone();
+                p.color = Some(current.color.clone());
+            }
+            None => participants.push(ArchivedParticipant {
+                id: current.id.clone(),
+                name: current.name.clone(),
+                color: Some(current.color.clone()),
+                is_host: current.is_host,
+                joined_at_ms: current.joined_at_ms,
+                left_at_ms: None,
+            }),
+        }
+    }
+
+    let mut votes: Vec<serde_json::Value> = room
+        .votes
+        .iter()
+        .map(|(transform, (up, down))| serde_json::json!({"transform": transform, "up": up, "down": down}))
+        .collect();
+    votes.sort_by(|a, b| a["transform"].as_str().cmp(&b["transform"].as_str()));
+
+    Ok(serde_json::json!({
+        "code": room.code,
+        "created_at_ms": room.created_at_ms,
+        "archived_at_ms": now_ms(),
+        "participants": participants,
+        "tokens": room.session_tokens,
+        "recording": room.recorded_events,
+        "surgery_log": room.surgery_log,
+        "chat_log": room.chat_log,
+        "annotations": room.annotations,
+        "votes": votes,
+    }))
+}
+
 /// Begin recording events in a room.
 pub fn start_recording(store: &RoomStore, code: &str) {
     if let Ok(mut guard) = store.lock() {
@@ -378,6 +1757,23 @@ pub fn stop_recording(store: &RoomStore, code: &str) -> Vec<RecordedEvent> {
     Vec::new()
 }
 
+/// Set how many events room `code` keeps in its recording, dropping the
+/// oldest already recorded beyond the new cap.  A cap of 0 is treated as 1.
+///
+/// Returns `false` if the room does not exist.
+pub fn set_recording_cap(store: &RoomStore, code: &str, cap: usize) -> bool {
+    let Ok(mut guard) = store.lock() else {
+        return false;
+    };
+    let Some(room) = guard.get_mut(code) else {
+        return false;
+    };
+    room.recording_cap = cap.max(1);
+    let excess = room.recorded_events.len().saturating_sub(room.recording_cap);
+    room.recorded_events.drain(..excess);
+    true
+}
+
 /// Append a recorded event to the room's log if recording is active.
 pub fn maybe_record(store: &RoomStore, code: &str, payload: serde_json::Value) {
     if let Ok(mut guard) = store.lock() {
@@ -388,15 +1784,8 @@ pub fn maybe_record(store: &RoomStore, code: &str, payload: serde_json::Value) {
                 room.recorded_events
                     .push(RecordedEvent { offset_ms, payload });
                 // Count dropped events and warn
-                let dropped = if room.recorded_events.len() > room.recording_cap {
-                    let excess = room.recorded_events.len() - room.recording_cap;
-                    for _ in 0..excess {
-                        room.recorded_events.remove(0);
-                    }
-                    excess
-                } else {
-                    0
-                };
+                let dropped = room.recorded_events.len().saturating_sub(room.recording_cap);
+                room.recorded_events.drain(..dropped);
 
                 if dropped > 0 {
                     // Notify all subscribers that recording was truncated
@@ -463,6 +1852,7 @@ pub fn evict_abandoned_rooms(store: &RoomStore) {
                         "token_count": room.token_count,
                         "surgery_log": room.surgery_log,
                         "chat_log": room.chat_log,
+                        "annotations": room.annotations,
                         "votes": room.votes,
                         "created_at_ms": room.created_at_ms,
                         "last_activity_ms": room.last_activity_ms,
@@ -503,29 +1893,50 @@ pub fn evict_abandoned_rooms(store: &RoomStore) {
 /// `store`      — the shared room store
 /// `code`       — the room code
 /// `is_host`    — whether this connection is the room creator
+/// `client_id`  — stable browser identifier used to keep the participant's color across reconnects
+/// `max_name_chars` — longest display name accepted from `set_name` (see [`sanitize_participant_name`])
 pub async fn handle_ws(
     ws_stream: tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
     store: RoomStore,
     code: String,
     is_host: bool,
+    client_id: Option<String>,
+    reconnect_grace: std::time::Duration,
+    max_name_chars: usize,
 ) {
-    let initial_name = if is_host { "Host" } else { "Guest" };
-
-    let (participant, mut room_rx) = match join_room(&store, &code, initial_name, is_host) {
+    // Guests get a generated name; `set_name` can still override it.
+    let initial_name = if is_host { "Host" } else { "" };
+
+    // A client reconnecting within its grace period resumes its old seat
+    // (same id, name, color and host role) instead of joining afresh.
+    let rejoined = client_id.as_deref().and_then(|id| rejoin_room(&store, &code, id));
+    let reconnected = rejoined.is_some();
+    let joined = match rejoined {
+        Some(pair) => Ok(pair),
+        None => join_room_as(&store, &code, initial_name, is_host, client_id.as_deref()),
+    };
+    let (participant, mut room_rx) = match joined {
         Ok(pair) => pair,
         Err(err) => {
-            // Room not found — send error and close.
+            // Reject with an application close code so clients can tell
+            // "not found" from "full" from "locked" without parsing text.
             let (mut sink, _) = ws_stream.split();
-            let err_msg = serde_json::json!({"type": "error", "message": err});
+            let err_msg = serde_json::json!({"type": "error", "message": err.to_string()});
             if let Ok(text) = serde_json::to_string(&err_msg) {
                 let _ = sink.send(WsMessage::Text(text)).await;
             }
+            let frame = CloseFrame {
+                code: CloseCode::from(err.close_code()),
+                reason: err.to_string().into(),
+            };
+            let _ = sink.send(WsMessage::Close(Some(frame))).await;
+            let _ = sink.close().await;
             return;
         }
     };
 
     let participant_id = participant.id.clone();
-    let participant_name = participant.name.clone();
+    let is_host = participant.is_host;
 
     // Track active WebSocket connection count for abandoned-room timeout.
     ws_connect(&store, &code);
@@ -542,12 +1953,13 @@ pub async fn handle_ws(
         let _ = ws_sink.send(WsMessage::Text(text)).await;
     }
 
-    // Notify all OTHER participants about the new joiner.
+    // Notify all OTHER participants about the new joiner, or that an away
+    // participant is back.
     broadcast(
         &store,
         &code,
         serde_json::json!({
-            "type": "participant_join",
+            "type": if reconnected { "participant_update" } else { "participant_join" },
             "participant": participant,
         }),
     );
@@ -556,6 +1968,7 @@ pub async fn handle_ws(
     let mut ping_interval = tokio::time::interval(std::time::Duration::from_secs(30));
     let idle_timeout = std::time::Duration::from_secs(3600);
     let mut last_msg_at = tokio::time::Instant::now();
+    let mut last_cursor_at: Option<tokio::time::Instant> = None;
     loop {
         tokio::select! {
             // Message from this client.
@@ -578,21 +1991,31 @@ pub async fn handle_ws(
 
                         match msg_type.as_str() {
                             "set_name" => {
-                                if let Some(new_name) = parsed.get("name").and_then(|v| v.as_str()) {
-                                    let new_name = truncate_utf8(new_name, 64);
-                                    let updated = update_participant_name(&store, &code, &participant_id, new_name);
-                                    if let Some(p) = updated {
-                                        broadcast(&store, &code, serde_json::json!({
-                                            "type": "participant_update",
-                                            "participant": p,
-                                        }));
+                                let raw = parsed.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                                match sanitize_participant_name(raw, max_name_chars) {
+                                    Some(new_name) => {
+                                        let updated = update_participant_name(&store, &code, &participant_id, &new_name);
+                                        if let Some(p) = updated {
+                                            broadcast(&store, &code, serde_json::json!({
+                                                "type": "participant_update",
+                                                "participant": p,
+                                            }));
+                                        }
+                                    }
+                                    None => {
+                                        if let Ok(s) = serde_json::to_string(&serde_json::json!({
+                                            "type": "error",
+                                            "message": "invalid name",
+                                        })) {
+                                            let _ = ws_sink.send(WsMessage::Text(s)).await;
+                                        }
                                     }
                                 }
                             }
                             "vote" => {
                                 let transform = parsed.get("transform").and_then(|v| v.as_str()).unwrap_or("").to_string();
                                 let dir = parsed.get("dir").and_then(|v| v.as_str()).unwrap_or("up").to_string();
-                                if let Some((up, down)) = vote(&store, &code, &transform, &dir) {
+                                if let Some((up, down)) = vote_as(&store, &code, &transform, &dir, Some(&participant_id)) {
                                     broadcast(&store, &code, serde_json::json!({
                                         "type": "vote_update",
                                         "transform": transform,
@@ -642,6 +2065,49 @@ pub async fn handle_ws(
                                 };
                                 add_chat(&store, &code, chat_msg);
                             }
+                            "annotate" => {
+                                let note = {
+                                    let raw = parsed.get("note").and_then(|v| v.as_str()).unwrap_or("");
+                                    truncate_utf8(raw.trim(), 1024).to_string()
+                                };
+                                let token_index = parsed.get("token_index").and_then(|v| v.as_u64()).map(|n| n as usize);
+                                if let Some(token_index) = token_index.filter(|_| !note.is_empty()) {
+                                    let (author_color, author_name) = get_participant_info(&store, &code, &participant_id);
+                                    let annotation = TokenAnnotation {
+                                        id: uuid::Uuid::new_v4().to_string(),
+                                        token_index,
+                                        note,
+                                        author_id: participant_id.clone(),
+                                        author_name,
+                                        author_color,
+                                        timestamp_ms: now_ms(),
+                                    };
+                                    if !add_annotation(&store, &code, annotation) {
+                                        if let Ok(s) = serde_json::to_string(&serde_json::json!({
+                                            "type": "error",
+                                            "message": format!("annotation limit reached ({})", MAX_ROOM_ANNOTATIONS),
+                                        })) {
+                                            let _ = ws_sink.send(WsMessage::Text(s)).await;
+                                        }
+                                    }
+                                }
+                            }
+                            "pause" | "resume" => {
+                                if is_host {
+                                    set_room_paused(&store, &code, msg_type == "pause");
+                                } else if let Ok(s) = serde_json::to_string(&serde_json::json!({
+                                    "type": "error",
+                                    "message": "only the host can pause the stream",
+                                })) {
+                                    let _ = ws_sink.send(WsMessage::Text(s)).await;
+                                }
+                            }
+                            "lock_room" => {
+                                if is_host {
+                                    let locked = parsed.get("locked").and_then(|v| v.as_bool()).unwrap_or(true);
+                                    set_room_locked(&store, &code, locked);
+                                }
+                            }
                             "record_start" => {
                                 start_recording(&store, &code);
                                 broadcast(&store, &code, serde_json::json!({"type": "record_started"}));
@@ -673,20 +2139,81 @@ pub async fn handle_ws(
                                     let _ = ws_sink.send(WsMessage::Text(done)).await;
                                 }
                             }
+                            // Hover sharing: relayed to peers, never stored or recorded.
+                            "cursor" => {
+                                let due = last_cursor_at.map_or(true, |at| at.elapsed() >= CURSOR_MIN_INTERVAL);
+                                if due {
+                                    last_cursor_at = Some(tokio::time::Instant::now());
+                                    let token_index = parsed.get("token_index").and_then(|v| v.as_u64()).map(|n| n as usize);
+                                    broadcast_cursor(&store, &code, &participant_id, token_index);
+                                }
+                            }
                             "ping" => {
                                 if let Ok(pong) = serde_json::to_string(&serde_json::json!({"type": "pong"})) {
                                     let _ = ws_sink.send(WsMessage::Text(pong)).await;
                                 }
                             }
+                            // Re-sync after a transient disconnect or UI glitch:
+                            // reply with a fresh snapshot to this client only.
+                            "request_state" => {
+                                let reply = serde_json::json!({
+                                    "type": "room_state",
+                                    "room_state": room_state_snapshot(&store, &code),
+                                });
+                                if let Ok(text) = serde_json::to_string(&reply) {
+                                    let _ = ws_sink.send(WsMessage::Text(text)).await;
+                                }
+                            }
                             // Host broadcasts a token event to all guests in the room.
                             "token" => {
                                 if is_host {
                                     // Re-broadcast the full token payload so guests render it.
-                                    broadcast(&store, &code, parsed.clone());
+                                    broadcast_token(&store, &code, parsed.clone());
                                     // Also record if active.
                                     maybe_record(&store, &code, parsed);
                                 }
                             }
+                            "lock_transform" => {
+                                let requested = parsed
+                                    .get("transform")
+                                    .and_then(|v| v.as_str())
+                                    .filter(|t| !t.is_empty());
+                                let err = if !is_host {
+                                    Some("only the host can lock the transform".to_string())
+                                } else if let Some(t) = requested.filter(|t| crate::transforms::Transform::from_str_loose(t).is_err()) {
+                                    Some(format!("unknown transform: {}", truncate_utf8(t, 64)))
+                                } else {
+                                    set_transform_lock(&store, &code, requested.map(str::to_string));
+                                    None
+                                };
+                                if let Some(message) = err {
+                                    let e = serde_json::json!({"type": "error", "message": message});
+                                    if let Ok(s) = serde_json::to_string(&e) {
+                                        let _ = ws_sink.send(WsMessage::Text(s)).await;
+                                    }
+                                }
+                            }
+                            // Any participant re-renders the room's stream with
+                            // another transform, unless the host locked it.
+                            "set_transform" => {
+                                let requested = parsed.get("transform").and_then(|v| v.as_str()).unwrap_or("");
+                                let err = match crate::transforms::Transform::from_str_loose(requested) {
+                                    Err(_) => Some(format!("unknown transform: {}", truncate_utf8(requested, 64))),
+                                    Ok(_) if !is_host && locked_transform(&store, &code).is_some() => {
+                                        Some("the host has locked the transform".to_string())
+                                    }
+                                    Ok(transform) => {
+                                        set_room_transform(&store, &code, &transform, Some(&participant_id));
+                                        None
+                                    }
+                                };
+                                if let Some(message) = err {
+                                    let e = serde_json::json!({"type": "error", "message": message});
+                                    if let Ok(s) = serde_json::to_string(&e) {
+                                        let _ = ws_sink.send(WsMessage::Text(s)).await;
+                                    }
+                                }
+                            }
                             // Host sends this to record a token without broadcasting.
                             "_record_token" => {
                                 if is_host {
@@ -724,6 +2251,7 @@ pub async fn handle_ws(
             // Broadcast message from the room channel.
             bcast = room_rx.recv() => {
                 match bcast {
+                    Ok(msg) if is_own_cursor(&msg, &participant_id) => {}
                     Ok(msg) => {
                         if let Ok(text) = serde_json::to_string(&msg) {
                             if ws_sink.send(WsMessage::Text(text)).await.is_err() {
@@ -769,14 +2297,9 @@ pub async fn handle_ws(
 
     // Client disconnected — clean up and notify others.
     ws_disconnect(&store, &code);
-    if let Some(tx) = leave_room(&store, &code, &participant_id) {
-        let leave_msg = serde_json::json!({
-            "type": "participant_leave",
-            "participant_id": participant_id,
-            "name": participant_name,
-        });
-        let _ = tx.send(leave_msg);
-    }
+    let (_, name) = get_participant_info(&store, &code, &participant_id);
+    let participant = Participant { name, ..participant };
+    depart(store, code, participant, client_id.is_some(), reconnect_grace).await;
 }
 
 // ---------------------------------------------------------------------------
@@ -796,8 +2319,15 @@ fn update_participant_name(
         .participants
         .iter_mut()
         .find(|p| p.id == participant_id)?;
-    p.name = new_name.to_string();
-    Some(p.clone())
+    let old_name = std::mem::replace(&mut p.name, new_name.to_string());
+    let updated = p.clone();
+    room.audit(
+        "rename",
+        Some(participant_id),
+        serde_json::json!({"old_name": old_name, "new_name": new_name}),
+    );
+    room.persist(store.sync.as_ref());
+    Some(updated)
 }
 
 /// Look up a participant's color and name. Returns empty strings if not found.
@@ -832,6 +2362,27 @@ mod tests {
 
     // -- truncate_utf8 -------------------------------------------------------
 
+    #[test]
+    fn test_sanitize_name_truncates_over_long_names() {
+        let long = "A".repeat(100);
+        assert_eq!(sanitize_participant_name(&long, 32).map(|n| n.chars().count()), Some(32));
+        assert_eq!(sanitize_participant_name("Émilie 日本語 🎉 name", 8).as_deref(), Some("Émilie 日"));
+        assert_eq!(sanitize_participant_name("Ann Lee", 4).as_deref(), Some("Ann"));
+    }
+
+    #[test]
+    fn test_sanitize_name_strips_html_and_control_characters() {
+        assert_eq!(
+            sanitize_participant_name("<script>alert(1)</script>Bob", 32).as_deref(),
+            Some("alert(1)Bob")
+        );
+        assert_eq!(sanitize_participant_name("<b>Bold</b> Ann", 32).as_deref(), Some("Bold Ann"));
+        assert_eq!(sanitize_participant_name("a < b > c", 32).as_deref(), Some("a c"));
+        assert_eq!(sanitize_participant_name("Tab\tBell\u{7}\nNew  line", 32).as_deref(), Some("Tab Bell New line"));
+        assert_eq!(sanitize_participant_name("   ", 32), None);
+        assert_eq!(sanitize_participant_name("<i></i>\u{0}", 32), None);
+    }
+
     #[test]
     fn test_truncate_utf8_ascii_unchanged() {
         assert_eq!(truncate_utf8("hello", 64), "hello");
@@ -999,6 +2550,45 @@ mod tests {
         assert_eq!(guard.len(), 3);
     }
 
+    #[test]
+    fn test_create_room_same_idempotency_key_returns_same_code() {
+        let store = new_room_store();
+        let first = create_room_idempotent(&store, Some("key-1"));
+        let second = create_room_idempotent(&store, Some("key-1"));
+        assert_eq!(first, second);
+        assert_eq!(store.lock().unwrap().len(), 1);
+
+        let other = create_room_idempotent(&store, Some("key-2"));
+        let guard = store.lock().unwrap();
+        assert_eq!(guard[&other].idempotency_key.as_deref(), Some("key-2"));
+    }
+
+    #[test]
+    fn test_idempotency_key_survives_poisoned_lock() {
+        let store = new_room_store();
+        let first = create_room_idempotent(&store, Some("key-1"));
+        let poisoner = store.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the room store");
+        })
+        .join();
+        assert!(store.lock().is_err());
+        assert_eq!(create_room_idempotent(&store, Some("key-1")), first);
+        assert_eq!(store.lock().unwrap_or_else(|e| e.into_inner()).len(), 1);
+    }
+
+    #[test]
+    fn test_idempotency_key_expires_after_ttl() {
+        let store = new_room_store();
+        let first = create_room_idempotent(&store, Some("key-1"));
+        store.lock().unwrap().get_mut(&first).unwrap().created_at_ms -= ROOM_IDEMPOTENCY_TTL_MS;
+        let second = create_room_idempotent(&store, Some("key-1"));
+        let guard = store.lock().unwrap();
+        // A fresh room was minted rather than the expired one returned.
+        assert!(now_ms() - guard[&second].created_at_ms < ROOM_IDEMPOTENCY_TTL_MS);
+    }
+
     #[test]
     fn test_create_room_starts_not_recording() {
         let store = new_room_store();
@@ -1079,41 +2669,139 @@ mod tests {
     }
 
     #[test]
-    fn test_join_room_colors_cycle_through_palette() {
+    fn test_join_room_same_client_id_keeps_color() {
         let store = new_room_store();
         let code = create_room(&store);
-        let mut colors = vec![];
-        for i in 0..PARTICIPANT_COLORS.len() {
-            let (p, _) = join_room(&store, &code, &format!("P{}", i), i == 0).unwrap();
-            colors.push(p.color.clone());
-        }
-        // Colors should match the palette in order
-        for (i, color) in colors.iter().enumerate() {
-            assert_eq!(color, PARTICIPANT_COLORS[i]);
+        let (first, _) = join_room_as(&store, &code, "Alice", true, Some("client-a")).unwrap();
+        let (bob, _) = join_room_as(&store, &code, "Bob", false, Some("client-b")).unwrap();
+        leave_room(&store, &code, &first.id);
+        let (carol, _) = join_room(&store, &code, "Carol", false).unwrap();
+        // Carol must not take Bob's color, nor the one kept for Alice.
+        assert_ne!(carol.color, bob.color);
+        assert_ne!(carol.color, first.color);
+        let (again, _) = join_room_as(&store, &code, "Alice", false, Some("client-a")).unwrap();
+        assert_eq!(again.color, first.color);
+        assert_ne!(again.id, first.id);
+    }
+
+    /// Drain `rx` and return the `type` of every message received.
+    fn drain_types(rx: &mut tokio::sync::broadcast::Receiver<serde_json::Value>) -> Vec<String> {
+        let mut types = vec![];
+        while let Ok(msg) = rx.try_recv() {
+            types.push(msg["type"].as_str().unwrap_or("").to_string());
         }
+        types
     }
 
-    #[test]
-    fn test_join_room_error_on_nonexistent_code() {
+    #[tokio::test]
+    async fn test_quick_reconnect_within_grace_sends_no_leave() {
         let store = new_room_store();
-        let result = join_room(&store, "XXXXXX", "Alice", true);
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(
-            err.contains("XXXXXX"),
-            "error should mention the code: {}",
-            err
-        );
+        let code = create_room(&store);
+        let (_, mut observer) = join_room(&store, &code, "Observer", true).unwrap();
+        let (guest, _) = join_room_as(&store, &code, "Guest", false, Some("client-g")).unwrap();
+
+        let grace = std::time::Duration::from_millis(200);
+        let departing = tokio::spawn(depart(store.clone(), code.clone(), guest.clone(), true, grace));
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        {
+            let guard = store.lock().unwrap();
+            let p = guard[&code].participants.iter().find(|p| p.id == guest.id).unwrap();
+            assert!(p.away, "disconnected participant is shown as away");
+        }
+        let (back, _) = rejoin_room(&store, &code, "client-g").expect("resumes the away seat");
+        departing.await.unwrap();
+
+        assert_eq!(back.id, guest.id);
+        assert_eq!(back.color, guest.color);
+        assert!(!back.away);
+        let types = drain_types(&mut observer);
+        assert!(!types.iter().any(|t| t == "participant_leave"), "got {:?}", types);
+        assert!(types.iter().any(|t| t == "participant_update"));
+        assert_eq!(store.lock().unwrap()[&code].participants.len(), 2);
     }
 
-    #[test]
-    fn test_join_room_host_flag_set_correctly() {
+    #[tokio::test]
+    async fn test_no_reconnect_within_grace_sends_leave() {
         let store = new_room_store();
         let code = create_room(&store);
-        let (host, _) = join_room(&store, &code, "Host", true).unwrap();
-        let (guest, _) = join_room(&store, &code, "Guest", false).unwrap();
-        assert!(host.is_host);
-        assert!(!guest.is_host);
+        let (_, mut observer) = join_room(&store, &code, "Observer", true).unwrap();
+        let (guest, _) = join_room_as(&store, &code, "Guest", false, Some("client-g")).unwrap();
+
+        depart(store.clone(), code.clone(), guest, true, std::time::Duration::from_millis(10)).await;
+
+        assert!(drain_types(&mut observer).iter().any(|t| t == "participant_leave"));
+        assert_eq!(store.lock().unwrap()[&code].participants.len(), 1);
+        assert!(rejoin_room(&store, &code, "client-g").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_depart_without_client_id_leaves_immediately() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (_, mut observer) = join_room(&store, &code, "Observer", true).unwrap();
+        let (guest, _) = join_room(&store, &code, "Guest", false).unwrap();
+        depart(store.clone(), code.clone(), guest, false, std::time::Duration::from_secs(60)).await;
+        assert_eq!(drain_types(&mut observer), vec!["participant_leave"]);
+    }
+
+    #[test]
+    fn test_stale_grace_timer_does_not_remove_after_second_disconnect() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (guest, _) = join_room_as(&store, &code, "Guest", false, Some("client-g")).unwrap();
+        let (_, first) = mark_away(&store, &code, &guest.id).unwrap();
+        rejoin_room(&store, &code, "client-g").unwrap();
+        let (_, second) = mark_away(&store, &code, &guest.id).unwrap();
+        assert!(!leave_if_still_away(&store, &code, &guest.id, first));
+        assert!(leave_if_still_away(&store, &code, &guest.id, second));
+    }
+
+    #[test]
+    fn test_join_room_different_client_ids_get_next_colors() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (a, _) = join_room_as(&store, &code, "A", true, Some("client-a")).unwrap();
+        let (b, _) = join_room_as(&store, &code, "B", false, Some("client-b")).unwrap();
+        assert_eq!(a.color, PARTICIPANT_COLORS[0]);
+        assert_eq!(b.color, PARTICIPANT_COLORS[1]);
+    }
+
+    #[test]
+    fn test_join_room_colors_cycle_through_palette() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let mut colors = vec![];
+        for i in 0..PARTICIPANT_COLORS.len() {
+            let (p, _) = join_room(&store, &code, &format!("P{}", i), i == 0).unwrap();
+            colors.push(p.color.clone());
+        }
+        // Colors should match the palette in order
+        for (i, color) in colors.iter().enumerate() {
+            assert_eq!(color, PARTICIPANT_COLORS[i]);
+        }
+    }
+
+    #[test]
+    fn test_join_room_error_on_nonexistent_code() {
+        let store = new_room_store();
+        let result = join_room(&store, "XXXXXX", "Alice", true);
+        assert!(result.is_err());
+        let err = result.unwrap_err().to_string();
+        assert!(
+            err.contains("XXXXXX"),
+            "error should mention the code: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_join_room_host_flag_set_correctly() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (host, _) = join_room(&store, &code, "Host", true).unwrap();
+        let (guest, _) = join_room(&store, &code, "Guest", false).unwrap();
+        assert!(host.is_host);
+        assert!(!guest.is_host);
     }
 
     #[test]
@@ -1372,6 +3060,66 @@ mod tests {
         assert_eq!(guard.get(&code).unwrap().chat_log.len(), 3);
     }
 
+    // -- add_annotation ------------------------------------------------------
+
+    fn annotation(token_index: usize, note: &str) -> TokenAnnotation {
+        TokenAnnotation {
+            id: format!("a{}", token_index),
+            token_index,
+            note: note.to_string(),
+            author_id: "p1".to_string(),
+            author_name: "Alice".to_string(),
+            author_color: "#58a6ff".to_string(),
+            timestamp_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_add_annotation_broadcasts_and_snapshots() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let mut rx = store.lock().unwrap()[&code].broadcast_tx.subscribe();
+        assert!(add_annotation(&store, &code, annotation(4, "hedging word")));
+
+        let msg = rx.try_recv().expect("annotation broadcast");
+        assert_eq!(msg["type"], "annotation");
+        assert_eq!(msg["annotation"]["token_index"], 4);
+        assert_eq!(msg["annotation"]["note"], "hedging word");
+
+        let snap = room_state_snapshot(&store, &code);
+        assert_eq!(snap["annotations"].as_array().map(Vec::len), Some(1));
+        assert_eq!(snap["annotations"][0]["author_name"], "Alice");
+        let audit = audit_log(&store, &code, &host_token(&store, &code).unwrap()).unwrap();
+        assert_eq!(audit.last().map(|e| e.event.as_str()), Some("annotate"));
+    }
+
+    #[test]
+    fn test_add_annotation_capped_and_missing_room() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        for i in 0..MAX_ROOM_ANNOTATIONS {
+            assert!(add_annotation(&store, &code, annotation(i, "n")));
+        }
+        assert!(!add_annotation(&store, &code, annotation(0, "one too many")));
+        assert_eq!(store.lock().unwrap()[&code].annotations.len(), MAX_ROOM_ANNOTATIONS);
+        assert!(!add_annotation(&store, "NOPE", annotation(0, "n")));
+    }
+
+    #[test]
+    fn test_remote_annotation_is_applied() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        deliver_remote(
+            &store,
+            RemoteEvent {
+                code: code.clone(),
+                revision: 1,
+                msg: serde_json::json!({"type": "annotation", "annotation": annotation(2, "from elsewhere")}),
+            },
+        );
+        assert_eq!(store.lock().unwrap()[&code].annotations[0].note, "from elsewhere");
+    }
+
     // -- vote ----------------------------------------------------------------
 
     #[test]
@@ -1665,6 +3413,7 @@ mod tests {
                 token_count: 0,
                 surgery_log: Vec::new(),
                 chat_log: Vec::new(),
+                annotations: Vec::new(),
                 votes: std::collections::HashMap::new(),
                 is_recording: true,
                 recording_start_ms: Some(now_ms()),
@@ -1675,6 +3424,19 @@ mod tests {
                 broadcast_tx: tx,
                 active_ws_count: 0,
                 last_ws_disconnect_ms: None,
+                locked_transform: None,
+                joins_locked: false,
+                paused: false,
+                held_tokens: std::collections::VecDeque::new(),
+                client_colors: HashMap::new(),
+                client_participants: HashMap::new(),
+                away_since: HashMap::new(),
+                departures: 0,
+                idempotency_key: None,
+                audit_log: std::collections::VecDeque::new(),
+                host_token: String::new(),
+                session_tokens: std::collections::VecDeque::new(),
+                revision: 0,
             };
             let _ = room.recording_cap;
             guard.insert(code.clone(), room);
@@ -1720,6 +3482,7 @@ mod tests {
             color: "#58a6ff".to_string(),
             joined_at_ms: 9999,
             is_host: true,
+            away: false,
         };
         let json = serde_json::to_string(&p).unwrap();
         assert!(json.contains("\"id\":\"abc\""));
@@ -1736,6 +3499,7 @@ mod tests {
             color: "#3fb950".to_string(),
             joined_at_ms: 42,
             is_host: false,
+            away: false,
         };
         let json = serde_json::to_string(&p).unwrap();
         let back: Participant = serde_json::from_str(&json).unwrap();
@@ -2161,6 +3925,30 @@ mod tests {
         assert_eq!(room.recorded_events[4].payload["seq"], 9);
     }
 
+    #[test]
+    fn test_set_recording_cap_trims_and_limits_recording() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        start_recording(&store, &code);
+        for i in 0..6 {
+            maybe_record(&store, &code, serde_json::json!({"seq": i}));
+        }
+        assert!(set_recording_cap(&store, &code, 4));
+        let seqs = |store: &RoomStore| -> Vec<i64> {
+            store.lock().unwrap()[&code]
+                .recorded_events
+                .iter()
+                .map(|e| e.payload["seq"].as_i64().unwrap())
+                .collect()
+        };
+        assert_eq!(seqs(&store), vec![2, 3, 4, 5]);
+        for i in 6..9 {
+            maybe_record(&store, &code, serde_json::json!({"seq": i}));
+        }
+        assert_eq!(seqs(&store), vec![5, 6, 7, 8]);
+        assert!(!set_recording_cap(&store, "NOPE", 4));
+    }
+
     #[test]
     fn test_recording_cap_default_is_ten_thousand() {
         let store = new_room_store();
@@ -2298,8 +4086,9 @@ mod tests {
         // The handle_ws match arm falls through to the unknown/warn branch — no panic.
         assert_eq!(msg_type, "unknown_type_xyz");
         // Simulate the match: none of the known types match.
-        let known = ["set_name", "vote", "surgery", "chat", "record_start",
-                     "record_stop", "replay_request", "ping", "token", "_record_token", "stream_done"];
+        let known = ["set_name", "vote", "surgery", "chat", "annotate", "record_start",
+                     "record_stop", "replay_request", "ping", "request_state", "token",
+                     "_record_token", "stream_done"];
         assert!(!known.contains(&msg_type), "unknown_type_xyz should not match any known type");
     }
 
@@ -2317,6 +4106,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_generate_participant_name_distinct_alphanumeric() {
+        let names: std::collections::HashSet<String> =
+            (0..200).map(|_| generate_participant_name()).collect();
+        assert!(names.len() > 50, "expected varied names, got {}", names.len());
+        for name in &names {
+            assert!(!name.is_empty());
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric()), "not alphanumeric: {}", name);
+            assert!(name.starts_with(|c: char| c.is_ascii_uppercase()));
+        }
+    }
+
+    #[test]
+    fn test_join_without_name_gets_generated_name() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (host, _rx1) = join_room(&store, &code, "Host", true).unwrap();
+        let (guest, _rx2) = join_room(&store, &code, "", false).unwrap();
+        assert_eq!(host.name, "Host");
+        assert!(!guest.name.is_empty() && guest.name != "Guest");
+        let guard = store.lock().unwrap();
+        let room = guard.get(&code).unwrap();
+        assert!(room.participants.iter().any(|p| p.id == guest.id && p.name == guest.name));
+    }
+
     #[test]
     fn test_generate_code_all_uppercase() {
         for _ in 0..20 {
@@ -2378,4 +4192,830 @@ mod tests {
         assert_eq!(parsed["type"], "lag_warning");
         assert_eq!(parsed["skipped"], 5);
     }
+
+    // -- join rejection / close codes -----------------------------------------
+
+    #[test]
+    fn test_join_room_full_rejected() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        for i in 0..MAX_ROOM_PARTICIPANTS {
+            join_room(&store, &code, &format!("p{i}"), i == 0).expect("join");
+        }
+        let err = join_room(&store, &code, "late", false).unwrap_err();
+        assert_eq!(err, JoinError::Full);
+    }
+
+    #[test]
+    fn test_join_room_locked_rejects_guest_not_host() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        assert!(set_room_locked(&store, &code, true));
+        assert_eq!(join_room(&store, &code, "Bob", false).unwrap_err(), JoinError::Locked);
+        assert!(join_room(&store, &code, "Host", true).is_ok());
+        set_room_locked(&store, &code, false);
+        assert!(join_room(&store, &code, "Bob", false).is_ok());
+    }
+
+    #[test]
+    fn test_join_error_close_codes() {
+        assert_eq!(JoinError::NotFound("X".into()).close_code(), 4004);
+        assert_eq!(JoinError::Full.close_code(), 4029);
+        assert_eq!(JoinError::Locked.close_code(), 4003);
+        assert_eq!(JoinError::Internal.close_code(), 1011);
+    }
+
+    /// Connect a client WebSocket to `handle_ws` for room `code` over a
+    /// loopback TCP pair and return the close frame the server sends.
+    async fn rejection_close_frame(store: RoomStore, code: &str) -> Option<(u16, String)> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let server_code = code.to_string();
+        let server = tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.expect("accept");
+            let ws = tokio_tungstenite::accept_async(tcp).await.expect("handshake");
+            handle_ws(ws, store, server_code, false, None, std::time::Duration::ZERO, DEFAULT_MAX_NAME_CHARS).await;
+        });
+        let tcp = tokio::net::TcpStream::connect(addr).await.expect("connect");
+        let url = format!("ws://{}/ws/{}", addr, code);
+        let (mut client, _) = tokio_tungstenite::client_async(url, tcp).await.expect("client");
+        let mut close = None;
+        while let Some(Ok(msg)) = client.next().await {
+            if let WsMessage::Close(Some(frame)) = msg {
+                close = Some((u16::from(frame.code), frame.reason.to_string()));
+                break;
+            }
+        }
+        let _ = server.await;
+        close
+    }
+
+    #[tokio::test]
+    async fn test_handle_ws_closes_with_4004_for_missing_room() {
+        let store = new_room_store();
+        let (code, reason) = rejection_close_frame(store, "NOPE-ROOM-00").await.expect("close frame");
+        assert_eq!(code, 4004);
+        assert!(reason.contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_ws_closes_with_4029_for_full_room() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        for i in 0..MAX_ROOM_PARTICIPANTS {
+            join_room(&store, &code, &format!("p{i}"), i == 0).expect("join");
+        }
+        let (close_code, _) = rejection_close_frame(store, &code).await.expect("close frame");
+        assert_eq!(close_code, 4029);
+    }
+
+    #[tokio::test]
+    async fn test_handle_ws_closes_with_4003_for_locked_room() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        set_room_locked(&store, &code, true);
+        let (close_code, reason) = rejection_close_frame(store, &code).await.expect("close frame");
+        assert_eq!(close_code, 4003);
+        assert!(reason.contains("locked"));
+    }
+
+    #[tokio::test]
+    async fn test_request_state_replies_with_snapshot_to_requester_only() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        add_chat(&store, &code, ChatMessage {
+            id: "m1".into(),
+            author_id: "x".into(),
+            author_name: "X".into(),
+            author_color: "#fff".into(),
+            text: "hi".into(),
+            token_index: None,
+            timestamp_ms: 0,
+        });
+        let mut others = store.lock().unwrap()[&code].broadcast_tx.subscribe();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let (server_store, server_code) = (store.clone(), code.clone());
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.expect("accept");
+            let ws = tokio_tungstenite::accept_async(tcp).await.expect("handshake");
+            handle_ws(ws, server_store, server_code, true, None, std::time::Duration::ZERO, DEFAULT_MAX_NAME_CHARS).await;
+        });
+        let tcp = tokio::net::TcpStream::connect(addr).await.expect("connect");
+        let url = format!("ws://{}/ws/{}", addr, code);
+        let (mut client, _) = tokio_tungstenite::client_async(url, tcp).await.expect("client");
+        client
+            .send(WsMessage::Text(r#"{"type":"request_state"}"#.to_string()))
+            .await
+            .expect("send");
+
+        let state = loop {
+            let msg = client.next().await.expect("open").expect("frame");
+            if let WsMessage::Text(text) = msg {
+                let v: serde_json::Value = serde_json::from_str(&text).expect("json");
+                if v["type"] == "room_state" {
+                    break v["room_state"].clone();
+                }
+            }
+        };
+        for field in [
+            "participants", "surgery_log", "chat_log", "votes", "locked_transform",
+            "joins_locked", "paused", "is_recording",
+        ] {
+            assert!(state.get(field).is_some(), "missing {field}: {state}");
+        }
+        assert_eq!(state["participants"].as_array().map(Vec::len), Some(1));
+        assert_eq!(state["chat_log"][0]["text"], "hi");
+
+        // Nothing state-related was fanned out to the rest of the room.
+        while let Ok(msg) = others.try_recv() {
+            assert_ne!(msg["type"], "room_state");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_name_is_sanitized_before_broadcast() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let (server_store, server_code) = (store.clone(), code.clone());
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.expect("accept");
+            let ws = tokio_tungstenite::accept_async(tcp).await.expect("handshake");
+            handle_ws(ws, server_store, server_code, false, None, std::time::Duration::ZERO, 8).await;
+        });
+        let tcp = tokio::net::TcpStream::connect(addr).await.expect("connect");
+        let url = format!("ws://{}/ws/{}", addr, code);
+        let (mut client, _) = tokio_tungstenite::client_async(url, tcp).await.expect("client");
+        client
+            .send(WsMessage::Text(r#"{"type":"set_name","name":"<img src=x onerror=alert(1)>Mallory the Great"}"#.to_string()))
+            .await
+            .expect("send");
+        client
+            .send(WsMessage::Text(r#"{"type":"set_name","name":"<b></b>\u0007"}"#.to_string()))
+            .await
+            .expect("send");
+
+        // The update comes through the room broadcast and the error straight
+        // from the handler, so either may arrive first.
+        let (mut update, mut rejected) = (None, false);
+        while update.is_none() || !rejected {
+            let msg = client.next().await.expect("open").expect("frame");
+            let WsMessage::Text(text) = msg else { continue };
+            let v: serde_json::Value = serde_json::from_str(&text).expect("json");
+            match v["type"].as_str() {
+                Some("participant_update") => update = Some(v),
+                Some("error") => {
+                    assert_eq!(v["message"], "invalid name");
+                    rejected = true;
+                }
+                _ => {}
+            }
+        }
+        let update = update.expect("participant_update broadcast");
+        assert_eq!(update["participant"]["name"], "Mallory");
+        let room_name = store.lock().unwrap()[&code].participants[0].name.clone();
+        assert_eq!(room_name, "Mallory");
+    }
+
+    // -- session tokens -------------------------------------------------------
+
+    #[test]
+    fn test_session_tokens_recorded_and_cleared() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        record_session_token(&store, &code, serde_json::json!({"index": 0}));
+        record_session_token(&store, &code, serde_json::json!({"index": 1}));
+        assert_eq!(session_tokens(&store, &code).map(|t| t.len()), Some(2));
+        clear_session_tokens(&store, &code);
+        assert_eq!(session_tokens(&store, &code).map(|t| t.len()), Some(0));
+    }
+
+    #[test]
+    fn test_session_tokens_capped() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        for i in 0..SESSION_TOKEN_CAP + 5 {
+            record_session_token(&store, &code, serde_json::json!({"index": i}));
+        }
+        let tokens = session_tokens(&store, &code).expect("room");
+        assert_eq!(tokens.len(), SESSION_TOKEN_CAP);
+        assert_eq!(tokens[0]["index"], 5);
+    }
+
+    #[test]
+    fn test_surgery_targets_logical_index_not_buffer_position() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        // The buffer starts at logical index 5, as it would for a session
+        // whose early tokens were evicted or never seen by a late joiner.
+        for i in 5..15 {
+            record_session_token(&store, &code, serde_json::json!({"index": i, "text": format!("t{}", i)}));
+        }
+        let mut late = store.lock().unwrap()[&code].broadcast_tx.subscribe();
+        apply_surgery(
+            &store,
+            &code,
+            SurgeryEdit {
+                token_index: 7,
+                new_text: "edited".to_string(),
+                old_text: "t7".to_string(),
+                editor_id: "p1".to_string(),
+                editor_color: "#3fb950".to_string(),
+                editor_name: "Bob".to_string(),
+                timestamp_ms: 0,
+            },
+        );
+        let tokens = session_tokens(&store, &code).expect("room");
+        let edited: Vec<u64> = tokens
+            .iter()
+            .filter(|t| t["text"] == "edited")
+            .filter_map(|t| t["index"].as_u64())
+            .collect();
+        assert_eq!(edited, vec![7]);
+        assert_eq!(tokens[7]["text"], "t12");
+        let msg = late.try_recv().expect("surgery broadcast");
+        assert_eq!(msg["edit"]["token_index"], 7);
+    }
+
+    #[test]
+    fn test_session_tokens_unknown_room_none() {
+        let store = new_room_store();
+        assert!(session_tokens(&store, "NOPE").is_none());
+    }
+
+    // -- transform lock -------------------------------------------------------
+
+    #[test]
+    fn test_set_transform_lock_exposed_in_snapshot() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        assert!(room_state_snapshot(&store, &code)["locked_transform"].is_null());
+        assert!(set_transform_lock(&store, &code, Some("reverse".to_string())));
+        assert_eq!(room_state_snapshot(&store, &code)["locked_transform"], "reverse");
+        assert_eq!(locked_transform(&store, &code).as_deref(), Some("reverse"));
+    }
+
+    #[test]
+    fn test_set_transform_lock_none_clears() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        set_transform_lock(&store, &code, Some("reverse".to_string()));
+        set_transform_lock(&store, &code, None);
+        assert!(locked_transform(&store, &code).is_none());
+    }
+
+    #[test]
+    fn test_set_transform_lock_unknown_room_returns_false() {
+        let store = new_room_store();
+        assert!(!set_transform_lock(&store, "NOPE", Some("reverse".to_string())));
+        assert!(locked_transform(&store, "NOPE").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_set_transform_lock_broadcasts_event() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (_, mut rx) = join_room(&store, &code, "Alice", true).expect("join");
+        set_transform_lock(&store, &code, Some("mock".to_string()));
+        let msg = rx.recv().await.expect("lock event");
+        assert_eq!(msg["type"], "transform_lock");
+        assert_eq!(msg["transform"], "mock");
+    }
+
+    // -- shared transform -----------------------------------------------------
+
+    #[tokio::test]
+    async fn test_set_transform_rerenders_stored_originals() {
+        use crate::transforms::Transform;
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (_, mut rx) = join_room(&store, &code, "Alice", true).expect("join");
+        let streamed = [("The", "The", false), ("kciuq", "quick", true), ("brown", "brown", false), ("xof", "fox", true)];
+        for (index, (text, original, transformed)) in streamed.iter().enumerate() {
+            record_session_token(
+                &store,
+                &code,
+                serde_json::json!({"index": index, "text": text, "original": original, "transformed": transformed}),
+            );
+        }
+
+        let tokens = set_room_transform(&store, &code, &Transform::Uppercase, None).expect("room");
+        let texts: Vec<&str> = tokens.iter().filter_map(|t| t["text"].as_str()).collect();
+        assert_eq!(texts, vec!["The", "QUICK", "brown", "FOX"]);
+        for (token, (_, original, transformed)) in tokens.iter().zip(&streamed) {
+            let expected = if *transformed { Transform::Uppercase.apply(original) } else { original.to_string() };
+            assert_eq!(token["text"], expected.as_str());
+            assert_eq!(token["original"], *original);
+        }
+        assert_eq!(session_tokens(&store, &code), Some(tokens.clone()));
+
+        let msg = rx.recv().await.expect("set_transform event");
+        assert_eq!(msg["type"], "set_transform");
+        assert_eq!(msg["transform"], "uppercase");
+        assert_eq!(msg["tokens"], serde_json::json!(tokens));
+
+        let tokens = set_room_transform(&store, &code, &Transform::Delete, None).expect("room");
+        assert_eq!(tokens[1]["text"], "");
+        assert_eq!(tokens[1]["chaos_label"], "deleted");
+        assert!(set_room_transform(&store, "NOPE", &Transform::Reverse, None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_reaches_peers_without_echo_or_recording() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (alice, mut alice_rx) = join_room(&store, &code, "Alice", true).expect("join");
+        let (bob, mut bob_rx) = join_room(&store, &code, "Bob", false).expect("join");
+        while alice_rx.try_recv().is_ok() {}
+        while bob_rx.try_recv().is_ok() {}
+        record_session_token(&store, &code, serde_json::json!({"index": 0, "text": "hi", "original": "hi"}));
+        start_recording(&store, &code);
+
+        broadcast_cursor(&store, &code, &alice.id, Some(4));
+        let msg = bob_rx.recv().await.expect("cursor event");
+        assert_eq!(msg["type"], "cursor");
+        assert_eq!(msg["participant_id"], alice.id.as_str());
+        assert_eq!(msg["color"], alice.color.as_str());
+        assert_eq!(msg["name"], "Alice");
+        assert_eq!(msg["token_index"], 4);
+        assert!(!is_own_cursor(&msg, &bob.id), "peers receive the cursor");
+
+        let echoed = alice_rx.recv().await.expect("channel echo");
+        assert!(is_own_cursor(&echoed, &alice.id), "the sender's connection drops its own cursor");
+
+        broadcast_cursor(&store, &code, &bob.id, None);
+        assert!(bob_rx.recv().await.expect("cursor cleared")["token_index"].is_null());
+
+        assert!(stop_recording(&store, &code).is_empty(), "cursors are never recorded");
+        assert_eq!(session_tokens(&store, &code).map(|t| t.len()), Some(1));
+    }
+
+    // -- room backends --------------------------------------------------------
+
+    fn shared_backends() -> (Arc<dyn RoomBackend>, Arc<dyn RoomBackend>) {
+        let a = InMemoryRoomBackend::new();
+        let b = a.handle();
+        (Arc::new(a), Arc::new(b))
+    }
+
+    fn remote(code: &str, revision: u64, msg: serde_json::Value) -> RemoteEvent {
+        RemoteEvent {
+            code: code.to_string(),
+            revision,
+            msg,
+        }
+    }
+
+    #[test]
+    fn test_create_room_with_backend_persists_snapshot() {
+        let (a, _) = shared_backends();
+        let store = new_room_store_with_backend(Arc::clone(&a));
+        let code = create_room(&store);
+        store.flush_backend();
+        let snap = a.load_room(&code).expect("load").expect("saved");
+        assert_eq!(snap["code"], code);
+        assert_eq!(snap["revision"], 1);
+    }
+
+    #[test]
+    fn test_backend_handles_share_rooms() {
+        let (a, b) = shared_backends();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(b);
+        let code = create_room(&store_a);
+        join_room(&store_a, &code, "Alice", true).expect("join");
+        store_a.flush_backend();
+        assert!(hydrate_room(&store_b, &code));
+        let snap = room_state_snapshot(&store_b, &code);
+        assert_eq!(snap["participants"].as_array().map(|p| p.len()), Some(1));
+        assert_eq!(store_b.lock().unwrap()[&code].revision, 2);
+    }
+
+    #[test]
+    fn test_client_colors_survive_hydration_but_not_client_snapshot() {
+        let (a, b) = shared_backends();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(b);
+        let code = create_room(&store_a);
+        let (alice, _) = join_room_as(&store_a, &code, "Alice", true, Some("client-a")).expect("join");
+        assert!(room_state_snapshot(&store_a, &code).get("client_colors").is_none());
+        store_a.flush_backend();
+
+        assert!(hydrate_room(&store_b, &code));
+        leave_room(&store_b, &code, &alice.id);
+        let (guest, _) = join_room(&store_b, &code, "Guest", false).expect("join b");
+        assert_ne!(guest.color, alice.color, "the color kept for client-a is not handed out");
+        let (again, _) = join_room_as(&store_b, &code, "Alice", false, Some("client-a")).expect("rejoin b");
+        assert_eq!(again.color, alice.color);
+    }
+
+    #[test]
+    fn test_hydrate_room_missing_returns_false() {
+        let (_, b) = shared_backends();
+        let store = new_room_store_with_backend(b);
+        assert!(!hydrate_room(&store, "NONE-ROOM-00"));
+        assert!(!hydrate_room(&new_room_store(), "NONE-ROOM-00"));
+    }
+
+    #[test]
+    fn test_backend_refuses_stale_snapshot() {
+        let (a, b) = shared_backends();
+        assert_eq!(a.save_room("ROOM", 3, &serde_json::json!({"v": 3})), Ok(true));
+        assert_eq!(b.save_room("ROOM", 2, &serde_json::json!({"v": 2})), Ok(false));
+        assert_eq!(a.load_room("ROOM").expect("load").expect("saved")["v"], 3);
+        assert_eq!(b.save_room("ROOM", 3, &serde_json::json!({"v": 33})), Ok(true));
+    }
+
+    #[test]
+    fn test_lagging_instance_does_not_roll_back_snapshot() {
+        let (a, b) = shared_backends();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(Arc::clone(&b));
+        let code = create_room(&store_a);
+        store_a.flush_backend();
+        assert!(hydrate_room(&store_b, &code));
+        for i in 0..3 {
+            add_chat(
+                &store_a,
+                &code,
+                ChatMessage {
+                    id: format!("m{}", i),
+                    author_id: "p1".to_string(),
+                    author_name: "Alice".to_string(),
+                    author_color: "#58a6ff".to_string(),
+                    text: format!("m{}", i),
+                    token_index: None,
+                    timestamp_ms: i,
+                },
+            );
+        }
+        store_a.flush_backend();
+        // store_b has not seen those chats and writes from an older revision.
+        set_room_locked(&store_b, &code, true);
+        store_b.flush_backend();
+        let snap = b.load_room(&code).expect("load").expect("saved");
+        assert_eq!(snap["chat_log"].as_array().map(|c| c.len()), Some(3));
+    }
+
+    #[test]
+    fn test_remote_events_advance_revision_and_repersist() {
+        let (a, b) = shared_backends();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(Arc::clone(&b));
+        let code = create_room(&store_a);
+        store_a.flush_backend();
+        assert!(hydrate_room(&store_b, &code));
+        deliver_remote(&store_b, remote(&code, 7, serde_json::json!({"type": "room_lock", "locked": true})));
+        assert_eq!(store_b.lock().unwrap()[&code].revision, 8);
+        store_b.flush_backend();
+        assert_eq!(b.load_room(&code).expect("load").expect("saved")["joins_locked"], true);
+    }
+
+    #[tokio::test]
+    async fn test_backend_broadcast_reaches_other_handle_only() {
+        let (a, b) = shared_backends();
+        let mut own_rx = a.subscribe().expect("subscribe a");
+        let mut other_rx = b.subscribe().expect("subscribe b");
+        let store = new_room_store_with_backend(a);
+        let code = create_room(&store);
+        broadcast(&store, &code, serde_json::json!({"type": "token", "text": "x"}));
+        let event = other_rx.recv().await.expect("remote message");
+        assert_eq!(event.code, code);
+        assert_eq!(event.revision, 1);
+        assert_eq!(event.msg["text"], "x");
+        assert!(own_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_tokens_relay_to_other_instance() {
+        let (a, b) = shared_backends();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(b);
+        let code = create_room(&store_a);
+        store_a.flush_backend();
+        assert!(hydrate_room(&store_b, &code));
+        spawn_remote_relay(&store_b).expect("relay");
+        let (_, mut rx) = join_room(&store_b, &code, "Bob", false).expect("join");
+
+        for i in 0..2 {
+            let token = serde_json::json!({"index": i, "text": format!("t{}", i)});
+            record_session_token(&store_a, &code, token.clone());
+            broadcast_token(&store_a, &code, token);
+        }
+        for i in 0..2 {
+            assert_eq!(rx.recv().await.expect("relayed token")["index"], i);
+        }
+        let retained = session_tokens(&store_b, &code).expect("room");
+        assert_eq!(retained.len(), 2, "late joiners on the other instance see the stream");
+
+        clear_session_tokens(&store_a, &code);
+        broadcast_token(&store_a, &code, serde_json::json!({"index": 0, "text": "new"}));
+        assert_eq!(rx.recv().await.expect("next stream")["text"], "new", "session_reset is not forwarded");
+        assert_eq!(session_tokens(&store_b, &code).map(|t| t.len()), Some(1));
+    }
+
+    #[test]
+    fn test_remote_error_notice_is_not_retained() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        deliver_remote(&store, remote(&code, 1, serde_json::json!({"index": 0, "is_error": true})));
+        assert_eq!(session_tokens(&store, &code).map(|t| t.len()), Some(0));
+    }
+
+    #[test]
+    fn test_room_writes_do_not_wait_for_backend() {
+        struct SlowBackend(Mutex<Vec<u64>>);
+        impl RoomBackend for SlowBackend {
+            fn save_room(&self, _: &str, revision: u64, _: &serde_json::Value) -> Result<bool, String> {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                self.0.lock().unwrap().push(revision);
+                Ok(true)
+            }
+            fn load_room(&self, _: &str) -> Result<Option<serde_json::Value>, String> {
+                Ok(None)
+            }
+            fn publish(&self, _: &str, _: u64, _: &serde_json::Value) -> Result<(), String> {
+                Ok(())
+            }
+            fn subscribe(&self) -> Result<tokio::sync::mpsc::UnboundedReceiver<RemoteEvent>, String> {
+                Err("unsupported".to_string())
+            }
+        }
+        let backend = Arc::new(SlowBackend(Mutex::new(Vec::new())));
+        let store = new_room_store_with_backend(Arc::clone(&backend) as Arc<dyn RoomBackend>);
+        let started = std::time::Instant::now();
+        let code = create_room(&store);
+        for locked in [true, false, true, false] {
+            set_room_locked(&store, &code, locked);
+        }
+        assert!(started.elapsed() < std::time::Duration::from_millis(200), "writes are queued, not awaited");
+        store.flush_backend();
+        let saved = backend.0.lock().unwrap().clone();
+        assert_eq!(saved.last(), Some(&5), "the newest snapshot always lands");
+        assert!(saved.len() < 5, "queued snapshots of one room are coalesced");
+    }
+
+    #[tokio::test]
+    async fn test_deliver_remote_applies_surgery_and_forwards() {
+        let (a, b) = shared_backends();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(b);
+        let code = create_room(&store_a);
+        store_a.flush_backend();
+        assert!(hydrate_room(&store_b, &code));
+        let (_, mut rx) = join_room(&store_b, &code, "Bob", false).expect("join");
+        let msg = serde_json::json!({
+            "type": "surgery",
+            "edit": {
+                "token_index": 2,
+                "old_text": "a",
+                "new_text": "b",
+                "editor_id": "p",
+                "editor_color": "#fff",
+                "editor_name": "Alice",
+                "timestamp_ms": 1,
+            },
+        });
+        deliver_remote(&store_b, remote(&code, 1, msg));
+        let snap = room_state_snapshot(&store_b, &code);
+        assert_eq!(snap["surgery_log"][0]["new_text"], "b");
+        let fwd = rx.recv().await.expect("forwarded");
+        assert_eq!(fwd["type"], "surgery");
+    }
+
+    #[test]
+    fn test_deliver_remote_unknown_room_is_noop() {
+        let store = new_room_store();
+        deliver_remote(&store, remote("NOPE", 1, serde_json::json!({"type": "chat"})));
+        assert!(store.lock().expect("lock").is_empty());
+    }
+
+    // -- stream pause ---------------------------------------------------------
+
+    #[test]
+    fn test_paused_room_holds_tokens_until_resume_in_order() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let mut rx = {
+            let guard = store.lock().unwrap();
+            guard.get(&code).unwrap().broadcast_tx.subscribe()
+        };
+        broadcast_token(&store, &code, serde_json::json!({"index": 0}));
+        assert!(set_room_paused(&store, &code, true));
+        for i in 1..4 {
+            broadcast_token(&store, &code, serde_json::json!({"index": i}));
+        }
+
+        assert_eq!(rx.try_recv().expect("token 0")["index"], 0);
+        let pause = rx.try_recv().expect("pause event");
+        assert_eq!(pause["type"], "stream_pause");
+        assert_eq!(pause["paused"], true);
+        assert!(rx.try_recv().is_err(), "tokens must be held while paused");
+
+        assert!(set_room_paused(&store, &code, false));
+        let resume = rx.try_recv().expect("resume event");
+        assert_eq!(resume["paused"], false);
+        for i in 1..4 {
+            assert_eq!(rx.try_recv().expect("held token")["index"], i);
+        }
+        assert!(rx.try_recv().is_err());
+
+        broadcast_token(&store, &code, serde_json::json!({"index": 4}));
+        assert_eq!(rx.try_recv().expect("live token")["index"], 4);
+    }
+
+    #[test]
+    fn test_pause_state_in_snapshot() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        assert_eq!(room_state_snapshot(&store, &code)["paused"], false);
+        set_room_paused(&store, &code, true);
+        assert_eq!(room_state_snapshot(&store, &code)["paused"], true);
+        assert!(!set_room_paused(&store, "NOPE", true));
+    }
+
+    #[test]
+    fn test_held_tokens_are_capped_and_cleared_by_a_new_stream() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        set_room_paused(&store, &code, true);
+        for i in 0..HELD_TOKEN_CAP + 3 {
+            broadcast_token(&store, &code, serde_json::json!({"index": i}));
+        }
+        {
+            let guard = store.lock().unwrap();
+            let held = &guard.get(&code).unwrap().held_tokens;
+            assert_eq!(held.len(), HELD_TOKEN_CAP);
+            assert_eq!(held.front().unwrap()["index"], 3, "oldest tokens are dropped");
+        }
+        clear_session_tokens(&store, &code);
+        assert!(store.lock().unwrap().get(&code).unwrap().held_tokens.is_empty());
+    }
+
+    #[test]
+    fn test_host_leaving_a_paused_room_resumes_it() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let (host, _rx_h) = join_room(&store, &code, "Host", true).expect("host join");
+        let (guest, mut rx) = join_room(&store, &code, "Guest", false).expect("guest join");
+        set_room_paused(&store, &code, true);
+        broadcast_token(&store, &code, serde_json::json!({"index": 0}));
+        assert_eq!(rx.try_recv().expect("pause")["paused"], true);
+
+        // A guest leaving changes nothing.
+        let (other, _rx_o) = join_room(&store, &code, "Other", false).expect("join");
+        leave_room(&store, &code, &other.id);
+        assert!(rx.try_recv().is_err());
+
+        leave_room(&store, &code, &host.id);
+        assert_eq!(rx.try_recv().expect("resume")["paused"], false);
+        assert_eq!(rx.try_recv().expect("held token")["index"], 0);
+        assert_eq!(room_state_snapshot(&store, &code)["paused"], false);
+        assert!(store.lock().unwrap().get(&code).unwrap().participants.iter().any(|p| p.id == guest.id));
+    }
+
+    // -- audit log -----------------------------------------------------------
+
+    #[test]
+    fn test_audit_log_records_actions_in_order() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let token = host_token(&store, &code).expect("token");
+        let (host, _rx_h) = join_room(&store, &code, "Host", true).expect("host join");
+        let (guest, _rx_g) = join_room(&store, &code, "Guest", false).expect("guest join");
+        add_chat(&store, &code, ChatMessage {
+            id: "c1".to_string(),
+            author_id: guest.id.clone(),
+            author_name: guest.name.clone(),
+            author_color: guest.color.clone(),
+            text: "hi".to_string(),
+            token_index: Some(1),
+            timestamp_ms: now_ms(),
+        });
+        apply_surgery(&store, &code, SurgeryEdit {
+            token_index: 1,
+            new_text: "b".to_string(),
+            old_text: "a".to_string(),
+            editor_id: guest.id.clone(),
+            editor_color: guest.color.clone(),
+            editor_name: guest.name.clone(),
+            timestamp_ms: now_ms(),
+        });
+        vote_as(&store, &code, "reverse", "up", Some(&guest.id));
+        set_transform_lock(&store, &code, Some("mock".to_string()));
+        set_room_paused(&store, &code, true);
+        leave_room(&store, &code, &guest.id);
+
+        let log = audit_log(&store, &code, &token).expect("audit log");
+        let events: Vec<&str> = log.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(
+            events,
+            ["join", "join", "chat", "surgery", "vote", "transform_lock", "pause", "leave"]
+        );
+        assert!(log.windows(2).all(|w| w[0].timestamp_ms <= w[1].timestamp_ms));
+        assert_eq!(log[1].actor_id.as_deref(), Some(guest.id.as_str()));
+        assert_eq!(log[3].detail["new_text"], "b");
+        assert_eq!(log[5].actor_id.as_deref(), Some(host.id.as_str()));
+        assert_eq!(log[6].detail["paused"], true);
+    }
+
+    #[test]
+    fn test_audit_log_requires_host_token() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        assert_eq!(audit_log(&store, &code, "wrong").unwrap_err(), AuditError::Forbidden);
+        assert_eq!(audit_log(&store, &code, "").unwrap_err(), AuditError::Forbidden);
+        assert_eq!(audit_log(&store, "NOPE", "x").unwrap_err(), AuditError::NotFound);
+    }
+
+    #[test]
+    fn test_room_archive_contains_every_section() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let token = host_token(&store, &code).expect("token");
+        let (host, _rx_h) = join_room(&store, &code, "Host", true).expect("host join");
+        let (guest, _rx_g) = join_room(&store, &code, "Guest", false).expect("guest join");
+        start_recording(&store, &code);
+        let streamed = serde_json::json!({"index": 0, "text": "olleh", "original": "hello", "transformed": true});
+        record_session_token(&store, &code, streamed.clone());
+        maybe_record(&store, &code, streamed);
+        add_chat(&store, &code, ChatMessage {
+            id: "c1".to_string(),
+            author_id: guest.id.clone(),
+            author_name: guest.name.clone(),
+            author_color: guest.color.clone(),
+            text: "nice".to_string(),
+            token_index: Some(0),
+            timestamp_ms: now_ms(),
+        });
+        apply_surgery(&store, &code, SurgeryEdit {
+            token_index: 0,
+            new_text: "howdy".to_string(),
+            old_text: "olleh".to_string(),
+            editor_id: guest.id.clone(),
+            editor_color: guest.color.clone(),
+            editor_name: guest.name.clone(),
+            timestamp_ms: now_ms(),
+        });
+        vote_as(&store, &code, "reverse", "up", Some(&guest.id));
+        vote_as(&store, &code, "reverse", "down", Some(&host.id));
+        vote_as(&store, &code, "mock", "up", Some(&host.id));
+        add_annotation(&store, &code, TokenAnnotation {
+            id: "a1".to_string(),
+            token_index: 0,
+            note: "greeting".to_string(),
+            author_id: host.id.clone(),
+            author_name: host.name.clone(),
+            author_color: host.color.clone(),
+            timestamp_ms: now_ms(),
+        });
+        leave_room(&store, &code, &guest.id);
+
+        assert_eq!(room_archive(&store, &code, "wrong").unwrap_err(), AuditError::Forbidden);
+        assert_eq!(room_archive(&store, "NOPE", &token).unwrap_err(), AuditError::NotFound);
+        let archive = room_archive(&store, &code, &token).expect("archive");
+
+        assert_eq!(archive["code"], code.as_str());
+        assert_eq!(archive["tokens"][0]["text"], "howdy");
+        assert_eq!(archive["recording"].as_array().map(Vec::len), Some(1));
+        assert_eq!(archive["recording"][0]["payload"]["original"], "hello");
+        assert_eq!(archive["chat_log"][0]["text"], "nice");
+        assert_eq!(archive["surgery_log"][0]["new_text"], "howdy");
+        assert_eq!(archive["surgery_log"][0]["editor_id"], guest.id.as_str());
+        assert_eq!(archive["annotations"][0]["note"], "greeting");
+        assert_eq!(
+            archive["votes"],
+            serde_json::json!([
+                {"transform": "mock", "up": 1, "down": 0},
+                {"transform": "reverse", "up": 1, "down": 1},
+            ])
+        );
+
+        let people = archive["participants"].as_array().expect("participants");
+        assert_eq!(people.len(), 2);
+        assert_eq!(people[0]["id"], host.id.as_str());
+        assert_eq!(people[0]["is_host"], true);
+        assert_eq!(people[0]["color"], host.color.as_str());
+        assert!(people[0]["left_at_ms"].is_null());
+        assert_eq!(people[1]["name"], "Guest");
+        assert!(people[1]["joined_at_ms"].as_u64() > Some(0));
+        assert!(people[1]["left_at_ms"].as_u64() >= people[1]["joined_at_ms"].as_u64());
+    }
+
+    #[test]
+    fn test_audit_log_is_capped() {
+        let store = new_room_store();
+        let code = create_room(&store);
+        let token = host_token(&store, &code).expect("token");
+        for _ in 0..AUDIT_LOG_CAP + 3 {
+            vote(&store, &code, "reverse", "up");
+        }
+        set_room_locked(&store, &code, true);
+        let log = audit_log(&store, &code, &token).expect("audit log");
+        assert_eq!(log.len(), AUDIT_LOG_CAP);
+        assert_eq!(log.last().map(|e| e.event.as_str()), Some("room_lock"));
+    }
 }
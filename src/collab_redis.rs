@@ -0,0 +1,532 @@
+//! # Stage: Redis-Backed Room Backend
+//!
+//! ## Responsibility
+//! Implements [`RoomBackend`] on top of Redis so that several web-server
+//! instances can share collaboration rooms.  Room snapshots are stored under
+//! `eot:room:<code>` (revision under `eot:room:<code>:rev`) with an idle TTL,
+//! and broadcasts are fanned out over the `eot:rooms` pub/sub channel.
+//!
+//! ## Guarantees
+//! - Non-panicking: all Redis error paths are handled via `Result`
+//! - Echo-free: each backend instance drops messages it published itself
+//! - Bounded: room keys expire after [`ROOM_KEY_TTL_SECS`] without writes
+//! - Monotonic: a snapshot older than the stored revision is never written
+//!   (checked and set atomically in a Lua script)
+//! - Self-healing: a dropped command connection is reopened on the next
+//!   command, and a dropped subscription reconnects with capped back-off
+//!
+//! ## NOT Responsible For
+//! - Recording / replay data (recordings stay on the instance that made them)
+//! - Ordering guarantees across instances beyond what Redis pub/sub provides
+//! - Broadcasts published while a subscription is reconnecting (pub/sub does
+//!   not buffer them; the saved snapshot still carries their state changes)
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::collab::{RemoteEvent, RoomBackend};
+
+// ---------------------------------------------------------------------------
+// Redis key constants
+// ---------------------------------------------------------------------------
+
+const KEY_ROOM_PREFIX: &str = "eot:room:";
+const CHANNEL_ROOMS: &str = "eot:rooms";
+
+/// Seconds a room snapshot survives in Redis without being rewritten.
+pub const ROOM_KEY_TTL_SECS: u64 = 3600;
+
+/// First delay before resubscribing after the pub/sub connection drops;
+/// doubles per failed attempt up to [`SUBSCRIBE_BACKOFF_MAX`].
+const SUBSCRIBE_BACKOFF_BASE: Duration = Duration::from_millis(250);
+const SUBSCRIBE_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Write `ARGV[1]` to `KEYS[1]` and revision `ARGV[2]` to `KEYS[2]` unless
+/// the stored revision is newer; returns 1 if written.
+const SAVE_IF_NEWER_LUA: &str = r#"
+local stored = tonumber(redis.call('GET', KEYS[2]) or '-1')
+if tonumber(ARGV[2]) < stored then
+  return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[3])
+redis.call('SET', KEYS[2], ARGV[2], 'EX', ARGV[3])
+return 1
+"#;
+
+/// Callback invoked with each raw pub/sub payload; returns `false` to stop.
+pub(crate) type PayloadSink = Box<dyn FnMut(String) -> bool + Send>;
+
+// ---------------------------------------------------------------------------
+// RoomRedisOps trait
+// ---------------------------------------------------------------------------
+
+/// Abstraction over the Redis operations needed by `RedisRoomBackend`.
+pub(crate) trait RoomRedisOps: Send {
+    /// Store `value` at `key` and `revision` at `rev_key`, both expiring after
+    /// `ttl_secs`, unless `rev_key` holds a newer revision.  Returns whether
+    /// the write happened.
+    fn save_if_newer(
+        &mut self,
+        key: &str,
+        rev_key: &str,
+        value: &str,
+        revision: u64,
+        ttl_secs: u64,
+    ) -> Result<bool, String>;
+    fn get(&mut self, key: &str) -> Result<Option<String>, String>;
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String>;
+    fn subscribe(&mut self, channel: &str, sink: PayloadSink) -> Result<(), String>;
+    fn ping(&mut self) -> Result<(), String>;
+}
+
+// ---------------------------------------------------------------------------
+// redis::Client impl
+// ---------------------------------------------------------------------------
+
+/// Live Redis operations: one command connection plus a dedicated
+/// connection per subscription.
+struct RedisClientOps {
+    client: redis::Client,
+    conn: redis::Connection,
+    save_script: redis::Script,
+}
+
+impl RedisClientOps {
+    /// Run `f` on the command connection, reopening it and retrying once if
+    /// the connection was dropped.
+    fn query<T>(&mut self, f: impl Fn(&mut redis::Connection) -> redis::RedisResult<T>) -> Result<T, String> {
+        match f(&mut self.conn) {
+            Err(e) if e.is_connection_dropped() || e.is_io_error() => {
+                tracing::warn!(err = %e, "redis room connection dropped; reconnecting");
+                self.conn = self
+                    .client
+                    .get_connection()
+                    .map_err(|e| format!("Redis reconnect failed: {}", e))?;
+                f(&mut self.conn).map_err(|e| e.to_string())
+            }
+            result => result.map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl RoomRedisOps for RedisClientOps {
+    fn save_if_newer(
+        &mut self,
+        key: &str,
+        rev_key: &str,
+        value: &str,
+        revision: u64,
+        ttl_secs: u64,
+    ) -> Result<bool, String> {
+        let script = self.save_script.clone();
+        self.query(|conn| {
+            script
+                .key(key)
+                .key(rev_key)
+                .arg(value)
+                .arg(revision)
+                .arg(ttl_secs)
+                .invoke::<i64>(conn)
+                .map(|written| written == 1)
+        })
+    }
+
+    fn get(&mut self, key: &str) -> Result<Option<String>, String> {
+        self.query(|conn| redis::cmd("GET").arg(key).query::<Option<String>>(conn))
+    }
+
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String> {
+        self.query(|conn| redis::cmd("PUBLISH").arg(channel).arg(payload).query::<()>(conn))
+    }
+
+    fn subscribe(&mut self, channel: &str, sink: PayloadSink) -> Result<(), String> {
+        let client = self.client.clone();
+        let channel = channel.to_string();
+        let session = move |sink: &mut dyn FnMut(String) -> bool| -> Result<(), String> {
+            let mut conn = client
+                .get_connection()
+                .map_err(|e| format!("Redis connection failed: {}", e))?;
+            let mut pubsub = conn.as_pubsub();
+            pubsub.subscribe(&channel).map_err(|e| e.to_string())?;
+            loop {
+                let payload = pubsub
+                    .get_message()
+                    .and_then(|m| m.get_payload::<String>())
+                    .map_err(|e| e.to_string())?;
+                if !sink(payload) {
+                    return Ok(());
+                }
+            }
+        };
+        std::thread::Builder::new()
+            .name("eot-room-sub".to_string())
+            .spawn(move || run_subscription(session, sink, SUBSCRIBE_BACKOFF_BASE))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn ping(&mut self) -> Result<(), String> {
+        self.query(|conn| redis::cmd("PING").query::<String>(conn)).map(|_| ())
+    }
+}
+
+/// Keep a subscription alive: run `session`, which feeds payloads to its
+/// sink until the connection fails, and reconnect after each failure with
+/// exponential back-off from `base` up to [`SUBSCRIBE_BACKOFF_MAX`].
+/// Returns once the sink asks to stop.
+pub(crate) fn run_subscription(
+    mut session: impl FnMut(&mut dyn FnMut(String) -> bool) -> Result<(), String>,
+    mut sink: PayloadSink,
+    base: Duration,
+) {
+    let mut backoff = base;
+    loop {
+        let mut delivered = false;
+        let mut stopped = false;
+        let result = session(&mut |payload| {
+            delivered = true;
+            stopped = !sink(payload);
+            !stopped
+        });
+        let err = match result {
+            Ok(()) => return,
+            Err(_) if stopped => return,
+            Err(e) => e,
+        };
+        if delivered {
+            backoff = base;
+        }
+        tracing::warn!(err = %err, backoff_ms = backoff.as_millis() as u64, "redis room subscription dropped; reconnecting");
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(SUBSCRIBE_BACKOFF_MAX);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// MockRoomRedisOps
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+#[derive(Default)]
+struct MockRedisState {
+    kv: std::collections::HashMap<String, String>,
+    revisions: std::collections::HashMap<String, u64>,
+    subscribers: Vec<(String, PayloadSink)>,
+}
+
+/// In-process Redis stand-in; clones share the same keyspace and channels.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct MockRoomRedisOps {
+    state: std::sync::Arc<Mutex<MockRedisState>>,
+}
+
+#[cfg(test)]
+impl RoomRedisOps for MockRoomRedisOps {
+    fn save_if_newer(
+        &mut self,
+        key: &str,
+        rev_key: &str,
+        value: &str,
+        revision: u64,
+        _ttl_secs: u64,
+    ) -> Result<bool, String> {
+        let mut s = self.state.lock().map_err(|_| "lock poisoned".to_string())?;
+        if s.revisions.get(rev_key).is_some_and(|stored| *stored > revision) {
+            return Ok(false);
+        }
+        s.kv.insert(key.to_string(), value.to_string());
+        s.revisions.insert(rev_key.to_string(), revision);
+        Ok(true)
+    }
+
+    fn get(&mut self, key: &str) -> Result<Option<String>, String> {
+        let s = self.state.lock().map_err(|_| "lock poisoned".to_string())?;
+        Ok(s.kv.get(key).cloned())
+    }
+
+    fn publish(&mut self, channel: &str, payload: &str) -> Result<(), String> {
+        let mut s = self.state.lock().map_err(|_| "lock poisoned".to_string())?;
+        s.subscribers
+            .retain_mut(|(ch, sink)| ch != channel || sink(payload.to_string()));
+        Ok(())
+    }
+
+    fn subscribe(&mut self, channel: &str, sink: PayloadSink) -> Result<(), String> {
+        let mut s = self.state.lock().map_err(|_| "lock poisoned".to_string())?;
+        s.subscribers.push((channel.to_string(), sink));
+        Ok(())
+    }
+
+    fn ping(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RedisRoomBackend
+// ---------------------------------------------------------------------------
+
+/// [`RoomBackend`] that stores room snapshots in Redis and relays broadcasts
+/// between instances over Redis pub/sub.
+///
+/// # Construction
+/// - `connect(url)` — opens a blocking Redis connection
+/// - `with_ops(ops)` — inject any `RoomRedisOps` (used in tests)
+pub struct RedisRoomBackend {
+    ops: Mutex<Box<dyn RoomRedisOps>>,
+    instance_id: String,
+}
+
+impl RedisRoomBackend {
+    /// Open a connection to Redis and return a new backend.
+    pub fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| e.to_string())?;
+        let conn = client
+            .get_connection()
+            .map_err(|e| format!("Redis connection failed: {}", e))?;
+        let mut ops = RedisClientOps {
+            client,
+            conn,
+            save_script: redis::Script::new(SAVE_IF_NEWER_LUA),
+        };
+        ops.ping()
+            .map_err(|e| format!("Redis ping failed: {}", e))?;
+        Ok(Self::with_ops(Box::new(ops)))
+    }
+
+    /// Construct with an injected `RoomRedisOps` implementation.
+    pub(crate) fn with_ops(ops: Box<dyn RoomRedisOps>) -> Self {
+        Self {
+            ops: Mutex::new(ops),
+            instance_id: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    fn with_ops_locked<T>(
+        &self,
+        f: impl FnOnce(&mut dyn RoomRedisOps) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut guard = self
+            .ops
+            .lock()
+            .map_err(|_| "internal: lock poisoned".to_string())?;
+        f(guard.as_mut())
+    }
+}
+
+impl RoomBackend for RedisRoomBackend {
+    fn save_room(&self, code: &str, revision: u64, snapshot: &serde_json::Value) -> Result<bool, String> {
+        let key = format!("{}{}", KEY_ROOM_PREFIX, code);
+        let rev_key = format!("{}:rev", key);
+        let value = serde_json::to_string(snapshot).map_err(|e| e.to_string())?;
+        self.with_ops_locked(|ops| ops.save_if_newer(&key, &rev_key, &value, revision, ROOM_KEY_TTL_SECS))
+    }
+
+    fn load_room(&self, code: &str) -> Result<Option<serde_json::Value>, String> {
+        let key = format!("{}{}", KEY_ROOM_PREFIX, code);
+        match self.with_ops_locked(|ops| ops.get(&key))? {
+            Some(raw) => serde_json::from_str(&raw)
+                .map(Some)
+                .map_err(|e| format!("corrupt room snapshot: {}", e)),
+            None => Ok(None),
+        }
+    }
+
+    fn publish(&self, code: &str, revision: u64, msg: &serde_json::Value) -> Result<(), String> {
+        let envelope = serde_json::json!({
+            "origin": self.instance_id,
+            "code": code,
+            "revision": revision,
+            "msg": msg,
+        });
+        let payload = serde_json::to_string(&envelope).map_err(|e| e.to_string())?;
+        self.with_ops_locked(|ops| ops.publish(CHANNEL_ROOMS, &payload))
+    }
+
+    fn subscribe(&self) -> Result<tokio::sync::mpsc::UnboundedReceiver<RemoteEvent>, String> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let own_id = self.instance_id.clone();
+        let sink: PayloadSink = Box::new(move |payload: String| {
+            let envelope: serde_json::Value = match serde_json::from_str(&payload) {
+                Ok(v) => v,
+                Err(_) => return !tx.is_closed(),
+            };
+            if envelope.get("origin").and_then(|v| v.as_str()) == Some(own_id.as_str()) {
+                return !tx.is_closed();
+            }
+            tx.send(RemoteEvent {
+                code: envelope.get("code").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                revision: envelope.get("revision").and_then(|v| v.as_u64()).unwrap_or(0),
+                msg: envelope.get("msg").cloned().unwrap_or(serde_json::Value::Null),
+            })
+            .is_ok()
+        });
+        self.with_ops_locked(|ops| ops.subscribe(CHANNEL_ROOMS, sink))?;
+        Ok(rx)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collab::{self, new_room_store_with_backend};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn backend_pair() -> (Arc<dyn RoomBackend>, Arc<dyn RoomBackend>) {
+        let shared = MockRoomRedisOps::default();
+        let a: Arc<dyn RoomBackend> = Arc::new(RedisRoomBackend::with_ops(Box::new(shared.clone())));
+        let b: Arc<dyn RoomBackend> = Arc::new(RedisRoomBackend::with_ops(Box::new(shared)));
+        (a, b)
+    }
+
+    #[test]
+    fn test_save_then_load_roundtrip() {
+        let (a, _) = backend_pair();
+        let snap = serde_json::json!({"code": "X", "token_count": 3});
+        assert_eq!(a.save_room("X", 1, &snap), Ok(true));
+        assert_eq!(a.load_room("X").expect("load"), Some(snap));
+    }
+
+    #[test]
+    fn test_older_revision_is_not_saved() {
+        let (a, b) = backend_pair();
+        assert_eq!(a.save_room("X", 5, &serde_json::json!({"v": 5})), Ok(true));
+        assert_eq!(b.save_room("X", 4, &serde_json::json!({"v": 4})), Ok(false));
+        assert_eq!(b.load_room("X").expect("load"), Some(serde_json::json!({"v": 5})));
+    }
+
+    #[test]
+    fn test_load_missing_room_is_none() {
+        let (a, _) = backend_pair();
+        assert_eq!(a.load_room("NOPE").expect("load"), None);
+    }
+
+    #[test]
+    fn test_second_handle_sees_room_created_by_first() {
+        let (a, b) = backend_pair();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(b);
+        let code = collab::create_room(&store_a);
+        let (host, _rx) = collab::join_room(&store_a, &code, "Alice", true).expect("join a");
+        store_a.flush_backend();
+        assert!(collab::hydrate_room(&store_b, &code));
+        let snap = collab::room_state_snapshot(&store_b, &code);
+        assert_eq!(snap["host_id"], host.id);
+        assert_eq!(snap["participants"][0]["name"], "Alice");
+    }
+
+    #[test]
+    fn test_hydrate_unknown_room_returns_false() {
+        let (_, b) = backend_pair();
+        let store_b = new_room_store_with_backend(b);
+        assert!(!collab::hydrate_room(&store_b, "NOPE-ROOM-00"));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_reaches_other_handle() {
+        let (a, b) = backend_pair();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(Arc::clone(&b));
+        let code = collab::create_room(&store_a);
+        store_a.flush_backend();
+        assert!(collab::hydrate_room(&store_b, &code));
+        let mut remote = b.subscribe().expect("subscribe");
+        collab::broadcast(&store_a, &code, serde_json::json!({"type": "token", "text": "hi"}));
+        let event = remote.recv().await.expect("remote message");
+        assert_eq!(event.code, code);
+        assert_eq!(event.revision, 1);
+        assert_eq!(event.msg["text"], "hi");
+    }
+
+    #[tokio::test]
+    async fn test_remote_chat_applied_to_local_room() {
+        let (a, b) = backend_pair();
+        let store_a = new_room_store_with_backend(a);
+        let store_b = new_room_store_with_backend(Arc::clone(&b));
+        let code = collab::create_room(&store_a);
+        store_a.flush_backend();
+        assert!(collab::hydrate_room(&store_b, &code));
+        let mut remote = b.subscribe().expect("subscribe");
+        let (_, mut local_rx) = collab::join_room(&store_b, &code, "Bob", false).expect("join b");
+        collab::add_chat(
+            &store_a,
+            &code,
+            collab::ChatMessage {
+                id: "m1".to_string(),
+                author_id: "p1".to_string(),
+                author_name: "Alice".to_string(),
+                author_color: "#fff".to_string(),
+                text: "hello".to_string(),
+                token_index: None,
+                timestamp_ms: 1,
+            },
+        );
+        let event = remote.recv().await.expect("remote message");
+        collab::deliver_remote(&store_b, event);
+        let snap = collab::room_state_snapshot(&store_b, &code);
+        assert_eq!(snap["chat_log"][0]["text"], "hello");
+        let fwd = local_rx.recv().await.expect("local forward");
+        assert_eq!(fwd["type"], "chat");
+    }
+
+    #[tokio::test]
+    async fn test_own_messages_are_not_echoed() {
+        let (a, _) = backend_pair();
+        let mut own = a.subscribe().expect("subscribe");
+        a.publish("ROOM", 1, &serde_json::json!({"type": "ping"})).expect("publish");
+        assert!(own.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_subscription_reconnects_after_drop() {
+        let attempts = AtomicUsize::new(0);
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink_received = Arc::clone(&received);
+        let sink: PayloadSink = Box::new(move |payload| {
+            let mut got = sink_received.lock().unwrap();
+            got.push(payload);
+            got.len() < 3
+        });
+        run_subscription(
+            |sink: &mut dyn FnMut(String) -> bool| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                match attempt {
+                    0 => Err("refused".to_string()),
+                    1 => {
+                        sink(format!("a{}", attempt));
+                        Err("connection reset".to_string())
+                    }
+                    _ => {
+                        while sink(format!("b{}", attempt)) {}
+                        Ok(())
+                    }
+                }
+            },
+            sink,
+            Duration::from_millis(1),
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(*received.lock().unwrap(), vec!["a1", "b2", "b2"]);
+    }
+
+    #[test]
+    fn test_subscription_stops_when_sink_stops() {
+        let attempts = AtomicUsize::new(0);
+        run_subscription(
+            |sink: &mut dyn FnMut(String) -> bool| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                sink("x".to_string());
+                Err("connection reset".to_string())
+            },
+            Box::new(|_| false),
+            Duration::from_millis(1),
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 1, "no reconnect once the receiver is gone");
+    }
+}
@@ -479,15 +479,9 @@ mod tests {
             text: original.to_string(),
             original: original.to_string(),
             index: 0,
-            transformed: false,
             importance: 0.5,
-            chaos_label: None,
-            provider: None,
             confidence,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         }
     }
 
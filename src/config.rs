@@ -12,6 +12,7 @@
 //! rate         = 0.5
 //! port         = 8888
 //! top_logprobs = 5
+//! max_prompt_chars = 32000
 //! ```
 
 use std::path::PathBuf;
@@ -42,6 +43,8 @@ pub struct EotConfig {
     pub anthropic_max_tokens: Option<u32>,
     /// Optional bearer token required for /api/ web UI endpoints.
     pub api_key: Option<String>,
+    /// Longest accepted prompt in characters. Overrides the CLI default of 32000.
+    pub max_prompt_chars: Option<usize>,
 }
 
 impl EotConfig {
@@ -87,6 +90,9 @@ impl EotConfig {
         if other.api_key.is_some() {
             self.api_key = other.api_key;
         }
+        if other.max_prompt_chars.is_some() {
+            self.max_prompt_chars = other.max_prompt_chars;
+        }
     }
 }
 
@@ -239,6 +245,7 @@ mod tests {
             system_a: Some("Be concise.".to_string()),
             anthropic_max_tokens: None,
             api_key: None,
+            max_prompt_chars: Some(1000),
         };
         base.merge(other);
         assert_eq!(base.provider.as_deref(), Some("openai"));
@@ -248,6 +255,7 @@ mod tests {
         assert_eq!(base.port, Some(9999));
         assert_eq!(base.top_logprobs, Some(10));
         assert_eq!(base.system_a.as_deref(), Some("Be concise."));
+        assert_eq!(base.max_prompt_chars, Some(1000));
     }
 
     // -- Rate validation tests (#15) --
@@ -0,0 +1,229 @@
+//! Setup check (`--doctor`, alias `--check`).
+//!
+//! For each remote provider, checks that its API key variable is set and
+//! lists the provider's models with that key.  The models call proves both
+//! connectivity and that the key is accepted, without spending tokens.  The
+//! report also shows the default model for each provider and the Cargo
+//! feature flags compiled into the binary.
+
+use std::time::Duration;
+
+use crate::providers::{Provider, ANTHROPIC_API_VERSION};
+
+/// Anthropic's model listing endpoint.
+pub const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
+
+/// How long to wait for a provider before reporting it unreachable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Providers `--doctor` checks, in report order.
+pub const CHECKED_PROVIDERS: [Provider; 2] = [Provider::Openai, Provider::Anthropic];
+
+/// Environment variable holding `provider`'s API key; `None` for the mock
+/// provider, which needs none.
+pub fn api_key_var(provider: &Provider) -> Option<&'static str> {
+    match provider {
+        Provider::Openai => Some("OPENAI_API_KEY"),
+        Provider::Anthropic => Some("ANTHROPIC_API_KEY"),
+        Provider::Mock => None,
+    }
+}
+
+/// Model listing endpoint probed for `provider`.
+pub fn models_url(provider: &Provider) -> Option<&'static str> {
+    match provider {
+        Provider::Openai => Some(crate::model_list::OPENAI_MODELS_URL),
+        Provider::Anthropic => Some(ANTHROPIC_MODELS_URL),
+        Provider::Mock => None,
+    }
+}
+
+/// The outcome of checking one provider.
+#[derive(Debug, Clone)]
+pub struct ProviderCheck {
+    pub provider: Provider,
+    /// Model requests use when none is given.
+    pub model: String,
+    /// `Err` carries the reason the check failed.
+    pub result: Result<(), String>,
+}
+
+impl ProviderCheck {
+    /// One report line: `✓ openai  ...` or `✗ anthropic  ...: reason`.
+    pub fn report_line(&self) -> String {
+        match &self.result {
+            Ok(()) => format!("  ✓ {:<10} key accepted, model {}", self.provider.to_string(), self.model),
+            Err(reason) => format!("  ✗ {:<10} {} (model {})", self.provider.to_string(), reason, self.model),
+        }
+    }
+}
+
+/// Check `provider` with `api_key` by listing models at `url`.
+///
+/// A missing or blank key fails without a request.  `headers` are the
+/// `--header` extras, so organisation or beta headers apply to the probe too.
+pub async fn check_provider(
+    client: &reqwest::Client,
+    provider: &Provider,
+    api_key: Option<&str>,
+    url: &str,
+    headers: &[(String, String)],
+) -> Result<(), String> {
+    let var = api_key_var(provider).unwrap_or("API key");
+    let Some(api_key) = api_key.filter(|k| !k.trim().is_empty()) else {
+        return Err(format!("{} is not set", var));
+    };
+    let mut request = client.get(url).timeout(PROBE_TIMEOUT);
+    request = match provider {
+        Provider::Anthropic => request
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION),
+        _ => request.bearer_auth(api_key),
+    };
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("cannot reach {}: {}", url, e.without_url()))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("HTTP {}: {}", status.as_u16(), error_message(&body)))
+}
+
+/// The provider's error message from a JSON error body (both providers use
+/// `{"error":{"message":...}}`), or the body itself, truncated.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().chars().take(200).collect())
+}
+
+/// Check every provider in [`CHECKED_PROVIDERS`] against its real endpoint,
+/// reading keys from the environment.  `selected` and `model` are the
+/// `--provider` and `--model` flags, used to resolve that provider's model.
+pub async fn run_checks(selected: &Provider, model: Option<&str>, headers: &[(String, String)]) -> Vec<ProviderCheck> {
+    let client = reqwest::Client::new();
+    let mut checks = Vec::new();
+    for provider in CHECKED_PROVIDERS {
+        let model = if provider == *selected {
+            crate::cli::resolve_model(&provider, model)
+        } else {
+            provider.default_model().to_string()
+        };
+        let api_key = api_key_var(&provider).and_then(|var| std::env::var(var).ok());
+        let url = models_url(&provider).unwrap_or_default();
+        let result = check_provider(&client, &provider, api_key.as_deref(), url, headers).await;
+        checks.push(ProviderCheck { provider, model, result });
+    }
+    checks
+}
+
+/// The full `--doctor` report for `checks`.
+pub fn report(checks: &[ProviderCheck]) -> String {
+    let mut out = format!(
+        "every-other-token {} ({})\n\nProviders\n",
+        crate::build_info::VERSION,
+        crate::build_info::GIT_COMMIT
+    );
+    for check in checks {
+        out.push_str(&check.report_line());
+        out.push('\n');
+    }
+    let features = crate::build_info::enabled_features();
+    out.push_str("\nFeatures\n  ");
+    out.push_str(&if features.is_empty() { "(none)".to_string() } else { features.join(", ") });
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one response with `status` and `body`, returning the URL and
+    /// the request head the server received.
+    async fn mock_server(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("http://{}/v1/models", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("accept");
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_accepted_key_passes_with_provider_auth_headers() {
+        let client = reqwest::Client::new();
+        let (url, head) = mock_server("200 OK", r#"{"data":[]}"#).await;
+        let extra = vec![("OpenAI-Organization".to_string(), "org-1".to_string())];
+        assert_eq!(check_provider(&client, &Provider::Openai, Some("sk-a"), &url, &extra).await, Ok(()));
+        let head = head.await.unwrap();
+        assert!(head.contains("authorization: bearer sk-a"), "{}", head);
+        assert!(head.contains("openai-organization: org-1"), "{}", head);
+
+        let (url, head) = mock_server("200 OK", r#"{"data":[]}"#).await;
+        assert_eq!(check_provider(&client, &Provider::Anthropic, Some("sk-ant-b"), &url, &[]).await, Ok(()));
+        let head = head.await.unwrap();
+        assert!(head.contains("x-api-key: sk-ant-b"), "{}", head);
+        assert!(head.contains("anthropic-version: "), "{}", head);
+    }
+
+    #[tokio::test]
+    async fn test_failures_report_the_reason() {
+        let client = reqwest::Client::new();
+        let err = check_provider(&client, &Provider::Openai, None, "http://127.0.0.1:9/", &[]).await;
+        assert_eq!(err, Err("OPENAI_API_KEY is not set".to_string()));
+        let err = check_provider(&client, &Provider::Anthropic, Some("  "), "http://127.0.0.1:9/", &[]).await;
+        assert_eq!(err, Err("ANTHROPIC_API_KEY is not set".to_string()));
+
+        let (url, _) = mock_server(
+            "401 Unauthorized",
+            r#"{"error":{"message":"Incorrect API key provided","type":"invalid_request_error"}}"#,
+        )
+        .await;
+        let err = check_provider(&client, &Provider::Openai, Some("sk-bad"), &url, &[]).await;
+        assert_eq!(err, Err("HTTP 401: Incorrect API key provided".to_string()));
+
+        let err = check_provider(&client, &Provider::Openai, Some("sk-a"), "http://127.0.0.1:9/v1/models", &[])
+            .await
+            .unwrap_err();
+        assert!(err.starts_with("cannot reach http://127.0.0.1:9/v1/models"), "{}", err);
+    }
+
+    #[test]
+    fn test_report_marks_each_provider() {
+        let checks = vec![
+            ProviderCheck { provider: Provider::Openai, model: "gpt-4o".to_string(), result: Ok(()) },
+            ProviderCheck {
+                provider: Provider::Anthropic,
+                model: "claude-sonnet-4-6".to_string(),
+                result: Err("ANTHROPIC_API_KEY is not set".to_string()),
+            },
+        ];
+        let text = report(&checks);
+        assert!(text.contains("✓ openai     key accepted, model gpt-4o"), "{}", text);
+        assert!(text.contains("✗ anthropic  ANTHROPIC_API_KEY is not set (model claude-sonnet-4-6)"), "{}", text);
+        assert!(text.contains(crate::build_info::VERSION));
+        assert!(text.contains("Features"));
+    }
+}
@@ -22,6 +22,18 @@ pub enum EotError {
     #[error("provider HTTP {status} from {url}")]
     ProviderHttp { status: u16, url: String },
 
+    /// A provider rejected the request or aborted the stream with a typed
+    /// error, such as Anthropic's `overloaded_error` or `rate_limit_error`.
+    /// `status` is the HTTP status of a rejected request; `None` when the
+    /// error arrived mid-stream.
+    #[error("{provider} API error ({error_type}): {message}")]
+    ProviderApi {
+        provider: String,
+        error_type: String,
+        message: String,
+        status: Option<u16>,
+    },
+
     /// The provider response body could not be parsed.
     #[error("provider JSON parse error: {0}")]
     ProviderJson(String),
@@ -48,6 +60,33 @@ pub enum EotError {
     Other(String),
 }
 
+impl EotError {
+    /// A typed Anthropic error from its [`AnthropicErrorBody`](crate::providers::AnthropicErrorBody).
+    pub fn anthropic(body: crate::providers::AnthropicErrorBody, status: Option<u16>) -> Self {
+        EotError::ProviderApi {
+            provider: "Anthropic".to_string(),
+            error_type: body.error_type,
+            message: body.message,
+            status,
+        }
+    }
+
+    /// The provider is temporarily over capacity (Anthropic `overloaded_error`).
+    pub fn is_overloaded(&self) -> bool {
+        matches!(self, EotError::ProviderApi { error_type, .. } if error_type == "overloaded_error")
+    }
+
+    /// The request was rate limited (Anthropic `rate_limit_error`).
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, EotError::ProviderApi { error_type, .. } if error_type == "rate_limit_error")
+    }
+
+    /// Whether the same request may succeed after backing off.
+    pub fn is_retryable(&self) -> bool {
+        self.is_overloaded() || self.is_rate_limited()
+    }
+}
+
 impl From<Box<dyn std::error::Error + Send + Sync>> for EotError {
     fn from(e: Box<dyn std::error::Error + Send + Sync>) -> Self {
         EotError::Other(e.to_string())
@@ -100,6 +139,28 @@ mod tests {
         assert!(msg.contains("openai.com"));
     }
 
+    #[test]
+    fn test_anthropic_overloaded_body_is_typed() {
+        let body = crate::providers::AnthropicErrorEnvelope::parse(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        )
+        .expect("envelope");
+        let e = EotError::anthropic(body, Some(529));
+        assert!(matches!(
+            &e,
+            EotError::ProviderApi { provider, error_type, status: Some(529), .. }
+                if provider == "Anthropic" && error_type == "overloaded_error"
+        ));
+        assert!(e.is_overloaded() && e.is_retryable() && !e.is_rate_limited());
+        assert_eq!(e.to_string(), "Anthropic API error (overloaded_error): Overloaded");
+
+        let invalid = crate::providers::AnthropicErrorEnvelope::parse(
+            r#"{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: too large"}}"#,
+        )
+        .expect("envelope");
+        assert!(!EotError::anthropic(invalid, Some(400)).is_retryable());
+    }
+
     #[test]
     fn test_other_wraps_string() {
         let e: EotError = EotError::from("something went wrong");
@@ -133,15 +133,8 @@ mod tests {
             text: "tok".to_string(),
             original: "tok".to_string(),
             index: idx,
-            transformed: false,
-            importance: 0.0,
-            chaos_label: None,
-            provider: None,
             confidence,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         }
     }
 
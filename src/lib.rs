@@ -27,7 +27,7 @@
 //! | `intelligence` | Reserved namespace for future interpretability features. |
 //! | `evolution` | Reserved namespace for future evolutionary optimisation. |
 //! | `helix-bridge` | HTTP bridge that polls `/api/stats` and pushes config patches. |
-//! | `redis-backing` | Write-through Redis persistence for agent memory and snapshots. |
+//! | `redis-backing` | Write-through Redis persistence for agent memory, snapshots, and shared collaboration rooms. |
 //! | `wasm` | WASM target bindings via `wasm-bindgen`. |
 //!
 //! ## Quickstart
@@ -42,8 +42,11 @@
 pub mod adaptive;
 pub mod attribution;
 pub mod batch;
+pub mod build_info;
 pub mod cli;
 pub mod collab;
+#[cfg(feature = "redis-backing")]
+pub mod collab_redis;
 pub mod comparison;
 pub mod config;
 pub mod divergence;
@@ -52,6 +55,8 @@ pub mod heatmap;
 pub mod intervention;
 pub mod mutation_lab;
 pub mod providers;
+pub mod session_search;
+pub mod perplexity_percentile;
 pub mod bayesian;
 pub mod checkpoint;
 pub mod render;
@@ -67,6 +72,7 @@ pub mod sensitivity;
 pub mod experiments;
 pub mod token_dictionary;
 pub mod transforms;
+pub mod watch;
 pub mod web;
 pub mod patching;
 pub mod logit_lens;
@@ -97,6 +103,14 @@ pub mod quality_scorer;
 pub mod context_optimizer;
 pub mod output_parser;
 pub mod prompt_library;
+pub mod prompt_template;
+pub mod preprocess;
+pub mod mcp_server;
+pub mod model_list;
+pub mod doctor;
+pub mod transform_bench;
+pub mod ab_export;
+pub mod token_align;
 pub mod token_budget;
 pub mod conversation_memory;
 pub mod query_rewriter;
@@ -129,6 +143,9 @@ pub mod context_compressor;
 pub mod chain_of_thought;
 pub mod memory_retrieval;
 
+#[cfg(test)]
+mod mock_http;
+
 #[cfg(feature = "self-tune")]
 pub mod self_tune;
 
@@ -175,11 +192,13 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 
 use providers::*;
-use transforms::{apply_heatmap_color, calculate_token_importance, tokenize, Transform};
+use transforms::{tokenize, Transform};
 
 // ---------------------------------------------------------------------------
 // Token probability types
@@ -208,7 +227,7 @@ pub struct TokenAlternative {
 /// represented as a `TokenEvent`.  Events are sent over the `web_tx` channel
 /// for SSE fan-out to the web UI, written as JSON lines in `--json-stream`
 /// mode, or recorded to a replay file.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TokenEvent {
     /// The (possibly transformed) token text shown to the user.
     pub text: String,
@@ -239,9 +258,59 @@ pub struct TokenEvent {
     /// When true, this event represents an error notification rather than a real token.
     #[serde(default)]
     pub is_error: bool,
-    /// Milliseconds elapsed since stream start when this token arrived (for latency tracking).
+    /// Milliseconds elapsed since stream start when this token arrived (for
+    /// latency tracking); see [`inter_token_gaps_ms`].
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arrival_ms: Option<u64>,
+    /// Percentile of this token's perplexity within its session, in `(0.0, 1.0]`
+    /// (1.0 = most surprising).  See [`perplexity_percentile`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perplexity_percentile: Option<f32>,
+    /// Zero-based sentence this token belongs to.  Set only with
+    /// [`Granularity::Sentence`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence_index: Option<usize>,
+    /// Exponential moving average of `importance` over the stream, which
+    /// drives heatmap colour instead of the raw score.  Set only with
+    /// [`TokenInterceptor::importance_smoothing`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smoothed_importance: Option<f64>,
+}
+
+/// Gaps in milliseconds between consecutive events' [`TokenEvent::arrival_ms`];
+/// events without an arrival time are skipped.
+pub fn inter_token_gaps_ms(events: &[TokenEvent]) -> Vec<u64> {
+    let stamps: Vec<u64> = events.iter().filter_map(|e| e.arrival_ms).collect();
+    stamps.windows(2).map(|w| w[1].saturating_sub(w[0])).collect()
+}
+
+/// One streamed tool-call fragment, written as
+/// `{"type":"tool_call","index":0,"name":"get_weather","arguments_fragment":"{\"ci"}`
+/// in `--json-stream` mode.
+///
+/// A model that calls a tool sends `delta.tool_calls` instead of
+/// `delta.content`, so without these the stream would look empty.  `name`
+/// is set on the first fragment of each call only; concatenating the
+/// `arguments_fragment`s of one `index` gives the call's JSON arguments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "tool_call")]
+pub struct ToolCallEvent {
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub arguments_fragment: String,
+}
+
+impl ToolCallEvent {
+    /// Build the event for one OpenAI tool-call fragment.
+    pub fn from_delta(delta: &OpenAIToolCallDelta) -> Self {
+        let function = delta.function.as_ref();
+        ToolCallEvent {
+            index: delta.index,
+            name: function.and_then(|f| f.name.clone()),
+            arguments_fragment: function.and_then(|f| f.arguments.clone()).unwrap_or_default(),
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -260,6 +329,9 @@ pub struct TokenEvent {
 /// - **Web UI** — events sent over the `web_tx` unbounded channel for SSE fan-out.
 /// - **JSON stream** — one JSON line per token written to stdout (`json_stream = true`).
 ///
+/// Independently of the sink, the plain transformed text (no ANSI colors) can be
+/// teed to any [`std::io::Write`] via [`TokenInterceptor::with_file_sink`].
+///
 /// Construct with [`TokenInterceptor::new`] then call [`TokenInterceptor::intercept_stream`].
 pub struct TokenInterceptor {
     client: Client,
@@ -273,6 +345,10 @@ pub struct TokenInterceptor {
     pub heatmap_mode: bool,
     pub orchestrator: bool,
     pub orchestrator_url: String,
+    /// Per-attempt limit on the orchestrator MCP call (default
+    /// [`ORCHESTRATOR_TIMEOUT`]); [`Self::timeout_secs`] lowers it further.
+    /// Attempts follow [`Self::max_retries`].
+    pub orchestrator_timeout: std::time::Duration,
     /// When set, token events are sent here instead of printed to stdout.
     pub web_tx: Option<mpsc::UnboundedSender<TokenEvent>>,
     /// When set, each emitted TokenEvent carries this provider label (for diff mode).
@@ -282,6 +358,10 @@ pub struct TokenInterceptor {
     /// When set, token processing metrics are recorded into the self-improvement bus.
     #[cfg(feature = "self-tune")]
     pub telemetry_bus: Option<std::sync::Arc<crate::self_tune::telemetry_bus::TelemetryBus>>,
+    /// When set, the model is downgraded to a cheaper alternative under budget
+    /// pressure before each stream, and each stream's estimated cost is charged.
+    #[cfg(feature = "self-tune")]
+    pub cost_router: Option<std::sync::Arc<crate::self_tune::cost::CostRouter>>,
     /// Optional in-session prompt deduplication cache.
     ///
     /// When set, `intercept_stream` checks whether the incoming prompt has been
@@ -298,6 +378,24 @@ pub struct TokenInterceptor {
     pub rate: f64,
     /// Number of top alternative tokens to request per position (OpenAI only, 0–20).
     pub top_logprobs: u8,
+    /// Request per-token log probabilities from OpenAI.  When off, events carry
+    /// no confidence, perplexity, or alternatives.
+    pub logprobs: bool,
+    /// When a model rejects `logprobs` with HTTP 400, retry once without them
+    /// (and keep them off) instead of failing the stream.  On by default.
+    pub logprobs_fallback: bool,
+    /// Treat an unparseable SSE `data:` line as a stream error instead of
+    /// logging and skipping it (configurable via --strict-parsing).
+    pub strict_parsing: bool,
+    /// SSE `data:` lines of the current stream that failed to parse.
+    pub parse_error_count: usize,
+    /// Index given to the first token, so a continued session (a later turn,
+    /// or a resumed recording) keeps numbering where the previous one
+    /// stopped.  Alternation follows the global index, not `token_count`.
+    pub start_index: usize,
+    /// Tool calls the model made in the current stream (OpenAI
+    /// `delta.tool_calls`); see [`ToolCallEvent`].
+    pub tool_call_count: usize,
     /// Per-session RNG used for Noise/Chaos transforms.  Seeded from entropy
     /// unless a fixed seed is provided via `with_seed()`.
     rng: StdRng,
@@ -310,17 +408,426 @@ pub struct TokenInterceptor {
     /// Minimum confidence threshold for transform gating. When set, only tokens
     /// with confidence at or below this value are transformed.
     pub min_confidence: Option<f64>,
+    /// EMA weight `alpha` in `(0, 1]` of the newest token's importance when
+    /// smoothing the heatmap (configurable via --importance-smoothing);
+    /// `None` colours by raw importance.
+    pub importance_smoothing: Option<f64>,
+    /// Last smoothed importance of the current stream.
+    smoothed_importance: Option<f64>,
+    /// Render each transformed token's `text` through this template, e.g.
+    /// `{original}→{text}`; `original` is unchanged (configurable via
+    /// --inline-mapping).
+    pub inline_mapping: Option<String>,
+    /// Show each run of whitespace between tokens as one space before the
+    /// next token, and collapse whitespace runs inside emitted `text` to one
+    /// space; `original` is unchanged (configurable via --collapse-whitespace).
+    pub collapse_whitespace: bool,
+    /// Whitespace was skipped since the last emitted token of the current
+    /// stream, with `collapse_whitespace` set.
+    pending_space: bool,
+    /// Which tokens the transform targets (configurable via --select).
+    pub selector: TransformSelector,
+    /// Only transform selected tokens of this heuristic part of speech
+    /// (configurable via --pos-filter).
+    pub pos_filter: Option<PosFilter>,
+    /// Whether the alternating selector works per token or per sentence
+    /// (configurable via --granularity).
+    pub granularity: Granularity,
+    /// Sentence segmentation state for [`Granularity::Sentence`].
+    sentences: transforms::SentenceTracker,
+    /// `(token_index, sub_transform)` for every token transformed by
+    /// [`Transform::Chaos`] in the current stream; see [`Self::chaos_script`].
+    chaos_script: Vec<(usize, String)>,
     /// Timestamp of the last received token, used for timing-based confidence proxy.
     last_token_instant: Option<std::time::Instant>,
     /// Maximum retry attempts for API calls on 429/5xx (configurable via --max-retries).
     pub max_retries: u32,
     /// Maximum tokens in the Anthropic response (configurable via --anthropic-max-tokens).
     pub anthropic_max_tokens: u32,
+    /// Sampling temperature sent to the provider (default 0.7).
+    pub temperature: f32,
+    /// Per-request output token cap.  Sent as `max_tokens` to OpenAI and, when
+    /// set, overrides `anthropic_max_tokens` for Anthropic.
+    pub max_tokens: Option<u32>,
     /// Instant recorded at stream start for per-token arrival latency measurement.
     stream_start_instant: Option<std::time::Instant>,
     /// Optional stream timeout in seconds. When set, `intercept_stream` will fail
     /// with a timeout error if the entire stream does not complete within this duration.
     pub timeout_secs: Option<u64>,
+    /// Additional provider-specific headers (e.g. `OpenAI-Organization`,
+    /// `anthropic-beta`) attached to every outgoing API request.  Validated by
+    /// [`providers::parse_extra_header`]; credential headers are never allowed.
+    pub extra_headers: Vec<(String, String)>,
+    /// Settings of the parameterised transforms (`--noise-chars`,
+    /// `--noise-count`, `--zalgo-intensity`) used for this interceptor's tokens.
+    pub transform_options: transforms::TransformOptions,
+    /// When set, OpenAI and Anthropic requests are sent here instead of the
+    /// provider's public endpoint (a local proxy or a test server).
+    pub endpoint_url: Option<String>,
+    /// Longest prompt, in characters, that `intercept_stream` will send
+    /// (configurable via --max-prompt-chars).
+    pub max_prompt_chars: usize,
+    /// Optional tee for the human-readable transformed text (no ANSI colors),
+    /// written alongside whichever output sink is active.  Flushed at the end
+    /// of each stream.
+    pub file_sink: Option<Box<dyn Write + Send + Sync>>,
+    /// When set, every raw SSE line received from OpenAI or Anthropic is
+    /// written here, prefixed with the provider name, before it is parsed
+    /// (configurable via --debug-raw / --debug-raw-file).
+    pub raw_sink: Option<Box<dyn Write + Send + Sync>>,
+    /// When set, tokens come from this backend instead of the built-in
+    /// provider (see [`TokenInterceptor::with_stream_backend`]).
+    pub stream_backend: Option<Box<dyn LlmStream>>,
+    /// Context-aware transform applied to selected tokens instead of
+    /// [`Self::transform`]; see [`Self::with_window_transform`].
+    pub window_transform: Option<Box<dyn transforms::WindowTransform>>,
+    /// The most recent original tokens of the current stream, oldest first,
+    /// kept for [`Self::window_transform`].
+    window_context: std::collections::VecDeque<String>,
+    /// Terminal mode: follow each transformed token with its original in
+    /// brackets, e.g. `dlrow[world]` (configurable via --show-original).
+    pub show_original: bool,
+    /// Terminal mode: colour tokens by importance or by model confidence
+    /// (configurable via --color-by).
+    pub color_by: render::ColorBy,
+    /// Print the orchestrator's original-vs-enriched prompt diff to stderr
+    /// before streaming (configurable via --show-enriched).
+    pub show_enriched: bool,
+    /// What the orchestrator did to the most recent prompt; `None` unless
+    /// `orchestrator` is set.
+    pub enrichment: Option<PromptEnrichment>,
+    /// Ordered prompt preprocessing stages run before sending (configurable
+    /// via --preprocess; see [`preprocess`]).  With `orchestrator` set and no
+    /// [`preprocess::PreprocessStage::Orchestrator`] stage, enrichment runs
+    /// after the listed stages.
+    pub preprocessors: Vec<preprocess::PreprocessStage>,
+    /// The prompt actually sent on the most recent stream, after
+    /// preprocessing and enrichment.
+    pub effective_prompt: Option<String>,
+    /// Buffer provider deltas to whole words before tokenizing, so a word
+    /// split across deltas is emitted (and transformed) as one token
+    /// (configurable via --word-boundaries).
+    pub word_boundaries: bool,
+    /// Partial word held back between deltas when `word_boundaries` is set,
+    /// with the logprob data of the delta that started it.
+    word_buffer: WordBuffer<(Option<f32>, Vec<TokenAlternative>)>,
+    /// How often terminal mode flushes stdout (configurable via --flush-policy).
+    pub flush_policy: FlushPolicy,
+    /// Unicode normalization of emitted token text (configurable via --normalize).
+    pub normalize: Normalization,
+    /// Also normalize each token's `original` (configurable via
+    /// --normalize-original); has no effect while `normalize` is `None`.
+    pub normalize_original: bool,
+    /// Tokens printed to stdout since the last flush.
+    unflushed_tokens: usize,
+    /// Omit the random jitter from heuristic importance scores so heatmaps
+    /// are reproducible (configurable via --deterministic-importance).
+    pub deterministic_importance: bool,
+    /// Cooperative cancellation flag (set by the Ctrl+C handler).  Checked
+    /// between chunks; once set, the stream stops and the footer reports the
+    /// partial counts.
+    pub stop_flag: Option<Arc<AtomicBool>>,
+    /// `true` if the last stream was cut short by [`Self::stop_flag`] or
+    /// [`Self::finish_interrupted`].
+    pub interrupted: bool,
+}
+
+/// Default for [`TokenInterceptor::temperature`].
+pub const DEFAULT_TEMPERATURE: f32 = 0.7;
+
+/// Default for [`TokenInterceptor::max_prompt_chars`] and `--max-prompt-chars`.
+pub const DEFAULT_MAX_PROMPT_CHARS: usize = 32_000;
+
+/// First back-off before retrying an Anthropic stream that aborted with
+/// `overloaded_error` or `rate_limit_error`; doubled on each further attempt.
+pub const ANTHROPIC_OVERLOADED_BACKOFF_MS: u64 = 1_000;
+
+/// Default for [`TokenInterceptor::orchestrator_timeout`].
+pub const ORCHESTRATOR_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Which tokens [`TokenInterceptor`] transforms ([`TokenInterceptor::selector`]).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TransformSelector {
+    /// Spread transforms evenly at the configured rate (every other token at
+    /// the default rate of 0.5).
+    #[default]
+    Alternating,
+    /// Transform every token whose importance exceeds the threshold,
+    /// regardless of position; the rate is ignored.  Importance is the API
+    /// confidence when available, otherwise the heuristic score.
+    ImportanceAbove(f64),
+}
+
+impl TransformSelector {
+    /// Parse `alternating` or `importance:T` with `T` in `[0.0, 1.0]`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("alternating") {
+            return Ok(TransformSelector::Alternating);
+        }
+        let threshold = s
+            .strip_prefix("importance:")
+            .ok_or_else(|| format!("invalid selector '{}': expected alternating or importance:T", s))?;
+        match threshold.trim().parse::<f64>() {
+            Ok(t) if (0.0..=1.0).contains(&t) => Ok(TransformSelector::ImportanceAbove(t)),
+            _ => Err(format!(
+                "invalid importance threshold '{}': expected a number in [0.0, 1.0]",
+                threshold
+            )),
+        }
+    }
+}
+
+/// Part-of-speech gate applied on top of the [`TransformSelector`]
+/// ([`TokenInterceptor::pos_filter`]): a selected token is only transformed
+/// when its heuristic class ([`transforms::pos_class`]) matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PosFilter {
+    /// Any word that is not a stopword.
+    Content,
+    /// Only stopwords.
+    Stopword,
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+}
+
+impl PosFilter {
+    /// Parse `content`, `stopword`, `noun`, `verb`, `adjective` or `adverb`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "content" => Ok(PosFilter::Content),
+            "stopword" | "stopwords" => Ok(PosFilter::Stopword),
+            "noun" => Ok(PosFilter::Noun),
+            "verb" => Ok(PosFilter::Verb),
+            "adjective" | "adj" => Ok(PosFilter::Adjective),
+            "adverb" | "adv" => Ok(PosFilter::Adverb),
+            _ => Err(format!(
+                "invalid part-of-speech filter '{}': expected content, stopword, noun, verb, adjective or adverb",
+                s
+            )),
+        }
+    }
+
+    /// Whether `token` passes the filter.
+    pub fn matches(self, token: &str) -> bool {
+        use transforms::PosClass;
+        match (self, transforms::pos_class(token)) {
+            (PosFilter::Content, class) => !matches!(class, PosClass::Stopword | PosClass::Other),
+            (PosFilter::Stopword, class) => class == PosClass::Stopword,
+            (PosFilter::Noun, class) => class == PosClass::Noun,
+            (PosFilter::Verb, class) => class == PosClass::Verb,
+            (PosFilter::Adjective, class) => class == PosClass::Adjective,
+            (PosFilter::Adverb, class) => class == PosClass::Adverb,
+        }
+    }
+}
+
+/// The unit [`TransformSelector::Alternating`] alternates over
+/// (configurable via `--granularity`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    /// Alternate over individual tokens (the default).
+    #[default]
+    Token,
+    /// Alternate over whole sentences: every token of a selected sentence is
+    /// transformed, and events carry their `sentence_index`.  Sentences are
+    /// split by [`transforms::SentenceTracker`].
+    Sentence,
+}
+
+impl Granularity {
+    /// Parse `token` or `sentence`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "token" => Ok(Granularity::Token),
+            "sentence" => Ok(Granularity::Sentence),
+            _ => Err(format!("invalid granularity '{}': expected token or sentence", s)),
+        }
+    }
+}
+
+/// When terminal mode flushes stdout ([`TokenInterceptor::flush_policy`]).
+///
+/// Flushing after every token gives the live typing feel but dominates
+/// runtime when large outputs are piped; the coarser policies trade latency
+/// for throughput.  Stdout is always flushed at the end of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// Flush after every printed token (the default).
+    #[default]
+    Token,
+    /// Flush once per provider chunk.
+    Chunk,
+    /// Flush after every `N` printed tokens.
+    Every(usize),
+}
+
+impl FlushPolicy {
+    /// Parse `token`, `chunk`, or a positive token count `N`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "token" => Ok(FlushPolicy::Token),
+            "chunk" => Ok(FlushPolicy::Chunk),
+            other => match other.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(FlushPolicy::Every(n)),
+                _ => Err(format!(
+                    "invalid flush policy '{}': expected token, chunk, or a positive token count",
+                    s
+                )),
+            },
+        }
+    }
+
+    /// Whether to flush given `pending` unflushed tokens, checked after each
+    /// printed token and again (with `end_of_chunk`) after each chunk.
+    pub fn should_flush(self, pending: usize, end_of_chunk: bool) -> bool {
+        if pending == 0 {
+            return false;
+        }
+        match self {
+            FlushPolicy::Token => true,
+            FlushPolicy::Chunk => end_of_chunk,
+            FlushPolicy::Every(n) => pending >= n,
+        }
+    }
+}
+
+/// Unicode normalization applied to emitted token text
+/// ([`TokenInterceptor::normalize`]).
+///
+/// Transforms such as homoglyph and zalgo, and non-ASCII model output, mix
+/// composed and decomposed forms; normalizing gives downstream consumers and
+/// the diff view one canonical form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Normalization {
+    /// Emit text as produced (the default).
+    #[default]
+    None,
+    /// Canonical composition (`e` + U+0301 becomes `é`).
+    Nfc,
+    /// Canonical decomposition (`é` becomes `e` + U+0301).
+    Nfd,
+}
+
+impl Normalization {
+    /// Parse `none`, `nfc`, or `nfd`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(Normalization::None),
+            "nfc" => Ok(Normalization::Nfc),
+            "nfd" => Ok(Normalization::Nfd),
+            _ => Err(format!("invalid normalization '{}': expected nfc, nfd, or none", s)),
+        }
+    }
+
+    /// Normalize `text` into this form.
+    pub fn apply(self, text: &str) -> String {
+        use unicode_normalization::UnicodeNormalization;
+        match self {
+            Normalization::None => text.to_string(),
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfd => text.nfd().collect(),
+        }
+    }
+}
+
+/// Template used by `--inline-mapping` when no template is given.
+pub const DEFAULT_INLINE_MAPPING: &str = "{original}\u{2192}{text}";
+
+/// Parse an `--inline-mapping` template: it must reference `{original}`
+/// or `{text}`.
+pub fn parse_inline_mapping(template: &str) -> Result<String, String> {
+    if template.contains("{original}") || template.contains("{text}") {
+        Ok(template.to_string())
+    } else {
+        Err(format!("inline mapping template '{}' must contain {{original}} or {{text}}", template))
+    }
+}
+
+/// Fill an inline-mapping `template` with a token's original and transformed text.
+pub fn render_inline_mapping(template: &str, original: &str, text: &str) -> String {
+    template.replace("{original}", original).replace("{text}", text)
+}
+
+/// `text` with every run of whitespace replaced by a single space
+/// (see [`TokenInterceptor::collapse_whitespace`]).
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+/// Return a `"prompt too long"` error if `prompt` has more than `max_chars`
+/// characters.  Shared by [`TokenInterceptor::intercept_stream`] and the web
+/// streaming handlers.
+pub fn check_prompt_length(prompt: &str, max_chars: usize) -> Result<(), String> {
+    let chars = prompt.chars().count();
+    if chars > max_chars {
+        return Err(format!(
+            "prompt too long ({} chars; max {}). Use a shorter prompt or raise --max-prompt-chars.",
+            chars, max_chars
+        ));
+    }
+    Ok(())
+}
+
+/// What the `--orchestrator` MCP pipeline did to a prompt.
+///
+/// Captured on every orchestrated stream in [`TokenInterceptor::enrichment`];
+/// printed to stderr before streaming when `show_enriched` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptEnrichment {
+    /// The prompt as supplied by the user.
+    pub original: String,
+    /// The prompt actually sent to the provider.  Equal to `original` when
+    /// enrichment failed.
+    pub enriched: String,
+    /// Why enrichment failed, if it did; the raw prompt was used instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PromptEnrichment {
+    /// Line diff of `original` against `enriched`: unchanged leading and
+    /// trailing lines are prefixed with two spaces, the differing middle with
+    /// `- ` (original) and `+ ` (enriched).  A failed enrichment is reported
+    /// as such instead of a diff.
+    pub fn render_diff(&self) -> String {
+        if let Some(err) = &self.error {
+            return format!("[orchestrator] enrichment failed ({}); raw prompt used unchanged", err);
+        }
+        if self.original == self.enriched {
+            return "[orchestrator] enriched prompt is identical to the original".to_string();
+        }
+        let old: Vec<&str> = self.original.lines().collect();
+        let new: Vec<&str> = self.enriched.lines().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut out = vec!["[orchestrator] original -> enriched prompt:".to_string()];
+        out.extend(old[..prefix].iter().map(|l| format!("  {}", l)));
+        out.extend(old[prefix..old.len() - suffix].iter().map(|l| format!("- {}", l)));
+        out.extend(new[prefix..new.len() - suffix].iter().map(|l| format!("+ {}", l)));
+        out.extend(old[old.len() - suffix..].iter().map(|l| format!("  {}", l)));
+        out.join("\n")
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -405,6 +912,37 @@ fn circuit_record_failure() {
     }
 }
 
+/// A failed orchestrator attempt: `Transient` failures (network, 5xx) are
+/// retried, `Fatal` ones (bad response) are not.
+enum OrchestratorError {
+    Transient(String),
+    Fatal(String),
+}
+
+/// Request-level metrics for one provider stream: body bytes each way, the
+/// final HTTP status, and the time from send until the body is fully read.
+struct NetworkMeter {
+    started: std::time::Instant,
+    status: u16,
+    request_bytes: u64,
+    response_bytes: u64,
+}
+
+impl NetworkMeter {
+    /// Start timing `req`, which is about to be sent.
+    fn start(req: &reqwest::Request) -> Self {
+        NetworkMeter {
+            started: std::time::Instant::now(),
+            status: 0,
+            request_bytes: req
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map_or(0, |b| b.len() as u64),
+            response_bytes: 0,
+        }
+    }
+}
+
 /// Execute a pre-built `reqwest::Request`, retrying up to `max_attempts`
 /// times on 429 / 5xx responses and network errors with exponential back-off.
 ///
@@ -440,7 +978,7 @@ async fn execute_with_retry(
             Ok(resp) => {
                 let status = resp.status().as_u16();
                 if attempt + 1 < max_attempts
-                    && (status == 429 || status == 500 || status == 502 || status == 503)
+                    && (status == 429 || status == 500 || status == 502 || status == 503 || status == 529)
                 {
                     tracing::warn!(status, attempt, "got retryable HTTP status");
                     last_err = Some(format!("HTTP {status}"));
@@ -470,6 +1008,22 @@ async fn execute_with_retry(
         .into())
 }
 
+/// Whether an OpenAI error response (`status`, `body`) is the model rejecting
+/// the `logprobs` / `top_logprobs` parameters, which some models do with a
+/// 400 whose error names the parameter.
+fn is_logprobs_rejection(status: u16, body: &str) -> bool {
+    if status != 400 {
+        return false;
+    }
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(body) else {
+        return false;
+    };
+    let error = &json["error"];
+    let param = error["param"].as_str().unwrap_or("");
+    let message = error["message"].as_str().unwrap_or("");
+    param.contains("logprobs") || message.contains("logprobs")
+}
+
 impl TokenInterceptor {
     /// Construct a new `TokenInterceptor`.
     ///
@@ -522,25 +1076,70 @@ impl TokenInterceptor {
             heatmap_mode,
             orchestrator,
             orchestrator_url: "http://localhost:3000".to_string(),
+            orchestrator_timeout: ORCHESTRATOR_TIMEOUT,
             web_tx: None,
             web_provider_label: None,
             system_prompt: None,
             #[cfg(feature = "self-tune")]
             telemetry_bus: None,
+            #[cfg(feature = "self-tune")]
+            cost_router: None,
             #[cfg(feature = "self-modify")]
             dedup: None,
             rate: 0.5,
             top_logprobs: 5,
+            logprobs: true,
+            logprobs_fallback: true,
+            strict_parsing: false,
+            parse_error_count: 0,
+            start_index: 0,
+            tool_call_count: 0,
             rng: StdRng::from_entropy(),
             recorder: None,
             json_stream: false,
             pending_delay_ms: 0,
             min_confidence: None,
+            importance_smoothing: None,
+            smoothed_importance: None,
+            selector: TransformSelector::Alternating,
+            pos_filter: None,
+            granularity: Granularity::Token,
+            sentences: transforms::SentenceTracker::default(),
+            chaos_script: Vec::new(),
             last_token_instant: None,
             max_retries: 3,
             anthropic_max_tokens: 4096,
+            temperature: DEFAULT_TEMPERATURE,
+            max_tokens: None,
             stream_start_instant: None,
             timeout_secs: None,
+            extra_headers: Vec::new(),
+            transform_options: transforms::TransformOptions::default(),
+            endpoint_url: None,
+            max_prompt_chars: DEFAULT_MAX_PROMPT_CHARS,
+            file_sink: None,
+            raw_sink: None,
+            stream_backend: None,
+            window_transform: None,
+            window_context: std::collections::VecDeque::new(),
+            show_original: false,
+            color_by: render::ColorBy::Importance,
+            show_enriched: false,
+            enrichment: None,
+            preprocessors: Vec::new(),
+            effective_prompt: None,
+            word_boundaries: false,
+            word_buffer: WordBuffer::new(),
+            flush_policy: FlushPolicy::Token,
+            normalize: Normalization::None,
+            normalize_original: false,
+            inline_mapping: None,
+            collapse_whitespace: false,
+            pending_space: false,
+            unflushed_tokens: 0,
+            deterministic_importance: false,
+            stop_flag: None,
+            interrupted: false,
         })
     }
 
@@ -551,12 +1150,33 @@ impl TokenInterceptor {
         self
     }
 
+    /// Set the sampling temperature.  Clamped to [0.0, 2.0].
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        if temperature.is_finite() {
+            self.temperature = temperature.clamp(0.0, 2.0);
+        }
+        self
+    }
+
+    /// Cap the number of tokens the provider generates per response.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens.max(1));
+        self
+    }
+
     /// Seed the internal RNG for reproducible Noise/Chaos output.
     pub fn with_seed(mut self, seed: u64) -> Self {
         self.rng = StdRng::seed_from_u64(seed);
         self
     }
 
+    /// Number tokens from `start_index` instead of 0 (see
+    /// [`Self::start_index`]).
+    pub fn with_start_index(mut self, start_index: usize) -> Self {
+        self.start_index = start_index;
+        self
+    }
+
     /// Set the channel used to fan out token events to the web UI.
     ///
     /// Calling this completes the builder chain for web-mode construction
@@ -585,6 +1205,20 @@ impl TokenInterceptor {
         self
     }
 
+    /// Request per-token log probabilities (OpenAI only; on by default).
+    /// Turning them off trims latency and response size when confidence,
+    /// perplexity, and alternatives are not needed.
+    pub fn with_logprobs(mut self, enabled: bool) -> Self {
+        self.logprobs = enabled;
+        self
+    }
+
+    /// Retry without logprobs when the model rejects them (on by default).
+    pub fn with_logprobs_fallback(mut self, enabled: bool) -> Self {
+        self.logprobs_fallback = enabled;
+        self
+    }
+
     /// Enable JSON-stream mode: emit one JSON line per token instead of ANSI text.
     pub fn with_json_stream(mut self, enabled: bool) -> Self {
         self.json_stream = enabled;
@@ -610,6 +1244,125 @@ impl TokenInterceptor {
         self
     }
 
+    /// Tee the plain transformed text of every stream to `sink`.
+    pub fn with_file_sink(mut self, sink: Box<dyn Write + Send + Sync>) -> Self {
+        self.file_sink = Some(sink);
+        self
+    }
+
+    /// Log every raw provider SSE line to `sink` before parsing.
+    pub fn with_raw_sink(mut self, sink: Box<dyn Write + Send + Sync>) -> Self {
+        self.raw_sink = Some(sink);
+        self
+    }
+
+    /// Rewrite selected tokens with `window`, which sees the tokens before
+    /// each one, instead of the interceptor's [`Transform`].  Selection (rate,
+    /// selector, confidence gating) is unchanged.
+    pub fn with_window_transform(mut self, window: Box<dyn transforms::WindowTransform>) -> Self {
+        self.window_transform = Some(window);
+        self
+    }
+
+    /// Take tokens from `backend` instead of calling the configured provider.
+    ///
+    /// Everything after the network round trip (transforms, rate, scoring,
+    /// web/file/terminal output) is unchanged, so this is the hook for custom,
+    /// replayed or in-memory providers.
+    pub fn with_stream_backend(mut self, backend: Box<dyn LlmStream>) -> Self {
+        self.stream_backend = Some(backend);
+        self
+    }
+
+    /// Append `preprocessor` to the prompt preprocessing pipeline; stages run
+    /// in the order they were added.
+    pub fn with_preprocessor(mut self, preprocessor: Box<dyn preprocess::PromptPreprocessor>) -> Self {
+        self.preprocessors.push(preprocess::PreprocessStage::Custom(preprocessor));
+        self
+    }
+
+    /// Score importance without random jitter (see `deterministic_importance`).
+    pub fn with_deterministic_importance(mut self, deterministic: bool) -> Self {
+        self.deterministic_importance = deterministic;
+        self
+    }
+
+    /// Heuristic importance of `token` at `position`, jittered unless
+    /// `deterministic_importance` is set.
+    fn heuristic_importance(&mut self, token: &str, position: usize) -> f64 {
+        if self.deterministic_importance {
+            transforms::calculate_token_importance_deterministic(token, position)
+        } else {
+            transforms::calculate_token_importance_rng(token, position, &mut self.rng)
+        }
+    }
+
+    /// Stop streaming as soon as `flag` is set.
+    pub fn with_stop_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.stop_flag = Some(flag);
+        self
+    }
+
+    /// `true` once the attached stop flag has been set.
+    fn stop_requested(&self) -> bool {
+        self.stop_flag
+            .as_ref()
+            .is_some_and(|f| f.load(Ordering::Relaxed))
+    }
+
+    /// Reject prompts longer than `chars` characters.
+    pub fn with_max_prompt_chars(mut self, chars: usize) -> Self {
+        self.max_prompt_chars = chars;
+        self
+    }
+
+    /// Attach `headers` to every outgoing provider request.
+    pub fn with_extra_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.extra_headers = headers;
+        self
+    }
+
+    /// Configure the parameterised transforms (see [`transforms::TransformOptions`]).
+    pub fn with_transform_options(mut self, options: transforms::TransformOptions) -> Self {
+        self.transform_options = options;
+        self
+    }
+
+    /// The prompt the last stream actually sent, when preprocessing or
+    /// enrichment changed it from `prompt`.
+    pub fn rewritten_prompt(&self, prompt: &str) -> Option<&str> {
+        self.effective_prompt.as_deref().filter(|sent| *sent != prompt)
+    }
+
+    /// Consult `router` before each stream and downgrade the model under budget pressure.
+    #[cfg(feature = "self-tune")]
+    pub fn with_cost_router(
+        mut self,
+        router: std::sync::Arc<crate::self_tune::cost::CostRouter>,
+    ) -> Self {
+        self.cost_router = Some(router);
+        self
+    }
+
+    /// Choose which tokens are transformed (see [`TransformSelector`]).
+    pub fn with_selector(mut self, selector: TransformSelector) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Smooth heatmap importance with an exponential moving average of
+    /// weight `alpha` (see [`Self::importance_smoothing`]).
+    pub fn with_importance_smoothing(mut self, alpha: f64) -> Self {
+        self.importance_smoothing = Some(alpha);
+        self
+    }
+
+    /// Alternate over tokens or whole sentences (see [`Granularity`]).
+    pub fn with_granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
     /// Only transform tokens whose API confidence is at or below this threshold.
     pub fn with_min_confidence(mut self, threshold: f64) -> Self {
         self.min_confidence = Some(threshold);
@@ -641,7 +1394,7 @@ impl TokenInterceptor {
     /// sent over the `web_tx` channel for SSE fan-out.
     ///
     /// # Errors
-    /// Returns an error if the prompt is empty, exceeds 512 KB, the API key is
+    /// Returns an error if the prompt is empty, exceeds `max_prompt_chars`, the API key is
     /// missing, the HTTP request fails after all retries, or JSON parsing fails.
     pub async fn intercept_stream(
         &mut self,
@@ -667,6 +1420,14 @@ impl TokenInterceptor {
     ) -> Result<(), Box<dyn std::error::Error>> {
         // Record stream start for per-token arrival latency measurement (item 8).
         self.stream_start_instant = Some(std::time::Instant::now());
+        self.interrupted = false;
+        self.chaos_script.clear();
+        self.sentences = transforms::SentenceTracker::default();
+        self.smoothed_importance = None;
+        self.window_context.clear();
+        self.parse_error_count = 0;
+        self.tool_call_count = 0;
+        self.pending_space = false;
         // Note: we log diagnostics here but do not hold an entered span across
         // await points -- EnteredSpan is !Send and would prevent tokio::spawn.
         tracing::info!(
@@ -681,15 +1442,11 @@ impl TokenInterceptor {
             tracing::error!("prompt is empty — aborting");
             return Err("Prompt must not be empty".into());
         }
-        // Rough guard against prompts that would exceed typical API limits.
-        // 512 KB ≈ ~128K tokens at 4 bytes/token; APIs will reject anyway but
-        // failing fast gives a clearer error message.
-        if prompt.len() > 512_000 {
-            return Err(format!(
-                "Prompt is too long ({} bytes; max 512 KB). Use a shorter prompt.",
-                prompt.len()
-            )
-            .into());
+        // Size and cost guard: reject oversized prompts before any API call.
+        // A preprocessing pipeline may shorten the prompt, so it is checked
+        // after preprocessing instead.
+        if self.preprocessors.is_empty() {
+            check_prompt_length(prompt, self.max_prompt_chars)?;
         }
 
         // ── Prompt deduplication gate ─────────────────────────────────────────
@@ -718,15 +1475,8 @@ impl TokenInterceptor {
                                 text: msg.clone(),
                                 original: prompt.to_string(),
                                 index: 0,
-                                transformed: false,
-                                importance: 0.0,
-                                chaos_label: None,
                                 provider: self.web_provider_label.clone(),
-                                confidence: None,
-                                perplexity: None,
-                                alternatives: vec![],
-                                is_error: false,
-                                arrival_ms: None,
+                                ..Default::default()
                             };
                             let _ = tx.send(evt);
                         } else {
@@ -742,65 +1492,103 @@ impl TokenInterceptor {
             }
         }
 
+        // ── Budget-aware model downgrade ──────────────────────────────────────
+        #[cfg(feature = "self-tune")]
+        if let Some(router) = &self.cost_router {
+            if let Some(cheaper) = router.route(&self.model) {
+                let msg = format!(
+                    "[cost] budget pressure {}: downgrading {} → {}",
+                    router.pressure(),
+                    self.model,
+                    cheaper
+                );
+                tracing::warn!(from = %self.model, to = %cheaper, "budget pressure model downgrade");
+                if let Some(tx) = &self.web_tx {
+                    let evt = TokenEvent {
+                        text: msg,
+                        index: 0,
+                        provider: self.web_provider_label.clone(),
+                        is_error: true,
+                        ..Default::default()
+                    };
+                    let _ = tx.send(evt);
+                } else {
+                    eprintln!("{}", msg.bright_yellow());
+                }
+                self.model = cheaper;
+            }
+        }
+
         if self.web_tx.is_none() {
             self.print_header(prompt);
         }
 
-        // If --orchestrator is active, pre-process through MCP pipeline
-        let effective_prompt = if self.orchestrator {
-            eprintln!(
-                "{}",
-                "[orchestrator] routing through MCP pipeline at localhost:3000".bright_magenta()
-            );
-            match self.orchestrator_infer(prompt).await {
-                Ok(enriched) => enriched,
-                Err(e) => {
-                    eprintln!(
-                        "{} {}",
-                        "[orchestrator] pipeline unavailable, using raw prompt:".bright_red(),
-                        e
-                    );
-                    if let Some(tx) = &self.web_tx {
-                        let evt = TokenEvent {
-                            text: format!("[orchestrator error] {}", e),
-                            original: String::new(),
-                            index: 0,
-                            transformed: false,
-                            importance: 0.0,
-                            chaos_label: None,
-                            provider: self.web_provider_label.clone(),
-                            confidence: None,
-                            perplexity: None,
-                            alternatives: vec![],
-                            is_error: true,
-                            arrival_ms: None,
-                        };
-                        let _ = tx.send(evt);
-                    }
-                    prompt.to_string()
-                }
-            }
+        let effective_prompt = self.preprocess_prompt(prompt).await?;
+        check_prompt_length(&effective_prompt, self.max_prompt_chars)?;
+        self.effective_prompt = Some(effective_prompt.clone());
+
+        if let Some(backend) = self.stream_backend.take() {
+            let result = self.stream_from_backend(backend.as_ref(), &effective_prompt).await;
+            self.stream_backend = Some(backend);
+            result?;
         } else {
-            prompt.to_string()
-        };
+            match self.provider {
+                Provider::Openai => self.stream_openai(&effective_prompt).await?,
+                Provider::Anthropic => self.stream_anthropic(&effective_prompt).await?,
+                Provider::Mock => self.stream_from_backend(&MockStream, &effective_prompt).await?,
+            }
+        }
 
-        match self.provider {
-            Provider::Openai => self.stream_openai(&effective_prompt).await?,
-            Provider::Anthropic => self.stream_anthropic(&effective_prompt).await?,
-            Provider::Mock => self.stream_mock(&effective_prompt).await?,
+        #[cfg(feature = "self-tune")]
+        if let Some(router) = &self.cost_router {
+            // ~4 bytes per token is the same rough estimate used elsewhere.
+            router.record(
+                &self.model,
+                (effective_prompt.len() / 4) as u64,
+                self.token_count as u64,
+            );
+        }
+
+        if self.stop_requested() {
+            self.finish_interrupted();
+            return Ok(());
         }
 
+        if let Some(sink) = &mut self.file_sink {
+            if let Err(e) = sink.flush() {
+                tracing::warn!(err = %e, "file sink flush failed");
+            }
+        }
+        self.flush_stdout();
+
         if self.web_tx.is_none() {
             self.print_footer();
         }
         Ok(())
     }
 
+    /// Wrap up a stream that was cancelled part-way: mark it interrupted,
+    /// flush the file sink, and (in terminal mode) print the footer with the
+    /// counts accumulated so far.
+    pub fn finish_interrupted(&mut self) {
+        self.interrupted = true;
+        if let Some(sink) = &mut self.file_sink {
+            if let Err(e) = sink.flush() {
+                tracing::warn!(err = %e, "file sink flush failed");
+            }
+        }
+        self.flush_stdout();
+        if self.web_tx.is_none() {
+            self.print_footer();
+        }
+    }
+
     // -----------------------------------------------------------------------
     // OpenAI streaming
     // -----------------------------------------------------------------------
 
-    async fn stream_openai(&mut self, prompt: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// Build the authenticated OpenAI streaming request for `prompt`.
+    fn openai_request(&self, prompt: &str) -> Result<reqwest::Request, reqwest::Error> {
         let mut messages = Vec::new();
         if let Some(sys) = &self.system_prompt {
             messages.push(OpenAIChatMessage {
@@ -816,51 +1604,135 @@ impl TokenInterceptor {
             model: self.model.clone(),
             messages,
             stream: true,
-            temperature: 0.7,
-            logprobs: true,
-            top_logprobs: self.top_logprobs,
+            temperature: self.temperature,
+            logprobs: self.logprobs,
+            top_logprobs: self.logprobs.then_some(self.top_logprobs),
+            max_tokens: self.max_tokens,
         };
 
-        let req = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .build()?;
+        self.with_extra_request_headers(
+            self.client
+                .post(
+                    self.endpoint_url
+                        .as_deref()
+                        .unwrap_or("https://api.openai.com/v1/chat/completions"),
+                )
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json"),
+        )
+        .json(&request)
+        .build()
+    }
 
-        // Retry on 429 / 5xx with exponential back-off (#5).
-        let response = execute_with_retry(&self.client, req, self.max_retries)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error> { e.to_string().into() })?;
+    /// Log a finished provider request and, with `self-tune`, publish it as a `PipelineStage::Network` event.
+    fn record_network(&self, meter: &NetworkMeter) {
+        let duration = meter.started.elapsed();
+        tracing::debug!(
+            provider = %self.provider,
+            status = meter.status,
+            request_bytes = meter.request_bytes,
+            response_bytes = meter.response_bytes,
+            duration_ms = duration.as_millis() as u64,
+            "provider request finished"
+        );
+        #[cfg(feature = "self-tune")]
+        if let Some(bus) = &self.telemetry_bus {
+            bus.record_network(crate::self_tune::telemetry_bus::NetworkObs {
+                status: meter.status,
+                request_bytes: meter.request_bytes,
+                response_bytes: meter.response_bytes,
+                duration_us: duration.as_micros() as u64,
+            });
+        }
+    }
 
-        if !response.status().is_success() {
+    /// Write one raw SSE line to [`Self::raw_sink`].  A failing sink is
+    /// dropped so debugging output can never abort a stream.
+    fn log_raw_line(&mut self, line: &str) {
+        if let Some(sink) = &mut self.raw_sink {
+            if let Err(e) = writeln!(sink, "[{}] {}", self.provider, line).and_then(|_| sink.flush()) {
+                eprintln!("[warn] raw SSE log write failed: {} (logging disabled)", e);
+                self.raw_sink = None;
+            }
+        }
+    }
+
+    /// Append the user-supplied [`extra_headers`](Self::extra_headers) to `builder`.
+    fn with_extra_request_headers(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        self.extra_headers
+            .iter()
+            .fold(builder, |b, (name, value)| b.header(name.as_str(), value.as_str()))
+    }
+
+    async fn stream_openai(&mut self, prompt: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (response, mut meter) = loop {
+            let req = self.openai_request(prompt)?;
+            let mut meter = NetworkMeter::start(&req);
+
+            // Retry on 429 / 5xx with exponential back-off (#5).
+            let response = execute_with_retry(&self.client, req, self.max_retries)
+                .await
+                .map_err(|e| -> Box<dyn std::error::Error> { e.to_string().into() })?;
+            meter.status = response.status().as_u16();
+
+            if response.status().is_success() {
+                break (response, meter);
+            }
             let error_text = response.text().await?;
+            meter.response_bytes += error_text.len() as u64;
+            self.record_network(&meter);
+            if self.logprobs && self.logprobs_fallback && is_logprobs_rejection(meter.status, &error_text) {
+                // Turning logprobs off bounds this to one retry.
+                self.logprobs = false;
+                tracing::warn!(model = %self.model, "model rejected logprobs; retrying without them");
+                let msg = format!(
+                    "[info] {} does not support logprobs — retrying without them; confidence metrics will be unavailable for this run",
+                    self.model
+                );
+                if let Some(tx) = &self.web_tx {
+                    let _ = tx.send(TokenEvent {
+                        text: msg,
+                        index: self.start_index + self.token_count,
+                        provider: self.web_provider_label.clone(),
+                        is_error: true,
+                        ..Default::default()
+                    });
+                } else {
+                    eprintln!("{}", msg);
+                }
+                continue;
+            }
             return Err(format!("OpenAI API error: {}", error_text).into());
-        }
+        };
 
         let mut stream = response.bytes_stream();
         let mut buffer = String::new();
-        let mut dropped_chunks: usize = 0;
+        let mut decoder = Utf8ChunkDecoder::new();
 
         while let Some(chunk) = stream.next().await {
+            if self.stop_requested() {
+                break;
+            }
             let chunk = chunk?;
-            // Reject invalid UTF-8 rather than silently replacing bytes (#4).
-            let chunk_str = match std::str::from_utf8(&chunk) {
-                Ok(s) => s.to_string(),
-                Err(e) => {
-                    tracing::warn!(error = %e, "invalid UTF-8 in OpenAI stream chunk — skipping");
-                    continue;
-                }
-            };
-            buffer.push_str(&chunk_str);
+            meter.response_bytes += chunk.len() as u64;
+            // Characters split across chunks are held back until complete;
+            // invalid bytes are dropped rather than replaced (#4).
+            buffer.push_str(&decoder.decode(&chunk));
 
             while let Some(line_end) = buffer.find('\n') {
                 let line = buffer[..line_end].trim().to_string();
                 buffer.drain(..=line_end);
+                if !line.is_empty() {
+                    self.log_raw_line(&line);
+                }
 
                 if line.starts_with("data: ") && line != "data: [DONE]" {
                     let json_str = line.strip_prefix("data: ").unwrap_or(&line);
+                    if let Some(message) = providers::sse_stream_error(json_str) {
+                        self.flush_word_buffer();
+                        self.record_network(&meter);
+                        return Err(format!("OpenAI stream error: {}", message).into());
+                    }
                     match serde_json::from_str::<OpenAIChunk>(json_str) {
                         Ok(parsed) => {
                             if let Some(choice) = parsed.choices.first() {
@@ -882,7 +1754,7 @@ impl TokenInterceptor {
                                             (Some(lc.logprob), alts)
                                         })
                                         .unwrap_or((None, vec![]));
-                                    self.process_content_logprob(content, log_prob, top_alts);
+                                    self.process_delta(content, log_prob, top_alts);
                                     if self.pending_delay_ms > 0 {
                                         tokio::time::sleep(std::time::Duration::from_millis(
                                             self.pending_delay_ms,
@@ -891,93 +1763,202 @@ impl TokenInterceptor {
                                         self.pending_delay_ms = 0;
                                     }
                                 }
+                                for call in &choice.delta.tool_calls {
+                                    self.process_tool_call_delta(call);
+                                }
                             }
                         }
-                        Err(_) => {
-                            tracing::warn!(line = %json_str, "failed to parse SSE chunk; skipping");
-                            dropped_chunks += 1;
+                        Err(e) => {
+                            if let Err(message) = self.note_parse_error(json_str, &e) {
+                                self.flush_word_buffer();
+                                self.record_network(&meter);
+                                return Err(format!("OpenAI stream parse error: {}", message).into());
+                            }
                         }
                     }
                 }
             }
         }
 
-        if dropped_chunks > 0 {
-            tracing::warn!(dropped_chunks, "SSE chunks were dropped during stream");
+        self.flush_word_buffer();
+        self.record_network(&meter);
+
+        if self.parse_error_count > 0 {
+            tracing::warn!(dropped_chunks = self.parse_error_count, "SSE chunks were dropped during stream");
         }
 
         Ok(())
     }
 
-    // -----------------------------------------------------------------------
-    // Anthropic streaming
-    // -----------------------------------------------------------------------
-
-    async fn stream_anthropic(&mut self, prompt: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Anthropic's streaming API does not expose logprobs (#8).
-        // confidence/perplexity fields will be None for every token in this
-        // stream. Cross-provider perplexity comparisons require normalisation
-        // because the models operate over different vocabulary sizes (#20).
-        tracing::debug!(
-            "Anthropic stream: logprobs unavailable; confidence/perplexity will be None"
-        );
-        if self.web_tx.is_none() {
-            eprintln!("[info] Anthropic does not provide logprobs — confidence metrics will be unavailable for this run");
+    /// Surface one tool-call fragment instead of dropping it: a
+    /// [`ToolCallEvent`] line in `--json-stream` mode, otherwise a
+    /// `[tool call] NAME` note when the call starts — sent to the web channel
+    /// flagged `is_error` so it is not counted as a token, or printed to
+    /// stderr in the terminal.
+    fn process_tool_call_delta(&mut self, delta: &OpenAIToolCallDelta) {
+        let event = ToolCallEvent::from_delta(delta);
+        if event.name.is_some() {
+            self.tool_call_count += 1;
+        }
+        if self.web_tx.is_none() && self.json_stream {
+            if let Ok(line) = serde_json::to_string(&event) {
+                println!("{}", line);
+            }
+            return;
+        }
+        let Some(name) = event.name else { return };
+        if let Some(tx) = &self.web_tx {
+            let _ = tx.send(TokenEvent {
+                text: format!("[tool call] {}", name),
+                index: self.start_index + self.token_count,
+                provider: self.web_provider_label.clone(),
+                is_error: true,
+                ..Default::default()
+            });
+        } else {
+            eprintln!(
+                "{}",
+                format!("[tool call] the model is calling `{}` instead of replying with text", name)
+                    .bright_yellow()
+            );
         }
+    }
+
+    /// Count an SSE `data:` line that failed to parse.  Lenient mode logs it
+    /// and skips it; with [`Self::strict_parsing`] the returned message
+    /// becomes the stream's error.
+    fn note_parse_error(&mut self, json_str: &str, err: &serde_json::Error) -> Result<(), String> {
+        self.parse_error_count += 1;
+        if self.strict_parsing {
+            let excerpt: String = json_str.chars().take(120).collect();
+            return Err(format!("{} in chunk {:?}", err, excerpt));
+        }
+        tracing::warn!(line = %json_str, err = %err, "failed to parse SSE chunk; skipping");
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Anthropic streaming
+    // -----------------------------------------------------------------------
 
+    /// Build the authenticated Anthropic streaming request for `prompt`.
+    fn anthropic_request(&self, prompt: &str) -> Result<reqwest::Request, reqwest::Error> {
         let request = AnthropicRequest {
             model: self.model.clone(),
             messages: vec![AnthropicMessage {
                 role: "user".to_string(),
                 content: prompt.to_string(),
             }],
-            max_tokens: self.anthropic_max_tokens,
+            max_tokens: self.max_tokens.unwrap_or(self.anthropic_max_tokens),
             stream: true,
-            temperature: 0.7,
+            // Anthropic accepts 0.0–1.0.
+            temperature: self.temperature.min(1.0),
             system: self.system_prompt.clone(),
         };
 
-        let req = self
-            .client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", providers::ANTHROPIC_API_VERSION)
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .build()?;
+        self.with_extra_request_headers(
+            self.client
+                .post(
+                    self.endpoint_url
+                        .as_deref()
+                        .unwrap_or("https://api.anthropic.com/v1/messages"),
+                )
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", providers::ANTHROPIC_API_VERSION)
+                .header("Content-Type", "application/json"),
+        )
+        .json(&request)
+        .build()
+    }
+
+    /// Stream from Anthropic, retrying up to [`Self::max_retries`] attempts
+    /// in total when the stream aborts with `overloaded_error` or
+    /// `rate_limit_error` before any token arrived.  Rejected requests are
+    /// already retried by [`execute_with_retry`].
+    async fn stream_anthropic(&mut self, prompt: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let attempts = self.max_retries.max(1);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let tokens_before = self.token_count;
+            let retryable = |e: &(dyn std::error::Error + 'static)| {
+                matches!(
+                    e.downcast_ref::<error::EotError>(),
+                    Some(err @ error::EotError::ProviderApi { status: None, .. }) if err.is_retryable()
+                )
+            };
+            match self.stream_anthropic_once(prompt).await {
+                Err(e) if attempt < attempts && self.token_count == tokens_before && retryable(e.as_ref()) => {}
+                result => return result,
+            }
+            let backoff = ANTHROPIC_OVERLOADED_BACKOFF_MS << (attempt - 1).min(4);
+            tracing::warn!(attempt, backoff_ms = backoff, "Anthropic stream overloaded or rate limited; retrying");
+            tokio::time::sleep(std::time::Duration::from_millis(backoff)).await;
+        }
+    }
+
+    async fn stream_anthropic_once(&mut self, prompt: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Anthropic's streaming API does not expose logprobs (#8).
+        // confidence/perplexity fields will be None for every token in this
+        // stream. Cross-provider perplexity comparisons require normalisation
+        // because the models operate over different vocabulary sizes (#20).
+        tracing::debug!(
+            "Anthropic stream: logprobs unavailable; confidence/perplexity will be None"
+        );
+        if self.web_tx.is_none() {
+            eprintln!("[info] Anthropic does not provide logprobs — confidence metrics will be unavailable for this run");
+        }
+
+        let req = self.anthropic_request(prompt)?;
+        let mut meter = NetworkMeter::start(&req);
 
         // Retry on 429 / 5xx with exponential back-off (#5).
         let response = execute_with_retry(&self.client, req, self.max_retries)
             .await
             .map_err(|e| -> Box<dyn std::error::Error> { e.to_string().into() })?;
+        meter.status = response.status().as_u16();
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
-            return Err(format!("Anthropic API error: {}", error_text).into());
+            meter.response_bytes += error_text.len() as u64;
+            self.record_network(&meter);
+            return Err(match providers::AnthropicErrorEnvelope::parse(&error_text) {
+                Some(body) => error::EotError::anthropic(body, Some(meter.status)).into(),
+                None => format!("Anthropic API error: {}", error_text).into(),
+            });
         }
 
         let mut stream = response.bytes_stream();
         let mut buffer = String::new();
-        let mut dropped_chunks: usize = 0;
+        let mut decoder = Utf8ChunkDecoder::new();
 
         while let Some(chunk) = stream.next().await {
+            if self.stop_requested() {
+                break;
+            }
             let chunk = chunk?;
-            // Reject invalid UTF-8 rather than silently replacing bytes (#4).
-            let chunk_str = match std::str::from_utf8(&chunk) {
-                Ok(s) => s.to_string(),
-                Err(e) => {
-                    tracing::warn!(error = %e, "invalid UTF-8 in Anthropic stream chunk — skipping");
-                    continue;
-                }
-            };
-            buffer.push_str(&chunk_str);
+            meter.response_bytes += chunk.len() as u64;
+            // Characters split across chunks are held back until complete;
+            // invalid bytes are dropped rather than replaced (#4).
+            buffer.push_str(&decoder.decode(&chunk));
 
             while let Some(line_end) = buffer.find('\n') {
                 let line = buffer[..line_end].trim().to_string();
                 buffer.drain(..=line_end);
+                if !line.is_empty() {
+                    self.log_raw_line(&line);
+                }
 
                 if line.starts_with("data: ") {
                     let json_str = line.strip_prefix("data: ").unwrap_or(&line);
+                    if let Some(message) = providers::sse_stream_error(json_str) {
+                        self.flush_word_buffer();
+                        self.record_network(&meter);
+                        return Err(match providers::AnthropicErrorEnvelope::parse(json_str) {
+                            Some(body) => error::EotError::anthropic(body, None).into(),
+                            None => format!("Anthropic stream error: {}", message).into(),
+                        });
+                    }
                     match serde_json::from_str::<AnthropicStreamEvent>(json_str) {
                         Ok(event) => {
                             if event.event_type == "content_block_delta" {
@@ -1000,7 +1981,7 @@ impl TokenInterceptor {
                                         // Convert timing_confidence to a log_prob approximation if available
                                         let timing_logprob =
                                             timing_confidence.map(|c| c.ln().max(-10.0));
-                                        self.process_content_logprob(text, timing_logprob, vec![]);
+                                        self.process_delta(text, timing_logprob, vec![]);
                                         if self.pending_delay_ms > 0 {
                                             tokio::time::sleep(std::time::Duration::from_millis(
                                                 self.pending_delay_ms,
@@ -1012,121 +1993,167 @@ impl TokenInterceptor {
                                 }
                             }
                         }
-                        Err(_) => {
-                            tracing::warn!(line = %json_str, "failed to parse SSE chunk; skipping");
-                            dropped_chunks += 1;
+                        Err(e) => {
+                            if let Err(message) = self.note_parse_error(json_str, &e) {
+                                self.flush_word_buffer();
+                                self.record_network(&meter);
+                                return Err(format!("Anthropic stream parse error: {}", message).into());
+                            }
                         }
                     }
                 }
             }
         }
 
-        if dropped_chunks > 0 {
-            tracing::warn!(dropped_chunks, "SSE chunks were dropped during stream");
+        self.flush_word_buffer();
+        self.record_network(&meter);
+
+        if self.parse_error_count > 0 {
+            tracing::warn!(dropped_chunks = self.parse_error_count, "SSE chunks were dropped during stream");
         }
 
         Ok(())
     }
 
     // -----------------------------------------------------------------------
-    // Mock streaming (no network call — replays a canned fixture)
+    // Pluggable backend streaming
     // -----------------------------------------------------------------------
 
-    async fn stream_mock(&mut self, prompt: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Canned fixture: realistic token stream with logprob data.
-        // Simulates a response to any prompt without hitting any API.
-        let prompt_prefix = prompt[..prompt.len().min(20)].to_string();
-        let fixture: Vec<(String, f32)> = vec![
-            ("The".to_string(), -0.12),
-            (" quick".to_string(), -0.45),
-            (" brown".to_string(), -0.78),
-            (" fox".to_string(), -0.23),
-            (" jumps".to_string(), -0.56),
-            (" over".to_string(), -0.34),
-            (" the".to_string(), -0.11),
-            (" lazy".to_string(), -0.89),
-            (" dog".to_string(), -0.19),
-            (".".to_string(), -0.07),
-            (" This".to_string(), -0.62),
-            (" is".to_string(), -0.15),
-            (" a".to_string(), -0.08),
-            (" mock".to_string(), -0.31),
-            (" response".to_string(), -0.44),
-            (" for".to_string(), -0.27),
-            (" prompt".to_string(), -0.53),
-            (":".to_string(), -0.18),
-            (" \"".to_string(), -0.39),
-            (prompt_prefix, -0.71),
-        ];
+    async fn stream_from_backend(
+        &mut self,
+        backend: &dyn LlmStream,
+        prompt: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let req = StreamRequest {
+            prompt: prompt.to_string(),
+            system: self.system_prompt.clone(),
+            model: self.model.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            logprobs: self.logprobs,
+        };
+        let mut sink = |delta: TokenDelta| {
+            if !self.stop_requested() {
+                self.process_delta(&delta.text, delta.logprob, delta.top_alternatives);
+            }
+        };
+        let result = backend.stream(&req, &mut sink).await;
+        self.flush_word_buffer();
+        result.map_err(|e| e as Box<dyn std::error::Error>)
+    }
 
-        // Vary fixture starting position based on prompt content for more realistic tests
-        let prompt_hash: usize = prompt
-            .bytes()
-            .fold(0usize, |acc, b| acc.wrapping_add(b as usize));
-        let offset = prompt_hash % fixture.len();
-
-        for idx in 0..fixture.len() {
-            let (token_text, logprob) = &fixture[(idx + offset) % fixture.len()];
-            let token_text = token_text.clone();
-            let confidence = logprob.exp().clamp(0.0_f32, 1.0_f32);
-            let perplexity = (-logprob).exp();
-            let importance = calculate_token_importance(&token_text, idx);
-            let should_transform = idx % 2 == 1;
-
-            let (display_text, chaos_label) = if should_transform {
-                let (t, label) = self.transform.apply_with_label(&token_text);
-                let cl = if matches!(self.transform, Transform::Chaos) {
-                    Some(label.to_string())
-                } else {
-                    None
-                };
-                (t, cl)
-            } else {
-                (token_text.clone(), None)
-            };
+    // -----------------------------------------------------------------------
+    // Prompt preprocessing
+    // -----------------------------------------------------------------------
 
-            if should_transform {
-                self.transformed_count += 1;
+    /// Run `prompt` through [`Self::preprocessors`] in order, with orchestrator
+    /// enrichment last when `orchestrator` is set and the pipeline does not
+    /// place it.
+    async fn preprocess_prompt(&mut self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let stages = std::mem::take(&mut self.preprocessors);
+        let implicit_orchestrator = self.orchestrator
+            && !stages.iter().any(|s| matches!(s, preprocess::PreprocessStage::Orchestrator));
+        let mut current = prompt.to_string();
+        let mut result = Ok(());
+        for stage in &stages {
+            match stage {
+                preprocess::PreprocessStage::Orchestrator => current = self.enrich_prompt(&current).await,
+                preprocess::PreprocessStage::Custom(p) => match p.process(&current) {
+                    Ok(next) => current = next,
+                    Err(e) => {
+                        result = Err(format!("preprocess stage `{}` failed: {}", p.name(), e));
+                        break;
+                    }
+                },
             }
-            self.token_count += 1;
-
-            if let Some(tx) = &self.web_tx {
-                let evt = TokenEvent {
-                    text: display_text.clone(),
-                    original: token_text.clone(),
-                    index: idx,
-                    transformed: should_transform,
-                    importance,
-                    chaos_label,
-                    provider: self.web_provider_label.clone(),
-                    confidence: Some(confidence),
-                    perplexity: Some(perplexity),
-                    alternatives: vec![
-                        TokenAlternative {
-                            token: "a".to_string(),
-                            probability: 0.15,
-                        },
-                        TokenAlternative {
-                            token: "the".to_string(),
-                            probability: 0.10,
-                        },
-                    ],
-                    is_error: false,
-                    arrival_ms: None,
-                };
-                let _ = tx.send(evt);
-            } else {
-                self.process_content_logprob(&token_text, Some(*logprob), vec![]);
+        }
+        self.preprocessors = stages;
+        result?;
+        if implicit_orchestrator {
+            current = self.enrich_prompt(&current).await;
+        }
+        Ok(current)
+    }
+
+    /// Pre-process `prompt` through the MCP orchestrator pipeline, recording
+    /// the outcome in [`Self::enrichment`].  Falls back to `prompt` unchanged
+    /// when the orchestrator is unavailable.
+    async fn enrich_prompt(&mut self, prompt: &str) -> String {
+        if self.web_tx.is_none() {
+            eprintln!(
+                "{}",
+                format!("[orchestrator] routing through MCP pipeline at {}", self.orchestrator_url).bright_magenta()
+            );
+        }
+        let enrichment = match self.orchestrator_infer(prompt).await {
+            Ok(enriched) => PromptEnrichment {
+                original: prompt.to_string(),
+                enriched,
+                error: None,
+            },
+            Err(e) => {
+                eprintln!(
+                    "{} {}",
+                    "[orchestrator] pipeline unavailable, using raw prompt:".bright_red(),
+                    e
+                );
+                if let Some(tx) = &self.web_tx {
+                    let evt = TokenEvent {
+                        text: format!("[orchestrator error] {}", e),
+                        index: 0,
+                        provider: self.web_provider_label.clone(),
+                        is_error: true,
+                        ..Default::default()
+                    };
+                    let _ = tx.send(evt);
+                }
+                PromptEnrichment {
+                    original: prompt.to_string(),
+                    enriched: prompt.to_string(),
+                    error: Some(e.to_string()),
+                }
             }
+        };
+        if self.show_enriched {
+            eprintln!("{}", enrichment.render_diff().bright_magenta());
         }
-        Ok(())
+        let effective = enrichment.enriched.clone();
+        self.enrichment = Some(enrichment);
+        effective
     }
 
     // -----------------------------------------------------------------------
     // Orchestrator MCP infer call
     // -----------------------------------------------------------------------
 
+    /// Call the orchestrator, retrying timeouts, network errors and 5xx
+    /// responses up to [`Self::max_retries`] attempts in total.
     async fn orchestrator_infer(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let timeout = self
+            .timeout_secs
+            .map_or(self.orchestrator_timeout, |secs| {
+                self.orchestrator_timeout.min(std::time::Duration::from_secs(secs))
+            });
+        let attempts = self.max_retries.max(1);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = tokio::time::timeout(timeout, self.orchestrator_infer_once(prompt)).await;
+            let (retryable, err) = match result {
+                Ok(Ok(text)) => return Ok(text),
+                Ok(Err(OrchestratorError::Transient(e))) => (true, e),
+                Ok(Err(OrchestratorError::Fatal(e))) => (false, e),
+                Err(_) => (true, format!("orchestrator timed out after {} ms", timeout.as_millis())),
+            };
+            if !retryable || attempt >= attempts {
+                return Err(err.into());
+            }
+            tracing::warn!(attempt, err = %err, "orchestrator call failed; retrying");
+            tokio::time::sleep(std::time::Duration::from_millis(200u64 << attempt.min(4))).await;
+        }
+    }
+
+    async fn orchestrator_infer_once(&self, prompt: &str) -> Result<String, OrchestratorError> {
         let mcp_request = McpInferRequest {
             jsonrpc: "2.0".to_string(),
             method: "tools/call".to_string(),
@@ -1146,16 +2173,26 @@ impl TokenInterceptor {
             .header("Content-Type", "application/json")
             .json(&mcp_request)
             .send()
-            .await?;
+            .await
+            .map_err(|e| OrchestratorError::Transient(e.to_string()))?;
 
-        if !response.status().is_success() {
-            return Err(format!("Orchestrator returned HTTP {}", response.status()).into());
+        let status = response.status();
+        if !status.is_success() {
+            let err = format!("Orchestrator returned HTTP {}", status);
+            return Err(if status.is_server_error() {
+                OrchestratorError::Transient(err)
+            } else {
+                OrchestratorError::Fatal(err)
+            });
         }
 
-        let mcp_resp: McpInferResponse = response.json().await?;
+        let mcp_resp: McpInferResponse = response
+            .json()
+            .await
+            .map_err(|e| OrchestratorError::Fatal(e.to_string()))?;
 
         if let Some(err) = mcp_resp.error {
-            return Err(format!("Orchestrator MCP error: {}", err.message).into());
+            return Err(OrchestratorError::Fatal(format!("Orchestrator MCP error: {}", err.message)));
         }
 
         if let Some(result) = mcp_resp.result {
@@ -1166,13 +2203,32 @@ impl TokenInterceptor {
             }
         }
 
-        Err("Orchestrator returned empty result".into())
+        Err(OrchestratorError::Fatal("Orchestrator returned empty result".to_string()))
     }
 
     // -----------------------------------------------------------------------
     // Token processing (shared by both providers)
     // -----------------------------------------------------------------------
 
+    /// Process one provider delta, first buffering it to whole words when
+    /// `word_boundaries` is set.
+    pub fn process_delta(&mut self, text: &str, log_prob: Option<f32>, top_alts: Vec<TokenAlternative>) {
+        if !self.word_boundaries {
+            self.process_content_logprob(text, log_prob, top_alts);
+            return;
+        }
+        if let Some((words, (log_prob, top_alts))) = self.word_buffer.push(text, (log_prob, top_alts)) {
+            self.process_content_logprob(&words, log_prob, top_alts);
+        }
+    }
+
+    /// Emit any partial word still held by the word buffer (end of stream).
+    pub fn flush_word_buffer(&mut self) {
+        if let Some((rest, (log_prob, top_alts))) = self.word_buffer.flush() {
+            self.process_content_logprob(&rest, log_prob, top_alts);
+        }
+    }
+
     /// Process a content chunk without logprob data.
     pub fn process_content(&mut self, content: &str) {
         self.process_content_logprob(content, None, vec![]);
@@ -1219,14 +2275,20 @@ impl TokenInterceptor {
         let mut first_real = true; // attach logprob data to first non-whitespace token
 
         for token in tokens {
+            let sentence_index = match self.granularity {
+                Granularity::Token => None,
+                Granularity::Sentence => Some(self.sentences.observe(&token)),
+            };
             if !token.trim().is_empty() {
-                let i = self.token_count;
+                let i = self.start_index + self.token_count;
 
-                // Bresenham-style spread: transform token i when
-                // floor((i+1)*rate) > floor(i*rate), giving a uniform
+                // Bresenham-style spread: transform unit n when
+                // floor((n+1)*rate) > floor(n*rate), giving a uniform
                 // distribution at any rate without probabilistic sampling.
+                // The unit is the token, or its sentence in sentence mode.
+                let unit = sentence_index.unwrap_or(i);
                 let rate = self.rate;
-                let should_transform = ((i + 1) as f64 * rate).floor() > (i as f64 * rate).floor();
+                let alternating = ((unit + 1) as f64 * rate).floor() > (unit as f64 * rate).floor();
 
                 // Logprob data only goes on the first real token of each API chunk.
                 // Compute before the transform so confidence can drive importance.
@@ -1239,6 +2301,29 @@ impl TokenInterceptor {
                     (None, None, vec![])
                 };
 
+                // Use real API confidence as importance when available; fall back
+                // to the heuristic scorer for tokens without logprob data.
+                let importance = match token_confidence {
+                    Some(c) => c as f64,
+                    None => self.heuristic_importance(&token, i),
+                };
+
+                // Heatmap colour follows an EMA of importance when smoothing is
+                // on; selection and `importance` keep the raw score.
+                let smoothed_importance = self.importance_smoothing.map(|alpha| {
+                    let s = match self.smoothed_importance {
+                        Some(prev) => alpha * importance + (1.0 - alpha) * prev,
+                        None => importance,
+                    };
+                    self.smoothed_importance = Some(s);
+                    s
+                });
+
+                let should_transform = match self.selector {
+                    TransformSelector::Alternating => alternating,
+                    TransformSelector::ImportanceAbove(threshold) => importance > threshold,
+                };
+
                 // Confidence gating: if min_confidence is set and token has API confidence,
                 // only transform tokens whose confidence is BELOW the threshold
                 let should_transform =
@@ -1247,17 +2332,19 @@ impl TokenInterceptor {
                     } else {
                         should_transform
                     };
+                let should_transform = should_transform && self.pos_filter.map_or(true, |f| f.matches(&token));
 
-                // Use real API confidence as importance when available; fall back
-                // to the heuristic scorer for tokens without logprob data.
-                let importance = token_confidence.map(|c| c as f64).unwrap_or_else(|| {
-                    transforms::calculate_token_importance_rng(&token, i, &mut self.rng)
-                });
-
+                let chaos = self.window_transform.is_none() && matches!(self.transform, Transform::Chaos);
                 let (display_text, chaos_label) = if should_transform {
                     self.transformed_count += 1;
-                    let (text, label) = self.transform.apply_with_label_rng(&token, &mut self.rng);
-                    let cl = if matches!(self.transform, Transform::Chaos) || text.is_empty() {
+                    let (text, label) = match &self.window_transform {
+                        Some(window) => (window.apply(&token, self.window_context.make_contiguous()), String::new()),
+                        None => self.transform.apply_with_options_rng(&token, &mut self.rng, &self.transform_options),
+                    };
+                    if chaos {
+                        self.chaos_script.push((i, label.clone()));
+                    }
+                    let cl = if chaos || text.is_empty() {
                         // Chaos: use sub-transform label; Delete: mark explicitly as "deleted"
                         Some(if text.is_empty() {
                             "deleted".to_string()
@@ -1272,9 +2359,16 @@ impl TokenInterceptor {
                     (token.clone(), None)
                 };
 
+                if let Some(window) = &self.window_transform {
+                    self.window_context.push_back(token.clone());
+                    while self.window_context.len() > window.window() {
+                        self.window_context.pop_front();
+                    }
+                }
+
                 // Delay transform: record the desired delay so the caller can
                 // await it asynchronously after this (non-async) method returns.
-                if should_transform {
+                if should_transform && self.window_transform.is_none() {
                     if let Transform::Delay(ms) = self.transform {
                         self.pending_delay_ms = ms;
                     }
@@ -1282,6 +2376,22 @@ impl TokenInterceptor {
 
                 // Delete transform: the result is an empty string (chaos_label="deleted").
                 let is_deleted = should_transform && display_text.is_empty();
+                let (display_text, original) = self.normalized(display_text, &token);
+                let display_text = match &self.inline_mapping {
+                    Some(template) if should_transform && !is_deleted => {
+                        render_inline_mapping(template, &original, &display_text)
+                    }
+                    _ => display_text,
+                };
+                let display_text = if self.collapse_whitespace && !is_deleted {
+                    let mut text = collapse_whitespace(&display_text);
+                    if std::mem::take(&mut self.pending_space) && !text.starts_with(' ') {
+                        text.insert(0, ' ');
+                    }
+                    text
+                } else {
+                    display_text
+                };
 
                 // Web / terminal / json output — skip deleted tokens for display.
                 if !is_deleted {
@@ -1291,7 +2401,7 @@ impl TokenInterceptor {
                     if let Some(tx) = &self.web_tx {
                         let event = TokenEvent {
                             text: display_text.clone(),
-                            original: token.clone(),
+                            original: original.clone(),
                             index: i,
                             transformed: should_transform,
                             importance,
@@ -1302,6 +2412,9 @@ impl TokenInterceptor {
                             alternatives: token_alts,
                             is_error: false,
                             arrival_ms,
+                            perplexity_percentile: None,
+                            sentence_index,
+                            smoothed_importance,
                         };
                         if let Some(rec) = &mut self.recorder {
                             rec.record(&event);
@@ -1311,7 +2424,7 @@ impl TokenInterceptor {
                         // JSON stream mode: one line per token
                         let event = TokenEvent {
                             text: display_text.clone(),
-                            original: token.clone(),
+                            original: original.clone(),
                             index: i,
                             transformed: should_transform,
                             importance,
@@ -1322,28 +2435,48 @@ impl TokenInterceptor {
                             alternatives: token_alts.clone(),
                             is_error: false,
                             arrival_ms,
+                            perplexity_percentile: None,
+                            sentence_index,
+                            smoothed_importance,
                         };
                         if let Ok(line) = serde_json::to_string(&event) {
                             println!("{}", line);
                         }
                     } else {
                         // Terminal mode: print with colors
-                        if self.heatmap_mode {
-                            print!("{}", apply_heatmap_color(&display_text, importance));
-                        } else if self.visual_mode && should_transform {
-                            print!("{}", display_text.bright_cyan().bold());
-                        } else if self.visual_mode {
-                            print!("{}", display_text.normal());
-                        } else {
-                            print!("{}", display_text);
+                        let color = colored::control::SHOULD_COLORIZE.should_colorize();
+                        print!(
+                            "{}",
+                            self.render_terminal_token(
+                                &display_text,
+                                &original,
+                                smoothed_importance.unwrap_or(importance),
+                                token_confidence,
+                                should_transform,
+                                color
+                            )
+                        );
+                        self.unflushed_tokens += 1;
+                        if self.flush_policy.should_flush(self.unflushed_tokens, false) {
+                            self.flush_stdout();
+                        }
+                    }
+                    if let Some(sink) = &mut self.file_sink {
+                        if let Err(e) = sink.write_all(display_text.as_bytes()) {
+                            tracing::warn!(err = %e, "file sink write failed; disabling");
+                            self.file_sink = None;
                         }
-                        let _ = io::stdout().flush();
                     }
                 }
 
                 self.token_count += 1;
+            } else if self.collapse_whitespace {
+                self.pending_space = true;
             }
         }
+        if self.flush_policy.should_flush(self.unflushed_tokens, true) {
+            self.flush_stdout();
+        }
 
         // Feed token-processing metrics into the self-improvement telemetry bus.
         #[cfg(feature = "self-tune")]
@@ -1359,6 +2492,52 @@ impl TokenInterceptor {
         }
     }
 
+    /// Apply [`Self::normalize`] to a token's emitted text, and to its
+    /// original when [`Self::normalize_original`] is set.
+    fn normalized(&self, text: String, original: &str) -> (String, String) {
+        match self.normalize {
+            Normalization::None => (text, original.to_string()),
+            form if self.normalize_original => (form.apply(&text), form.apply(original)),
+            form => (form.apply(&text), original.to_string()),
+        }
+    }
+
+    /// The chaos script of the last stream: `(token_index, sub_transform)` for
+    /// every token [`Transform::Chaos`] transformed, in order.
+    ///
+    /// With a fixed seed (`with_seed`) the same prompt and stream yield the
+    /// same script; [`Transform::replay_chaos_script`] re-applies it to any
+    /// token sequence.  Empty for other transforms.
+    pub fn chaos_script(&self) -> Vec<(usize, String)> {
+        self.chaos_script.clone()
+    }
+
+    /// Flush stdout and reset the unflushed-token count.
+    fn flush_stdout(&mut self) {
+        let _ = io::stdout().flush();
+        self.unflushed_tokens = 0;
+    }
+
+    /// Render one token for terminal output: heatmap / visual coloring, plus the
+    /// bracketed original for transformed tokens when `show_original` is set.
+    fn render_terminal_token(
+        &self,
+        display_text: &str,
+        original: &str,
+        importance: f64,
+        confidence: Option<f32>,
+        transformed: bool,
+        color: bool,
+    ) -> String {
+        let style = render::TerminalStyle {
+            heatmap: self.heatmap_mode,
+            visual: self.visual_mode,
+            show_original: self.show_original,
+            color_by: self.color_by,
+        };
+        render::terminal_token(display_text, original, importance, confidence, transformed, style, color)
+    }
+
     /// Print a formatted session header to stdout.
     ///
     /// Displays provider, transform, model, and prompt. When `visual_mode` or
@@ -1413,7 +2592,11 @@ impl TokenInterceptor {
     /// Reports total token count and how many tokens were transformed.
     pub fn print_footer(&self) {
         println!("\n{}", "=".repeat(50).bright_blue());
-        println!("Complete! Processed {} tokens.", self.token_count);
+        if self.interrupted {
+            println!("Interrupted! Processed {} tokens.", self.token_count);
+        } else {
+            println!("Complete! Processed {} tokens.", self.token_count);
+        }
         println!("Transform applied to {} tokens.", self.transformed_count);
     }
 }
@@ -1427,36 +2610,95 @@ impl TokenInterceptor {
 /// Produced by [`run_research_headless`].  Fields summarise token-level metrics
 /// across all runs; fields that require logprob data are `Option` because not
 /// all providers expose logprobs (Anthropic does not).
-#[derive(Debug, Clone, serde::Serialize)]
+///
+/// Fields serialize in declaration order — run parameters, then scalar
+/// summaries, then `citation` and the `top_perplexity_tokens` array last — so
+/// exported sessions diff cleanly under version control.  Keep this order
+/// stable when adding fields.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ResearchSession {
     /// The prompt submitted to the provider for all runs in this session.
     pub prompt: String,
+    /// What the first run actually sent, when it differs from `prompt`
+    /// (`--transform-prompt`, `--preprocess` or orchestrator enrichment).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_prompt: Option<String>,
     /// Provider identifier (`"openai"`, `"anthropic"`, or `"mock"`).
     pub provider: String,
     /// Model identifier used for all runs (e.g. `"gpt-4"`).
     pub model: String,
     /// Transform applied to intercepted tokens (e.g. `"reverse"`).
     pub transform: String,
+    /// Sampling temperature sent with every run.
+    pub temperature: f32,
     /// Number of inference runs executed.
     pub runs: u32,
+    /// Experiment name from `--tag`; see [`ResearchSession::tagged`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Free-form notes from `--notes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
     /// Total tokens streamed across all runs.
     pub total_tokens: usize,
     /// Total tokens that had a transform applied across all runs.
     pub total_transformed: usize,
     /// Unique-token fraction: `unique_tokens / total_tokens`.
     pub vocabulary_diversity: f64,
+    /// Fraction of tokens whose emitted text differs from the original, i.e.
+    /// how much of the output the transform actually changed.
+    pub transform_impact: f64,
     /// Mean character length of all original (pre-transform) tokens.
     pub mean_token_length: f64,
     /// Mean per-token perplexity across all runs, or `None` when unavailable.
     pub mean_perplexity: Option<f64>,
     /// Mean per-token model confidence across all runs, or `None` when unavailable.
     pub mean_confidence: Option<f64>,
-    /// The 10 tokens with the highest perplexity values (most uncertain positions).
-    pub top_perplexity_tokens: Vec<String>,
     /// Rough cost estimate in USD based on token count and GPT-3.5 pricing.
     pub estimated_cost_usd: f64,
     /// Human-readable citation string recording key run parameters for reproducibility.
     pub citation: String,
+    /// The 10 tokens with the highest perplexity values (most uncertain positions).
+    pub top_perplexity_tokens: Vec<String>,
+}
+
+/// Citation string for a research session.  The version comes from
+/// `Cargo.toml` so citations never drift from the actual release.
+fn research_citation(
+    prompt: &str,
+    provider: &providers::Provider,
+    model: &str,
+    transform: &transforms::Transform,
+    runs: u32,
+    total: usize,
+) -> String {
+    format!(
+        "Every Other Token v{} | prompt=\"{}\" | provider={} | model={} | transform={:?} | runs={} | tokens={}",
+        crate::build_info::VERSION,
+        prompt,
+        provider,
+        model,
+        transform,
+        runs,
+        total
+    )
+}
+
+impl ResearchSession {
+    /// Record the experiment `tag` and `notes`, appending them to
+    /// [`citation`](Self::citation) so a quoted citation still says which
+    /// experiment it came from.
+    pub fn tagged(mut self, tag: Option<String>, notes: Option<String>) -> Self {
+        if let Some(ref tag) = tag {
+            self.citation.push_str(&format!(" | tag={}", tag));
+        }
+        if let Some(ref notes) = notes {
+            self.citation.push_str(&format!(" | notes=\"{}\"", notes));
+        }
+        self.tag = tag;
+        self.notes = notes;
+        self
+    }
 }
 
 /// Run `runs` headless inference calls, collect all `TokenEvent`s, and return
@@ -1468,26 +2710,65 @@ pub async fn run_research_headless(
     transform: transforms::Transform,
     model: String,
     runs: u32,
+) -> Result<ResearchSession, Box<dyn std::error::Error>> {
+    run_research_headless_at(prompt, provider, transform, model, runs, DEFAULT_TEMPERATURE).await
+}
+
+/// [`run_research_headless`] with every run sampled at `temperature`
+/// (clamped to `[0.0, 2.0]`).
+pub async fn run_research_headless_at(
+    prompt: &str,
+    provider: providers::Provider,
+    transform: transforms::Transform,
+    model: String,
+    runs: u32,
+    temperature: f32,
+) -> Result<ResearchSession, Box<dyn std::error::Error>> {
+    run_research_headless_with(prompt, provider, transform, model, runs, temperature, &|i| Ok(i)).await
+}
+
+/// Builds on a freshly constructed interceptor before each headless run, e.g.
+/// [`research::configure_interceptor`] to apply the command-line settings.
+pub type ConfigureInterceptor<'a> =
+    &'a dyn Fn(TokenInterceptor) -> Result<TokenInterceptor, Box<dyn std::error::Error>>;
+
+/// [`run_research_headless_at`] with every interceptor passed through
+/// `configure` before the temperature is set.
+pub async fn run_research_headless_with(
+    prompt: &str,
+    provider: providers::Provider,
+    transform: transforms::Transform,
+    model: String,
+    runs: u32,
+    temperature: f32,
+    configure: ConfigureInterceptor<'_>,
 ) -> Result<ResearchSession, Box<dyn std::error::Error>> {
     let mut all_tokens: Vec<TokenEvent> = Vec::new();
+    let mut effective_prompt = None;
 
     for _ in 0..runs {
         let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
-        let mut interceptor = TokenInterceptor::new(
+        let mut interceptor = configure(TokenInterceptor::new(
             provider.clone(),
             transform.clone(),
             model.clone(),
             false,
             false,
             false,
-        )?;
+        )?)?
+        .with_temperature(temperature);
         interceptor.web_tx = Some(tx);
         interceptor.intercept_stream(prompt).await?;
+        if effective_prompt.is_none() {
+            effective_prompt = interceptor.rewritten_prompt(prompt).map(str::to_string);
+        }
         // Drain channel
         while let Ok(ev) = rx.try_recv() {
             all_tokens.push(ev);
         }
     }
+    // Second pass: rank each token's perplexity against the whole session.
+    perplexity_percentile::assign_perplexity_percentiles(&mut all_tokens);
 
     let total = all_tokens.len();
     let total_transformed = all_tokens.iter().filter(|t| t.transformed).count();
@@ -1502,6 +2783,12 @@ pub async fn run_research_headless(
         0.0
     };
 
+    let transform_impact = if total > 0 {
+        all_tokens.iter().filter(|t| t.text != t.original).count() as f64 / total as f64
+    } else {
+        0.0
+    };
+
     let mean_token_length = if total > 0 {
         all_tokens
             .iter()
@@ -1551,20 +2838,22 @@ pub async fn run_research_headless(
     // Cost estimate: GPT-3.5 rate $0.002 / 1K tokens
     let estimated_cost_usd = total as f64 / 1000.0 * 0.002;
 
-    let citation = format!(
-        "Every Other Token v4.0.0 | prompt=\"{}\" | provider={} | model={} | transform={:?} | runs={} | tokens={}",
-        prompt, provider, model, transform, runs, total
-    );
+    let citation = research_citation(prompt, &provider, &model, &transform, runs, total);
 
     Ok(ResearchSession {
         prompt: prompt.to_string(),
+        effective_prompt,
         provider: provider.to_string(),
         model,
         transform: format!("{:?}", transform),
+        temperature: temperature.clamp(0.0, 2.0),
         runs,
+        tag: None,
+        notes: None,
         total_tokens: total,
         total_transformed,
         vocabulary_diversity: vocab_diversity,
+        transform_impact,
         mean_token_length,
         mean_perplexity,
         mean_confidence,
@@ -1574,44 +2863,119 @@ pub async fn run_research_headless(
     })
 }
 
+/// How one temperature in a [`TemperatureSweep`] compares with the others.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TemperatureSweepRow {
+    pub temperature: f32,
+    pub vocabulary_diversity: f64,
+    pub mean_perplexity: Option<f64>,
+    pub mean_confidence: Option<f64>,
+    pub transform_impact: f64,
+}
+
+/// One [`ResearchSession`] per swept temperature, in the order given, plus a
+/// side-by-side comparison of the metrics most sensitive to sampling.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TemperatureSweep {
+    pub sessions: Vec<ResearchSession>,
+    pub comparison: Vec<TemperatureSweepRow>,
+}
+
+impl TemperatureSweep {
+    /// Build the comparison table from `sessions`.
+    pub fn from_sessions(sessions: Vec<ResearchSession>) -> Self {
+        let comparison = sessions
+            .iter()
+            .map(|s| TemperatureSweepRow {
+                temperature: s.temperature,
+                vocabulary_diversity: s.vocabulary_diversity,
+                mean_perplexity: s.mean_perplexity,
+                mean_confidence: s.mean_confidence,
+                transform_impact: s.transform_impact,
+            })
+            .collect();
+        TemperatureSweep { sessions, comparison }
+    }
+
+    /// Plain-text table of [`Self::comparison`], one row per temperature.
+    pub fn render_table(&self) -> String {
+        let opt = |v: Option<f64>| v.map_or_else(|| "-".to_string(), |v| format!("{:.3}", v));
+        let mut out = format!(
+            "{:>6}  {:>9}  {:>10}  {:>10}  {:>9}\n",
+            "temp", "diversity", "perplexity", "confidence", "impact"
+        );
+        for row in &self.comparison {
+            out.push_str(&format!(
+                "{:>6.2}  {:>9.3}  {:>10}  {:>10}  {:>9.3}\n",
+                row.temperature,
+                row.vocabulary_diversity,
+                opt(row.mean_perplexity),
+                opt(row.mean_confidence),
+                row.transform_impact
+            ));
+        }
+        out
+    }
+}
+
+/// Run a headless research session at each of `temperatures` (see
+/// [`run_research_headless_at`]) and compare them.
+pub async fn run_temperature_sweep(
+    prompt: &str,
+    provider: providers::Provider,
+    transform: transforms::Transform,
+    model: String,
+    runs: u32,
+    temperatures: &[f32],
+) -> Result<TemperatureSweep, Box<dyn std::error::Error>> {
+    run_temperature_sweep_with(prompt, provider, transform, model, runs, temperatures, &|i| Ok(i)).await
+}
+
+/// [`run_temperature_sweep`] with every interceptor passed through
+/// `configure` (see [`run_research_headless_with`]).
+pub async fn run_temperature_sweep_with(
+    prompt: &str,
+    provider: providers::Provider,
+    transform: transforms::Transform,
+    model: String,
+    runs: u32,
+    temperatures: &[f32],
+    configure: ConfigureInterceptor<'_>,
+) -> Result<TemperatureSweep, Box<dyn std::error::Error>> {
+    let mut sessions = Vec::with_capacity(temperatures.len());
+    for &temperature in temperatures {
+        sessions.push(
+            run_research_headless_with(
+                prompt,
+                provider.clone(),
+                transform.clone(),
+                model.clone(),
+                runs,
+                temperature,
+                configure,
+            )
+            .await?,
+        );
+    }
+    Ok(TemperatureSweep::from_sessions(sessions))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tokio::sync::mpsc;
 
-    fn make_test_interceptor() -> TokenInterceptor {
-        TokenInterceptor {
-            client: Client::new(),
-            api_key: "test-key".to_string(),
-            provider: Provider::Openai,
-            transform: Transform::Reverse,
-            model: "test-model".to_string(),
-            token_count: 0,
-            transformed_count: 0,
-            visual_mode: false,
-            heatmap_mode: false,
-            orchestrator: false,
-            orchestrator_url: "http://localhost:3000".to_string(),
-            web_tx: None,
-            web_provider_label: None,
-            system_prompt: None,
-            #[cfg(feature = "self-tune")]
-            telemetry_bus: None,
-            #[cfg(feature = "self-modify")]
-            dedup: None,
-            rate: 0.5,
-            rng: StdRng::seed_from_u64(42),
-            top_logprobs: 5,
-            recorder: None,
-            json_stream: false,
-            pending_delay_ms: 0,
-            min_confidence: None,
-            last_token_instant: None,
-            max_retries: 3,
-            anthropic_max_tokens: 4096,
-            stream_start_instant: None,
-            timeout_secs: None,
-        }
+    /// A Reverse-transform interceptor with a fixed RNG seed and OpenAI as
+    /// its provider, built without reading an API key from the environment.
+    pub(crate) fn make_test_interceptor() -> TokenInterceptor {
+        let mut interceptor =
+            TokenInterceptor::new(Provider::Mock, Transform::Reverse, "test-model".to_string(), false, false, false)
+                .expect("mock interceptor")
+                .with_seed(42)
+                .with_temperature(0.7);
+        interceptor.provider = Provider::Openai;
+        interceptor.api_key = "test-key".to_string();
+        interceptor
     }
 
     // -- TokenInterceptor construction --
@@ -1662,7 +3026,82 @@ mod tests {
         assert!(interceptor.web_tx.is_none());
     }
 
-    // -- process_content tests --
+    #[tokio::test]
+    async fn test_preprocessors_run_in_order_before_sending() {
+        struct Shout;
+        impl preprocess::PromptPreprocessor for Shout {
+            fn name(&self) -> &str {
+                "shout"
+            }
+            fn process(&self, prompt: &str) -> Result<String, String> {
+                Ok(format!("{}!", prompt.to_uppercase()))
+            }
+        }
+        let (tx, _rx) = mpsc::unbounded_channel::<TokenEvent>();
+        let mut interceptor = make_test_interceptor()
+            .with_stream_backend(Box::new(ScriptedStream::new(vec![TokenDelta::text("ok")])))
+            .with_preprocessor(Box::new(preprocess::Truncate { max_chars: 5 }))
+            .with_preprocessor(Box::new(Shout));
+        interceptor.web_tx = Some(tx);
+
+        interceptor.intercept_stream("hello world").await.unwrap();
+        assert_eq!(interceptor.effective_prompt.as_deref(), Some("HELLO!"));
+        assert_eq!(interceptor.preprocessors.len(), 2, "stages are kept for the next stream");
+
+        // Reversed, the shouted prompt is what gets truncated.
+        interceptor.preprocessors.reverse();
+        interceptor.intercept_stream("hello world").await.unwrap();
+        assert_eq!(interceptor.effective_prompt.as_deref(), Some("HELLO"));
+
+        interceptor.preprocessors =
+            vec![preprocess::PreprocessStage::Custom(Box::new(preprocess::TemplateVars::default()))];
+        let err = interceptor.intercept_stream("about {topic}").await.unwrap_err();
+        assert!(err.to_string().contains("preprocess stage `template` failed"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_stream_backend_feeds_normal_pipeline() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        let backend = ScriptedStream::new(vec![
+            TokenDelta::text("hello"),
+            TokenDelta { text: " world".to_string(), logprob: Some(-0.5), top_alternatives: vec![] },
+        ]);
+        let mut interceptor = make_test_interceptor().with_stream_backend(Box::new(backend));
+        interceptor.web_tx = Some(tx);
+
+        // The configured provider is OpenAI; the backend means no request is sent.
+        interceptor.intercept_stream("anything").await.unwrap();
+        assert_eq!(interceptor.token_count, 2);
+        assert!(interceptor.stream_backend.is_some(), "backend is kept for the next stream");
+
+        let events: Vec<TokenEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].text, "hello");
+        assert!(!events[0].transformed);
+        assert_eq!(events[1].original, "world");
+        assert_eq!(events[1].text, "dlrow");
+        assert!(events[1].transformed);
+        assert!(events[1].confidence.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_streams_through_normal_pipeline() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        let mut interceptor = make_test_interceptor();
+        interceptor.provider = Provider::Mock;
+        interceptor.rate = 0.0;
+        interceptor.web_tx = Some(tx);
+
+        interceptor.intercept_stream("hi").await.unwrap();
+        let events: Vec<TokenEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(events.len() >= 20);
+        assert_eq!(interceptor.token_count, events.len());
+        // --rate is honoured like any other provider, and logprobs come through.
+        assert!(events.iter().all(|e| !e.transformed));
+        assert!(events.iter().any(|e| e.confidence.is_some()));
+    }
+
+    // -- process_content tests --
 
     #[test]
     fn test_process_content_two_tokens() {
@@ -1687,6 +3126,138 @@ mod tests {
         assert_eq!(events[1].index, 1);
     }
 
+    #[test]
+    fn test_identity_transform_matches_treatment_event_structure() {
+        let run = |transform: Transform| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+            let mut interceptor = make_test_interceptor();
+            interceptor.transform = transform;
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content("the quick brown fox");
+            let events: Vec<TokenEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+            (interceptor.token_count, interceptor.transformed_count, events)
+        };
+        let (base_count, base_transformed, baseline) = run(Transform::Identity);
+        let (count, transformed, treatment) = run(Transform::Reverse);
+        assert_eq!((base_count, base_transformed), (count, transformed));
+        assert_eq!(baseline.len(), treatment.len());
+        for (b, t) in baseline.iter().zip(&treatment) {
+            assert_eq!((b.index, b.transformed, &b.original), (t.index, t.transformed, &t.original));
+            assert_eq!(b.text, b.original);
+        }
+        assert!(baseline.iter().any(|e| e.transformed));
+    }
+
+    #[test]
+    fn test_importance_smoothing_reduces_consecutive_variance() {
+        let text = "the quick brown fox jumps over a lazy dog while an extraordinarily \
+                    long sentence keeps going with short and lengthy words alternating";
+        let run = |smoothing: Option<f64>| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+            let mut interceptor = make_test_interceptor().with_seed(3);
+            interceptor.importance_smoothing = smoothing;
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content(text);
+            std::iter::from_fn(|| rx.try_recv().ok()).collect::<Vec<TokenEvent>>()
+        };
+        let step_variance = |values: &[f64]| {
+            let steps: Vec<f64> = values.windows(2).map(|w| w[1] - w[0]).collect();
+            steps.iter().map(|d| d * d).sum::<f64>() / steps.len() as f64
+        };
+        let raw = run(None);
+        let smoothed = run(Some(0.3));
+        assert!(raw.iter().all(|e| e.smoothed_importance.is_none()));
+        let raw_importance: Vec<f64> = raw.iter().map(|e| e.importance).collect();
+        let smoothed_importance: Vec<f64> =
+            smoothed.iter().map(|e| e.smoothed_importance.expect("smoothed")).collect();
+        // The raw score is still exported unchanged.
+        assert_eq!(smoothed.iter().map(|e| e.importance).collect::<Vec<_>>(), raw_importance);
+        assert!(step_variance(&smoothed_importance) < step_variance(&raw_importance));
+    }
+
+    #[test]
+    fn test_window_transform_sees_preceding_tokens() {
+        struct ShoutAfterThe;
+        impl transforms::WindowTransform for ShoutAfterThe {
+            fn window(&self) -> usize {
+                2
+            }
+            fn apply(&self, token: &str, context: &[String]) -> String {
+                assert!(context.len() <= 2);
+                match context.last() {
+                    Some(prior) if prior.eq_ignore_ascii_case("the") => token.to_uppercase(),
+                    _ => token.to_string(),
+                }
+            }
+        }
+        let run = |window: Box<dyn transforms::WindowTransform>, text: &str| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+            let mut interceptor = make_test_interceptor().with_rate(1.0).with_window_transform(window);
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content(text);
+            std::iter::from_fn(|| rx.try_recv().ok()).map(|e| e.text).collect::<Vec<String>>()
+        };
+        assert_eq!(
+            run(Box::new(ShoutAfterThe), "the fox saw the dog"),
+            vec!["the", "FOX", "saw", "the", "DOG"]
+        );
+        // Stutter looks at the original prior token, not its stuttered output.
+        assert_eq!(
+            run(Box::new(transforms::Stutter::default()), "a fox jumped over it"),
+            vec!["a", "fox-fox", "jumped-jumped", "over", "it"]
+        );
+    }
+
+    #[test]
+    fn test_arrival_timestamps_are_monotonic() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        let mut interceptor = make_test_interceptor();
+        interceptor.stream_start_instant = Some(std::time::Instant::now());
+        interceptor.web_tx = Some(tx);
+        for chunk in ["the quick", " brown fox", " jumps over", " the lazy dog"] {
+            interceptor.process_content(chunk);
+        }
+        let events: Vec<TokenEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        let stamps: Vec<u64> = events.iter().map(|e| e.arrival_ms.expect("arrival_ms")).collect();
+        assert!(stamps.len() > 4);
+        assert!(stamps.windows(2).all(|w| w[0] <= w[1]), "{:?}", stamps);
+        assert_eq!(inter_token_gaps_ms(&events).len(), stamps.len() - 1);
+    }
+
+    #[test]
+    fn test_sentence_granularity_transforms_alternate_sentences() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        let mut interceptor = make_test_interceptor().with_granularity(Granularity::Sentence);
+        interceptor.web_tx = Some(tx);
+        interceptor.process_content("The cat sat down. It was very tired! Then it slept.");
+        let events: Vec<TokenEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        let sentence = |n: usize| -> Vec<&TokenEvent> {
+            events.iter().filter(|e| e.sentence_index == Some(n)).collect()
+        };
+        assert_eq!(sentence(0).len(), 5);
+        assert!(sentence(0).iter().all(|e| !e.transformed));
+        assert_eq!(sentence(1).len(), 5);
+        assert!(sentence(1).iter().all(|e| e.transformed));
+        assert_eq!(sentence(1)[0].text, "tI");
+        assert!(sentence(2).iter().all(|e| !e.transformed));
+        assert_eq!(events.len(), 14);
+    }
+
+    #[test]
+    fn test_zalgo_transform_keeps_original_text() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        let mut interceptor = make_test_interceptor().with_seed(5);
+        interceptor.transform = Transform::Zalgo;
+        interceptor.web_tx = Some(tx);
+        interceptor.process_content("the quick brown fox");
+        let events: Vec<TokenEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(events.iter().any(|e| e.transformed));
+        for event in &events {
+            assert_eq!(transforms::strip_combining_marks(&event.text), event.original);
+            assert_eq!(event.transformed, event.text != event.original);
+        }
+    }
+
     #[test]
     fn test_process_content_transforms_odd_tokens() {
         let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
@@ -1899,6 +3470,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_start_index_continues_numbering_and_alternation() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        let mut interceptor = make_test_interceptor().with_start_index(10);
+        interceptor.web_tx = Some(tx);
+
+        interceptor.process_content("one two three four");
+
+        let mut events = Vec::new();
+        while let Ok(e) = rx.try_recv() {
+            events.push(e);
+        }
+
+        assert_eq!(events[0].index, 10);
+        let indices: Vec<usize> = events.iter().map(|e| e.index).collect();
+        assert_eq!(indices, vec![10, 11, 12, 13]);
+        let transformed: Vec<bool> = events.iter().map(|e| e.transformed).collect();
+        assert_eq!(transformed, vec![false, true, false, true]);
+        assert_eq!(interceptor.token_count, 4, "token_count still counts this session only");
+    }
+
     // -- export structure tests --
 
     #[test]
@@ -2114,7 +3706,7 @@ mod tests {
             events.push(e);
         }
         // "world" is the odd token — should have chaos_label
-        let known = ["reverse", "uppercase", "mock", "noise"];
+        let known = ["reverse", "uppercase", "mock", "noise", "spongebob", "zalgo", "hashnoise", "nato"];
         let odd = events
             .iter()
             .find(|e| e.transformed)
@@ -2181,12 +3773,7 @@ mod tests {
             transformed: true,
             importance: 0.5,
             chaos_label: Some("reverse".to_string()),
-            provider: None,
-            confidence: None,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&event).expect("serialize");
         assert!(json.contains("chaos_label"));
@@ -2199,15 +3786,8 @@ mod tests {
             text: "hello".to_string(),
             original: "hello".to_string(),
             index: 0,
-            transformed: false,
             importance: 0.3,
-            chaos_label: None,
-            provider: None,
-            confidence: None,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&event).expect("serialize");
         assert!(
@@ -2252,15 +3832,8 @@ mod tests {
             text: "hello".to_string(),
             original: "hello".to_string(),
             index: 0,
-            transformed: false,
             importance: 0.5,
-            chaos_label: None,
-            provider: None,
-            confidence: None,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&event).expect("serialize");
         assert!(
@@ -2275,15 +3848,9 @@ mod tests {
             text: "hello".to_string(),
             original: "hello".to_string(),
             index: 0,
-            transformed: false,
             importance: 0.5,
-            chaos_label: None,
             provider: Some("anthropic".to_string()),
-            confidence: None,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&event).expect("serialize");
         assert!(json.contains("\"provider\""));
@@ -2416,18 +3983,14 @@ mod tests {
             text: "hi".to_string(),
             original: "hi".to_string(),
             index: 0,
-            transformed: false,
             importance: 0.5,
-            chaos_label: None,
-            provider: None,
             confidence: Some(0.92),
             perplexity: Some(1.08),
             alternatives: vec![TokenAlternative {
                 token: "hey".to_string(),
                 probability: 0.05,
             }],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&event).expect("serialize");
         assert!(json.contains("confidence"));
@@ -2442,15 +4005,8 @@ mod tests {
             text: "hi".to_string(),
             original: "hi".to_string(),
             index: 0,
-            transformed: false,
             importance: 0.5,
-            chaos_label: None,
-            provider: None,
-            confidence: None,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&event).expect("serialize");
         assert!(!json.contains("confidence"));
@@ -2503,11 +4059,854 @@ mod tests {
             "subsequent tokens should not"
         );
     }
+
+    // -- interceptor options: chaos scripts, rendering, selection, streaming --
+
+    async fn seeded_chaos_script(seed: u64) -> Vec<(usize, String)> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut i = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Chaos,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_seed(seed)
+        .with_web_tx(tx);
+        i.intercept_stream("the same input every time").await.expect("mock stream");
+        let mut labelled = vec![];
+        while let Ok(event) = rx.try_recv() {
+            if let Some(label) = event.chaos_label {
+                labelled.push((event.index, label));
+            }
+        }
+        assert_eq!(i.chaos_script(), labelled, "script matches the streamed labels");
+        i.chaos_script()
+    }
+
+    #[tokio::test]
+    async fn test_seeded_chaos_runs_produce_identical_scripts() {
+        let first = seeded_chaos_script(7).await;
+        let second = seeded_chaos_script(7).await;
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+    }
+
+    /// `Write` into a shared buffer the test can inspect afterwards.
+    #[derive(Clone, Default)]
+    struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_show_original_brackets_transformed_tokens_only() {
+        let mut i = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor");
+        i.show_original = true;
+        // Rate 0.5 transforms odd-indexed tokens.
+        let rendered: Vec<String> = ["hello", "world", "foo", "bar"]
+            .iter()
+            .enumerate()
+            .map(|(idx, tok)| {
+                let transformed = idx % 2 == 1;
+                let display = if transformed { i.transform.apply(tok) } else { tok.to_string() };
+                i.render_terminal_token(&display, tok, 0.5, None, transformed, false)
+            })
+            .collect();
+        assert_eq!(rendered, vec!["hello", "dlrow[world]", "foo", "rab[bar]"]);
+    }
+
+    #[test]
+    fn test_show_original_off_renders_plain_text() {
+        let i = make_test_interceptor();
+        assert_eq!(i.render_terminal_token("dlrow", "world", 0.5, None, true, false), "dlrow");
+    }
+
+    #[test]
+    fn test_file_sink_matches_terminal_text_without_ansi() {
+        let chunks = ["The quick", " brown fox", " jumps over", " the lazy dog."];
+        let buf = SharedBuf::default();
+        // Terminal mode with visual colors on: stdout gets ANSI, the sink must not.
+        let mut terminal = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            true,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_file_sink(Box::new(buf.clone()));
+        // The same chunks in web mode yield exactly the text the terminal prints.
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut web = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            true,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_web_tx(tx);
+        for chunk in chunks {
+            terminal.process_content_logprob(chunk, Some(-0.2), vec![]);
+            web.process_content_logprob(chunk, Some(-0.2), vec![]);
+        }
+        let mut expected = String::new();
+        while let Ok(ev) = rx.try_recv() {
+            expected.push_str(&ev.text);
+        }
+
+        let written = String::from_utf8(buf.0.lock().expect("lock").clone()).expect("utf8");
+        assert!(!written.is_empty());
+        assert!(!written.contains('\x1b'), "sink must not contain ANSI escapes");
+        assert_eq!(written, expected);
+        assert!(written.contains("kciuq") || written.contains("nworb"), "got: {}", written);
+    }
+
+    #[tokio::test]
+    async fn test_over_limit_prompt_rejected() {
+        let mut interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_max_prompt_chars(10);
+        let err = interceptor
+            .intercept_stream("ééééééééééé")
+            .await
+            .expect_err("11 chars > 10");
+        assert!(err.to_string().contains("prompt too long"), "got: {}", err);
+        assert_eq!(interceptor.token_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_at_limit_prompt_proceeds() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_web_tx(tx)
+        .with_max_prompt_chars(10);
+        // Multi-byte characters count once each.
+        interceptor.intercept_stream("éééééééééé").await.expect("10 chars == limit");
+        assert!(interceptor.token_count > 0);
+    }
+
+    #[test]
+    fn test_deterministic_importance_is_reproducible() {
+        let importances = || {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let mut interceptor = make_test_interceptor().with_deterministic_importance(true);
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content_logprob("The robot builds data models", None, vec![]);
+            drop(interceptor);
+            let mut out = Vec::new();
+            while let Ok(e) = rx.try_recv() {
+                out.push(e.importance.to_bits());
+            }
+            out
+        };
+        let first = importances();
+        assert_eq!(first.len(), 5);
+        assert_eq!(first, importances());
+    }
+
+    #[tokio::test]
+    async fn test_stop_flag_keeps_partial_counts_for_footer() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_stop_flag(stop.clone());
+        // Tokens already streamed before the interrupt arrives.
+        interceptor.process_content_logprob("one two three four", None, vec![]);
+        let (tokens, transformed) = (interceptor.token_count, interceptor.transformed_count);
+        assert_eq!(tokens, 4);
+
+        stop.store(true, Ordering::Relaxed);
+        interceptor.intercept_stream("hello").await.expect("cancel is not an error");
+        assert!(interceptor.interrupted);
+        assert_eq!(interceptor.token_count, tokens);
+        assert_eq!(interceptor.transformed_count, transformed);
+    }
+
+    #[tokio::test]
+    async fn test_unset_stop_flag_completes_stream() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_web_tx(tx)
+        .with_stop_flag(Arc::new(AtomicBool::new(false)));
+        interceptor.intercept_stream("hello").await.expect("stream");
+        assert!(!interceptor.interrupted);
+        assert!(interceptor.token_count > 0);
+    }
+
+    #[cfg(feature = "self-tune")]
+    #[tokio::test]
+    async fn test_cost_router_downgrades_model_under_pressure() {
+        use crate::self_tune::cost::{BudgetConfig, CostRouter, ModelDowngradeTable, TokenPrice};
+        let router = CostRouter::new(
+            BudgetConfig {
+                ceiling_usd: 1.0,
+                ..BudgetConfig::default()
+            },
+            ModelDowngradeTable::empty().with_entry("mock-big", "mock-small"),
+        );
+        router.set_price("mock-big", TokenPrice::new(0.01, 0.01));
+        router.record("mock-big", 50, 45);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock-big".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_web_tx(tx)
+        .with_cost_router(std::sync::Arc::new(router));
+        interceptor.intercept_stream("hello").await.expect("stream");
+        assert_eq!(interceptor.model, "mock-small");
+        let first = rx.recv().await.expect("downgrade note");
+        assert!(first.text.starts_with("[cost]"), "got: {}", first.text);
+        assert!(first.is_error, "the downgrade note is not a model token");
+    }
+
+    #[test]
+    fn test_transform_selector_parse() {
+        assert_eq!(TransformSelector::parse("alternating"), Ok(TransformSelector::Alternating));
+        assert_eq!(
+            TransformSelector::parse("importance:0.6"),
+            Ok(TransformSelector::ImportanceAbove(0.6))
+        );
+        assert!(TransformSelector::parse("importance:1.5").is_err());
+        assert!(TransformSelector::parse("importance:high").is_err());
+        assert!(TransformSelector::parse("random").is_err());
+    }
+
+    #[test]
+    fn test_importance_selector_ignores_even_odd_pattern() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut interceptor =
+            TokenInterceptor::new(Provider::Mock, Transform::Uppercase, "mock".to_string(), false, false, false)
+                .expect("mock interceptor")
+                .with_web_tx(tx)
+                .with_selector(TransformSelector::ImportanceAbove(0.5));
+        interceptor.deterministic_importance = true;
+        // Six tokens whose confidences straddle the threshold out of step
+        // with the alternating pattern.
+        for (word, log_prob) in [("a", 0.9_f32), ("b", 0.8), ("c", 0.1), ("d", 0.2), ("e", 0.7), ("f", 0.05)] {
+            interceptor.process_content_logprob(&format!("{} ", word), Some(log_prob.ln()), vec![]);
+        }
+        let mut events = Vec::new();
+        while let Ok(ev) = rx.try_recv() {
+            events.push(ev);
+        }
+        assert_eq!(events.len(), 6);
+        for ev in &events {
+            assert_eq!(ev.transformed, ev.importance > 0.5, "{:?}", ev);
+            let expected = if ev.transformed { ev.original.to_uppercase() } else { ev.original.clone() };
+            assert_eq!(ev.text, expected);
+        }
+        let transformed: Vec<usize> = events.iter().filter(|e| e.transformed).map(|e| e.index).collect();
+        assert_eq!(transformed, vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn test_importance_selector_high_threshold_uses_heuristic() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut interceptor =
+            TokenInterceptor::new(Provider::Mock, Transform::Reverse, "mock".to_string(), false, false, false)
+                .expect("mock interceptor")
+                .with_web_tx(tx)
+                .with_selector(TransformSelector::ImportanceAbove(0.99));
+        interceptor.deterministic_importance = true;
+        interceptor.process_content("the quick brown fox jumps over the lazy dog");
+        while let Ok(ev) = rx.try_recv() {
+            assert_eq!(ev.transformed, ev.importance > 0.99, "{:?}", ev);
+        }
+    }
+
+    #[test]
+    fn test_pos_filter_content_skips_stopwords() {
+        let run = |filter: Option<PosFilter>| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+            let mut interceptor = make_test_interceptor().with_rate(1.0);
+            interceptor.pos_filter = filter;
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content("the fox jumped over a lazy dog");
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .filter(|e| !e.original.trim().is_empty())
+                .map(|e| (e.original, e.text, e.transformed))
+                .collect::<Vec<_>>()
+        };
+        let content = run(Some(PosFilter::Content));
+        for (original, text, transformed) in &content {
+            let stopword = ["the", "over", "a"].contains(&original.as_str());
+            assert_eq!(*transformed, !stopword, "{}", original);
+            if stopword {
+                assert_eq!(text, original, "stopwords pass through");
+            } else {
+                assert_eq!(*text, Transform::Reverse.apply(original));
+            }
+        }
+        let stopwords: Vec<String> =
+            run(Some(PosFilter::Stopword)).into_iter().filter(|t| t.2).map(|t| t.0).collect();
+        assert_eq!(stopwords, vec!["the", "over", "a"]);
+        assert!(run(None).iter().all(|t| t.2), "no filter transforms every token at rate 1");
+
+        assert_eq!(PosFilter::parse("Content"), Ok(PosFilter::Content));
+        assert_eq!(PosFilter::parse("adj"), Ok(PosFilter::Adjective));
+        assert!(PosFilter::parse("pronoun").is_err());
+    }
+
+    #[test]
+    fn test_collapse_whitespace_emits_single_spaces() {
+        let run = |collapse: bool, inline_mapping: Option<&str>| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+            let mut interceptor = make_test_interceptor();
+            interceptor.collapse_whitespace = collapse;
+            interceptor.inline_mapping = inline_mapping.map(str::to_string);
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content("the   quick \n\t brown  fox");
+            std::iter::from_fn(|| rx.try_recv().ok()).collect::<Vec<_>>()
+        };
+        let events = run(true, None);
+        let text: String = events.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(text, "the kciuq brown xof");
+        assert_eq!(events[1].original, "quick", "originals keep no whitespace");
+
+        let off: String = run(false, None).iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(off, "thekciuqbrownxof", "off by default: whitespace is not emitted");
+
+        let mapped: String = run(true, Some("{original}   {text}")).iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(mapped, "the quick kciuq brown fox xof");
+        assert_eq!(collapse_whitespace(" a \t\n b  "), " a b ");
+    }
+
+    #[test]
+    fn test_inline_mapping_renders_transformed_tokens_only() {
+        let run = |template: &str| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+            let mut interceptor = make_test_interceptor();
+            interceptor.inline_mapping = Some(template.to_string());
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content("the quick brown fox");
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .filter(|e| !e.text.trim().is_empty())
+                .collect::<Vec<_>>()
+        };
+        let events = run(DEFAULT_INLINE_MAPPING);
+        assert_eq!(events.len(), 4);
+        for event in &events {
+            if event.transformed {
+                let expected = format!("{}\u{2192}{}", event.original, Transform::Reverse.apply(&event.original));
+                assert_eq!(event.text, expected);
+            } else {
+                assert_eq!(event.text, event.original, "untransformed tokens carry no mapping");
+            }
+        }
+        assert!(events.iter().any(|e| e.transformed));
+        assert!(events.iter().any(|e| !e.transformed));
+        assert_eq!(events[1].original, "quick");
+        assert_eq!(events[1].text, "quick\u{2192}kciuq");
+
+        assert_eq!(run("[{original}|{text}]")[3].text, "[fox|xof]");
+        assert!(parse_inline_mapping("no placeholders").is_err());
+    }
+
+    #[test]
+    fn test_normalize_nfc_composes_decomposed_tokens() {
+        let run = |normalize: Normalization, normalize_original: bool| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+            let mut interceptor = make_test_interceptor().with_rate(0.0);
+            interceptor.normalize = normalize;
+            interceptor.normalize_original = normalize_original;
+            interceptor.web_tx = Some(tx);
+            interceptor.process_content("cafe\u{301} ole\u{301}");
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .filter(|e| !e.text.trim().is_empty())
+                .map(|e| (e.text, e.original))
+                .collect::<Vec<_>>()
+        };
+        let composed = run(Normalization::Nfc, false);
+        assert_eq!(composed[0].0, "caf\u{e9}");
+        assert_eq!(composed[0].0.chars().count(), 4);
+        assert_eq!(composed[0].1, "cafe\u{301}", "original untouched without --normalize-original");
+        assert_eq!(run(Normalization::Nfc, true)[1], ("ol\u{e9}".to_string(), "ol\u{e9}".to_string()));
+        assert_eq!(run(Normalization::None, true)[0].0, "cafe\u{301}");
+
+        assert_eq!(Normalization::Nfd.apply("caf\u{e9}"), "cafe\u{301}");
+        assert_eq!(Normalization::parse("NFC"), Ok(Normalization::Nfc));
+        assert!(Normalization::parse("nfkc").is_err());
+    }
+
+    #[test]
+    fn test_flush_policy_parse() {
+        assert_eq!(FlushPolicy::parse("token"), Ok(FlushPolicy::Token));
+        assert_eq!(FlushPolicy::parse("Chunk"), Ok(FlushPolicy::Chunk));
+        assert_eq!(FlushPolicy::parse("64"), Ok(FlushPolicy::Every(64)));
+        assert!(FlushPolicy::parse("0").is_err());
+        assert!(FlushPolicy::parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_flush_policy_controls_flush_frequency() {
+        // Simulate 3 chunks of 4 tokens each and count flushes.
+        fn flushes(policy: FlushPolicy) -> usize {
+            let (mut pending, mut count) = (0, 0);
+            for _chunk in 0..3 {
+                for _token in 0..4 {
+                    pending += 1;
+                    if policy.should_flush(pending, false) {
+                        count += 1;
+                        pending = 0;
+                    }
+                }
+                if policy.should_flush(pending, true) {
+                    count += 1;
+                    pending = 0;
+                }
+            }
+            count
+        }
+        assert_eq!(flushes(FlushPolicy::Token), 12);
+        assert_eq!(flushes(FlushPolicy::Chunk), 3);
+        assert_eq!(flushes(FlushPolicy::Every(5)), 2);
+        assert_eq!(flushes(FlushPolicy::Every(1)), 12);
+    }
+
+    #[test]
+    fn test_word_boundaries_join_split_word() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut interceptor =
+            TokenInterceptor::new(Provider::Mock, Transform::Reverse, "mock".to_string(), false, false, false)
+                .expect("mock interceptor")
+                .with_rate(1.0)
+                .with_web_tx(tx);
+        interceptor.word_boundaries = true;
+        interceptor.process_delta("wor", Some(-0.1), vec![]);
+        interceptor.process_delta("ld", Some(-2.0), vec![]);
+        interceptor.flush_word_buffer();
+        let mut events = Vec::new();
+        while let Ok(ev) = rx.try_recv() {
+            events.push(ev);
+        }
+        let words: Vec<_> = events.iter().filter(|e| !e.original.trim().is_empty()).collect();
+        assert_eq!(words.len(), 1, "got: {:?}", events);
+        assert_eq!(words[0].original, "world");
+        assert_eq!(words[0].text, "dlrow");
+        assert!(words[0].transformed);
+        assert_eq!(words[0].perplexity, Some((0.1_f32).exp()));
+    }
+
+    #[cfg(feature = "self-tune")]
+    #[tokio::test]
+    async fn test_provider_request_sizes_recorded_on_telemetry_bus() {
+        use crate::self_tune::telemetry_bus::{BusConfig, PipelineStage, TelemetryBus};
+        const BODY: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n\
+                            data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n\
+                            data: [DONE]\n\n";
+        let (addr, server) =
+            mock_http::serve(1, |_, _| Some(mock_http::response("200 OK", "text/event-stream", BODY))).await;
+
+        let bus = std::sync::Arc::new(TelemetryBus::new(BusConfig::default()));
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut interceptor = make_test_interceptor().with_web_tx(tx);
+        interceptor.endpoint_url = Some(format!("http://{}/v1/chat/completions", addr));
+        interceptor.telemetry_bus = Some(bus.clone());
+        interceptor.intercept_stream("hi").await.expect("stream");
+        let received = server.await.expect("server");
+        assert_eq!(interceptor.token_count, 2);
+
+        let recent = bus.recent_network().await;
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].status, 200);
+        assert_eq!(recent[0].response_bytes, BODY.len() as u64);
+        assert!(recent[0].request_bytes > 0);
+        assert_eq!(recent[0].request_bytes as usize, received[0].body.len());
+        let totals = bus.network_totals();
+        assert_eq!((totals.requests, totals.failed), (1, 0));
+        assert_eq!(totals.response_bytes, BODY.len() as u64);
+        assert_eq!(bus.snapshot_stage(PipelineStage::Network).await.count, 1);
+    }
+
+    /// Answer the first OpenAI request with a logprobs-rejection 400 and the
+    /// rest with a two-token stream, returning the URL and the request bodies.
+    async fn logprobs_rejecting_server() -> (String, tokio::task::JoinHandle<Vec<mock_http::MockRequest>>) {
+        const REJECTION: &str = r#"{"error":{"message":"This model does not support the 'logprobs' parameter.","type":"invalid_request_error","param":"logprobs","code":"unsupported_parameter"}}"#;
+        const STREAM: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n\
+                              data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n\
+                              data: [DONE]\n\n";
+        let (addr, server) = mock_http::serve(2, |attempt, _| {
+            Some(if attempt == 0 {
+                mock_http::response("400 Bad Request", "application/json", REJECTION)
+            } else {
+                mock_http::response("200 OK", "text/event-stream", STREAM)
+            })
+        })
+        .await;
+        (format!("http://{}/v1/chat/completions", addr), server)
+    }
+
+    #[tokio::test]
+    async fn test_logprobs_rejection_retries_without_logprobs() {
+        let (url, server) = logprobs_rejecting_server().await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut interceptor = make_test_interceptor().with_web_tx(tx);
+        interceptor.endpoint_url = Some(url);
+        interceptor.intercept_stream("hi").await.expect("fallback stream succeeds");
+        assert_eq!(interceptor.token_count, 2);
+        let notice = rx.try_recv().expect("fallback notice");
+        assert!(notice.is_error);
+        assert!(notice.text.contains("does not support logprobs"), "{}", notice.text);
+        assert!(!interceptor.logprobs);
+        let bodies: Vec<_> = server.await.expect("server").iter().map(mock_http::MockRequest::json).collect();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["logprobs"], true);
+        assert_eq!(bodies[1]["logprobs"], false);
+        assert!(bodies[1].get("top_logprobs").is_none());
+
+        let (url, server) = logprobs_rejecting_server().await;
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut interceptor = make_test_interceptor().with_web_tx(tx).with_logprobs_fallback(false);
+        interceptor.endpoint_url = Some(url);
+        let err = interceptor.intercept_stream("hi").await.expect_err("no fallback");
+        assert!(err.to_string().contains("logprobs"), "{}", err);
+        server.abort();
+    }
+
+    /// Serve `body` as an OpenAI event stream to each of `requests`
+    /// connections and return the URL.
+    async fn sse_body_server(body: &'static str, requests: usize) -> String {
+        let (addr, _) =
+            mock_http::serve(requests, move |_, _| Some(mock_http::response("200 OK", "text/event-stream", body)))
+                .await;
+        format!("http://{}/v1/chat/completions", addr)
+    }
+
+    #[tokio::test]
+    async fn test_malformed_chunk_counted_and_fatal_when_strict() {
+        const STREAM: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n\
+                              data: {\"choices\":[{\"delta\":\n\n\
+                              data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n\
+                              data: [DONE]\n\n";
+        let url = sse_body_server(STREAM, 2).await;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut lenient = make_test_interceptor().with_web_tx(tx);
+        lenient.endpoint_url = Some(url.clone());
+        lenient.intercept_stream("hi").await.expect("lenient mode skips the bad chunk");
+        assert_eq!(lenient.parse_error_count, 1);
+        assert_eq!(lenient.token_count, 2);
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut strict = make_test_interceptor().with_web_tx(tx);
+        strict.strict_parsing = true;
+        strict.endpoint_url = Some(url);
+        let err = strict.intercept_stream("hi").await.expect_err("strict mode fails");
+        assert!(err.to_string().contains("parse error"), "{}", err);
+        assert_eq!(strict.parse_error_count, 1);
+        assert_eq!(strict.token_count, 1, "the stream stops at the bad chunk");
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_stream_emits_note_instead_of_nothing() {
+        const STREAM: &str = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":null,\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"get_weather\",\"arguments\":\"\"}}]}}]}\n\n\
+                              data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"city\\\":\\\"Oslo\\\"}\"}}]}}]}\n\n\
+                              data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n\
+                              data: [DONE]\n\n";
+        let url = sse_body_server(STREAM, 1).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut interceptor = make_test_interceptor().with_web_tx(tx);
+        interceptor.endpoint_url = Some(url);
+        interceptor.intercept_stream("weather?").await.expect("stream");
+        assert_eq!(interceptor.parse_error_count, 0);
+        assert_eq!(interceptor.tool_call_count, 1);
+        assert_eq!(interceptor.token_count, 0);
+        let note = rx.try_recv().expect("tool call note");
+        assert_eq!(note.text, "[tool call] get_weather");
+        assert!(note.is_error);
+        assert!(rx.try_recv().is_err(), "argument fragments are not sent as tokens");
+    }
+
+    #[test]
+    fn test_tool_call_event_serializes_with_type_tag() {
+        let chunk: OpenAIChunk = serde_json::from_str(
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":1,"function":{"arguments":"{\"ci"}}]}}]}"#,
+        )
+        .expect("deser");
+        let event = ToolCallEvent::from_delta(&chunk.choices[0].delta.tool_calls[0]);
+        assert_eq!(
+            serde_json::to_string(&event).expect("serialize"),
+            r#"{"type":"tool_call","index":1,"arguments_fragment":"{\"ci"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_overloaded_stream_is_typed_and_retried() {
+        const STREAM: &str = "event: error\n\
+                              data: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n";
+        let url = sse_body_server(STREAM, 2).await;
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut interceptor = make_test_interceptor().with_web_tx(tx);
+        interceptor.provider = Provider::Anthropic;
+        interceptor.endpoint_url = Some(url);
+        interceptor.max_retries = 2;
+
+        let start = std::time::Instant::now();
+        let err = interceptor.intercept_stream("hi").await.expect_err("overloaded");
+        assert!(
+            start.elapsed() >= std::time::Duration::from_millis(ANTHROPIC_OVERLOADED_BACKOFF_MS),
+            "the second attempt waits out the back-off"
+        );
+        match err.downcast_ref::<error::EotError>() {
+            Some(e @ error::EotError::ProviderApi { error_type, status: None, .. }) => {
+                assert_eq!(error_type, "overloaded_error");
+                assert!(e.is_overloaded());
+            }
+            other => panic!("expected a typed overloaded error, got {:?} ({})", other, err),
+        }
+        assert_eq!(err.to_string(), "Anthropic API error (overloaded_error): Overloaded");
+    }
+
+    #[test]
+    fn test_is_logprobs_rejection() {
+        let rejection = r#"{"error":{"message":"logprobs is not supported with this model","param":null}}"#;
+        assert!(is_logprobs_rejection(400, rejection));
+        assert!(is_logprobs_rejection(400, r#"{"error":{"message":"bad","param":"top_logprobs"}}"#));
+        assert!(!is_logprobs_rejection(401, rejection));
+        assert!(!is_logprobs_rejection(400, r#"{"error":{"message":"max_tokens is too large","param":"max_tokens"}}"#));
+        assert!(!is_logprobs_rejection(400, "logprobs"));
+    }
+
+    /// Serve one MCP `infer` response whose text is `enriched` and return its URL.
+    async fn one_shot_orchestrator(enriched: &'static str) -> String {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {"content": [{"type": "text", "text": enriched}]},
+        })
+        .to_string();
+        let (addr, _) = mock_http::serve(1, move |_, _| Some(mock_http::response("200 OK", "application/json", &body))).await;
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn test_show_enriched_captures_enriched_prompt() {
+        let url = one_shot_orchestrator("Be concise.\nhello").await;
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut interceptor =
+            TokenInterceptor::new(Provider::Mock, Transform::Reverse, "mock".to_string(), false, false, true)
+                .expect("mock interceptor")
+                .with_orchestrator_url(url)
+                .with_web_tx(tx);
+        interceptor.show_enriched = true;
+        interceptor.intercept_stream("hello").await.expect("stream");
+        let enrichment = interceptor.enrichment.clone().expect("enrichment captured");
+        assert_eq!(enrichment.original, "hello");
+        assert_eq!(enrichment.enriched, "Be concise.\nhello");
+        assert!(enrichment.error.is_none());
+        assert_eq!(
+            enrichment.render_diff(),
+            "[orchestrator] original -> enriched prompt:\n+ Be concise.\n  hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_enrichment_records_raw_prompt() {
+        // Bind then drop a listener so the port refuses connections.
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|l| l.local_addr())
+            .expect("addr");
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut interceptor =
+            TokenInterceptor::new(Provider::Mock, Transform::Reverse, "mock".to_string(), false, false, true)
+                .expect("mock interceptor")
+                .with_orchestrator_url(format!("http://{}/", addr))
+                .with_web_tx(tx);
+        interceptor.intercept_stream("hello").await.expect("stream");
+        let enrichment = interceptor.enrichment.clone().expect("enrichment captured");
+        assert_eq!(enrichment.enriched, "hello");
+        assert!(enrichment.error.is_some());
+        assert!(enrichment.render_diff().contains("raw prompt used unchanged"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_orchestrator_times_out_and_falls_back() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let attempts = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = std::sync::Arc::clone(&attempts);
+        // Read every request but never answer.
+        let (addr, _) = mock_http::serve(usize::MAX, move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            None
+        })
+        .await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut interceptor =
+            TokenInterceptor::new(Provider::Mock, Transform::Reverse, "mock".to_string(), false, false, true)
+                .expect("mock interceptor")
+                .with_orchestrator_url(format!("http://{}/", addr))
+                .with_max_retries(2)
+                .with_web_tx(tx);
+        interceptor.orchestrator_timeout = std::time::Duration::from_millis(100);
+        let started = std::time::Instant::now();
+        interceptor.intercept_stream("hello").await.expect("stream falls back");
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        let enrichment = interceptor.enrichment.clone().expect("enrichment captured");
+        assert_eq!(enrichment.enriched, "hello");
+        let error = enrichment.error.expect("timeout recorded");
+        assert_eq!(error, "orchestrator timed out after 100 ms");
+        let events: Vec<TokenEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(events[0].is_error && events[0].text.contains("timed out"));
+        assert!(events.iter().skip(1).any(|e| !e.is_error), "the raw prompt still streams");
+    }
+
+    /// Send `req` to a one-shot local server and return the raw request head it received.
+    async fn capture_request_head(client: &reqwest::Client, mut req: reqwest::Request) -> String {
+        let (addr, server) = mock_http::serve(1, |_, _| Some(mock_http::response("200 OK", "text/plain", ""))).await;
+        *req.url_mut() = format!("http://{}/", addr).parse().expect("url");
+        client.execute(req).await.expect("send");
+        server.await.expect("server").remove(0).head
+    }
+
+    #[tokio::test]
+    async fn test_extra_headers_sent_to_provider() {
+        let mut interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_extra_headers(vec![
+            ("OpenAI-Organization".to_string(), "org-123".to_string()),
+            ("anthropic-beta".to_string(), "tools-2024-04-04".to_string()),
+        ]);
+        interceptor.api_key = "sk-test".to_string();
+
+        let head = capture_request_head(
+            &interceptor.client,
+            interceptor.openai_request("hi").expect("openai request"),
+        )
+        .await;
+        assert!(head.contains("openai-organization: org-123"), "got: {}", head);
+        assert!(head.contains("authorization: bearer sk-test"), "got: {}", head);
+
+        let head = capture_request_head(
+            &interceptor.client,
+            interceptor.anthropic_request("hi").expect("anthropic request"),
+        )
+        .await;
+        assert!(head.contains("anthropic-beta: tools-2024-04-04"), "got: {}", head);
+        assert!(head.contains("x-api-key: sk-test"), "got: {}", head);
+    }
+
+    #[test]
+    fn test_temperature_and_max_tokens_reach_request_bodies() {
+        let interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor")
+        .with_temperature(1.5)
+        .with_max_tokens(128);
+        let body = |req: reqwest::Request| -> serde_json::Value {
+            let bytes = req.body().and_then(|b| b.as_bytes()).expect("buffered body");
+            serde_json::from_slice(bytes).expect("json body")
+        };
+        let openai = body(interceptor.openai_request("hi").expect("openai request"));
+        assert_eq!(openai["max_tokens"], 128);
+        assert_eq!(openai["temperature"], 1.5);
+        let anthropic = body(interceptor.anthropic_request("hi").expect("anthropic request"));
+        assert_eq!(anthropic["max_tokens"], 128);
+        assert_eq!(anthropic["temperature"], 1.0);
+    }
+
+    #[test]
+    fn test_openai_request_omits_top_logprobs_when_disabled() {
+        let interceptor = TokenInterceptor::new(
+            Provider::Mock,
+            Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .expect("mock interceptor");
+        let body = |i: &TokenInterceptor| -> serde_json::Value {
+            let req = i.openai_request("hi").expect("openai request");
+            let bytes = req.body().and_then(|b| b.as_bytes()).expect("buffered body");
+            serde_json::from_slice(bytes).expect("json body")
+        };
+        let on = body(&interceptor);
+        assert_eq!(on["logprobs"], true);
+        assert_eq!(on["top_logprobs"], 5);
+
+        let off = body(&interceptor.with_logprobs(false));
+        assert_eq!(off["logprobs"], false);
+        assert!(off.get("top_logprobs").is_none(), "body: {off}");
+    }
 }
 
 #[cfg(test)]
 mod research_tests {
     use super::*;
+    use crate::tests::make_test_interceptor;
 
     fn make_session(
         tokens: usize,
@@ -2516,57 +4915,28 @@ mod research_tests {
     ) -> ResearchSession {
         ResearchSession {
             prompt: "test prompt".to_string(),
+            effective_prompt: None,
             provider: "openai".to_string(),
             model: "gpt-3.5-turbo".to_string(),
             transform: "Reverse".to_string(),
+            temperature: 0.7,
             runs: 1,
+            tag: None,
+            notes: None,
             total_tokens: tokens,
             total_transformed: tokens / 2,
             vocabulary_diversity: 0.8,
+            transform_impact: 0.5,
             mean_token_length: 4.5,
             mean_perplexity: perplexity.map(|p| p as f64),
             mean_confidence: confidence.map(|c| c as f64),
             top_perplexity_tokens: vec!["word".to_string()],
             estimated_cost_usd: tokens as f64 / 1000.0 * 0.002,
-            citation: format!("Every Other Token v4.0.0 | tokens={}", tokens),
-        }
-    }
-
-    /// Construct a minimal [`TokenInterceptor`] suitable for unit tests.
-    ///
-    /// Uses a fixed RNG seed, a no-op web channel, and the `Reverse` transform.
-    fn make_test_interceptor() -> TokenInterceptor {
-        TokenInterceptor {
-            client: reqwest::Client::new(),
-            api_key: "test-key".to_string(),
-            provider: Provider::Openai,
-            transform: Transform::Reverse,
-            model: "test-model".to_string(),
-            token_count: 0,
-            transformed_count: 0,
-            visual_mode: false,
-            heatmap_mode: false,
-            orchestrator: false,
-            orchestrator_url: "http://localhost:3000".to_string(),
-            web_tx: None,
-            web_provider_label: None,
-            system_prompt: None,
-            #[cfg(feature = "self-tune")]
-            telemetry_bus: None,
-            #[cfg(feature = "self-modify")]
-            dedup: None,
-            rate: 0.5,
-            rng: StdRng::seed_from_u64(42),
-            top_logprobs: 5,
-            recorder: None,
-            json_stream: false,
-            pending_delay_ms: 0,
-            min_confidence: None,
-            last_token_instant: None,
-            max_retries: 3,
-            anthropic_max_tokens: 4096,
-            stream_start_instant: None,
-            timeout_secs: None,
+            citation: format!(
+                "Every Other Token v{} | tokens={}",
+                crate::build_info::VERSION,
+                tokens
+            ),
         }
     }
 
@@ -2614,6 +4984,20 @@ mod research_tests {
         assert_eq!(s.top_perplexity_tokens.len(), 10);
     }
 
+    #[test]
+    fn test_research_citation_uses_package_version() {
+        let c = research_citation(
+            "hi",
+            &providers::Provider::Mock,
+            "m",
+            &transforms::Transform::Reverse,
+            2,
+            10,
+        );
+        assert!(c.starts_with(&format!("Every Other Token v{} |", env!("CARGO_PKG_VERSION"))));
+        assert!(c.contains("runs=2"));
+    }
+
     #[test]
     fn test_research_session_citation_contains_prompt() {
         let s = make_session(5, None, None);
@@ -2631,6 +5015,49 @@ mod research_tests {
         assert_eq!(v["runs"], 42);
     }
 
+    #[test]
+    fn test_research_session_json_key_order() {
+        let json = serde_json::to_string(&make_session(10, Some(0.5), Some(2.0))).expect("serialize");
+        let order = [
+            "prompt",
+            "provider",
+            "model",
+            "transform",
+            "temperature",
+            "runs",
+            "total_tokens",
+            "total_transformed",
+            "vocabulary_diversity",
+            "transform_impact",
+            "mean_token_length",
+            "mean_perplexity",
+            "mean_confidence",
+            "estimated_cost_usd",
+            "citation",
+            "top_perplexity_tokens",
+        ];
+        let positions: Vec<usize> = order
+            .iter()
+            .map(|k| json.find(&format!("\"{}\":", k)).unwrap_or_else(|| panic!("missing {}", k)))
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "keys out of order: {}", json);
+    }
+
+    #[test]
+    fn test_research_session_tag_and_notes_roundtrip_and_cite() {
+        let untagged = serde_json::to_string(&make_session(10, None, None)).expect("serialize");
+        assert!(!untagged.contains("\"tag\"") && !untagged.contains("\"notes\""));
+
+        let s = make_session(10, None, None)
+            .tagged(Some("ablation-3".to_string()), Some("seed sweep, warm cache".to_string()));
+        assert!(s.citation.ends_with(" | tag=ablation-3 | notes=\"seed sweep, warm cache\""));
+        let json = serde_json::to_string(&s).expect("serialize");
+        let back: ResearchSession = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(back.tag.as_deref(), Some("ablation-3"));
+        assert_eq!(back.notes.as_deref(), Some("seed sweep, warm cache"));
+        assert_eq!(back.citation, s.citation);
+    }
+
     #[test]
     fn test_research_session_transform_field() {
         let s = make_session(10, None, None);
@@ -2759,6 +5186,58 @@ mod research_tests {
 
     // -- run_research_headless tests (Mock provider, no API key required) --
 
+    #[tokio::test]
+    async fn test_temperature_sweep_produces_one_session_per_temperature() {
+        let temps = [0.0_f32, 0.5, 1.0];
+        let sweep = run_temperature_sweep(
+            "hello",
+            providers::Provider::Mock,
+            transforms::Transform::Reverse,
+            "mock".to_string(),
+            2,
+            &temps,
+        )
+        .await
+        .expect("mock sweep should not fail");
+        assert_eq!(sweep.sessions.len(), 3);
+        assert_eq!(sweep.comparison.len(), 3);
+        for (session, &t) in sweep.sessions.iter().zip(&temps) {
+            assert_eq!(session.temperature, t);
+            assert_eq!(session.runs, 2);
+            assert!(session.total_tokens > 0);
+        }
+        // The mock flattens its distribution as temperature rises.
+        let perplexities: Vec<f64> = sweep.comparison.iter().map(|r| r.mean_perplexity.unwrap()).collect();
+        assert!((perplexities[0] - 1.0).abs() < 1e-6, "greedy sampling is fully confident");
+        assert!(perplexities.windows(2).all(|w| w[0] < w[1]), "{:?}", perplexities);
+
+        let table = sweep.render_table();
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.lines().nth(3).unwrap().trim_start().starts_with("1.00"));
+    }
+
+    #[tokio::test]
+    async fn test_temperature_sweep_with_configures_every_run() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let configure = |i: TokenInterceptor| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(i.with_start_index(100))
+        };
+        let temps = [0.2_f32, 0.8];
+        run_temperature_sweep_with("hello", Provider::Mock, Transform::Reverse, "mock".to_string(), 3, &temps, &configure)
+            .await
+            .expect("mock sweep should not fail");
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 6);
+
+        let failing = |_: TokenInterceptor| -> Result<TokenInterceptor, Box<dyn std::error::Error>> {
+            Err("bad --preprocess".into())
+        };
+        let err = run_temperature_sweep_with("hello", Provider::Mock, Transform::Reverse, "mock".to_string(), 1, &[0.5], &failing)
+            .await
+            .expect_err("configure errors propagate");
+        assert_eq!(err.to_string(), "bad --preprocess");
+    }
+
     #[tokio::test]
     async fn test_run_research_headless_mock_returns_session() {
         let session = run_research_headless(
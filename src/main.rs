@@ -1,8 +1,9 @@
 use clap::CommandFactory;
 use clap::Parser;
 use every_other_token::cli::Args;
-use every_other_token::transforms::Transform;
 use every_other_token::TokenInterceptor;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -22,12 +23,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // auto-launch the web UI instead of printing help and exiting immediately.
     if args.prompt.is_empty()
         && !args.web
+        && args.watch.is_none()
+        && !args.mcp_server
         && !args.research
         && !args.dry_run
         && args.record.is_none()
         && args.replay.is_none()
+        && args.replay_export.is_none()
         && !args.validate_config
         && args.list_models.is_none()
+        && !args.list_transforms
+        && !args.bench_transforms
+        && !args.doctor
         && !args.json_schema
         && !args.diff_terminal
         && args.batch.is_none()
@@ -57,10 +64,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
-        if args.model == "gpt-3.5-turbo" {
-            if let Some(m) = cfg.model {
-                args.model = m;
-            }
+        if args.model.is_none() {
+            args.model = cfg.model;
         }
         if args.transform == "reverse" {
             if let Some(t) = cfg.transform {
@@ -93,8 +98,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         if args.api_key.is_none() {
             args.api_key = cfg.api_key;
         }
+        if args.max_prompt_chars == every_other_token::DEFAULT_MAX_PROMPT_CHARS {
+            if let Some(n) = cfg.max_prompt_chars {
+                args.max_prompt_chars = n;
+            }
+        }
+    }
+
+    if args.no_color {
+        colored::control::set_override(false);
     }
 
+    let budget = match every_other_token::cli::SharedBudget::from_args(&args) {
+        Ok(budget) => budget,
+        Err(e) => {
+            eprintln!("[eot] {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let transform_options = match every_other_token::cli::resolve_transform_options(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("[eot] {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Stdin support (#17): if prompt is "-", read from stdin.
     if args.prompt == "-" {
         use std::io::Read;
@@ -105,7 +135,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Model validation (#18): warn early about unknown model names.
     {
-        let model = every_other_token::cli::resolve_model(&args.provider, &args.model);
+        let model = every_other_token::cli::resolve_model(&args.provider, args.model.as_deref());
         every_other_token::cli::validate_model(&args.provider, &model);
     }
 
@@ -114,7 +144,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         use every_other_token::config::EotConfig;
         let cfg = EotConfig::load();
         println!("[eot config] provider: {}", args.provider);
-        println!("[eot config] model: {}", args.model);
+        println!(
+            "[eot config] model: {}",
+            every_other_token::cli::resolve_model(&args.provider, args.model.as_deref())
+        );
         println!("[eot config] transform: {}", args.transform);
         println!("[eot config] rate: {}", args.rate.unwrap_or(0.5));
         println!("[eot config] port: {}", args.port);
@@ -127,6 +160,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(0);
     }
 
+    // --list-transforms: print the transform catalog and exit
+    if args.list_transforms {
+        let catalog = every_other_token::transforms::Transform::catalog();
+        let width = catalog.iter().map(|t| t.name.len()).max().unwrap_or(0);
+        for t in &catalog {
+            println!("  {:<width$}  {}", t.name, t.description, width = width);
+            println!("  {:<width$}  e.g. {} -> {}", "", t.example_input, t.example_output, width = width);
+        }
+        std::process::exit(0);
+    }
+
+    if args.bench_transforms {
+        let rows = every_other_token::transform_bench::run(every_other_token::transform_bench::DEFAULT_BENCH_WORDS);
+        print!("{}", every_other_token::transform_bench::report(&rows));
+        std::process::exit(0);
+    }
+
     // --list-models: print known models and exit
     if let Some(ref provider_filter) = args.list_models.clone() {
         let openai_models = ["gpt-3.5-turbo", "gpt-4", "gpt-4o", "gpt-4o-mini", "gpt-4-turbo"];
@@ -154,6 +204,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(0);
     }
 
+    // --doctor: check keys and provider reachability, then exit
+    if args.doctor {
+        let checks =
+            every_other_token::doctor::run_checks(&args.provider, args.model.as_deref(), &args.headers).await;
+        print!("{}", every_other_token::doctor::report(&checks));
+        let selected_failed = checks.iter().any(|c| c.provider == args.provider && c.result.is_err());
+        std::process::exit(if selected_failed { 1 } else { 0 });
+    }
+
     // --json-schema: print embedded research schema and exit
     if args.json_schema {
         const RESEARCH_SCHEMA: &str = include_str!("../docs/research-schema.json");
@@ -186,7 +245,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Dry-run mode: validate transform and show sample token transformations
     if args.dry_run {
-        let transform = every_other_token::transforms::Transform::from_str_loose(&args.transform)
+        let transform = every_other_token::cli::resolve_transform(&args)
             .map_err(|e| format!("Invalid transform: {}", e))?;
         println!("[dry-run] Transform: {:?}", transform);
         println!("[dry-run] Rate: {}", args.rate.unwrap_or(0.5));
@@ -202,14 +261,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("  Input: {:?}", token);
                 let mut current = token.to_string();
                 for step in steps {
-                    let (next, _label) = step.apply_with_label(&current);
+                    let (next, _label) =
+                        step.apply_with_options_rng(&current, &mut rand::thread_rng(), &transform_options);
                     println!("  After {:?}: {:?}", step, next);
                     current = next;
                 }
             }
         } else {
             for (i, token) in sample_tokens.iter().enumerate() {
-                let (result, label) = transform.apply_with_label(token);
+                let (result, label) =
+                    transform.apply_with_options_rng(token, &mut rand::thread_rng(), &transform_options);
                 let marker = if (i % 2) == 1 {
                     "→ TRANSFORMED"
                 } else {
@@ -226,6 +287,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         args.prompt = every_other_token::cli::apply_template(tmpl, &args.prompt);
     }
 
+    // Substitute {name} variables from --var / --vars-file into the prompt,
+    // unless --preprocess places the template stage itself
+    let template_vars = every_other_token::cli::resolve_template_vars(&args)?;
+    if !template_vars.is_empty() && !args.preprocess.contains(&every_other_token::preprocess::StageKind::Template) {
+        args.prompt = every_other_token::prompt_template::apply_template(&args.prompt, &template_vars)
+            .map_err(|e| format!("Invalid prompt template: {}", e))?;
+    }
+
     // Apply rate range: pick a random rate in [min, max] if --rate-range is set
     if let Some(ref range_str) = args.rate_range.clone() {
         if let Some((min, max)) = every_other_token::cli::parse_rate_range(range_str) {
@@ -244,6 +313,67 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Watch mode: follow a collaboration room read-only
+    if let Some(ref code) = args.watch {
+        let style = every_other_token::render::TerminalStyle {
+            heatmap: args.heatmap,
+            visual: args.visual,
+            show_original: args.show_original,
+            color_by: args.color_by,
+        };
+        tokio::select! {
+            result = every_other_token::watch::watch_room(&args.watch_url, code, style) => {
+                result?;
+            }
+            _ = tokio::signal::ctrl_c() => {
+                eprintln!("\n[eot] stopped watching");
+            }
+        }
+        return Ok(());
+    }
+
+    // Replay-export mode: re-render an exported session without any API call
+    if let Some(ref path) = args.replay_export {
+        let session = every_other_token::replay::ExportedSession::load(path)?;
+        let style = every_other_token::render::TerminalStyle {
+            heatmap: args.heatmap,
+            visual: args.visual,
+            show_original: args.show_original,
+            color_by: args.color_by,
+        };
+        let color = colored::control::SHOULD_COLORIZE.should_colorize();
+        println!("{}", session.render_terminal(style, color));
+        let (tokens, transformed) = session.loaded_counts();
+        eprintln!("[eot] replayed {} tokens ({} transformed) from {}", tokens, transformed, path);
+        if !session.matches_recorded_counts() {
+            eprintln!(
+                "[eot] warning: export recorded {:?} tokens / {:?} transformed",
+                session.token_count, session.transformed_count
+            );
+        }
+        return Ok(());
+    }
+
+    // MCP worker mode: serve tools/call infer over JSON-RPC
+    if args.mcp_server {
+        let config = match every_other_token::mcp_server::WorkerConfig::from_args(&args) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("[eot] {}", e);
+                std::process::exit(1);
+            }
+        };
+        tokio::select! {
+            result = every_other_token::mcp_server::serve(args.port, config) => {
+                result?;
+            }
+            _ = tokio::signal::ctrl_c() => {
+                eprintln!("\n[eot] shutting down MCP worker");
+            }
+        }
+        return Ok(());
+    }
+
     // Web UI mode
     if args.web {
         tokio::select! {
@@ -259,7 +389,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Research mode: run N iterations, collect aggregate stats, write JSON
     if args.research {
-        if args.prompt_file.is_some() {
+        if !args.temp_sweep.is_empty() {
+            every_other_token::research::run_temperature_sweep(&args).await?;
+        } else if args.prompt_file.is_some() {
             tokio::select! {
                 result = every_other_token::research::run_research_suite(&args) => {
                     result?;
@@ -269,13 +401,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         } else {
-            tokio::select! {
-                result = every_other_token::research::run_research(&args) => {
-                    result?;
+            // First Ctrl+C stops after writing the completed runs; a second
+            // one aborts immediately.
+            let stop = Arc::new(AtomicBool::new(false));
+            let signal_stop = stop.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    eprintln!("\n[eot] interrupted — writing partial results (Ctrl+C again to abort)");
+                    signal_stop.store(true, Ordering::Relaxed);
                 }
-                _ = tokio::signal::ctrl_c() => {
-                    eprintln!("\n[eot] interrupted — partial results may not have been written");
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
                 }
+            });
+            every_other_token::research::run_research_with_stop(&args, stop.clone()).await?;
+            if stop.load(Ordering::Relaxed) {
+                std::process::exit(130);
             }
         }
         return Ok(());
@@ -588,11 +729,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .map_err(|e| format!("Failed to load synonym file '{}': {}", path, e))?;
     }
 
-    let transform = Transform::from_str_loose(&args.transform)
+    let transform = every_other_token::cli::resolve_transform(&args)
         .map_err(|e| format!("Invalid transform: {}", e))?;
 
     // Auto-select a sensible default model when switching providers
-    let model = every_other_token::cli::resolve_model(&args.provider, &args.model);
+    let model = every_other_token::cli::resolve_model(&args.provider, args.model.as_deref());
+    let sent_prompt = every_other_token::cli::transform_prompt(&args, &args.prompt, &transform)?;
+    if let Some(ref sent) = sent_prompt {
+        eprintln!("[eot] --transform-prompt: sending {:?}", sent);
+    }
+    let preprocessors = every_other_token::cli::preprocess_stages(&args)?;
 
     let mut interceptor = {
         let mut i = TokenInterceptor::new(
@@ -603,7 +749,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             args.heatmap,
             args.orchestrator,
         )?
-        .with_rate(args.rate.unwrap_or(0.5));
+        .with_rate(args.rate.unwrap_or(0.5))
+        .with_start_index(args.start_index)
+        .with_extra_headers(args.headers.clone())
+        .with_transform_options(transform_options);
         if let Some(seed) = args.seed {
             i = i.with_seed(seed);
         }
@@ -611,22 +760,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     interceptor.top_logprobs = args.top_logprobs;
+    interceptor.logprobs = !args.no_logprobs;
+    interceptor.logprobs_fallback = !args.no_logprobs_fallback;
+    interceptor.strict_parsing = args.strict_parsing;
+    interceptor.preprocessors = preprocessors;
     interceptor.json_stream = args.json_stream;
     interceptor.orchestrator_url = args.orchestrator_url.clone();
+    interceptor.show_enriched = args.show_enriched;
+    interceptor.word_boundaries = args.word_boundaries;
+    if let Some(window) = args
+        .window_transform
+        .as_deref()
+        .and_then(every_other_token::transforms::window_transform_by_name)
+    {
+        interceptor = interceptor.with_window_transform(window);
+    }
+    interceptor.flush_policy = args.flush_policy;
+    interceptor.normalize = args.normalize;
+    interceptor.normalize_original = args.normalize_original;
+    interceptor.inline_mapping = args.inline_mapping.clone();
+    interceptor.collapse_whitespace = args.collapse_whitespace;
     interceptor.max_retries = args.max_retries;
     interceptor.min_confidence = args.min_confidence;
+    interceptor.importance_smoothing = args.importance_smoothing;
+    interceptor.selector = args.select;
+    interceptor.pos_filter = args.pos_filter;
+    interceptor.granularity = args.granularity;
     interceptor.anthropic_max_tokens = args.anthropic_max_tokens;
     if args.timeout > 0 {
         interceptor = interceptor.with_timeout(args.timeout);
     }
+    interceptor.max_prompt_chars = args.max_prompt_chars;
+    interceptor.show_original = args.show_original;
+    interceptor.color_by = args.color_by;
+    interceptor.deterministic_importance = args.deterministic_importance;
+    if let Some(ref path) = args.tee {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("cannot create --tee file {}: {}", path, e))?;
+        interceptor = interceptor.with_file_sink(Box::new(std::io::BufWriter::new(file)));
+    }
+    if let Some(ref path) = args.debug_raw_file {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("cannot create --debug-raw-file {}: {}", path, e))?;
+        interceptor = interceptor.with_raw_sink(Box::new(file));
+    } else if args.debug_raw {
+        interceptor = interceptor.with_raw_sink(Box::new(std::io::stderr()));
+    }
+    interceptor = budget.attach(interceptor);
 
-    tokio::select! {
-        result = interceptor.intercept_stream(&args.prompt) => {
+    // On Ctrl+C the stream future is dropped, which releases the borrow so
+    // the footer can report what was processed before the interrupt.
+    let finished = tokio::select! {
+        result = interceptor.intercept_stream(sent_prompt.as_deref().unwrap_or(&args.prompt)) => {
             result?;
+            true
         }
-        _ = tokio::signal::ctrl_c() => {
-            eprintln!("\n[eot] shutting down gracefully");
-        }
+        _ = tokio::signal::ctrl_c() => false,
+    };
+    if !finished {
+        interceptor.finish_interrupted();
+        std::process::exit(130);
     }
 
     Ok(())
@@ -0,0 +1,273 @@
+//! JSON-RPC MCP worker mode (`--mcp-server`).
+//!
+//! The inverse of `--orchestrator`: instead of calling an MCP pipeline, the
+//! crate serves one.  Each HTTP `POST` carries a JSON-RPC 2.0 `tools/call`
+//! request for the `infer` tool (the same [`McpInferRequest`] shape the
+//! orchestrator client sends); the prompt is streamed through a
+//! [`TokenInterceptor`] with the server's provider, transform, rate and seed,
+//! and the transformed tokens are returned as a single text content item.
+//!
+//! The interceptor runs with `collapse_whitespace` set, so each token carries
+//! the whitespace that preceded it and the returned text is the emitted token
+//! texts concatenated as-is.
+
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use crate::cli::Args;
+use crate::providers::{McpContent, McpError, McpInferParams, McpInferResponse, McpInferResult, Provider};
+use crate::transforms::Transform;
+use crate::{TokenEvent, TokenInterceptor, TransformSelector};
+
+/// JSON-RPC error code for a request body that is not JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for a method other than `tools/call`.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for missing arguments or a tool other than `infer`.
+pub const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for a failed inference.
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// Inference settings applied to every `infer` call, taken from [`Args`].
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    pub provider: Provider,
+    pub transform: Transform,
+    pub model: String,
+    pub rate: f64,
+    pub seed: Option<u64>,
+    pub selector: TransformSelector,
+    pub logprobs: bool,
+    pub max_prompt_chars: usize,
+    pub extra_headers: Vec<(String, String)>,
+    pub transform_options: crate::transforms::TransformOptions,
+}
+
+impl WorkerConfig {
+    /// Resolve the worker settings from the command line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(String)` when the transform or its options cannot be
+    /// resolved (see [`crate::cli::resolve_transform`] and
+    /// [`crate::cli::resolve_transform_options`]).
+    pub fn from_args(args: &Args) -> Result<Self, String> {
+        Ok(WorkerConfig {
+            provider: args.provider.clone(),
+            transform: crate::cli::resolve_transform(args)?,
+            model: crate::cli::resolve_model(&args.provider, args.model.as_deref()),
+            rate: args.rate.unwrap_or(0.5),
+            seed: args.seed,
+            selector: args.select,
+            logprobs: !args.no_logprobs,
+            max_prompt_chars: args.max_prompt_chars,
+            extra_headers: args.headers.clone(),
+            transform_options: crate::cli::resolve_transform_options(args)?,
+        })
+    }
+
+    /// A fresh interceptor with these settings.
+    fn interceptor(&self) -> Result<TokenInterceptor, String> {
+        let mut interceptor = TokenInterceptor::new(
+            self.provider.clone(),
+            self.transform.clone(),
+            self.model.clone(),
+            false,
+            false,
+            false,
+        )
+        .map_err(|e| e.to_string())?
+        .with_rate(self.rate)
+        .with_logprobs(self.logprobs)
+        .with_selector(self.selector)
+        .with_extra_headers(self.extra_headers.clone())
+        .with_transform_options(self.transform_options.clone());
+        if let Some(seed) = self.seed {
+            interceptor = interceptor.with_seed(seed);
+        }
+        interceptor.max_prompt_chars = self.max_prompt_chars;
+        interceptor.collapse_whitespace = true;
+        Ok(interceptor)
+    }
+
+    /// Stream `prompt` through a fresh interceptor and return the transformed
+    /// text.
+    pub async fn infer(&self, prompt: &str) -> Result<String, String> {
+        let mut interceptor = self.interceptor()?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        interceptor.web_tx = Some(tx);
+        let prompt = prompt.to_string();
+        let task = tokio::spawn(async move {
+            interceptor.intercept_stream(&prompt).await.map_err(|e| e.to_string())
+        });
+        let mut text = String::new();
+        while let Some(event) = rx.recv().await {
+            if event.is_error {
+                return Err(event.text);
+            }
+            text.push_str(&event.text);
+        }
+        task.await.map_err(|e| e.to_string())??;
+        Ok(text)
+    }
+}
+
+fn error_response(id: serde_json::Value, code: i64, message: impl Into<String>) -> McpInferResponse {
+    McpInferResponse {
+        jsonrpc: Some("2.0".to_string()),
+        id,
+        result: None,
+        error: Some(McpError { code, message: message.into() }),
+    }
+}
+
+/// Answer one JSON-RPC request body.
+///
+/// `tools/call` with `{"name":"infer","arguments":{"prompt":...}}` returns
+/// `{"result":{"content":[{"type":"text","text":...}]}}`; anything else
+/// returns a JSON-RPC `error` with the matching code.
+pub async fn handle_rpc(body: &str, config: &WorkerConfig) -> McpInferResponse {
+    let request: serde_json::Value = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(e) => return error_response(serde_json::Value::Null, PARSE_ERROR, format!("parse error: {}", e)),
+    };
+    let id = request.get("id").cloned().unwrap_or(serde_json::Value::Null);
+    let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
+    if method != "tools/call" {
+        return error_response(id, METHOD_NOT_FOUND, format!("method not found: {}", method));
+    }
+    let params = match request.get("params").map(McpInferParams::deserialize) {
+        Some(Ok(params)) => params,
+        Some(Err(e)) => return error_response(id, INVALID_PARAMS, format!("invalid params: {}", e)),
+        None => return error_response(id, INVALID_PARAMS, "missing params"),
+    };
+    if params.name != "infer" {
+        return error_response(id, INVALID_PARAMS, format!("unknown tool: {}", params.name));
+    }
+    match config.infer(&params.arguments.prompt).await {
+        Ok(text) => McpInferResponse {
+            jsonrpc: Some("2.0".to_string()),
+            id,
+            result: Some(McpInferResult {
+                content: vec![McpContent { kind: Some("text".to_string()), text: Some(text) }],
+            }),
+            error: None,
+        },
+        Err(message) => error_response(id, INTERNAL_ERROR, message),
+    }
+}
+
+/// Serve `infer` calls on `127.0.0.1:port` until the process exits.
+pub async fn serve(port: u16, config: WorkerConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
+    eprintln!("[eot] MCP worker listening on http://127.0.0.1:{} (tools/call infer)", port);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &config).await {
+                eprintln!("  mcp connection error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: tokio::net::TcpStream, config: &WorkerConfig) -> std::io::Result<()> {
+    let mut head = vec![0u8; 8192];
+    let n = stream.read(&mut head).await?;
+    head.truncate(n);
+    if !head.starts_with(b"POST ") {
+        let body = r#"{"error":"POST a JSON-RPC tools/call request"}"#;
+        let response = format!(
+            "HTTP/1.1 405 Method Not Allowed\r\nAllow: POST\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        return stream.write_all(response.as_bytes()).await;
+    }
+    let body = crate::web::read_request_body(&mut stream, &head, MAX_BODY_BYTES).await?;
+    let reply = handle_rpc(&String::from_utf8_lossy(&body), config).await;
+    let body = serde_json::to_string(&reply).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_config() -> WorkerConfig {
+        WorkerConfig {
+            provider: Provider::Mock,
+            transform: Transform::Uppercase,
+            model: "mock".to_string(),
+            rate: 1.0,
+            seed: Some(1),
+            selector: TransformSelector::Alternating,
+            logprobs: true,
+            max_prompt_chars: crate::DEFAULT_MAX_PROMPT_CHARS,
+            extra_headers: Vec::new(),
+            transform_options: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_infer_request_returns_transformed_content() {
+        let body = r#"{"jsonrpc":"2.0","method":"tools/call","id":7,"params":{"name":"infer","arguments":{"prompt":"hello","worker":"llama_cpp"}}}"#;
+        let resp = handle_rpc(body, &mock_config()).await;
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        assert_eq!(resp.id, 7);
+        let content = &resp.result.expect("result").content[0];
+        assert_eq!(content.kind.as_deref(), Some("text"));
+        let text = content.text.as_deref().expect("text");
+        assert!(text.contains("QUICK") && text.contains("HELLO"), "got: {}", text);
+        assert!(!text.contains("  "), "got: {}", text);
+    }
+
+    #[test]
+    fn test_token_texts_concatenate_without_added_spaces() {
+        let mut interceptor = mock_config().interceptor().expect("interceptor");
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+        interceptor.web_tx = Some(tx);
+        for delta in ["Hel", "lo", ",", " wor", "ld", "!", "  How", " are", " you", "?"] {
+            interceptor.process_content(delta);
+        }
+        let text: String = std::iter::from_fn(|| rx.try_recv().ok()).map(|e| e.text).collect();
+        assert_eq!(text, "HELLO, WORLD! HOW ARE YOU?");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_method_returns_jsonrpc_error() {
+        let body = r#"{"jsonrpc":"2.0","method":"tools/list","id":"a"}"#;
+        let resp = handle_rpc(body, &mock_config()).await;
+        assert!(resp.result.is_none());
+        let err = resp.error.expect("error");
+        assert_eq!(err.code, METHOD_NOT_FOUND);
+        assert_eq!(resp.id, "a");
+        let json = serde_json::to_value(handle_rpc(body, &mock_config()).await).unwrap();
+        assert_eq!(json["error"]["code"], -32601);
+        assert!(json.get("result").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_requests() {
+        let resp = handle_rpc("not json", &mock_config()).await;
+        assert_eq!(resp.error.map(|e| e.code), Some(PARSE_ERROR));
+        assert!(resp.id.is_null());
+
+        let wrong_tool = r#"{"jsonrpc":"2.0","method":"tools/call","id":1,"params":{"name":"summarize","arguments":{"prompt":"x"}}}"#;
+        assert_eq!(handle_rpc(wrong_tool, &mock_config()).await.error.map(|e| e.code), Some(INVALID_PARAMS));
+
+        let no_prompt = r#"{"jsonrpc":"2.0","method":"tools/call","id":1,"params":{"name":"infer","arguments":{}}}"#;
+        assert_eq!(handle_rpc(no_prompt, &mock_config()).await.error.map(|e| e.code), Some(INVALID_PARAMS));
+    }
+}
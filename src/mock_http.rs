@@ -0,0 +1,98 @@
+//! Test-only HTTP/1.1 server for exercising provider and orchestrator
+//! requests over a real socket.
+//!
+//! [`serve`] binds an ephemeral local port, reads each request in full
+//! (head plus `Content-Length` body) and answers with whatever the caller's
+//! responder returns, collecting the requests it saw.
+
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// One request received by [`serve`].
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    /// Request line and headers, lower-cased, including the blank line.
+    pub head: String,
+    /// Body bytes, as many as `Content-Length` announced.
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// The body parsed as JSON, or `Null` if it is not JSON.
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap_or_default()
+    }
+}
+
+/// Format a complete `Connection: close` response.
+pub(crate) fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Read one request from `sock`: the head, then the `Content-Length` body.
+pub(crate) async fn read_request(sock: &mut TcpStream) -> MockRequest {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = sock.read(&mut chunk).await.unwrap_or(0);
+        buf.extend_from_slice(&chunk[..n]);
+        let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4) else {
+            if n == 0 {
+                break;
+            }
+            continue;
+        };
+        let head = String::from_utf8_lossy(&buf[..end]).to_lowercase();
+        let len: usize = head
+            .lines()
+            .find_map(|l| l.strip_prefix("content-length:"))
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0);
+        if n == 0 || buf.len() >= end + len {
+            let body = buf[end..buf.len().min(end + len)].to_vec();
+            return MockRequest { head, body };
+        }
+    }
+    MockRequest {
+        head: String::from_utf8_lossy(&buf).to_lowercase(),
+        body: Vec::new(),
+    }
+}
+
+/// Accept up to `requests` connections on a fresh local port.  For each,
+/// `respond(n, &request)` gives the raw response to write, or `None` to keep
+/// the connection open without answering (a hung upstream).  The handle
+/// resolves to every request received once `requests` have been served.
+pub(crate) async fn serve<F>(requests: usize, mut respond: F) -> (SocketAddr, JoinHandle<Vec<MockRequest>>)
+where
+    F: FnMut(usize, &MockRequest) -> Option<String> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    let handle = tokio::spawn(async move {
+        let mut received = Vec::new();
+        let mut hung = Vec::new();
+        for n in 0..requests {
+            let Ok((mut sock, _)) = listener.accept().await else { break };
+            let request = read_request(&mut sock).await;
+            match respond(n, &request) {
+                Some(reply) => {
+                    let _ = sock.write_all(reply.as_bytes()).await;
+                }
+                None => hung.push(sock),
+            }
+            received.push(request);
+        }
+        received
+    });
+    (addr, handle)
+}
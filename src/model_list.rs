@@ -0,0 +1,198 @@
+//! Live OpenAI model list for the web UI's model selector
+//! (`GET /api/openai/models`).
+//!
+//! [`ModelListCache::openai_models`] calls OpenAI's `/v1/models`, keeps the
+//! chat-capable ids and caches them for the cache's TTL.  Without an API key,
+//! or when the call fails, it returns the static
+//! [`crate::cli::KNOWN_OPENAI_MODELS`] list instead; fallbacks are not
+//! cached, so the next request tries the API again.
+
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::providers::Provider;
+
+/// OpenAI's model listing endpoint.
+pub const OPENAI_MODELS_URL: &str = "https://api.openai.com/v1/models";
+
+/// How long a fetched model list is served before it is refreshed.
+pub const MODEL_LIST_TTL: Duration = Duration::from_secs(600);
+
+/// How long to wait for `/v1/models` before falling back.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Model ids with these fragments are not chat completion models.
+const NON_CHAT_MARKERS: &[&str] = &[
+    "instruct", "audio", "realtime", "transcribe", "tts", "search", "image", "embedding",
+];
+
+/// The process-wide cache behind `GET /api/openai/models`.
+pub static OPENAI_MODEL_CACHE: once_cell::sync::Lazy<ModelListCache> =
+    once_cell::sync::Lazy::new(|| ModelListCache::new(OPENAI_MODELS_URL, MODEL_LIST_TTL));
+
+#[derive(Deserialize)]
+struct ModelListResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// Whether `id` names an OpenAI chat completion model (`gpt-4o`, `o3-mini`,
+/// fine-tunes, ...) rather than an embedding, audio or image model.
+pub fn is_chat_model(id: &str) -> bool {
+    Provider::for_model(id) == Some(Provider::Openai)
+        && !NON_CHAT_MARKERS.iter().any(|marker| id.contains(marker))
+}
+
+/// Parse a `/v1/models` response body into its chat model ids, sorted.
+pub fn parse_model_list(body: &str) -> Result<Vec<String>, serde_json::Error> {
+    let response: ModelListResponse = serde_json::from_str(body)?;
+    let mut ids: Vec<String> = response
+        .data
+        .into_iter()
+        .map(|m| m.id)
+        .filter(|id| is_chat_model(id))
+        .collect();
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// The static model list served when the live list is unavailable.
+pub fn fallback_models() -> Vec<String> {
+    crate::cli::KNOWN_OPENAI_MODELS.iter().map(|m| m.to_string()).collect()
+}
+
+/// A model list fetched from `url` and kept for `ttl`.
+pub struct ModelListCache {
+    url: String,
+    ttl: Duration,
+    client: reqwest::Client,
+    entry: Mutex<Option<(Instant, Vec<String>)>>,
+}
+
+impl ModelListCache {
+    pub fn new(url: &str, ttl: Duration) -> Self {
+        ModelListCache {
+            url: url.to_string(),
+            ttl,
+            client: reqwest::Client::new(),
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// The chat models available to `api_key`: the cached list while it is
+    /// fresh, otherwise a new fetch, or [`fallback_models`] when there is no
+    /// key or the fetch fails.
+    pub async fn openai_models(&self, api_key: Option<&str>) -> Vec<String> {
+        let Some(api_key) = api_key.filter(|k| !k.trim().is_empty()) else {
+            return fallback_models();
+        };
+        // Holding the lock across the fetch keeps concurrent page loads from
+        // each calling the API when the cache is cold.
+        let mut entry = self.entry.lock().await;
+        if let Some((fetched_at, models)) = entry.as_ref() {
+            if fetched_at.elapsed() < self.ttl {
+                return models.clone();
+            }
+        }
+        match self.fetch(api_key).await {
+            Ok(models) if !models.is_empty() => {
+                *entry = Some((Instant::now(), models.clone()));
+                models
+            }
+            Ok(_) => fallback_models(),
+            Err(e) => {
+                tracing::warn!(err = %e, url = %self.url, "model list fetch failed; using fallback list");
+                fallback_models()
+            }
+        }
+    }
+
+    async fn fetch(&self, api_key: &str) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .client
+            .get(&self.url)
+            .bearer_auth(api_key)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(parse_model_list(&response.text().await?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve `status` and `body` to every connection, counting requests.
+    async fn mock_server(status: &'static str, body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("http://{}/v1/models", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&hits);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = vec![0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, hits)
+    }
+
+    #[test]
+    fn test_parse_model_list_keeps_chat_models() {
+        let body = r#"{"object":"list","data":[
+            {"id":"gpt-4o","object":"model"},{"id":"text-embedding-3-small"},
+            {"id":"whisper-1"},{"id":"o3-mini"},{"id":"gpt-4o-audio-preview"},
+            {"id":"dall-e-3"},{"id":"gpt-3.5-turbo-instruct"},{"id":"gpt-4o"}]}"#;
+        assert_eq!(parse_model_list(body).unwrap(), vec!["gpt-4o", "o3-mini"]);
+    }
+
+    #[tokio::test]
+    async fn test_models_are_fetched_once_and_cached() {
+        let (url, hits) = mock_server(
+            "200 OK",
+            r#"{"data":[{"id":"gpt-4.1"},{"id":"gpt-4o"},{"id":"tts-1"}]}"#,
+        )
+        .await;
+        let cache = ModelListCache::new(&url, Duration::from_secs(60));
+        assert_eq!(cache.openai_models(Some("sk-test")).await, vec!["gpt-4.1", "gpt-4o"]);
+        assert_eq!(cache.openai_models(Some("sk-test")).await, vec!["gpt-4.1", "gpt-4o"]);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failures_fall_back_to_static_list() {
+        let (url, hits) = mock_server("401 Unauthorized", r#"{"error":{"message":"bad key"}}"#).await;
+        let cache = ModelListCache::new(&url, Duration::from_secs(60));
+        assert_eq!(cache.openai_models(Some("sk-bad")).await, fallback_models());
+        // Failures are not cached.
+        assert_eq!(cache.openai_models(Some("sk-bad")).await, fallback_models());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // No key: the API is never called.
+        assert_eq!(cache.openai_models(None).await, fallback_models());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let unreachable = ModelListCache::new("http://127.0.0.1:9/v1/models", Duration::from_secs(60));
+        assert_eq!(unreachable.openai_models(Some("sk-test")).await, fallback_models());
+    }
+}
@@ -0,0 +1,201 @@
+//! Session-relative perplexity percentiles for [`TokenEvent`]s.
+//!
+//! Absolute perplexity depends on the model's vocabulary and calibration, so a
+//! value of `4.0` can be unremarkable for one model and alarming for another.
+//! The percentile of a token's perplexity *within its own session* is
+//! scale-free: `1.0` is the most surprising token seen, and anything at or
+//! above `0.9` is in the top decile.
+//!
+//! Two flavours are provided:
+//!
+//! - [`assign_perplexity_percentiles`] — a second pass over a completed batch
+//!   of events (used by [`crate::run_research_headless`]).
+//! - [`RollingPercentile`] — a live estimate over a sliding window of recent
+//!   tokens (used by the web `/stream` fan-out), where later tokens are not yet
+//!   known.  It reports nothing until [`DEFAULT_MIN_SAMPLES`] values have been
+//!   seen, so the opening tokens of a stream are not ranked against only
+//!   themselves.
+//!
+//! The percentile of a value `p` among `n` values is the fraction of those
+//! values that are `<= p`, so the largest value always maps to `1.0`.
+
+use std::collections::VecDeque;
+
+use crate::TokenEvent;
+
+/// Default window size for [`RollingPercentile`].
+pub const DEFAULT_ROLLING_WINDOW: usize = 512;
+
+/// Default number of values [`RollingPercentile`] needs before it reports.
+pub const DEFAULT_MIN_SAMPLES: usize = 20;
+
+/// Fraction of `sorted` (ascending) that is `<= value`.
+fn rank_in(sorted: &[f32], value: f32) -> f32 {
+    if sorted.is_empty() {
+        return 1.0;
+    }
+    let at_or_below = sorted.partition_point(|&p| p <= value);
+    at_or_below as f32 / sorted.len() as f32
+}
+
+/// Set `perplexity_percentile` on every event that has a perplexity, relative
+/// to all other perplexity-bearing events in `events`.
+///
+/// Events without a perplexity (e.g. Anthropic streams) keep `None`.
+pub fn assign_perplexity_percentiles(events: &mut [TokenEvent]) {
+    let mut sorted: Vec<f32> = events
+        .iter()
+        .filter_map(|e| e.perplexity)
+        .filter(|p| p.is_finite())
+        .collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    for event in events.iter_mut() {
+        event.perplexity_percentile = event
+            .perplexity
+            .filter(|p| p.is_finite())
+            .map(|p| rank_in(&sorted, p));
+    }
+}
+
+/// Live perplexity percentile over the most recent `window` tokens.
+#[derive(Debug, Clone)]
+pub struct RollingPercentile {
+    window: usize,
+    min_samples: usize,
+    recent: VecDeque<f32>,
+    sorted: Vec<f32>,
+}
+
+impl RollingPercentile {
+    /// Track percentiles over the last `window` perplexity values (minimum 1),
+    /// reporting once [`DEFAULT_MIN_SAMPLES`] (at most `window`) are held.
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            min_samples: DEFAULT_MIN_SAMPLES.min(window),
+            recent: VecDeque::new(),
+            sorted: Vec::new(),
+        }
+    }
+
+    /// Report nothing until `min_samples` values (clamped to `1..=window`)
+    /// are in the window.
+    pub fn with_min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples.clamp(1, self.window);
+        self
+    }
+
+    /// Add `perplexity` to the window and return its percentile among the
+    /// values currently in the window (itself included), or `None` while
+    /// fewer than `min_samples` values have been seen.
+    pub fn observe(&mut self, perplexity: f32) -> Option<f32> {
+        if !perplexity.is_finite() {
+            return None;
+        }
+        if self.recent.len() == self.window {
+            if let Some(old) = self.recent.pop_front() {
+                let pos = self.sorted.partition_point(|&p| p < old);
+                self.sorted.remove(pos);
+            }
+        }
+        self.recent.push_back(perplexity);
+        let pos = self.sorted.partition_point(|&p| p < perplexity);
+        self.sorted.insert(pos, perplexity);
+        if self.recent.len() < self.min_samples {
+            return None;
+        }
+        Some(rank_in(&self.sorted, perplexity))
+    }
+
+    /// Fill in `event.perplexity_percentile` from the rolling window.
+    pub fn annotate(&mut self, event: &mut TokenEvent) {
+        if let Some(p) = event.perplexity {
+            event.perplexity_percentile = self.observe(p);
+        }
+    }
+}
+
+impl Default for RollingPercentile {
+    fn default() -> Self {
+        Self::new(DEFAULT_ROLLING_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(index: usize, perplexity: Option<f32>) -> TokenEvent {
+        TokenEvent {
+            text: format!("t{index}"),
+            original: format!("t{index}"),
+            index,
+            importance: 0.5,
+            perplexity,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_most_perplexing_token_near_one() {
+        let mut events: Vec<TokenEvent> = [1.2, 3.0, 40.0, 2.1, 1.5, 2.8, 1.1, 2.0, 1.9, 1.3]
+            .iter()
+            .enumerate()
+            .map(|(i, p)| event(i, Some(*p)))
+            .collect();
+        assign_perplexity_percentiles(&mut events);
+        let top = events[2].perplexity_percentile.expect("percentile");
+        assert!((top - 1.0).abs() < 1e-6, "got {top}");
+        let lowest = events[6].perplexity_percentile.expect("percentile");
+        assert!((lowest - 0.1).abs() < 1e-6, "got {lowest}");
+    }
+
+    #[test]
+    fn test_missing_perplexity_stays_none() {
+        let mut events = vec![event(0, None), event(1, Some(2.0))];
+        assign_perplexity_percentiles(&mut events);
+        assert!(events[0].perplexity_percentile.is_none());
+        assert_eq!(events[1].perplexity_percentile, Some(1.0));
+    }
+
+    #[test]
+    fn test_percentile_skipped_in_json_when_none() {
+        let json = serde_json::to_string(&event(0, None)).expect("serialize");
+        assert!(!json.contains("perplexity_percentile"));
+    }
+
+    #[test]
+    fn test_rolling_percentile_tracks_window() {
+        let mut rolling = RollingPercentile::new(4).with_min_samples(1);
+        assert_eq!(rolling.observe(2.0), Some(1.0));
+        assert_eq!(rolling.observe(1.0), Some(0.5));
+        assert_eq!(rolling.observe(4.0), Some(1.0));
+        assert_eq!(rolling.observe(3.0), Some(0.75));
+        // 2.0 falls out of the window: [1.0, 4.0, 3.0, 0.5]
+        assert_eq!(rolling.observe(0.5), Some(0.25));
+        assert_eq!(rolling.observe(f32::NAN), None);
+    }
+
+    #[test]
+    fn test_rolling_percentile_waits_for_min_samples() {
+        let mut rolling = RollingPercentile::default();
+        for i in 0..DEFAULT_MIN_SAMPLES - 1 {
+            assert_eq!(rolling.observe(100.0 - i as f32), None, "value {i}");
+        }
+        assert_eq!(rolling.observe(1000.0), Some(1.0));
+        // The window is clamped, so a small window still reports once full.
+        let mut small = RollingPercentile::new(3);
+        assert_eq!(small.observe(1.0), None);
+        assert_eq!(small.observe(2.0), None);
+        assert_eq!(small.observe(3.0), Some(1.0));
+    }
+
+    #[test]
+    fn test_rolling_annotate_sets_field() {
+        let mut rolling = RollingPercentile::default().with_min_samples(1);
+        let mut ev = event(0, Some(3.0));
+        rolling.annotate(&mut ev);
+        assert_eq!(ev.perplexity_percentile, Some(1.0));
+    }
+}
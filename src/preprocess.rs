@@ -0,0 +1,169 @@
+//! Prompt preprocessing pipeline (`--preprocess`).
+//!
+//! Before a prompt is sent, [`TokenInterceptor`](crate::TokenInterceptor)
+//! runs it through an ordered list of [`PreprocessStage`]s, each one taking
+//! the previous stage's output.  The built-in stages are template variable
+//! substitution ([`TemplateVars`]), truncation to a character budget
+//! ([`Truncate`]), a prepended style instruction ([`StyleInstruction`]) and
+//! the `--orchestrator` MCP enrichment ([`PreprocessStage::Orchestrator`]).
+//! Library users add their own by implementing [`PromptPreprocessor`] and
+//! passing it to
+//! [`with_preprocessor`](crate::TokenInterceptor::with_preprocessor).
+//!
+//! The prompt that comes out of the last stage is what the provider sees; it
+//! is kept in [`TokenInterceptor::effective_prompt`](crate::TokenInterceptor::effective_prompt)
+//! and, when it differs from the given prompt, recorded as `effective_prompt`
+//! in research output and web UI exports.
+
+use std::collections::HashMap;
+
+/// One step of the preprocessing pipeline.
+pub trait PromptPreprocessor: Send + Sync {
+    /// Short name used in error messages.
+    fn name(&self) -> &str;
+
+    /// Rewrite `prompt`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message when the prompt cannot be processed; the stream is
+    /// aborted before anything is sent.
+    fn process(&self, prompt: &str) -> Result<String, String>;
+}
+
+/// A stage of [`TokenInterceptor::preprocessors`](crate::TokenInterceptor::preprocessors).
+pub enum PreprocessStage {
+    /// The MCP orchestrator enrichment.  It needs the interceptor's HTTP
+    /// client, URL and retry settings, so the interceptor runs it itself; a
+    /// failed call falls back to the incoming prompt as without a pipeline.
+    Orchestrator,
+    /// Any other preprocessor.
+    Custom(Box<dyn PromptPreprocessor>),
+}
+
+impl PreprocessStage {
+    /// Stage name, as accepted by [`StageKind::parse`] for the built-ins.
+    pub fn name(&self) -> &str {
+        match self {
+            PreprocessStage::Orchestrator => "orchestrator",
+            PreprocessStage::Custom(p) => p.name(),
+        }
+    }
+}
+
+impl std::fmt::Debug for PreprocessStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PreprocessStage({})", self.name())
+    }
+}
+
+/// A built-in stage named on the command line with `--preprocess`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageKind {
+    /// [`TemplateVars`] with the `--var` / `--vars-file` variables.
+    Template,
+    /// [`Truncate`] to `--preprocess-max-chars` (default `--max-prompt-chars`).
+    Truncate,
+    /// [`StyleInstruction`] with `--style-instruction`.
+    Style,
+    /// [`PreprocessStage::Orchestrator`].
+    Orchestrator,
+}
+
+impl StageKind {
+    /// Parse `template`, `truncate`, `style` or `orchestrator`
+    /// (case-insensitive).
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "template" => Ok(StageKind::Template),
+            "truncate" => Ok(StageKind::Truncate),
+            "style" => Ok(StageKind::Style),
+            "orchestrator" => Ok(StageKind::Orchestrator),
+            other => Err(format!(
+                "unknown preprocess stage '{}' (expected template, truncate, style or orchestrator)",
+                other
+            )),
+        }
+    }
+}
+
+/// Substitute `{name}` variables (see [`crate::prompt_template`]).
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVars {
+    pub vars: HashMap<String, String>,
+}
+
+impl PromptPreprocessor for TemplateVars {
+    fn name(&self) -> &str {
+        "template"
+    }
+
+    fn process(&self, prompt: &str) -> Result<String, String> {
+        crate::prompt_template::apply_template(prompt, &self.vars).map_err(|e| e.to_string())
+    }
+}
+
+/// Keep at most `max_chars` characters of the prompt.
+#[derive(Debug, Clone, Copy)]
+pub struct Truncate {
+    pub max_chars: usize,
+}
+
+impl PromptPreprocessor for Truncate {
+    fn name(&self) -> &str {
+        "truncate"
+    }
+
+    fn process(&self, prompt: &str) -> Result<String, String> {
+        Ok(match prompt.char_indices().nth(self.max_chars) {
+            Some((end, _)) => prompt[..end].to_string(),
+            None => prompt.to_string(),
+        })
+    }
+}
+
+/// Put `instruction` on its own paragraph ahead of the prompt.
+#[derive(Debug, Clone)]
+pub struct StyleInstruction {
+    pub instruction: String,
+}
+
+impl PromptPreprocessor for StyleInstruction {
+    fn name(&self) -> &str {
+        "style"
+    }
+
+    fn process(&self, prompt: &str) -> Result<String, String> {
+        Ok(format!("{}\n\n{}", self.instruction.trim(), prompt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_kind_parse() {
+        assert_eq!(StageKind::parse("Template"), Ok(StageKind::Template));
+        assert_eq!(StageKind::parse(" truncate "), Ok(StageKind::Truncate));
+        assert_eq!(StageKind::parse("style"), Ok(StageKind::Style));
+        assert_eq!(StageKind::parse("orchestrator"), Ok(StageKind::Orchestrator));
+        assert!(StageKind::parse("summarize").unwrap_err().contains("summarize"));
+    }
+
+    #[test]
+    fn test_builtin_stages() {
+        let vars = TemplateVars { vars: HashMap::from([("topic".to_string(), "tides".to_string())]) };
+        assert_eq!(vars.process("about {topic}").unwrap(), "about tides");
+        assert!(vars.process("about {missing}").is_err());
+
+        let truncate = Truncate { max_chars: 3 };
+        assert_eq!(truncate.process("héllo").unwrap(), "hél");
+        assert_eq!(truncate.process("hi").unwrap(), "hi");
+
+        let style = StyleInstruction { instruction: "Answer as a pirate. ".to_string() };
+        assert_eq!(style.process("hello").unwrap(), "Answer as a pirate.\n\nhello");
+        assert_eq!(PreprocessStage::Custom(Box::new(style)).name(), "style");
+        assert_eq!(PreprocessStage::Orchestrator.name(), "orchestrator");
+    }
+}
@@ -0,0 +1,179 @@
+//! Prompt templates with `{name}` variable substitution.
+//!
+//! Parameterized experiments send prompts like `"Tell me about {topic} in
+//! {style} style"` with variables from `--var topic=robots` (repeatable) or a
+//! JSON `--vars-file`.  Substitution happens before the prompt reaches any
+//! provider, so research output and citations record the resolved prompt.
+//!
+//! `{{` and `}}` produce literal braces.  A variable with no value is an
+//! error rather than being left in the prompt.
+
+use std::collections::HashMap;
+
+/// Why a template could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PromptTemplateError {
+    #[error("undefined template variable `{{{0}}}` (supply it with --var {0}=...)")]
+    UndefinedVariable(String),
+    #[error("unclosed `{{` at byte {0} (write `{{{{` for a literal brace)")]
+    Unclosed(usize),
+    #[error("unmatched `}}` at byte {0} (write `}}}}` for a literal brace)")]
+    Unmatched(usize),
+    #[error("invalid variable name `{0}`")]
+    InvalidName(String),
+}
+
+/// Replace each `{name}` in `template` with `vars[name]`.
+///
+/// Values are inserted verbatim and never re-expanded.
+///
+/// # Errors
+///
+/// Returns [`PromptTemplateError`] for a variable missing from `vars`, a
+/// malformed name, or an unescaped lone brace.
+pub fn apply_template(
+    template: &str,
+    vars: &HashMap<String, String>,
+) -> Result<String, PromptTemplateError> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().map(|&(_, n)| n) == Some('{') => {
+                chars.next();
+                out.push('{');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, ch)) => name.push(ch),
+                        None => return Err(PromptTemplateError::Unclosed(i)),
+                    }
+                }
+                if !is_valid_name(&name) {
+                    return Err(PromptTemplateError::InvalidName(name));
+                }
+                match vars.get(&name) {
+                    Some(value) => out.push_str(value),
+                    None => return Err(PromptTemplateError::UndefinedVariable(name)),
+                }
+            }
+            '}' if chars.peek().map(|&(_, n)| n) == Some('}') => {
+                chars.next();
+                out.push('}');
+            }
+            '}' => return Err(PromptTemplateError::Unmatched(i)),
+            _ => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Variable names are non-empty runs of ASCII letters, digits, `_` and `-`.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parse one `--var NAME=VALUE` argument.  The value may contain `=`.
+pub fn parse_var(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", s))?;
+    let name = name.trim();
+    if !is_valid_name(name) {
+        return Err(format!("invalid variable name '{}'", name));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Load variables from a JSON object file such as `{"topic": "robots"}`.
+/// Non-string values are inserted as their JSON text.
+pub fn load_vars_file(path: &str) -> Result<HashMap<String, String>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read vars file '{}': {}", path, e))?;
+    let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&text)
+        .map_err(|e| format!("vars file '{}' must be a JSON object: {}", path, e))?;
+    Ok(map
+        .into_iter()
+        .map(|(k, v)| {
+            let value = match v {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            (k, value)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_substitutes_variables() {
+        let v = vars(&[("topic", "robots"), ("style", "noir")]);
+        assert_eq!(
+            apply_template("Tell me about {topic} in {style} style", &v).unwrap(),
+            "Tell me about robots in noir style"
+        );
+    }
+
+    #[test]
+    fn test_values_are_not_reexpanded() {
+        let v = vars(&[("a", "{b}"), ("b", "x")]);
+        assert_eq!(apply_template("{a}{b}", &v).unwrap(), "{b}x");
+    }
+
+    #[test]
+    fn test_missing_variable_errors() {
+        let err = apply_template("About {topic}", &vars(&[])).unwrap_err();
+        assert_eq!(err, PromptTemplateError::UndefinedVariable("topic".to_string()));
+        assert!(err.to_string().contains("--var topic="));
+    }
+
+    #[test]
+    fn test_escaped_braces() {
+        let v = vars(&[("x", "1")]);
+        assert_eq!(apply_template("{{x}} = {x}, }}{{", &v).unwrap(), "{x} = 1, }{");
+    }
+
+    #[test]
+    fn test_lone_braces_error() {
+        assert_eq!(apply_template("oops {x", &vars(&[])), Err(PromptTemplateError::Unclosed(5)));
+        assert_eq!(apply_template("a } b", &vars(&[])), Err(PromptTemplateError::Unmatched(2)));
+        assert!(matches!(
+            apply_template("{a b}", &vars(&[])),
+            Err(PromptTemplateError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_var() {
+        assert_eq!(parse_var("topic=robots").unwrap(), ("topic".to_string(), "robots".to_string()));
+        assert_eq!(parse_var("eq=a=b").unwrap().1, "a=b");
+        assert!(parse_var("novalue").is_err());
+        assert!(parse_var("bad name=x").is_err());
+    }
+
+    #[test]
+    fn test_load_vars_file() {
+        let path = std::env::temp_dir().join(format!("eot_vars_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"topic": "robots", "n": 3}"#).unwrap();
+        let v = load_vars_file(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(v["topic"], "robots");
+        assert_eq!(v["n"], "3");
+    }
+}
@@ -11,7 +11,22 @@
 //! |---------|--------|----------|
 //! | `openai` | [`OpenAiPlugin`] | `https://api.openai.com/v1/chat/completions` |
 //! | `anthropic` | [`AnthropicPlugin`] | `https://api.anthropic.com/v1/messages` |
-//! | `mock` | (inline fixture) | n/a -- returns canned tokens for tests |
+//! | `mock` | [`MockStream`] | n/a -- returns canned tokens for tests |
+//!
+//! ## Custom streams
+//!
+//! Any other token source can be plugged into a [`TokenInterceptor`](crate::TokenInterceptor)
+//! by implementing [`LlmStream`] and passing it to
+//! [`with_stream_backend`](crate::TokenInterceptor::with_stream_backend).  The
+//! backend replaces the built-in provider for the HTTP round trip only; its
+//! deltas go through the same transform, rate and output pipeline.
+//! [`ScriptedStream`] is a ready-made in-memory backend for tests and replays,
+//! and the `mock` provider is itself the [`MockStream`] backend.
+//!
+//! OpenAI and Anthropic are not `LlmStream`s: their streams also drive the
+//! raw SSE log, strict parsing, tool-call notices, the logprobs fallback and
+//! Anthropic's retry-before-first-token, all of which need the interceptor
+//! itself rather than a token sink.
 
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
@@ -40,6 +55,165 @@ pub trait ProviderPlugin: Send + Sync {
     fn build_request(&self, prompt: &str, system: Option<&str>, model: &str) -> serde_json::Value;
 }
 
+// ---------------------------------------------------------------------------
+// Pluggable token streams
+// ---------------------------------------------------------------------------
+
+/// Everything a stream backend needs to generate a response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamRequest {
+    /// The prompt after templating, transformation and orchestrator enrichment.
+    pub prompt: String,
+    pub system: Option<String>,
+    pub model: String,
+    pub temperature: f32,
+    pub max_tokens: Option<u32>,
+    /// Whether the caller wants per-token logprob data.
+    pub logprobs: bool,
+}
+
+/// One chunk of generated text, as a provider would stream it.
+///
+/// `text` may hold several words; the interceptor tokenizes it.  `logprob` is
+/// the natural-log probability of the leading token, and `top_alternatives`
+/// its top-K alternatives already converted to probabilities.
+#[derive(Debug, Clone, Default)]
+pub struct TokenDelta {
+    pub text: String,
+    pub logprob: Option<f32>,
+    pub top_alternatives: Vec<crate::TokenAlternative>,
+}
+
+impl TokenDelta {
+    /// A delta with text only and no logprob data.
+    pub fn text(text: impl Into<String>) -> Self {
+        TokenDelta { text: text.into(), ..TokenDelta::default() }
+    }
+}
+
+/// Error returned by an [`LlmStream`] backend.
+pub type StreamError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A source of streamed tokens, independent of any concrete provider.
+///
+/// Implementations call `sink` once per delta, in order, and resolve when the
+/// response is complete.  The interceptor applies transforms, scoring and
+/// output to every delta, so a backend only has to produce text.
+pub trait LlmStream: Send + Sync {
+    fn stream<'a>(
+        &'a self,
+        req: &'a StreamRequest,
+        sink: &'a mut (dyn FnMut(TokenDelta) + Send),
+    ) -> futures_util::future::BoxFuture<'a, Result<(), StreamError>>;
+}
+
+/// An [`LlmStream`] that replays a fixed list of deltas, ignoring the request.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedStream {
+    pub deltas: Vec<TokenDelta>,
+}
+
+impl ScriptedStream {
+    pub fn new(deltas: Vec<TokenDelta>) -> Self {
+        ScriptedStream { deltas }
+    }
+}
+
+impl LlmStream for ScriptedStream {
+    fn stream<'a>(
+        &'a self,
+        _req: &'a StreamRequest,
+        sink: &'a mut (dyn FnMut(TokenDelta) + Send),
+    ) -> futures_util::future::BoxFuture<'a, Result<(), StreamError>> {
+        Box::pin(async move {
+            for delta in &self.deltas {
+                sink(delta.clone());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The `mock` provider: a canned response with logprob data, no network.
+///
+/// The fixture starts at an offset derived from the prompt so different
+/// prompts produce different streams, and its logprobs scale linearly with
+/// temperature away from [`DEFAULT_TEMPERATURE`](crate::DEFAULT_TEMPERATURE)
+/// (0.0 is fully confident, higher values flatten the distribution).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockStream;
+
+impl MockStream {
+    /// The deltas this backend streams for `req`, in order.
+    pub fn deltas(req: &StreamRequest) -> Vec<TokenDelta> {
+        let prompt_prefix: String = req.prompt.chars().take(20).collect();
+        let fixture: Vec<(String, f32)> = vec![
+            ("The".to_string(), -0.12),
+            (" quick".to_string(), -0.45),
+            (" brown".to_string(), -0.78),
+            (" fox".to_string(), -0.23),
+            (" jumps".to_string(), -0.56),
+            (" over".to_string(), -0.34),
+            (" the".to_string(), -0.11),
+            (" lazy".to_string(), -0.89),
+            (" dog".to_string(), -0.19),
+            (".".to_string(), -0.07),
+            (" This".to_string(), -0.62),
+            (" is".to_string(), -0.15),
+            (" a".to_string(), -0.08),
+            (" mock".to_string(), -0.31),
+            (" response".to_string(), -0.44),
+            (" for".to_string(), -0.27),
+            (" prompt".to_string(), -0.53),
+            (":".to_string(), -0.18),
+            (" \"".to_string(), -0.39),
+            (prompt_prefix, -0.71),
+        ];
+        let prompt_hash: usize = req
+            .prompt
+            .bytes()
+            .fold(0usize, |acc, b| acc.wrapping_add(b as usize));
+        let offset = prompt_hash % fixture.len();
+        let temperature_scale = req.temperature / crate::DEFAULT_TEMPERATURE;
+        let alternatives = vec![
+            crate::TokenAlternative {
+                token: "a".to_string(),
+                probability: 0.15,
+            },
+            crate::TokenAlternative {
+                token: "the".to_string(),
+                probability: 0.10,
+            },
+        ];
+
+        (0..fixture.len())
+            .map(|idx| {
+                let (text, logprob) = &fixture[(idx + offset) % fixture.len()];
+                TokenDelta {
+                    text: text.clone(),
+                    logprob: Some(*logprob * temperature_scale),
+                    top_alternatives: alternatives.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl LlmStream for MockStream {
+    fn stream<'a>(
+        &'a self,
+        req: &'a StreamRequest,
+        sink: &'a mut (dyn FnMut(TokenDelta) + Send),
+    ) -> futures_util::future::BoxFuture<'a, Result<(), StreamError>> {
+        Box::pin(async move {
+            for delta in Self::deltas(req) {
+                sink(delta);
+            }
+            Ok(())
+        })
+    }
+}
+
 /// Provider plug-in for the OpenAI Chat Completions API.
 pub struct OpenAiPlugin;
 /// Provider plug-in for the Anthropic Messages API.
@@ -50,7 +224,7 @@ impl ProviderPlugin for OpenAiPlugin {
         "openai"
     }
     fn default_model(&self) -> &str {
-        "gpt-3.5-turbo"
+        Provider::Openai.default_model()
     }
     fn api_url(&self) -> &str {
         "https://api.openai.com/v1/chat/completions"
@@ -76,12 +250,41 @@ impl ProviderPlugin for OpenAiPlugin {
 /// As of 2026-03: Anthropic has not published a newer stable version header. Revisit quarterly.
 pub const ANTHROPIC_API_VERSION: &str = "2023-06-01";
 
+/// Header names that carry provider credentials.  These are always set from the
+/// configured API key and may not be overridden through `--header`.
+pub const RESERVED_HEADERS: &[&str] = &["authorization", "x-api-key"];
+
+/// Parse a `"Key: Value"` header argument into a `(name, value)` pair.
+///
+/// Used for provider-specific passthrough headers such as `OpenAI-Organization`
+/// or `anthropic-beta`.  The name must be a valid HTTP header token, the value
+/// a valid header value, and credential headers ([`RESERVED_HEADERS`]) are
+/// rejected so that `--header` cannot leak or replace the API key.
+pub fn parse_extra_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("header '{}' must be in \"Key: Value\" form", s))?;
+    let name = name.trim();
+    let value = value.trim();
+    if name.is_empty() {
+        return Err(format!("header '{}' has an empty name", s));
+    }
+    reqwest::header::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("invalid header name '{}'", name))?;
+    reqwest::header::HeaderValue::from_str(value)
+        .map_err(|_| format!("invalid value for header '{}'", name))?;
+    if RESERVED_HEADERS.iter().any(|r| r.eq_ignore_ascii_case(name)) {
+        return Err(format!("header '{}' is reserved for the API key", name));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
 impl ProviderPlugin for AnthropicPlugin {
     fn name(&self) -> &str {
         "anthropic"
     }
     fn default_model(&self) -> &str {
-        "claude-sonnet-4-6"
+        Provider::Anthropic.default_model()
     }
     fn api_url(&self) -> &str {
         "https://api.anthropic.com/v1/messages"
@@ -140,6 +343,35 @@ pub enum Provider {
     Mock,
 }
 
+impl Provider {
+    /// Model used when the caller does not name one.
+    ///
+    /// The single source of truth for per-provider defaults: the CLI, the web
+    /// server, diff and A/B modes all resolve an omitted model through here.
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            Provider::Openai => "gpt-3.5-turbo",
+            Provider::Anthropic => "claude-sonnet-4-6",
+            Provider::Mock => "mock-fixture-v1",
+        }
+    }
+
+    /// The provider that serves `model`, judged by its family prefix
+    /// (`claude-` for Anthropic; `gpt-`, `chatgpt-`, `o1`-style and `ft:`
+    /// fine-tunes for OpenAI).  `None` when the family is not recognised.
+    pub fn for_model(model: &str) -> Option<Provider> {
+        let m = model.trim().to_ascii_lowercase();
+        if m.starts_with("claude") {
+            return Some(Provider::Anthropic);
+        }
+        let o_series = m.strip_prefix('o').is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+        if m.starts_with("gpt-") || m.starts_with("chatgpt-") || m.starts_with("ft:") || o_series {
+            return Some(Provider::Openai);
+        }
+        None
+    }
+}
+
 impl std::fmt::Display for Provider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -190,8 +422,13 @@ pub struct OpenAIChatRequest {
     pub temperature: f32,
     /// Whether to include per-token log probabilities in the response.
     pub logprobs: bool,
-    /// Number of top alternative tokens per position (0–20).
-    pub top_logprobs: u8,
+    /// Number of top alternative tokens per position (0–20); omitted when
+    /// `logprobs` is off, since OpenAI rejects it without `logprobs: true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u8>,
+    /// Optional cap on generated tokens; omitted to use the model default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
 }
 
 /// Incremental content fragment within a streaming choice delta.
@@ -199,6 +436,32 @@ pub struct OpenAIChatRequest {
 pub struct OpenAIDelta {
     /// Text fragment, absent on the final chunk where `finish_reason` is set.
     pub content: Option<String>,
+    /// Tool-call fragments, present instead of `content` when the model
+    /// calls a function.
+    #[serde(default)]
+    pub tool_calls: Vec<OpenAIToolCallDelta>,
+}
+
+/// One fragment of a streamed tool call.  The first fragment for a call
+/// carries its `id` and function name; later ones only append arguments.
+#[derive(Debug, Deserialize)]
+pub struct OpenAIToolCallDelta {
+    /// Position of the call among the tool calls of this response.
+    #[serde(default)]
+    pub index: usize,
+    /// Call identifier, on the first fragment only.
+    pub id: Option<String>,
+    /// Function name and argument fragment.
+    pub function: Option<OpenAIFunctionDelta>,
+}
+
+/// Function part of an [`OpenAIToolCallDelta`].
+#[derive(Debug, Deserialize)]
+pub struct OpenAIFunctionDelta {
+    /// Function name, on the first fragment only.
+    pub name: Option<String>,
+    /// The next piece of the JSON-encoded arguments.
+    pub arguments: Option<String>,
 }
 
 /// One streaming choice from an OpenAI chunk event.
@@ -269,10 +532,192 @@ pub struct AnthropicStreamEvent {
     pub delta: Option<AnthropicContentDelta>,
 }
 
+/// Anthropic's error envelope, sent as the body of a failed request and as a
+/// mid-stream `error` event: `{"type":"error","error":{"type":...,"message":...}}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicErrorEnvelope {
+    /// Always `"error"`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub error: AnthropicErrorBody,
+}
+
+/// The typed error inside an [`AnthropicErrorEnvelope`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AnthropicErrorBody {
+    /// Error type, e.g. `"overloaded_error"`, `"rate_limit_error"`,
+    /// `"invalid_request_error"`.
+    #[serde(rename = "type")]
+    pub error_type: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl AnthropicErrorEnvelope {
+    /// The typed error in `body`, if it is an Anthropic error envelope.
+    pub fn parse(body: &str) -> Option<AnthropicErrorBody> {
+        serde_json::from_str::<Self>(body)
+            .ok()
+            .filter(|envelope| envelope.kind == "error")
+            .map(|envelope| envelope.error)
+    }
+}
+
+/// The error message carried by an SSE `data:` payload, if it is an error
+/// object rather than a content chunk.
+///
+/// Recognises OpenAI's `{"error":{"message":...}}` and Anthropic's
+/// `{"type":"error","error":{"type":...,"message":...}}`.  Without this
+/// check such payloads either fail to parse as a chunk or parse as an
+/// ignored event type, and the stream silently ends early.
+pub fn sse_stream_error(json: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let error = value.get("error")?;
+    if error.is_null() {
+        return None;
+    }
+    let message = error
+        .get("message")
+        .and_then(|m| m.as_str())
+        .or_else(|| error.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| error.to_string());
+    Some(match error.get("type").and_then(|t| t.as_str()) {
+        Some(kind) => format!("{}: {}", kind, message),
+        None => message,
+    })
+}
+
+// -- SSE byte decoding ------------------------------------------------------
+
+/// Incremental UTF-8 decoder for streamed response bodies.
+///
+/// Network chunks can end partway through a multi-byte character.  Decoding
+/// each chunk on its own would corrupt or drop that character, so an
+/// incomplete trailing sequence is held back and completed by the next chunk.
+/// Bytes that can never form valid UTF-8 are dropped with a warning.
+#[derive(Debug, Default)]
+pub struct Utf8ChunkDecoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8ChunkDecoder {
+    /// Create a decoder with no buffered bytes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `chunk` and return every complete character decoded so far.
+    pub fn decode(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+        let mut out = String::new();
+        let mut rest: &[u8] = &self.pending;
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    out.push_str(s);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    // valid_up_to() guarantees this prefix is well-formed.
+                    out.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        // Incomplete sequence at the end: wait for more bytes.
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                        Some(bad) => {
+                            tracing::warn!(bytes = bad, "invalid UTF-8 in provider stream — skipping");
+                            rest = &after[bad..];
+                        }
+                    }
+                }
+            }
+        }
+        self.pending = rest.to_vec();
+        out
+    }
+
+    /// Number of bytes held back waiting for the rest of a character.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Word-boundary buffer for streamed text deltas.
+///
+/// Providers split words across deltas (`"wor"` + `"ld"`), which would
+/// otherwise be tokenized and transformed as two separate tokens.  The buffer
+/// holds back a trailing partial word until whitespace shows it is complete,
+/// releasing everything up to and including the last whitespace.
+///
+/// Each delta carries metadata `M` (e.g. its logprob).  Every released word
+/// keeps the metadata of the delta that started it, including a word held
+/// back across several deltas; metadata of a delta that only continues a
+/// held word is discarded.
+#[derive(Debug, Default)]
+pub struct WordBuffer<M: Default + Clone> {
+    pending: String,
+    meta: Option<M>,
+}
+
+impl<M: Default + Clone> WordBuffer<M> {
+    /// Create an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `delta` and return the complete words released so far, if any,
+    /// with the metadata that belongs to them.
+    pub fn push(&mut self, delta: &str, meta: M) -> Option<(String, M)> {
+        if self.pending.is_empty() {
+            self.meta = Some(meta.clone());
+        }
+        self.pending.push_str(delta);
+        let split = self
+            .pending
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())?;
+        let tail = self.pending.split_off(split);
+        let head = std::mem::replace(&mut self.pending, tail);
+        let head_meta = self.meta.take().unwrap_or_default();
+        // Held text never contains whitespace, so the split point lies in
+        // this delta and any new tail word was started by it.
+        if !self.pending.is_empty() {
+            self.meta = Some(meta);
+        }
+        if head.trim().is_empty() {
+            // A whitespace-only head has no token to attach metadata to.
+            Some((head, M::default()))
+        } else {
+            Some((head, head_meta))
+        }
+    }
+
+    /// Release whatever is held (call at end of stream).
+    pub fn flush(&mut self) -> Option<(String, M)> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let meta = self.meta.take().unwrap_or_default();
+        Some((std::mem::take(&mut self.pending), meta))
+    }
+
+    /// The partial word currently held back.
+    pub fn pending(&self) -> &str {
+        &self.pending
+    }
+}
+
 // -- Orchestrator MCP types -------------------------------------------------
 
-/// JSON-RPC 2.0 request sent to the MCP orchestrator (`tools/call infer`).
-#[derive(Debug, Serialize)]
+/// JSON-RPC 2.0 request sent to the MCP orchestrator (`tools/call infer`),
+/// and accepted by [`crate::mcp_server`].
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McpInferRequest {
     /// Always `"2.0"`.
     pub jsonrpc: String,
@@ -285,7 +730,7 @@ pub struct McpInferRequest {
 }
 
 /// Parameters block for an MCP `tools/call` request.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McpInferParams {
     /// Tool name to invoke (e.g. `"infer"`).
     pub name: String,
@@ -294,43 +739,58 @@ pub struct McpInferParams {
 }
 
 /// Arguments forwarded to the MCP `infer` tool.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McpInferArguments {
     /// Text prompt to enrich or process.
     pub prompt: String,
-    /// Target worker backend (e.g. `"llama_cpp"`).
+    /// Target worker backend (e.g. `"llama_cpp"`).  Ignored by
+    /// [`crate::mcp_server`], which always uses its configured provider.
+    #[serde(default)]
     pub worker: String,
 }
 
-/// JSON-RPC 2.0 response returned by the MCP orchestrator.
-#[derive(Debug, Deserialize)]
+/// JSON-RPC 2.0 response returned by the MCP orchestrator, and by
+/// [`crate::mcp_server`].
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McpInferResponse {
     /// Protocol version echo, always `"2.0"` when present.
     #[allow(dead_code)]
     pub jsonrpc: Option<String>,
+    /// Echo of the request `id` (`null` when the request could not be parsed).
+    #[serde(default)]
+    pub id: serde_json::Value,
     /// Successful result payload; mutually exclusive with `error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<McpInferResult>,
     /// Error payload; mutually exclusive with `result`.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<McpError>,
 }
 
 /// Successful result from an MCP `infer` call.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McpInferResult {
     /// List of content items returned by the tool.
     pub content: Vec<McpContent>,
 }
 
 /// A single content item within an MCP tool result.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McpContent {
+    /// Content kind, `"text"` for text items.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
     /// Text value of this content item, or `None` for non-text items.
     pub text: Option<String>,
 }
 
 /// Error payload from an MCP JSON-RPC response.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct McpError {
+    /// JSON-RPC error code (e.g. `-32601` for an unknown method); `0` when
+    /// the peer omitted it.
+    #[serde(default)]
+    pub code: i64,
     /// Human-readable error message.
     pub message: String,
 }
@@ -339,6 +799,99 @@ pub struct McpError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_mock_stream_offsets_by_prompt_and_scales_with_temperature() {
+        let req = |prompt: &str, temperature: f32| StreamRequest {
+            prompt: prompt.to_string(),
+            system: None,
+            model: "mock-fixture-v1".to_string(),
+            temperature,
+            max_tokens: None,
+            logprobs: true,
+        };
+        let base = MockStream::deltas(&req("hello", crate::DEFAULT_TEMPERATURE));
+        assert_eq!(base.len(), 20);
+        assert!(base.iter().any(|d| d.text == "hello"), "prompt prefix is in the fixture");
+        assert_ne!(base[0].text, MockStream::deltas(&req("hellp", crate::DEFAULT_TEMPERATURE))[0].text);
+
+        let cold = MockStream::deltas(&req("hello", 0.0));
+        assert!(cold.iter().all(|d| d.logprob == Some(0.0)));
+        // Multi-byte prompts are cut on a char boundary.
+        assert!(MockStream::deltas(&req(&"é".repeat(30), 0.7)).iter().any(|d| d.text == "é".repeat(20)));
+    }
+
+    #[test]
+    fn test_parse_extra_header_valid() {
+        assert_eq!(
+            parse_extra_header("OpenAI-Organization: org-123"),
+            Ok(("OpenAI-Organization".to_string(), "org-123".to_string()))
+        );
+        assert_eq!(
+            parse_extra_header("anthropic-beta:tools-2024-04-04"),
+            Ok(("anthropic-beta".to_string(), "tools-2024-04-04".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_extra_header_rejects_malformed() {
+        assert!(parse_extra_header("no-colon").is_err());
+        assert!(parse_extra_header(": value").is_err());
+        assert!(parse_extra_header("bad name: value").is_err());
+        assert!(parse_extra_header("X-Test: line\nbreak").is_err());
+    }
+
+    #[test]
+    fn test_parse_extra_header_rejects_credentials() {
+        assert!(parse_extra_header("Authorization: Bearer sk-x").is_err());
+        assert!(parse_extra_header("X-API-KEY: sk-x").is_err());
+    }
+
+    #[test]
+    fn test_anthropic_error_envelope_parse() {
+        let body = AnthropicErrorEnvelope::parse(
+            r#"{"type":"error","error":{"type":"rate_limit_error","message":"Number of requests has exceeded your rate limit"}}"#,
+        )
+        .expect("envelope");
+        assert_eq!(body.error_type, "rate_limit_error");
+        assert!(body.message.starts_with("Number of requests"));
+        assert!(AnthropicErrorEnvelope::parse(r#"{"type":"message_start","error":{"type":"x"}}"#).is_none());
+        assert!(AnthropicErrorEnvelope::parse(r#"{"error":{"message":"openai shape"}}"#).is_none());
+        assert!(AnthropicErrorEnvelope::parse("Bad Gateway").is_none());
+    }
+
+    #[test]
+    fn test_sse_stream_error_detects_mid_stream_errors() {
+        assert_eq!(
+            sse_stream_error(r#"{"error":{"message":"The server had an error","type":"server_error","code":null}}"#),
+            Some("server_error: The server had an error".to_string())
+        );
+        assert_eq!(
+            sse_stream_error(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#),
+            Some("overloaded_error: Overloaded".to_string())
+        );
+        assert_eq!(sse_stream_error(r#"{"error":"quota exceeded"}"#), Some("quota exceeded".to_string()));
+        assert_eq!(
+            sse_stream_error(r#"{"choices":[{"delta":{"content":"hi"}}]}"#),
+            None
+        );
+        assert_eq!(
+            sse_stream_error(r#"{"type":"content_block_delta","delta":{"type":"text_delta","text":"hi"}}"#),
+            None
+        );
+        assert_eq!(sse_stream_error(r#"{"error":null}"#), None);
+        assert_eq!(sse_stream_error("not json"), None);
+    }
+
+    #[test]
+    fn test_provider_for_model() {
+        assert_eq!(Provider::for_model("gpt-4o"), Some(Provider::Openai));
+        assert_eq!(Provider::for_model("o3-mini"), Some(Provider::Openai));
+        assert_eq!(Provider::for_model("ft:gpt-4o-mini:org::id"), Some(Provider::Openai));
+        assert_eq!(Provider::for_model("claude-sonnet-4-6"), Some(Provider::Anthropic));
+        assert_eq!(Provider::for_model("llama3"), None);
+        assert_eq!(Provider::for_model("opus"), None);
+    }
+
     #[test]
     fn test_provider_display() {
         assert_eq!(Provider::Openai.to_string(), "openai");
@@ -508,6 +1061,20 @@ mod tests {
         assert_eq!(chunk.choices[1].delta.content.as_deref(), Some("B"));
     }
 
+    #[test]
+    fn test_openai_chunk_tool_call_delta() {
+        let json = r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":null,"tool_calls":[{"index":0,"id":"call_abc","type":"function","function":{"name":"get_weather","arguments":"{\"ci"}}]},"finish_reason":null}]}"#;
+        let chunk: OpenAIChunk = serde_json::from_str(json).expect("deser");
+        let delta = &chunk.choices[0].delta;
+        assert!(delta.content.is_none());
+        assert_eq!(delta.tool_calls.len(), 1);
+        let call = &delta.tool_calls[0];
+        assert_eq!(call.id.as_deref(), Some("call_abc"));
+        let function = call.function.as_ref().expect("function");
+        assert_eq!(function.name.as_deref(), Some("get_weather"));
+        assert_eq!(function.arguments.as_deref(), Some("{\"ci"));
+    }
+
     #[test]
     fn test_openai_chunk_no_choices() {
         let json = r#"{"id":"chatcmpl-x","choices":[]}"#;
@@ -544,11 +1111,13 @@ mod tests {
             stream: true,
             temperature: 0.7,
             logprobs: true,
-            top_logprobs: 5,
+            top_logprobs: Some(5),
+            max_tokens: None,
         };
         let json = serde_json::to_string(&req).expect("serialize");
         assert!(json.contains("\"logprobs\":true"));
         assert!(json.contains("\"top_logprobs\":5"));
+        assert!(!json.contains("max_tokens"));
     }
 
     #[test]
@@ -713,4 +1282,56 @@ mod tests {
         assert!(v.chars().nth(4) == Some('-'), "4th char should be -");
         assert!(v.chars().nth(7) == Some('-'), "7th char should be -");
     }
+
+    #[test]
+    fn test_utf8_decoder_joins_split_multibyte_char() {
+        let bytes = "héllo 世界".as_bytes();
+        // Split inside 'é' (2 bytes) and inside '世' (3 bytes).
+        let (a, rest) = bytes.split_at(2);
+        let (b, c) = rest.split_at(7);
+        let mut decoder = Utf8ChunkDecoder::new();
+        let mut out = decoder.decode(a);
+        assert_eq!(out, "h");
+        assert_eq!(decoder.pending_len(), 1);
+        out.push_str(&decoder.decode(b));
+        out.push_str(&decoder.decode(c));
+        assert_eq!(out, "héllo 世界");
+        assert_eq!(decoder.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_utf8_decoder_skips_invalid_bytes() {
+        let mut decoder = Utf8ChunkDecoder::new();
+        assert_eq!(decoder.decode(b"ok\xFFgo"), "okgo");
+        assert_eq!(decoder.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_word_buffer_holds_partial_word() {
+        let mut buf: WordBuffer<u8> = WordBuffer::new();
+        assert_eq!(buf.push("Hello wor", 1), Some(("Hello ".to_string(), 1)));
+        assert_eq!(buf.pending(), "wor");
+        assert_eq!(buf.push("ld", 2), None);
+        assert_eq!(buf.push(" again", 3), Some(("world ".to_string(), 1)));
+        assert_eq!(buf.flush(), Some(("again".to_string(), 3)));
+        assert_eq!(buf.flush(), None);
+    }
+
+    #[test]
+    fn test_word_buffer_whitespace_head_leaves_meta_on_word() {
+        let mut buf: WordBuffer<u8> = WordBuffer::new();
+        assert_eq!(buf.push(" wor", 5), Some((" ".to_string(), 0)));
+        assert_eq!(buf.push("ld", 6), None);
+        assert_eq!(buf.flush(), Some(("world".to_string(), 5)));
+    }
+
+    #[test]
+    fn test_word_buffer_word_across_three_deltas_keeps_first_meta() {
+        let mut buf: WordBuffer<Option<f32>> = WordBuffer::new();
+        assert_eq!(buf.push("Hi un", Some(-0.1)), Some(("Hi ".to_string(), Some(-0.1))));
+        assert_eq!(buf.push("believ", Some(-2.5)), None);
+        assert_eq!(buf.push("able fo", Some(-0.7)), Some(("unbelievable ".to_string(), Some(-0.1))));
+        assert_eq!(buf.push("lks", Some(-3.0)), None);
+        assert_eq!(buf.flush(), Some(("folks".to_string(), Some(-0.7))));
+    }
 }
@@ -126,6 +126,85 @@ pub fn heat_colorize(text: &str, importance: f64) -> ColoredString {
     }
 }
 
+/// Append a transformed token's original text in brackets, e.g. `dlrow[world]`,
+/// for `--show-original`.
+///
+/// The bracketed part is dimmed when `color` is set and plain otherwise
+/// (`--no-color`).
+pub fn with_original(display: &str, original: &str, color: bool) -> String {
+    let bracketed = format!("[{}]", original);
+    if color {
+        format!("{}{}", display, bracketed.dimmed())
+    } else {
+        format!("{}{}", display, bracketed)
+    }
+}
+
+/// Confidence-band terminal colour: green for `High`, yellow for `Mid`, red
+/// for `Low`, matching the web UI's confidence bars.
+pub fn confidence_colorize(text: &str, confidence: f32) -> ColoredString {
+    match ConfidenceBand::from_confidence(confidence) {
+        ConfidenceBand::High => text.green(),
+        ConfidenceBand::Mid => text.yellow(),
+        ConfidenceBand::Low => text.red(),
+    }
+}
+
+/// What drives terminal token colour (`--color-by`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorBy {
+    /// `--heatmap` / `--visual` colouring (importance-based heat).
+    #[default]
+    Importance,
+    /// Model confidence bands; tokens without a confidence (e.g. Anthropic)
+    /// fall back to importance colouring.
+    Confidence,
+}
+
+/// Terminal styling flags shared by streaming output and `--watch`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TerminalStyle {
+    /// Color tokens by importance (`--heatmap`).
+    pub heatmap: bool,
+    /// Highlight transformed tokens (`--visual`).
+    pub visual: bool,
+    /// Follow transformed tokens with their original (`--show-original`).
+    pub show_original: bool,
+    /// Colour by importance or by model confidence (`--color-by`).
+    pub color_by: ColorBy,
+}
+
+/// Render one token for terminal output: confidence, heatmap or visual
+/// coloring, plus the bracketed original for transformed tokens when
+/// `show_original` is set.
+pub fn terminal_token(
+    display_text: &str,
+    original: &str,
+    importance: f64,
+    confidence: Option<f32>,
+    transformed: bool,
+    style: TerminalStyle,
+    color: bool,
+) -> String {
+    let by_confidence = confidence.filter(|_| style.color_by == ColorBy::Confidence);
+    let styled = if let Some(c) = by_confidence {
+        confidence_colorize(display_text, c).to_string()
+    } else if style.heatmap {
+        crate::transforms::apply_heatmap_color(display_text, importance)
+    } else if style.visual && transformed {
+        display_text.bright_cyan().bold().to_string()
+    } else if style.visual {
+        display_text.normal().to_string()
+    } else {
+        display_text.to_string()
+    };
+    if style.show_original && transformed {
+        with_original(&styled, original, color)
+    } else {
+        styled
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Visual-mode line formatting
 // ---------------------------------------------------------------------------
@@ -166,6 +245,49 @@ mod tests {
     use super::*;
     use crate::TokenAlternative;
 
+    // ---- with_original ----
+
+    #[test]
+    fn test_with_original_plain() {
+        assert_eq!(with_original("dlrow", "world", false), "dlrow[world]");
+    }
+
+    #[test]
+    fn test_with_original_colored_keeps_text() {
+        let s = with_original("dlrow", "world", true);
+        assert!(s.starts_with("dlrow"));
+        assert!(s.contains("[world]"));
+    }
+
+    // ---- terminal_token --color-by confidence ----
+
+    #[test]
+    fn test_terminal_token_colors_by_confidence() {
+        let style = TerminalStyle { color_by: ColorBy::Confidence, ..TerminalStyle::default() };
+        assert_eq!(confidence_colorize("sure", 0.95).fgcolor, Some(Color::Green));
+        assert_eq!(confidence_colorize("hm", 0.5).fgcolor, Some(Color::Yellow));
+        assert_eq!(confidence_colorize("maybe", 0.1).fgcolor, Some(Color::Red));
+        assert_eq!(
+            terminal_token("sure", "sure", 0.0, Some(0.95), false, style, true),
+            "sure".green().to_string()
+        );
+        assert_eq!(
+            terminal_token("maybe", "maybe", 1.0, Some(0.1), false, style, true),
+            "maybe".red().to_string()
+        );
+    }
+
+    #[test]
+    fn test_terminal_token_confidence_falls_back_without_confidence() {
+        let style = TerminalStyle { color_by: ColorBy::Confidence, heatmap: true, ..TerminalStyle::default() };
+        assert_eq!(
+            terminal_token("x", "x", 0.9, None, false, style, false),
+            terminal_token("x", "x", 0.9, None, false, TerminalStyle { heatmap: true, ..TerminalStyle::default() }, false)
+        );
+        // Importance mode ignores confidence entirely.
+        assert_eq!(terminal_token("x", "x", 0.5, Some(0.9), false, TerminalStyle::default(), false), "x");
+    }
+
     // ---- ConfidenceBand ----
 
     #[test]
@@ -249,15 +371,10 @@ mod tests {
             text: text.to_string(),
             original: text.to_string(),
             index: 0,
-            transformed: false,
             importance: 0.5,
-            chaos_label: None,
-            provider: None,
             confidence,
             perplexity,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         }
     }
 
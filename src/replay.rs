@@ -116,6 +116,108 @@ impl Replayer {
     }
 }
 
+/// Exported sessions compiled into the binary and served by `GET /demo`,
+/// as `(name, JSON)`.  The first entry is the default.
+pub const BUILTIN_DEMOS: &[(&str, &str)] = &[
+    ("recursion", include_str!("../static/demos/recursion.json")),
+    ("haiku", include_str!("../static/demos/haiku.json")),
+];
+
+/// A session bundle saved by the web UI's "Export JSON" button or
+/// `GET /api/session/export`, reloaded for inspection without any API call.
+///
+/// Only `tokens` is required.  `effective_prompt` is what was sent when the
+/// server rewrote `prompt`.  The recorded `token_count` and
+/// `transformed_count` are kept so a reload can be checked against them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedSession {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transformed_count: Option<usize>,
+    pub tokens: Vec<TokenEvent>,
+}
+
+impl ExportedSession {
+    /// Parse an exported bundle.
+    ///
+    /// # Errors
+    /// Returns a message when the JSON is malformed or `tokens` is missing.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid session export: {}", e))
+    }
+
+    /// Read and parse an exported bundle from `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a session export.
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&content)?)
+    }
+
+    /// The built-in demo session `name` (see [`BUILTIN_DEMOS`]).
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_DEMOS
+            .iter()
+            .find(|(demo, _)| *demo == name)
+            .and_then(|(_, json)| Self::parse(json).ok())
+    }
+
+    /// The tokens as [`ReplayRecord`]s for [`Replayer::replay_to_channel_timed`],
+    /// timed by each token's `arrival_ms`.  A token without one arrives
+    /// together with the token before it.
+    pub fn replay_records(&self) -> Vec<ReplayRecord> {
+        let mut last_ms = 0;
+        self.tokens
+            .iter()
+            .map(|event| {
+                last_ms = event.arrival_ms.unwrap_or(last_ms).max(last_ms);
+                ReplayRecord { timestamp_ms: last_ms, event: event.clone() }
+            })
+            .collect()
+    }
+
+    /// `(tokens, transformed tokens)` actually present in `tokens`.
+    pub fn loaded_counts(&self) -> (usize, usize) {
+        (self.tokens.len(), self.tokens.iter().filter(|t| t.transformed).count())
+    }
+
+    /// Whether the loaded tokens agree with the counts recorded at export
+    /// (a missing count always agrees).
+    pub fn matches_recorded_counts(&self) -> bool {
+        let (tokens, transformed) = self.loaded_counts();
+        !matches!(self.token_count, Some(n) if n != tokens)
+            && !matches!(self.transformed_count, Some(n) if n != transformed)
+    }
+
+    /// Re-render the session as terminal mode printed it, from the stored
+    /// `text`, `original`, `importance` and `confidence` of each token.
+    pub fn render_terminal(&self, style: crate::render::TerminalStyle, color: bool) -> String {
+        self.tokens
+            .iter()
+            .filter(|t| !t.is_error)
+            .map(|t| {
+                crate::render::terminal_token(
+                    &t.text,
+                    &t.original,
+                    t.importance,
+                    t.confidence,
+                    t.transformed,
+                    style,
+                    color,
+                )
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,15 +228,8 @@ mod tests {
             text: "hello".to_string(),
             original: "hello".to_string(),
             index: idx,
-            transformed: false,
-            importance: 0.0,
-            chaos_label: None,
-            provider: None,
             confidence: Some(0.9),
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         }
     }
 
@@ -218,6 +313,47 @@ mod tests {
         }
     }
 
+    /// A bundle in the shape the web UI's "Export JSON" button writes.
+    const UI_EXPORT: &str = r#"{
+        "prompt": "hi", "provider": "openai", "model": "auto", "transform": "reverse",
+        "timestamp": "2026-01-01T00:00:00Z", "mode": "single",
+        "token_count": 3, "transformed_count": 1,
+        "tokens": [
+            {"text":"The","original":"The","index":0,"transformed":false,"importance":0.4,"chaos_label":null,"confidence":0.9,"perplexity":1.1,"alternatives":[]},
+            {"text":"tac","original":"cat","index":1,"transformed":true,"importance":0.7,"chaos_label":null,"alternatives":[]},
+            {"text":"sat","original":"sat","index":2,"transformed":false,"importance":0.2,"chaos_label":null,"confidence":null,"alternatives":[]}
+        ],
+        "surgery_log": [], "graph": {"nodes": [], "edges": []}
+    }"#;
+
+    #[test]
+    fn test_exported_session_reload_matches_recorded_counts() {
+        let session = ExportedSession::parse(UI_EXPORT).expect("parse");
+        assert_eq!(session.loaded_counts(), (3, 1));
+        assert_eq!((session.token_count, session.transformed_count), (Some(3), Some(1)));
+        assert!(session.matches_recorded_counts());
+        assert_eq!(session.tokens[0].confidence, Some(0.9));
+
+        let style = crate::render::TerminalStyle { show_original: true, ..Default::default() };
+        assert_eq!(session.render_terminal(style, false), "Thetac[cat]sat");
+
+        let tampered = UI_EXPORT.replace("\"transformed_count\": 1", "\"transformed_count\": 2");
+        assert!(!ExportedSession::parse(&tampered).unwrap().matches_recorded_counts());
+        assert!(ExportedSession::parse(r#"{"token_count": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_builtin_demos_parse_with_monotonic_timing() {
+        for (name, _) in BUILTIN_DEMOS {
+            let session = ExportedSession::builtin(name).expect("demo parses");
+            assert!(session.matches_recorded_counts(), "{} counts", name);
+            let records = session.replay_records();
+            assert_eq!(records.len(), session.tokens.len());
+            assert!(records.windows(2).all(|w| w[0].timestamp_ms <= w[1].timestamp_ms));
+        }
+        assert!(ExportedSession::builtin("missing").is_none());
+    }
+
     #[tokio::test]
     async fn test_replay_to_channel_timed_instant_speed() {
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
@@ -15,11 +15,24 @@
 //!
 //! The [`ResearchOutput`] struct is versioned with a `schema_version` field so
 //! downstream consumers can detect breaking changes.  The current version is `1`.
+//!
+//! ## Tagged experiments
+//!
+//! With `--tag NAME` the tag (and any `--notes`) is recorded in the output,
+//! and a relative `--output` path is written under `experiments/NAME/` (see
+//! [`tagged_output_path`]), so each experiment keeps its own directory:
+//!
+//! ```text
+//! experiments/
+//!   ablation-3/research_output.json
+//!   baseline/research_output.json
+//! ```
 
 use crate::cli::Args;
-use crate::transforms::Transform;
 use crate::TokenInterceptor;
 use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
 /// Metrics collected from a single research run.
@@ -57,6 +70,9 @@ pub struct ResearchRun {
     /// P95 token arrival latency in milliseconds.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub p95_latency_ms: Option<u64>,
+    /// Provider SSE chunks that failed to parse and were skipped.
+    #[serde(default)]
+    pub parse_error_count: usize,
 }
 
 /// Top-level JSON output written by [`run_research`].
@@ -74,6 +90,49 @@ pub struct ResearchOutput {
     pub runs: Vec<ResearchRun>,
     /// Cross-run aggregated statistics.
     pub aggregate: ResearchAggregate,
+    /// What `--orchestrator` did to the prompt on the first run; present only
+    /// with `--show-enriched`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enriched_prompt: Option<crate::PromptEnrichment>,
+    /// The perturbed prompt sent to the model in place of `prompt`; present
+    /// only with `--transform-prompt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transformed_prompt: Option<String>,
+    /// The prompt actually sent to the model on the first run, when it
+    /// differs from `prompt` (`--transform-prompt`, `--preprocess` or
+    /// orchestrator enrichment).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_prompt: Option<String>,
+    /// Experiment name from `--tag`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Free-form notes from `--notes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// Directory that tagged experiments are written under.
+pub const EXPERIMENTS_DIR: &str = "experiments";
+
+/// Where research output for `output` goes: `experiments/<tag>/<output>`
+/// when tagged and `output` is relative, otherwise `output` unchanged.
+pub fn tagged_output_path(output: &str, tag: Option<&str>) -> std::path::PathBuf {
+    let path = std::path::Path::new(output);
+    match tag {
+        Some(tag) if path.is_relative() => std::path::Path::new(EXPERIMENTS_DIR).join(tag).join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Write `json` to [`tagged_output_path`], creating the experiment
+/// directory if needed, and return the path written.
+fn write_output(output: &str, args: &Args, json: &str) -> std::io::Result<std::path::PathBuf> {
+    let path = tagged_output_path(output, args.tag.as_deref());
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, json)?;
+    Ok(path)
 }
 
 /// Cross-run aggregate statistics, appended to every [`ResearchOutput`].
@@ -108,6 +167,9 @@ pub struct ResearchAggregate {
     /// True when `total_runs < 30`; the Z-score CI and t-test p-values are
     /// approximations that may be unreliable at small sample sizes.
     pub small_n_warning: bool,
+    /// Unparseable provider SSE chunks summed over all runs.
+    #[serde(default)]
+    pub total_parse_errors: usize,
 }
 
 /// Compute a percentile value (0–100) from a slice of latencies.
@@ -152,14 +214,32 @@ fn ci_95(mean: f64, sd: f64, n: usize) -> (f64, f64) {
 /// Returns an error if the transform string is invalid, the API call fails,
 /// or output file I/O fails.
 pub async fn run_research(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    run_research_with_stop(args, Arc::new(AtomicBool::new(false))).await
+}
+
+/// [`run_research`] with cooperative cancellation.
+///
+/// When `stop` is set (e.g. by a Ctrl+C handler) the run in progress is
+/// abandoned, no further runs start, and the output JSON is written from the
+/// runs that did complete.
+///
+/// # Errors
+/// As [`run_research`]; additionally errors if `stop` is set before the first
+/// run completes, since there is nothing to write.
+pub async fn run_research_with_stop(
+    args: &Args,
+    stop: Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error>> {
     if args.runs == 0 {
         return Err("--runs must be at least 1".into());
     }
+    // One router for every run, so spend accumulates across the session.
+    let budget = crate::cli::SharedBudget::from_args(args)?;
     let provider = args.provider.clone();
     let transform_str = args.transform.clone();
     let transform =
-        Transform::from_str_loose(&transform_str).map_err(|e| format!("Invalid transform: {e}"))?;
-    let model = crate::cli::resolve_model(&provider, &args.model);
+        crate::cli::resolve_transform(args).map_err(|e| format!("Invalid transform: {e}"))?;
+    let model = crate::cli::resolve_model(&provider, args.model.as_deref());
 
     tracing::info!(
         runs = args.runs,
@@ -207,47 +287,68 @@ pub async fn run_research(args: &Args) -> Result<(), Box<dyn std::error::Error>>
         .map(|_| crate::heatmap::HeatmapExporter::new());
 
     let mut runs: Vec<ResearchRun> = Vec::with_capacity(args.runs as usize);
+    let mut enriched_prompt = None;
+    let mut effective_prompt = None;
+    // One perturbation shared by every run, so runs stay comparable.
+    let transformed_prompt = crate::cli::transform_prompt(args, &args.prompt, &transform)?;
+    if let Some(ref sent) = transformed_prompt {
+        eprintln!("[research] --transform-prompt: sending {:?}", sent);
+    }
 
     for i in 0..args.runs {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
         tracing::info!(run = i + 1, total = args.runs, "starting research run");
         eprintln!("[research] run {}/{}", i + 1, args.runs);
 
         let (tx, mut rx) = mpsc::unbounded_channel();
 
-        let mut interceptor = TokenInterceptor::new(
-            provider.clone(),
-            transform.clone(),
-            model.clone(),
-            false,
-            false,
-            false,
+        let mut interceptor = configure_interceptor(
+            TokenInterceptor::new(
+                provider.clone(),
+                transform.clone(),
+                model.clone(),
+                false,
+                false,
+                args.orchestrator,
+            )?,
+            args,
+            &budget,
         )?;
+        interceptor.show_enriched = args.show_enriched && i == 0;
         interceptor.web_tx = Some(tx);
         // A/B mode: alternate system prompts on even/odd runs so --significance
         // actually compares two different conditions.
-        interceptor.system_prompt = if i % 2 == 1 {
-            args.system_b.clone().or_else(|| args.system_a.clone())
-        } else {
-            args.system_a.clone()
-        };
-        interceptor.top_logprobs = args.top_logprobs;
-        interceptor.min_confidence = args.min_confidence;
+        if i % 2 == 1 {
+            interceptor.system_prompt = args.system_b.clone().or_else(|| args.system_a.clone());
+        }
         // Enable in-session semantic dedup when the feature is compiled in.
         // Repeated identical prompts (common in research mode) hit the cache
         // after the first run, avoiding redundant API spend.
         #[cfg(feature = "self-modify")]
         interceptor.enable_dedup(300_000, 1_024);
-        if let Some(rate) = args.rate {
-            interceptor = interceptor.with_rate(rate);
-        }
-        if let Some(seed) = args.seed {
-            interceptor = interceptor.with_seed(seed);
-        }
+
+        interceptor = interceptor.with_stop_flag(stop.clone());
 
         let run_start = std::time::Instant::now();
-        interceptor.intercept_stream(&args.prompt).await?;
+        interceptor
+            .intercept_stream(transformed_prompt.as_deref().unwrap_or(&args.prompt))
+            .await?;
         let elapsed_ms = run_start.elapsed().as_millis() as u64;
+        let interrupted = interceptor.interrupted;
+        let parse_error_count = interceptor.parse_error_count;
+        if args.show_enriched && enriched_prompt.is_none() {
+            enriched_prompt = interceptor.enrichment.take();
+        }
+        if effective_prompt.is_none() {
+            effective_prompt = interceptor.rewritten_prompt(&args.prompt).map(str::to_string);
+        }
         drop(interceptor);
+        if interrupted {
+            // A half-finished run would skew every per-run metric.
+            break;
+        }
 
         // Collect events and record per-token latencies from arrival_ms stamps
         // set inline by the interceptor relative to stream start.
@@ -359,9 +460,21 @@ pub async fn run_research(args: &Args) -> Result<(), Box<dyn std::error::Error>>
             token_latencies_ms,
             p50_latency_ms,
             p95_latency_ms,
+            parse_error_count,
         });
     }
 
+    if stop.load(Ordering::Relaxed) {
+        if runs.is_empty() {
+            return Err("interrupted before the first run completed".into());
+        }
+        eprintln!(
+            "[research] interrupted after {}/{} runs — writing partial results",
+            runs.len(),
+            args.runs
+        );
+    }
+
     // Sample-size warning: CLT requires N >= 30 for valid inference
     if args.runs < 30 {
         eprintln!(
@@ -372,7 +485,7 @@ pub async fn run_research(args: &Args) -> Result<(), Box<dyn std::error::Error>>
         );
     }
 
-    let aggregate = build_aggregate(args.runs, &runs);
+    let aggregate = build_aggregate(runs.len() as u32, &runs);
 
     // Auto-baseline: compare A (even runs) vs B (odd runs) confidence when data available.
     // This always runs in research mode (no --baseline flag needed) if we have >= 2 runs.
@@ -473,11 +586,16 @@ pub async fn run_research(args: &Args) -> Result<(), Box<dyn std::error::Error>>
         transform: transform_str,
         runs,
         aggregate,
+        enriched_prompt,
+        transformed_prompt,
+        effective_prompt,
+        tag: args.tag.clone(),
+        notes: args.notes.clone(),
     };
 
     let json = serde_json::to_string_pretty(&output)?;
-    std::fs::write(&args.output, &json)?;
-    eprintln!("[research] wrote {} bytes to {}", json.len(), args.output);
+    let path = write_output(&args.output, args, &json)?;
+    eprintln!("[research] wrote {} bytes to {}", json.len(), path.display());
 
     // Export timeseries CSV if requested via --export-timeseries.
     if let Some(ref ts_path) = args.export_timeseries {
@@ -512,6 +630,102 @@ pub async fn run_research(args: &Args) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+/// Apply the command-line settings every research interceptor shares --
+/// research runs, prompt suites and temperature sweeps -- so their results
+/// are comparable for the same flags.  The system prompt is `--system-a`.
+/// `budget` is the run's shared `--budget-usd` router.
+///
+/// # Errors
+/// Returns an error if a `--preprocess` stage is invalid.
+pub fn configure_interceptor(
+    mut interceptor: TokenInterceptor,
+    args: &Args,
+    budget: &crate::cli::SharedBudget,
+) -> Result<TokenInterceptor, Box<dyn std::error::Error>> {
+    interceptor = budget
+        .attach(interceptor)
+        .with_orchestrator_url(args.orchestrator_url.clone())
+        .with_extra_headers(args.headers.clone())
+        .with_transform_options(crate::cli::resolve_transform_options(args)?);
+    interceptor.word_boundaries = args.word_boundaries;
+    interceptor.normalize = args.normalize;
+    interceptor.normalize_original = args.normalize_original;
+    interceptor.strict_parsing = args.strict_parsing;
+    interceptor.preprocessors = crate::cli::preprocess_stages(args)?;
+    interceptor.system_prompt = args.system_a.clone();
+    interceptor.top_logprobs = args.top_logprobs;
+    interceptor.logprobs = !args.no_logprobs;
+    interceptor.logprobs_fallback = !args.no_logprobs_fallback;
+    interceptor.min_confidence = args.min_confidence;
+    interceptor.selector = args.select;
+    interceptor.pos_filter = args.pos_filter;
+    interceptor.granularity = args.granularity;
+    interceptor.anthropic_max_tokens = args.anthropic_max_tokens;
+    interceptor.deterministic_importance = args.deterministic_importance;
+    if let Some(rate) = args.rate {
+        interceptor = interceptor.with_rate(rate);
+    }
+    if let Some(seed) = args.seed {
+        interceptor = interceptor.with_seed(seed);
+    }
+    Ok(interceptor)
+}
+
+/// Research mode with `--temp-sweep`: run `args.runs` headless runs at each
+/// temperature, write the sessions and comparison to `args.output`, and print
+/// the comparison table to stdout.
+///
+/// # Errors
+/// Returns an error if the transform is invalid, a run fails, or the output
+/// file cannot be written.
+pub async fn run_temperature_sweep(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    if args.runs == 0 {
+        return Err("--runs must be at least 1".into());
+    }
+    // One router for every run, so spend accumulates across the session.
+    let budget = crate::cli::SharedBudget::from_args(args)?;
+    let transform =
+        crate::cli::resolve_transform(args).map_err(|e| format!("Invalid transform: {e}"))?;
+    let model = crate::cli::resolve_model(&args.provider, args.model.as_deref());
+    let prompt = crate::cli::transform_prompt(args, &args.prompt, &transform)?
+        .unwrap_or_else(|| args.prompt.clone());
+    eprintln!(
+        "[research] temperature sweep over {:?} -- {} runs each, provider={} model={}",
+        args.temp_sweep, args.runs, args.provider, model
+    );
+
+    // Validate the preprocessor stages once, before any run starts.
+    crate::cli::preprocess_stages(args)?;
+    let mut sweep = crate::run_temperature_sweep_with(
+        &prompt,
+        args.provider.clone(),
+        transform,
+        model,
+        args.runs,
+        &args.temp_sweep,
+        &|interceptor| configure_interceptor(interceptor, args, &budget),
+    )
+    .await?;
+    sweep.sessions = sweep
+        .sessions
+        .into_iter()
+        .map(|mut s| {
+            // Report the prompt as given, with the perturbed one as sent.
+            if s.prompt != args.prompt {
+                s.effective_prompt = s.effective_prompt.or(Some(std::mem::take(&mut s.prompt)));
+                s.prompt = args.prompt.clone();
+            }
+            s.tagged(args.tag.clone(), args.notes.clone())
+        })
+        .collect();
+
+    let json = serde_json::to_string_pretty(&sweep)?;
+    let path = write_output(&args.output, args, &json)?;
+    eprintln!("[research] wrote {} bytes to {}", json.len(), path.display());
+    print!("{}", sweep.render_table());
+    Ok(())
+}
+
 // Cost estimate per model (output tokens, $/1K tokens).
 // These are approximate list prices; verify at platform.openai.com / anthropic.com.
 fn cost_per_1k_tokens(model: &str) -> f64 {
@@ -601,6 +815,7 @@ fn build_aggregate(total_runs: u32, runs: &[ResearchRun]) -> ResearchAggregate {
         aligned_length,
         mean_per_transform_perplexity,
         small_n_warning: total_runs < 30,
+        total_parse_errors: runs.iter().map(|r| r.parse_error_count).sum(),
     }
 }
 
@@ -620,6 +835,7 @@ impl ResearchAggregate {
             aligned_length: 0,
             mean_per_transform_perplexity: std::collections::HashMap::new(),
             small_n_warning: total_runs < 30,
+            total_parse_errors: 0,
         }
     }
 }
@@ -675,11 +891,13 @@ pub async fn run_research_suite(args: &Args) -> Result<(), Box<dyn std::error::E
         return Ok(());
     }
 
+    // One router for the whole suite, so spend accumulates across prompts.
+    let budget = crate::cli::SharedBudget::from_args(args)?;
     tracing::info!(count = prompts.len(), path = %path, "running research suite");
     eprintln!("[suite] Running {} prompts from {}", prompts.len(), path);
     for (idx, prompt) in prompts.iter().enumerate() {
         eprintln!("[suite] Prompt {}/{}: {}", idx + 1, prompts.len(), prompt);
-        run_research_for_prompt(args, prompt, idx).await?;
+        run_research_for_prompt(args, prompt, idx, &budget).await?;
     }
     Ok(())
 }
@@ -689,12 +907,13 @@ async fn run_research_for_prompt(
     args: &Args,
     prompt: &str,
     idx: usize,
+    budget: &crate::cli::SharedBudget,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let provider = args.provider.clone();
     let transform_str = args.transform.clone();
     let transform =
-        Transform::from_str_loose(&transform_str).map_err(|e| format!("Invalid transform: {e}"))?;
-    let model = crate::cli::resolve_model(&provider, &args.model);
+        crate::cli::resolve_transform(args).map_err(|e| format!("Invalid transform: {e}"))?;
+    let model = crate::cli::resolve_model(&provider, args.model.as_deref());
 
     let store = if let Some(db_path) = &args.db {
         Some(crate::store::ExperimentStore::open(db_path)?)
@@ -722,27 +941,38 @@ async fn run_research_for_prompt(
     let _ = exp_id;
 
     let mut runs: Vec<ResearchRun> = Vec::with_capacity(args.runs as usize);
+    let mut enriched_prompt = None;
+    let mut effective_prompt = None;
+    let transformed_prompt = crate::cli::transform_prompt(args, prompt, &transform)?;
     for i in 0..args.runs {
         eprintln!("[suite] run {}/{} for prompt {}", i + 1, args.runs, idx);
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
-        let mut interceptor = crate::TokenInterceptor::new(
-            provider.clone(),
-            transform.clone(),
-            model.clone(),
-            false,
-            false,
-            false,
+        let mut interceptor = configure_interceptor(
+            crate::TokenInterceptor::new(
+                provider.clone(),
+                transform.clone(),
+                model.clone(),
+                false,
+                false,
+                args.orchestrator,
+            )?,
+            args,
+            budget,
         )?;
+        interceptor.show_enriched = args.show_enriched && i == 0;
         interceptor.web_tx = Some(tx);
-        if let Some(rate) = args.rate {
-            interceptor = interceptor.with_rate(rate);
-        }
-        if let Some(seed) = args.seed {
-            interceptor = interceptor.with_seed(seed);
-        }
         let run_start = std::time::Instant::now();
-        interceptor.intercept_stream(prompt).await?;
+        interceptor
+            .intercept_stream(transformed_prompt.as_deref().unwrap_or(prompt))
+            .await?;
         let elapsed_ms = run_start.elapsed().as_millis() as u64;
+        let parse_error_count = interceptor.parse_error_count;
+        if args.show_enriched && enriched_prompt.is_none() {
+            enriched_prompt = interceptor.enrichment.take();
+        }
+        if effective_prompt.is_none() {
+            effective_prompt = interceptor.rewritten_prompt(prompt).map(str::to_string);
+        }
         drop(interceptor);
 
         let mut events = Vec::new();
@@ -832,6 +1062,7 @@ async fn run_research_for_prompt(
             token_latencies_ms: token_latencies_ms2,
             p50_latency_ms: p50_latency_ms2,
             p95_latency_ms: p95_latency_ms2,
+            parse_error_count,
         });
     }
 
@@ -847,10 +1078,15 @@ async fn run_research_for_prompt(
         transform: transform_str,
         runs,
         aggregate,
+        enriched_prompt,
+        transformed_prompt,
+        effective_prompt,
+        tag: args.tag.clone(),
+        notes: args.notes.clone(),
     };
     let json = serde_json::to_string_pretty(&output)?;
-    std::fs::write(&output_path, &json)?;
-    eprintln!("[suite] wrote {} bytes to {}", json.len(), output_path);
+    let output_path = write_output(&output_path, args, &json)?;
+    eprintln!("[suite] wrote {} bytes to {}", json.len(), output_path.display());
     Ok(())
 }
 
@@ -868,12 +1104,13 @@ pub async fn run_diff_terminal(args: &crate::cli::Args) -> Result<(), Box<dyn st
     use tokio::sync::mpsc;
     tracing::info!("starting diff terminal: OpenAI vs Anthropic in parallel");
 
-    let transform_openai = crate::transforms::Transform::from_str_loose(&args.transform)
+    let transform_openai = crate::cli::resolve_transform(args)
         .map_err(|e| format!("Invalid transform: {e}"))?;
     let transform_anthropic = transform_openai.clone();
+    let transform_options = crate::cli::resolve_transform_options(args)?;
 
-    let model_openai = crate::cli::resolve_model(&Provider::Openai, &args.model);
-    let model_anthropic = crate::cli::resolve_model(&Provider::Anthropic, &args.model);
+    let model_openai = crate::cli::resolve_model(&Provider::Openai, args.model.as_deref());
+    let model_anthropic = crate::cli::resolve_model(&Provider::Anthropic, args.model.as_deref());
 
     let (tx_a, mut rx_a) = mpsc::unbounded_channel();
     let (tx_b, mut rx_b) = mpsc::unbounded_channel();
@@ -885,7 +1122,9 @@ pub async fn run_diff_terminal(args: &crate::cli::Args) -> Result<(), Box<dyn st
         false,
         false,
         false,
-    )?;
+    )?
+    .with_extra_headers(args.headers.clone())
+    .with_transform_options(transform_options.clone());
     ia.web_tx = Some(tx_a);
     let mut ib = TokenInterceptor::new(
         Provider::Anthropic,
@@ -894,7 +1133,9 @@ pub async fn run_diff_terminal(args: &crate::cli::Args) -> Result<(), Box<dyn st
         false,
         false,
         false,
-    )?;
+    )?
+    .with_extra_headers(args.headers.clone())
+    .with_transform_options(transform_options);
     ib.web_tx = Some(tx_b);
 
     let prompt = args.prompt.clone();
@@ -999,6 +1240,10 @@ pub struct BatchEntry {
     pub model: String,
     #[serde(default)]
     pub transforms: Vec<String>,
+    /// Prompt template variables for this entry, layered over `--var` /
+    /// `--vars-file`.
+    #[serde(default)]
+    pub vars: std::collections::HashMap<String, String>,
 }
 
 /// Result record written to the batch output JSONL.
@@ -1080,15 +1325,32 @@ pub async fn run_batch(
     let output_path = format!("batch_results_{}.jsonl", timestamp);
     let mut out_file = std::fs::File::create(&output_path)?;
 
+    let base_vars = crate::cli::resolve_template_vars(args)?;
+    let transform_options = crate::cli::resolve_transform_options(args)?;
+
     let mut progress = BatchProgress::new(entries.len());
     eprintln!("[batch] Processing {} entries → {}", entries.len(), output_path);
 
     for (idx, entry) in entries.iter().enumerate() {
-        let label = format!("prompt #{}: {:.50}", idx + 1, entry.prompt);
+        let mut vars = base_vars.clone();
+        vars.extend(entry.vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        let prompt = if vars.is_empty() {
+            entry.prompt.clone()
+        } else {
+            match crate::prompt_template::apply_template(&entry.prompt, &vars) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("[batch] Skipping prompt #{}: {}", idx + 1, e);
+                    progress.advance(&format!("prompt #{} (skipped)", idx + 1));
+                    continue;
+                }
+            }
+        };
+        let label = format!("prompt #{}: {:.50}", idx + 1, prompt);
 
         let provider = args.provider.clone();
         let model = if entry.model.is_empty() {
-            crate::cli::resolve_model(&provider, &args.model)
+            crate::cli::resolve_model(&provider, args.model.as_deref())
         } else {
             entry.model.clone()
         };
@@ -1117,7 +1379,9 @@ pub async fn run_batch(
                 false,
                 false,
             ) {
-                Ok(i) => i,
+                Ok(i) => i
+                    .with_extra_headers(args.headers.clone())
+                    .with_transform_options(transform_options.clone()),
                 Err(e) => {
                     eprintln!("[batch] Interceptor error: {}", e);
                     continue;
@@ -1129,7 +1393,7 @@ pub async fn run_batch(
             }
 
             let run_start = std::time::Instant::now();
-            let _ = interceptor.intercept_stream(&entry.prompt).await;
+            let _ = interceptor.intercept_stream(&prompt).await;
             let elapsed_ms = run_start.elapsed().as_millis() as u64;
             drop(interceptor);
 
@@ -1166,7 +1430,7 @@ pub async fn run_batch(
             };
 
             let result = BatchResult {
-                prompt: entry.prompt.clone(),
+                prompt: prompt.clone(),
                 model: model.clone(),
                 transform: transform_str.clone(),
                 token_count,
@@ -1233,8 +1497,8 @@ pub async fn run_with_logprob_export(
     export_path: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let provider = args.provider.clone();
-    let model = crate::cli::resolve_model(&provider, &args.model);
-    let transform = crate::transforms::Transform::from_str_loose(&args.transform)
+    let model = crate::cli::resolve_model(&provider, args.model.as_deref());
+    let transform = crate::cli::resolve_transform(args)
         .map_err(|e| format!("Invalid transform: {e}"))?;
 
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
@@ -1245,9 +1509,15 @@ pub async fn run_with_logprob_export(
         args.visual,
         args.heatmap,
         args.orchestrator,
-    )?;
+    )?
+    .with_extra_headers(args.headers.clone())
+    .with_transform_options(crate::cli::resolve_transform_options(args)?);
     interceptor.web_tx = Some(tx);
     interceptor.top_logprobs = args.top_logprobs;
+    interceptor.logprobs = !args.no_logprobs;
+    interceptor.logprobs_fallback = !args.no_logprobs_fallback;
+    interceptor.strict_parsing = args.strict_parsing;
+    interceptor.preprocessors = crate::cli::preprocess_stages(args)?;
     if let Some(rate) = args.rate {
         interceptor = interceptor.with_rate(rate);
     }
@@ -1298,7 +1568,7 @@ pub async fn run_multi_model_compare(
     }
 
     let provider = args.provider.clone();
-    let transform = crate::transforms::Transform::from_str_loose(&args.transform)
+    let transform = crate::cli::resolve_transform(args)
         .map_err(|e| format!("Invalid transform: {e}"))?;
 
     eprintln!(
@@ -1319,9 +1589,15 @@ pub async fn run_multi_model_compare(
             false,
             false,
             false,
-        )?;
+        )?
+        .with_extra_headers(args.headers.clone())
+        .with_transform_options(crate::cli::resolve_transform_options(args)?);
         interceptor.web_tx = Some(tx);
         interceptor.top_logprobs = args.top_logprobs;
+        interceptor.logprobs = !args.no_logprobs;
+        interceptor.logprobs_fallback = !args.no_logprobs_fallback;
+        interceptor.strict_parsing = args.strict_parsing;
+        interceptor.preprocessors = crate::cli::preprocess_stages(args)?;
         if let Some(rate) = args.rate {
             interceptor = interceptor.with_rate(rate);
         }
@@ -1458,6 +1734,7 @@ mod tests {
                 token_latencies_ms: vec![],
                 p50_latency_ms: None,
                 p95_latency_ms: None,
+                parse_error_count: 0,
             })
             .collect()
     }
@@ -1513,6 +1790,16 @@ mod tests {
         assert!((agg.mean_token_count - 8.0).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_tagged_output_path_nests_relative_paths_under_experiment() {
+        assert_eq!(
+            tagged_output_path("out.json", Some("ablation-3")),
+            std::path::Path::new("experiments/ablation-3/out.json")
+        );
+        assert_eq!(tagged_output_path("out.json", None), std::path::Path::new("out.json"));
+        assert_eq!(tagged_output_path("/tmp/out.json", Some("x")), std::path::Path::new("/tmp/out.json"));
+    }
+
     #[test]
     fn test_research_output_serializes() {
         let output = ResearchOutput {
@@ -1535,12 +1822,19 @@ mod tests {
                 aligned_length: 0,
                 mean_per_transform_perplexity: std::collections::HashMap::new(),
                 small_n_warning: false,
+                total_parse_errors: 0,
             },
+            enriched_prompt: None,
+            transformed_prompt: None,
+            effective_prompt: None,
+            tag: None,
+            notes: None,
         };
         let json = serde_json::to_string(&output).expect("serialize");
         assert!(json.contains("schema_version"));
         assert!(json.contains(r#""provider":"openai""#));
         assert!(json.contains(r#""schema_version":2"#));
+        assert!(!json.contains("enriched_prompt"));
     }
 
     #[test]
@@ -1559,6 +1853,7 @@ mod tests {
             token_latencies_ms: vec![],
             p50_latency_ms: None,
             p95_latency_ms: None,
+            parse_error_count: 0,
         };
         let json = serde_json::to_string(&run).expect("serialize");
         let v: serde_json::Value = serde_json::from_str(&json).expect("parse");
@@ -1633,7 +1928,7 @@ mod tests {
         let args = crate::cli::Args {
             prompt: "test".to_string(),
             transform: "reverse".to_string(),
-            model: "gpt-3.5-turbo".to_string(),
+            model: None,
             provider: Provider::Mock,
             visual: false,
             heatmap: false,
@@ -1643,8 +1938,14 @@ mod tests {
             research: true,
             runs: 0,
             output: "/tmp/test_research_out.json".to_string(),
+            tag: None,
+            notes: None,
+            temp_sweep: Vec::new(),
             system_a: None,
             top_logprobs: 5,
+            no_logprobs: false,
+            no_logprobs_fallback: false,
+            strict_parsing: false,
             system_b: None,
             db: None,
             significance: false,
@@ -1653,8 +1954,10 @@ mod tests {
             heatmap_sort_by: "position".to_string(),
             record: None,
             replay: None,
+            replay_export: None,
             rate: None,
             seed: None,
+            start_index: 0,
             log_db: None,
             baseline: false,
             prompt_file: None,
@@ -1664,30 +1967,191 @@ mod tests {
             rate_range: None,
             dry_run: false,
             template: None,
+            vars: vec![],
+            vars_file: None,
+            preprocess: vec![],
+            preprocess_max_chars: None,
+            style_instruction: None,
             min_confidence: None,
+            importance_smoothing: None,
+            select: crate::TransformSelector::Alternating,
+            pos_filter: None,
+            granularity: crate::Granularity::Token,
             format: "json".to_string(),
             collapse_window: 5,
             orchestrator_url: "http://localhost:3000".to_string(),
             max_retries: 3,
             anthropic_max_tokens: 4096,
             synonym_file: None,
+            dict: None,
+            dict_ignore_case: false,
             api_key: None,
             replay_speed: 1.0,
             timeout: 120,
+            headers: Vec::new(),
             export_timeseries: None,
             json_schema: false,
             list_models: None,
+            mcp_server: false,
+            list_transforms: false,
+            bench_transforms: false,
+            doctor: false,
+            watch: None,
+            watch_url: "http://localhost:8888".to_string(),
             validate_config: false,
             sse_buffer_size: 1000,
+            sse_retry_ms: crate::web::DEFAULT_SSE_RETRY_MS,
+            sse_batch: 1,
+            render_rate: None,
+            reveal_delay: None,
+            max_prompt_chars: 32_000,
+            tee: None,
+            show_original: false,
+            transform_prompt: false,
+            color_by: crate::render::ColorBy::Importance,
+            show_enriched: false,
+            noise_chars: None,
+            noise_count: 1,
+            zalgo_intensity: 3,
+            word_boundaries: false,
+            window_transform: None,
+            normalize: crate::Normalization::None,
+            normalize_original: false,
+            inline_mapping: None,
+            collapse_whitespace: false,
+            flush_policy: crate::FlushPolicy::Token,
+            debug_raw: false,
+            debug_raw_file: None,
+            emit_granularity: crate::web::EmitGranularity::Word,
+            render_window: crate::web::DEFAULT_RENDER_WINDOW,
+            alt_display: crate::web::AltDisplay::Tooltip,
+            reconnect_grace: crate::collab::DEFAULT_RECONNECT_GRACE_SECS,
+            max_name_chars: crate::collab::DEFAULT_MAX_NAME_CHARS,
+            max_recording_events: crate::collab::DEFAULT_RECORDING_CAP,
+            max_concurrency: None,
+            max_connections: crate::web::DEFAULT_MAX_CONNECTIONS,
+            listen_backlog: crate::web::DEFAULT_LISTEN_BACKLOG,
+            no_color: false,
+            deterministic_importance: false,
             batch: None,
             export_logprobs: None,
             compare: None,
+            batch_tokens: None,
+            quality: false,
+            similarity: None,
+            diversity_filter: false,
+            importance: None,
+            chunk: None,
+            stats: false,
+            benchmark: false,
+            context_budget: 4096,
+            vocab_stats: false,
+            #[cfg(feature = "helix-bridge")]
+            helix_url: None,
+            #[cfg(feature = "redis-backing")]
+            redis_url: None,
+            budget_usd: None,
         };
         let result = run_research(&args).await;
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("--runs must be at least 1"));
     }
 
+    #[tokio::test]
+    async fn test_transform_prompt_sends_perturbed_prompt_and_keeps_original() {
+        use clap::Parser;
+        let path = std::env::temp_dir().join(format!("eot_transform_prompt_{}.json", std::process::id()));
+        let args = Args::parse_from([
+            "eot",
+            "tell me about the ocean",
+            "uppercase",
+            "--provider",
+            "mock",
+            "--research",
+            "--runs",
+            "1",
+            "--transform-prompt",
+            "--output",
+            path.to_str().unwrap(),
+        ]);
+        run_research(&args).await.expect("mock research run");
+        let json = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let output: ResearchOutput = serde_json::from_str(&json).unwrap();
+        assert_eq!(output.prompt, "tell me about the ocean");
+        assert_eq!(output.transformed_prompt.as_deref(), Some("tell ME about THE ocean"));
+        assert_eq!(output.effective_prompt.as_deref(), Some("tell ME about THE ocean"));
+        assert_eq!(output.runs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_temp_sweep_records_sent_prompt_as_effective_prompt() {
+        use clap::Parser;
+        let path = std::env::temp_dir().join(format!("eot_sweep_effective_{}.json", std::process::id()));
+        let args = Args::parse_from([
+            "eot",
+            "tell me about the ocean",
+            "uppercase",
+            "--provider",
+            "mock",
+            "--runs",
+            "1",
+            "--temp-sweep",
+            "0.2,0.8",
+            "--transform-prompt",
+            "--output",
+            path.to_str().unwrap(),
+        ]);
+        run_temperature_sweep(&args).await.expect("mock sweep");
+        let json = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let sweep: serde_json::Value = serde_json::from_str(&json).unwrap();
+        for session in sweep["sessions"].as_array().expect("sessions") {
+            assert_eq!(session["prompt"], "tell me about the ocean");
+            assert_eq!(session["effective_prompt"], "tell ME about THE ocean");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_prompt_has_no_effective_prompt() {
+        use clap::Parser;
+        let path = std::env::temp_dir().join(format!("eot_no_effective_{}.json", std::process::id()));
+        let args = Args::parse_from([
+            "eot", "tell me about the ocean", "uppercase", "--provider", "mock", "--research", "--runs", "1",
+            "--output", path.to_str().unwrap(),
+        ]);
+        run_research(&args).await.expect("mock research run");
+        let json = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let output: ResearchOutput = serde_json::from_str(&json).unwrap();
+        assert_eq!(output.effective_prompt, None);
+    }
+
+    #[test]
+    fn test_configure_interceptor_applies_args() {
+        use clap::Parser;
+        let args = Args::parse_from([
+            "eot", "p", "--provider", "mock", "--rate", "0.25", "--seed", "7", "--system-a", "Be brief.",
+            "--anthropic-max-tokens", "99", "--no-logprobs", "--top-logprobs", "3", "--header", "anthropic-beta: x",
+        ]);
+        let base = TokenInterceptor::new(
+            crate::providers::Provider::Mock,
+            crate::transforms::Transform::Reverse,
+            "mock".to_string(),
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        let i = configure_interceptor(base, &args, &crate::cli::SharedBudget::default()).expect("configure");
+        assert_eq!(i.rate, 0.25);
+        assert_eq!(i.system_prompt.as_deref(), Some("Be brief."));
+        assert_eq!(i.anthropic_max_tokens, 99);
+        assert!(!i.logprobs);
+        assert_eq!(i.top_logprobs, 3);
+        assert_eq!(i.extra_headers, vec![("anthropic-beta".to_string(), "x".to_string())]);
+    }
+
     #[test]
     fn test_collapse_positions_detected() {
         // 6-token dip starting at position 3
@@ -1802,6 +2266,7 @@ mod tests {
             token_latencies_ms: vec![],
             p50_latency_ms: None,
             p95_latency_ms: None,
+            parse_error_count: 0,
         }];
         write_timeseries_csv(path, &runs).expect("should write CSV");
         let content = std::fs::read_to_string(path).expect("should read CSV");
@@ -130,8 +130,8 @@ impl Default for BudgetConfig {
 // BudgetPressure
 // ---------------------------------------------------------------------------
 
-/// Current budget pressure level.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Current budget pressure level, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BudgetPressure {
     /// Spend is well below the ceiling.
     Normal,
@@ -472,6 +472,159 @@ pub struct BackendReport {
     pub quality_ema: f64,
 }
 
+// ---------------------------------------------------------------------------
+// ModelDowngradeTable — "cheaper alternative" mapping
+// ---------------------------------------------------------------------------
+
+/// Maps a model name to its cheaper alternative.
+///
+/// The default table covers the OpenAI and Anthropic models known to the CLI;
+/// use [`ModelDowngradeTable::with_entry`] to add or override entries.
+#[derive(Debug, Clone)]
+pub struct ModelDowngradeTable {
+    entries: HashMap<String, String>,
+}
+
+impl ModelDowngradeTable {
+    /// An empty table (no model is ever downgraded).
+    pub fn empty() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Add or replace the cheaper alternative for `model`.
+    pub fn with_entry(mut self, model: impl Into<String>, cheaper: impl Into<String>) -> Self {
+        self.entries.insert(model.into(), cheaper.into());
+        self
+    }
+
+    /// The cheaper alternative for `model`, if one is configured.
+    pub fn cheaper(&self, model: &str) -> Option<&str> {
+        self.entries.get(model).map(String::as_str)
+    }
+}
+
+impl Default for ModelDowngradeTable {
+    fn default() -> Self {
+        Self::empty()
+            .with_entry("gpt-4", "gpt-4o-mini")
+            .with_entry("gpt-4-turbo", "gpt-4o-mini")
+            .with_entry("gpt-4o", "gpt-4o-mini")
+            .with_entry("gpt-4.1", "gpt-4.1-mini")
+            .with_entry("o1", "o1-mini")
+            .with_entry("o3", "o3-mini")
+            .with_entry("claude-3-opus-20240229", "claude-3-5-sonnet-20241022")
+            .with_entry("claude-3-5-sonnet-20241022", "claude-3-5-haiku-20241022")
+            .with_entry("claude-opus-4-6", "claude-sonnet-4-6")
+            .with_entry("claude-sonnet-4-6", "claude-haiku-4-5-20251001")
+    }
+}
+
+/// Approximate list prices (USD per input token, USD per output token) used by
+/// [`CostRouter::with_default_prices`].
+pub const DEFAULT_MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-3.5-turbo", 0.000_000_5, 0.000_001_5),
+    ("gpt-4", 0.000_03, 0.000_06),
+    ("gpt-4-turbo", 0.000_01, 0.000_03),
+    ("gpt-4o", 0.000_002_5, 0.000_01),
+    ("gpt-4o-mini", 0.000_000_15, 0.000_000_6),
+    ("gpt-4.1", 0.000_002, 0.000_008),
+    ("gpt-4.1-mini", 0.000_000_4, 0.000_001_6),
+    ("o1", 0.000_015, 0.000_06),
+    ("o1-mini", 0.000_001_1, 0.000_004_4),
+    ("o3", 0.000_002, 0.000_008),
+    ("o3-mini", 0.000_001_1, 0.000_004_4),
+    ("claude-3-opus-20240229", 0.000_015, 0.000_075),
+    ("claude-3-5-sonnet-20241022", 0.000_003, 0.000_015),
+    ("claude-3-5-haiku-20241022", 0.000_000_8, 0.000_004),
+    ("claude-opus-4-6", 0.000_005, 0.000_025),
+    ("claude-sonnet-4-6", 0.000_003, 0.000_015),
+    ("claude-haiku-4-5-20251001", 0.000_001, 0.000_005),
+];
+
+// ---------------------------------------------------------------------------
+// CostRouter — budget-aware model selection for the interceptor
+// ---------------------------------------------------------------------------
+
+/// Budget-aware model selection shared between streams.
+///
+/// Wraps a [`CostOptimizer`] with a [`ModelDowngradeTable`].  Before a stream
+/// starts, [`CostRouter::route`] returns a cheaper model when budget pressure
+/// has reached `min_pressure`; after it ends, [`CostRouter::record`] charges the
+/// estimated cost so pressure reflects actual usage.
+pub struct CostRouter {
+    optimizer: std::sync::Mutex<CostOptimizer>,
+    table: ModelDowngradeTable,
+    min_pressure: BudgetPressure,
+}
+
+impl CostRouter {
+    /// Build a router with the given budget and downgrade table.
+    ///
+    /// Downgrades start once pressure reaches [`BudgetPressure::Warn`].
+    pub fn new(cfg: BudgetConfig, table: ModelDowngradeTable) -> Self {
+        Self {
+            optimizer: std::sync::Mutex::new(CostOptimizer::new(cfg, 0.1)),
+            table,
+            min_pressure: BudgetPressure::Warn,
+        }
+    }
+
+    /// Only downgrade once pressure reaches `pressure`.
+    pub fn with_min_pressure(mut self, pressure: BudgetPressure) -> Self {
+        self.min_pressure = pressure;
+        self
+    }
+
+    /// Register [`DEFAULT_MODEL_PRICES`] for every listed model.
+    pub fn with_default_prices(self) -> Self {
+        for (model, input, output) in DEFAULT_MODEL_PRICES {
+            self.set_price(model, TokenPrice::new(*input, *output));
+        }
+        self
+    }
+
+    /// Register or update the price model for `model`.
+    pub fn set_price(&self, model: &str, price: TokenPrice) {
+        if let Ok(mut opt) = self.optimizer.lock() {
+            opt.set_price(Backend::new(model), price);
+        }
+    }
+
+    /// Current budget pressure.
+    pub fn pressure(&self) -> BudgetPressure {
+        self.optimizer
+            .lock()
+            .map(|opt| opt.pressure())
+            .unwrap_or(BudgetPressure::Normal)
+    }
+
+    /// The model to use instead of `model`, or `None` to keep it.
+    pub fn route(&self, model: &str) -> Option<String> {
+        if self.pressure() < self.min_pressure {
+            return None;
+        }
+        self.table.cheaper(model).map(str::to_string)
+    }
+
+    /// Charge the estimated cost of a finished request against the budget.
+    pub fn record(&self, model: &str, input_tokens: u64, output_tokens: u64) {
+        if let Ok(mut opt) = self.optimizer.lock() {
+            let backend = Backend::new(model);
+            let estimated_usd = opt.estimate(&backend, input_tokens, output_tokens);
+            opt.record_request(RequestCost {
+                backend,
+                estimated_usd,
+                actual_usd: None,
+                input_tokens,
+                output_tokens,
+                quality_score: None,
+            });
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -902,4 +1055,80 @@ mod tests {
         let report = opt.backend_report();
         assert!((report[0].quality_ema - 0.75).abs() < 1e-9);
     }
+
+    // ===== ModelDowngradeTable / CostRouter =====
+
+    fn tiny_budget_router() -> CostRouter {
+        let router = CostRouter::new(
+            BudgetConfig {
+                ceiling_usd: 1.0,
+                ..BudgetConfig::default()
+            },
+            ModelDowngradeTable::default(),
+        );
+        router.set_price("gpt-4o", TokenPrice::new(0.001, 0.001));
+        router
+    }
+
+    #[test]
+    fn test_budget_pressure_ordering() {
+        assert!(BudgetPressure::Normal < BudgetPressure::Warn);
+        assert!(BudgetPressure::Warn < BudgetPressure::Critical);
+    }
+
+    #[test]
+    fn test_downgrade_table_default_maps_gpt4o() {
+        let t = ModelDowngradeTable::default();
+        assert_eq!(t.cheaper("gpt-4o"), Some("gpt-4o-mini"));
+        assert_eq!(t.cheaper("gpt-4o-mini"), None);
+    }
+
+    #[test]
+    fn test_downgrade_table_with_entry_overrides() {
+        let t = ModelDowngradeTable::default().with_entry("gpt-4o", "gpt-3.5-turbo");
+        assert_eq!(t.cheaper("gpt-4o"), Some("gpt-3.5-turbo"));
+    }
+
+    #[test]
+    fn test_cost_router_no_downgrade_under_normal_pressure() {
+        let router = tiny_budget_router();
+        assert_eq!(router.pressure(), BudgetPressure::Normal);
+        assert_eq!(router.route("gpt-4o"), None);
+    }
+
+    #[test]
+    fn test_cost_router_downgrades_under_high_pressure() {
+        let router = tiny_budget_router();
+        // 500 + 400 tokens at $0.001 each = $0.90 of a $1.00 budget → Warn.
+        router.record("gpt-4o", 500, 400);
+        assert_eq!(router.pressure(), BudgetPressure::Warn);
+        assert_eq!(router.route("gpt-4o").as_deref(), Some("gpt-4o-mini"));
+    }
+
+    #[test]
+    fn test_cost_router_respects_min_pressure() {
+        let router = tiny_budget_router().with_min_pressure(BudgetPressure::Critical);
+        router.record("gpt-4o", 500, 400);
+        assert_eq!(router.route("gpt-4o"), None);
+    }
+
+    #[test]
+    fn test_cost_router_default_prices_cover_downgrade_targets() {
+        let table = ModelDowngradeTable::default();
+        for (model, _, _) in DEFAULT_MODEL_PRICES {
+            if let Some(cheaper) = table.cheaper(model) {
+                assert!(
+                    DEFAULT_MODEL_PRICES.iter().any(|(m, _, _)| *m == cheaper),
+                    "no price for downgrade target {cheaper}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cost_router_unknown_model_not_downgraded() {
+        let router = tiny_budget_router();
+        router.record("gpt-4o", 1000, 1000);
+        assert_eq!(router.route("my-custom-model"), None);
+    }
 }
@@ -41,6 +41,9 @@ pub const WINDOW_1H_CAP: usize = 3_600;
 /// Broadcast channel capacity (number of snapshots in flight).
 pub const BUS_CHANNEL_CAP: usize = 256;
 
+/// Latency samples retained per [`PipelineStage`] for [`TelemetryBus::snapshot_stage`].
+pub const STAGE_WINDOW_CAP: usize = 1_024;
+
 // ---------------------------------------------------------------------------
 // Metric types
 // ---------------------------------------------------------------------------
@@ -52,6 +55,29 @@ pub struct LatencyObs {
     pub micros: u64,
 }
 
+/// Request-level metrics for one provider HTTP request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NetworkObs {
+    /// HTTP status code of the response.
+    pub status: u16,
+    /// Request body bytes sent.
+    pub request_bytes: u64,
+    /// Response body bytes received.
+    pub response_bytes: u64,
+    /// End-to-end request duration, in microseconds.
+    pub duration_us: u64,
+}
+
+/// Running totals over every [`NetworkObs`] recorded since startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NetworkTotals {
+    pub requests: u64,
+    /// Requests whose status was not 2xx.
+    pub failed: u64,
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+}
+
 /// Named pipeline stages whose metrics flow into the bus.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PipelineStage {
@@ -61,6 +87,9 @@ pub enum PipelineStage {
     Cache,
     Inference,
     CircuitBreaker,
+    /// Provider HTTP requests: one observation per stream, timed from send
+    /// until the response body is fully read (see [`NetworkObs`]).
+    Network,
     /// Any stage not explicitly named above.
     Other,
 }
@@ -74,6 +103,7 @@ impl std::fmt::Display for PipelineStage {
             PipelineStage::Cache => "cache",
             PipelineStage::Inference => "inference",
             PipelineStage::CircuitBreaker => "circuit_breaker",
+            PipelineStage::Network => "network",
             PipelineStage::Other => "other",
         };
         write!(f, "{s}")
@@ -146,21 +176,83 @@ impl RingBuffer {
 
     /// p95 of all stored samples, or `None` if empty.
     pub fn p95(&self) -> Option<f64> {
+        self.percentile(0.95)
+    }
+
+    /// Nearest-rank percentile `q` (0.0–1.0) of all stored samples, or `None`
+    /// if empty.
+    pub fn percentile(&self, q: f64) -> Option<f64> {
         if self.is_empty() {
             return None;
         }
         let mut v: Vec<f64> = self.iter().collect();
         v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-        let idx = ((v.len() as f64 * 0.95).ceil() as usize)
-            .saturating_sub(1)
-            .min(v.len() - 1);
-        Some(v[idx])
+        Some(nearest_rank(&v, q))
     }
 
     /// Maximum value, or `None` if empty.
     pub fn max(&self) -> Option<f64> {
         self.iter().reduce(f64::max)
     }
+
+    /// Minimum value, or `None` if empty.
+    pub fn min(&self) -> Option<f64> {
+        self.iter().reduce(f64::min)
+    }
+}
+
+/// Nearest-rank percentile `q` of the non-empty ascending slice `sorted`.
+fn nearest_rank(sorted: &[f64], q: f64) -> f64 {
+    let idx = ((sorted.len() as f64 * q.clamp(0.0, 1.0)).ceil() as usize)
+        .saturating_sub(1)
+        .min(sorted.len() - 1);
+    sorted[idx]
+}
+
+// ---------------------------------------------------------------------------
+// StageStats — per-stage latency distribution
+// ---------------------------------------------------------------------------
+
+/// Latency distribution for one [`PipelineStage`], in microseconds, over the
+/// most recent [`STAGE_WINDOW_CAP`] observations.
+///
+/// All values are `0.0` when the stage has no observations (`count == 0`).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StageStats {
+    /// Number of observations in the window.
+    pub count: usize,
+    /// Arithmetic mean.
+    pub mean_us: f64,
+    /// Median (nearest-rank).
+    pub p50_us: f64,
+    /// 95th percentile (nearest-rank).
+    pub p95_us: f64,
+    /// 99th percentile (nearest-rank).
+    pub p99_us: f64,
+    /// Smallest observation.
+    pub min_us: f64,
+    /// Largest observation.
+    pub max_us: f64,
+}
+
+impl StageStats {
+    /// Compute the distribution of `buf`.
+    pub fn from_samples(buf: &RingBuffer) -> Self {
+        let mut v: Vec<f64> = buf.iter().collect();
+        if v.is_empty() {
+            return Self::default();
+        }
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        Self {
+            count: v.len(),
+            mean_us: v.iter().sum::<f64>() / v.len() as f64,
+            p50_us: nearest_rank(&v, 0.50),
+            p95_us: nearest_rank(&v, 0.95),
+            p99_us: nearest_rank(&v, 0.99),
+            min_us: v[0],
+            max_us: v[v.len() - 1],
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -332,6 +424,13 @@ struct BusState {
     // Per-interval accumulator reset on each snapshot
     accumulator: Mutex<StageAccumulator>,
 
+    // Recent raw latencies per stage, for `snapshot_stage`
+    stage_latencies: Mutex<std::collections::HashMap<PipelineStage, RingBuffer>>,
+
+    // Most recent provider requests, for `recent_network`
+    network: Mutex<std::collections::VecDeque<NetworkObs>>,
+    network_totals: std::sync::Mutex<NetworkTotals>,
+
     // Latest snapshot, updated each interval
     latest: RwLock<TelemetrySnapshot>,
 
@@ -381,6 +480,9 @@ impl TelemetryBus {
             window_15m: Mutex::new(RingBuffer::new(WINDOW_15M_CAP)),
             window_1h: Mutex::new(RingBuffer::new(WINDOW_1H_CAP)),
             accumulator: Mutex::new(StageAccumulator::default()),
+            stage_latencies: Mutex::new(std::collections::HashMap::new()),
+            network: Mutex::new(std::collections::VecDeque::new()),
+            network_totals: std::sync::Mutex::new(NetworkTotals::default()),
             latest: RwLock::new(TelemetrySnapshot::zero()),
             tx,
             circuit_open: std::sync::atomic::AtomicBool::new(false),
@@ -416,12 +518,42 @@ impl TelemetryBus {
     }
 
     /// Record a latency observation from a pipeline stage (non-blocking).
-    pub fn record_latency(&self, _stage: PipelineStage, micros: u64) {
+    pub fn record_latency(&self, stage: PipelineStage, micros: u64) {
         self.inner.total_requests.fetch_add(1, Ordering::Relaxed);
         // Try to update accumulator; if lock contended, skip (best-effort telemetry)
         if let Ok(mut acc) = self.inner.accumulator.try_lock() {
             acc.record_latency(micros);
         }
+        if let Ok(mut stages) = self.inner.stage_latencies.try_lock() {
+            stages
+                .entry(stage)
+                .or_insert_with(|| RingBuffer::new(STAGE_WINDOW_CAP))
+                .push(micros as f64);
+        }
+    }
+
+    /// Record one provider request (non-blocking).
+    ///
+    /// The duration is also recorded as a [`PipelineStage::Network`] latency,
+    /// and a non-2xx status counts as a pipeline error.
+    pub fn record_network(&self, obs: NetworkObs) {
+        self.record_latency(PipelineStage::Network, obs.duration_us);
+        let failed = !(200..300).contains(&obs.status);
+        if failed {
+            self.record_error(PipelineStage::Network);
+        }
+        if let Ok(mut totals) = self.inner.network_totals.lock() {
+            totals.requests += 1;
+            totals.failed += u64::from(failed);
+            totals.request_bytes = totals.request_bytes.saturating_add(obs.request_bytes);
+            totals.response_bytes = totals.response_bytes.saturating_add(obs.response_bytes);
+        }
+        if let Ok(mut recent) = self.inner.network.try_lock() {
+            if recent.len() >= STAGE_WINDOW_CAP {
+                recent.pop_front();
+            }
+            recent.push_back(obs);
+        }
     }
 
     /// Record a dropped request.
@@ -465,6 +597,34 @@ impl TelemetryBus {
         self.inner.latest.read().await.clone()
     }
 
+    /// Current latency distribution for `stage`, independent of the emitter.
+    ///
+    /// Covers the last [`STAGE_WINDOW_CAP`] observations recorded via
+    /// [`Self::record_latency`]; returns all zeros for an unseen stage.
+    pub async fn snapshot_stage(&self, stage: PipelineStage) -> StageStats {
+        self.inner
+            .stage_latencies
+            .lock()
+            .await
+            .get(&stage)
+            .map(StageStats::from_samples)
+            .unwrap_or_default()
+    }
+
+    /// The last [`STAGE_WINDOW_CAP`] provider requests, oldest first.
+    pub async fn recent_network(&self) -> Vec<NetworkObs> {
+        self.inner.network.lock().await.iter().copied().collect()
+    }
+
+    /// Totals over every provider request recorded since startup.
+    pub fn network_totals(&self) -> NetworkTotals {
+        self.inner
+            .network_totals
+            .lock()
+            .map(|t| *t)
+            .unwrap_or_default()
+    }
+
     // --- internal ---
 
     async fn emit_snapshot(&self) {
@@ -849,6 +1009,45 @@ mod tests {
         assert_eq!(bus.inner.circuit_trips.load(Ordering::Relaxed), 0);
     }
 
+    #[tokio::test]
+    async fn test_snapshot_stage_percentiles_over_known_series() {
+        let bus = default_bus();
+        for us in 1..=100u64 {
+            bus.record_latency(PipelineStage::Inference, us * 10);
+        }
+        bus.record_latency(PipelineStage::Cache, 7);
+
+        let stats = bus.snapshot_stage(PipelineStage::Inference).await;
+        assert_eq!(stats.count, 100);
+        assert!((stats.mean_us - 505.0).abs() < 1e-9);
+        assert_eq!(stats.p50_us, 500.0);
+        assert_eq!(stats.p95_us, 950.0);
+        assert_eq!(stats.p99_us, 990.0);
+        assert_eq!(stats.min_us, 10.0);
+        assert_eq!(stats.max_us, 1000.0);
+
+        // Stages are tracked independently.
+        let cache = bus.snapshot_stage(PipelineStage::Cache).await;
+        assert_eq!((cache.count, cache.p99_us), (1, 7.0));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_stage_unseen_is_zero() {
+        let bus = default_bus();
+        assert_eq!(bus.snapshot_stage(PipelineStage::Dedup).await, StageStats::default());
+    }
+
+    #[test]
+    fn test_ringbuffer_percentile_and_min() {
+        let mut rb = RingBuffer::new(10);
+        for v in [5.0, 1.0, 4.0, 2.0, 3.0] {
+            rb.push(v);
+        }
+        assert_eq!(rb.percentile(0.5), Some(3.0));
+        assert_eq!(rb.min(), Some(1.0));
+        assert_eq!(RingBuffer::new(1).percentile(0.5), None);
+    }
+
     #[tokio::test]
     async fn test_bus_emit_snapshot_broadcasts() {
         let bus = TelemetryBus::new(BusConfig {
@@ -962,6 +1161,23 @@ mod tests {
         assert!((snap.queue_fill_frac - 1.0).abs() < 1e-9);
     }
 
+    #[tokio::test]
+    async fn test_record_network_accumulates_totals_and_errors() {
+        let bus = default_bus();
+        bus.record_network(NetworkObs { status: 200, request_bytes: 100, response_bytes: 900, duration_us: 5_000 });
+        bus.record_network(NetworkObs { status: 429, request_bytes: 100, response_bytes: 40, duration_us: 1_000 });
+        let totals = bus.network_totals();
+        assert_eq!(
+            totals,
+            NetworkTotals { requests: 2, failed: 1, request_bytes: 200, response_bytes: 940 }
+        );
+        assert_eq!(bus.recent_network().await[1].status, 429);
+        let stats = bus.snapshot_stage(PipelineStage::Network).await;
+        assert_eq!((stats.count, stats.max_us), (2, 5_000.0));
+        assert_eq!(bus.inner.total_errors.load(Ordering::Relaxed), 1);
+        assert_eq!(PipelineStage::Network.to_string(), "network");
+    }
+
     #[tokio::test]
     async fn test_bus_latest_returns_last_snapshot() {
         let bus = TelemetryBus::new(BusConfig {
@@ -0,0 +1,224 @@
+//! Token search over a buffered streaming session.
+//!
+//! Backs `GET /api/session/search?q=...&room=CODE`.  A query is either a plain
+//! substring matched case-insensitively against each token's `original` text,
+//! or a numeric range over one of the token metrics:
+//!
+//! | Query | Matches |
+//! |-------|---------|
+//! | `the` | tokens whose original text contains "the" |
+//! | `perplexity:2..5` | perplexity in `[2, 5]` |
+//! | `ppl:>3` | perplexity strictly above 3 |
+//! | `confidence:..0.3` | confidence at most 0.3 |
+//! | `conf:<0.5` | confidence strictly below 0.5 |
+//!
+//! Tokens are the JSON-serialized [`crate::TokenEvent`]s retained by
+//! [`crate::collab::record_session_token`]; matches are reported by their
+//! `index` field.
+
+/// A token metric that can be range-filtered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Perplexity,
+    Confidence,
+}
+
+impl Metric {
+    fn field(self) -> &'static str {
+        match self {
+            Metric::Perplexity => "perplexity",
+            Metric::Confidence => "confidence",
+        }
+    }
+}
+
+/// A numeric bound; `exclusive` bounds come from `<` / `>` syntax.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenQuery {
+    /// Case-insensitive substring of the token's original text.
+    Text(String),
+    /// Metric value within the (optionally open-ended) range.
+    Range {
+        metric: Metric,
+        min: Option<Bound>,
+        max: Option<Bound>,
+    },
+}
+
+impl TokenQuery {
+    /// Parse a query string.  Returns an error for an empty query or a
+    /// malformed metric range; anything without a known `metric:` prefix is a
+    /// substring query.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let q = raw.trim();
+        if q.is_empty() {
+            return Err("query must not be empty".to_string());
+        }
+        let Some((prefix, spec)) = q.split_once(':') else {
+            return Ok(TokenQuery::Text(q.to_lowercase()));
+        };
+        let metric = match prefix.trim().to_lowercase().as_str() {
+            "perplexity" | "ppl" => Metric::Perplexity,
+            "confidence" | "conf" => Metric::Confidence,
+            _ => return Ok(TokenQuery::Text(q.to_lowercase())),
+        };
+        let (min, max) = parse_range(spec.trim())?;
+        if min.is_none() && max.is_none() {
+            return Err(format!("range for {} needs at least one bound", metric.field()));
+        }
+        Ok(TokenQuery::Range { metric, min, max })
+    }
+
+    /// `true` if the JSON token `token` satisfies this query.
+    pub fn matches(&self, token: &serde_json::Value) -> bool {
+        match self {
+            TokenQuery::Text(needle) => token
+                .get("original")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_lowercase().contains(needle.as_str()))
+                .unwrap_or(false),
+            TokenQuery::Range { metric, min, max } => {
+                let Some(v) = token.get(metric.field()).and_then(|v| v.as_f64()) else {
+                    return false;
+                };
+                let above_min = match min {
+                    Some(b) if b.exclusive => v > b.value,
+                    Some(b) => v >= b.value,
+                    None => true,
+                };
+                let below_max = match max {
+                    Some(b) if b.exclusive => v < b.value,
+                    Some(b) => v <= b.value,
+                    None => true,
+                };
+                above_min && below_max
+            }
+        }
+    }
+}
+
+/// Parse `a..b`, `a..`, `..b`, `>a`, `<b`, `>=a`, `<=b`, or a single value `a`.
+fn parse_range(spec: &str) -> Result<(Option<Bound>, Option<Bound>), String> {
+    let num = |s: &str| -> Result<f64, String> {
+        s.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("invalid number '{}'", s.trim()))
+    };
+    let inclusive = |value| Bound { value, exclusive: false };
+    let exclusive = |value| Bound { value, exclusive: true };
+
+    if let Some((lo, hi)) = spec.split_once("..") {
+        let min = if lo.trim().is_empty() { None } else { Some(inclusive(num(lo)?)) };
+        let max = if hi.trim().is_empty() { None } else { Some(inclusive(num(hi)?)) };
+        if let (Some(a), Some(b)) = (min, max) {
+            if a.value > b.value {
+                return Err(format!("empty range {}..{}", a.value, b.value));
+            }
+        }
+        return Ok((min, max));
+    }
+    if let Some(rest) = spec.strip_prefix(">=") {
+        return Ok((Some(inclusive(num(rest)?)), None));
+    }
+    if let Some(rest) = spec.strip_prefix("<=") {
+        return Ok((None, Some(inclusive(num(rest)?))));
+    }
+    if let Some(rest) = spec.strip_prefix('>') {
+        return Ok((Some(exclusive(num(rest)?)), None));
+    }
+    if let Some(rest) = spec.strip_prefix('<') {
+        return Ok((None, Some(exclusive(num(rest)?))));
+    }
+    let v = num(spec)?;
+    Ok((Some(inclusive(v)), Some(inclusive(v))))
+}
+
+/// Return the `index` of every token in `tokens` matching `query`, in order.
+pub fn search(tokens: &[serde_json::Value], query: &TokenQuery) -> Vec<usize> {
+    tokens
+        .iter()
+        .filter(|t| query.matches(t))
+        .filter_map(|t| t.get("index").and_then(|v| v.as_u64()).map(|i| i as usize))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens() -> Vec<serde_json::Value> {
+        vec![
+            serde_json::json!({"index": 0, "original": "The", "perplexity": 1.2, "confidence": 0.9}),
+            serde_json::json!({"index": 1, "original": " quick", "perplexity": 4.5, "confidence": 0.3}),
+            serde_json::json!({"index": 2, "original": " brown", "perplexity": 2.0, "confidence": 0.6}),
+            serde_json::json!({"index": 3, "original": " theory", "perplexity": null, "confidence": null}),
+            serde_json::json!({"index": 4, "original": " fox", "perplexity": 8.0, "confidence": 0.1}),
+        ]
+    }
+
+    fn run(q: &str) -> Vec<usize> {
+        search(&tokens(), &TokenQuery::parse(q).expect("parse"))
+    }
+
+    #[test]
+    fn test_substring_case_insensitive() {
+        assert_eq!(run("the"), vec![0, 3]);
+        assert_eq!(run("FOX"), vec![4]);
+    }
+
+    #[test]
+    fn test_substring_no_match() {
+        assert!(run("zebra").is_empty());
+    }
+
+    #[test]
+    fn test_unknown_prefix_is_substring() {
+        assert_eq!(TokenQuery::parse("http://x").expect("parse"), TokenQuery::Text("http://x".into()));
+    }
+
+    #[test]
+    fn test_perplexity_closed_range() {
+        assert_eq!(run("perplexity:2..5"), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_perplexity_open_ranges() {
+        assert_eq!(run("ppl:4.5.."), vec![1, 4]);
+        assert_eq!(run("ppl:..2"), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_exclusive_bounds() {
+        assert_eq!(run("ppl:>4.5"), vec![4]);
+        assert_eq!(run("conf:<0.3"), vec![4]);
+        assert_eq!(run("conf:<=0.3"), vec![1, 4]);
+    }
+
+    #[test]
+    fn test_confidence_range_skips_missing_values() {
+        assert_eq!(run("confidence:0..1"), vec![0, 1, 2, 4]);
+    }
+
+    #[test]
+    fn test_single_value_is_exact_match() {
+        assert_eq!(run("ppl:2"), vec![2]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(TokenQuery::parse("   ").is_err());
+        assert!(TokenQuery::parse("ppl:abc").is_err());
+        assert!(TokenQuery::parse("ppl:..").is_err());
+        assert!(TokenQuery::parse("ppl:5..2").is_err());
+        assert!(TokenQuery::parse("conf:NaN").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Longest-common-subsequence alignment of two token streams.
+//!
+//! Shared by `/diff-stream`'s `diff_summary` event and the A/B experiment
+//! export ([`crate::ab_export`]), so both report the same match for the same
+//! pair of streams.  Callers compare whatever token key suits them, typically
+//! the trimmed original (pre-transform) text.
+
+/// Align `a` and `b` by longest common subsequence: matched positions pair
+/// up, and every unmatched token gets a row of its own, in stream order.
+///
+/// The table is `a × b` cells of `u16`, so each side must be shorter than
+/// 65 536 tokens; callers cap their input well below that.
+pub fn align(a: &[String], b: &[String]) -> Vec<(Option<usize>, Option<usize>)> {
+    debug_assert!(a.len() < u16::MAX as usize && b.len() < u16::MAX as usize);
+    // lcs[i][j] = LCS length of a[i..] and b[j..].
+    let width = b.len() + 1;
+    let mut lcs = vec![0u16; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut pairs = Vec::with_capacity(a.len().max(b.len()));
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((Some(i), Some(j)));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            pairs.push((Some(i), None));
+            i += 1;
+        } else {
+            pairs.push((None, Some(j)));
+            j += 1;
+        }
+    }
+    pairs.extend((i..a.len()).map(|i| (Some(i), None)));
+    pairs.extend((j..b.len()).map(|j| (None, Some(j))));
+    pairs
+}
+
+/// Length of the longest common subsequence of `a` and `b`: the number of
+/// matched rows [`align`] would produce, in `O(b)` memory.
+pub fn lcs_len(a: &[String], b: &[String]) -> usize {
+    let mut prev = vec![0usize; b.len() + 1];
+    let mut curr = vec![0usize; b.len() + 1];
+    for x in a {
+        for (j, y) in b.iter().enumerate() {
+            curr[j + 1] = if x == y {
+                prev[j] + 1
+            } else {
+                prev[j + 1].max(curr[j])
+            };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/// `matched` tokens as a percentage of the longer of two streams of
+/// `a_len` and `b_len` tokens, to one decimal; `0.0` when both are empty.
+pub fn match_pct(matched: usize, a_len: usize, b_len: usize) -> f64 {
+    let longest = a_len.max(b_len);
+    if longest == 0 {
+        0.0
+    } else {
+        (matched as f64 / longest as f64 * 1000.0).round() / 10.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: &[&str]) -> Vec<String> {
+        v.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_lcs_len() {
+        assert_eq!(lcs_len(&s(&["a", "b", "c", "d"]), &s(&["b", "x", "d"])), 2);
+        assert_eq!(lcs_len(&s(&[]), &s(&["a"])), 0);
+    }
+
+    #[test]
+    fn test_align_pairs_matches_and_keeps_order() {
+        let (a, b) = (s(&["a", "b", "c", "d"]), s(&["b", "x", "d"]));
+        let pairs = align(&a, &b);
+        assert_eq!(
+            pairs,
+            vec![(Some(0), None), (Some(1), Some(0)), (Some(2), None), (None, Some(1)), (Some(3), Some(2))]
+        );
+        let matched = pairs.iter().filter(|(x, y)| x.is_some() && y.is_some()).count();
+        assert_eq!(matched, lcs_len(&a, &b));
+    }
+
+    #[test]
+    fn test_match_pct() {
+        assert_eq!(match_pct(2, 4, 3), 50.0);
+        assert_eq!(match_pct(1, 3, 2), 33.3);
+        assert_eq!(match_pct(0, 0, 0), 0.0);
+    }
+}
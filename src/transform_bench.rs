@@ -0,0 +1,158 @@
+//! Transform throughput (`--bench-transforms`).
+//!
+//! Feeds a fixed, offline input through
+//! [`TokenInterceptor::process_content_logprob`] once per transform and
+//! reports tokens per second.  No provider is called: tokens go to a closed
+//! web channel, so the numbers cover selection, transformation and
+//! [`TokenEvent`](crate::TokenEvent) construction but not terminal output.
+//! `benches/transforms.rs` measures the same path under criterion.
+
+use std::time::{Duration, Instant};
+
+use crate::providers::Provider;
+use crate::transforms::Transform;
+use crate::TokenInterceptor;
+
+/// Words in the default `--bench-transforms` input (punctuation makes the
+/// token count somewhat higher).
+pub const DEFAULT_BENCH_WORDS: usize = 20_000;
+
+/// Words per simulated provider delta.
+pub const CHUNK_WORDS: usize = 8;
+
+/// Logprob attached to every delta, so confidence-driven importance runs.
+const CHUNK_LOGPROB: f32 = -0.3;
+
+const CORPUS: &[&str] = &[
+    "The", "quick", "brown", "fox", "jumps", "over", "the", "lazy", "dog.", "Language", "models",
+    "predict", "one", "token", "at", "a", "time,", "and", "every", "other", "token", "is", "rewritten",
+    "in", "flight", "before", "it", "reaches", "the", "reader.",
+];
+
+/// `words` words of deterministic English-like text, split into deltas of
+/// [`CHUNK_WORDS`] words the way a provider stream would arrive.
+pub fn bench_chunks(words: usize) -> Vec<String> {
+    let text: Vec<&str> = CORPUS.iter().cycle().take(words).copied().collect();
+    text.chunks(CHUNK_WORDS)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let joined = chunk.join(" ");
+            if i == 0 {
+                joined
+            } else {
+                format!(" {}", joined)
+            }
+        })
+        .collect()
+}
+
+/// An offline interceptor for `transform`: mock provider, fixed seed,
+/// deterministic importance, and a web channel whose receiver is already
+/// dropped so nothing is printed.
+pub fn bench_interceptor(transform: Transform) -> TokenInterceptor {
+    let mut interceptor = TokenInterceptor::new(Provider::Mock, transform, "mock".to_string(), false, false, false)
+        .expect("the mock provider needs no API key")
+        .with_seed(0);
+    interceptor.deterministic_importance = true;
+    let (tx, _) = tokio::sync::mpsc::unbounded_channel();
+    interceptor.web_tx = Some(tx);
+    interceptor
+}
+
+/// Run every chunk through `interceptor`, returning the tokens processed.
+pub fn process_chunks(interceptor: &mut TokenInterceptor, chunks: &[String]) -> usize {
+    let before = interceptor.token_count;
+    for chunk in chunks {
+        interceptor.process_content_logprob(chunk, Some(CHUNK_LOGPROB), vec![]);
+    }
+    interceptor.token_count - before
+}
+
+/// Throughput of one transform.
+#[derive(Debug, Clone)]
+pub struct BenchRow {
+    pub transform: String,
+    pub tokens: usize,
+    pub elapsed: Duration,
+}
+
+impl BenchRow {
+    /// Tokens processed per second; `0.0` for an empty run.
+    pub fn tokens_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if self.tokens == 0 || secs == 0.0 {
+            0.0
+        } else {
+            self.tokens as f64 / secs
+        }
+    }
+}
+
+/// Time every transform in [`Transform::all`] over `words` words of input.
+pub fn run(words: usize) -> Vec<BenchRow> {
+    let chunks = bench_chunks(words);
+    Transform::all()
+        .into_iter()
+        .map(|transform| {
+            let name = transform.name();
+            let mut interceptor = bench_interceptor(transform);
+            let start = Instant::now();
+            let tokens = process_chunks(&mut interceptor, &chunks);
+            BenchRow { transform: name, tokens, elapsed: start.elapsed() }
+        })
+        .collect()
+}
+
+/// Plain-text table of `rows`, fastest first.
+pub fn report(rows: &[BenchRow]) -> String {
+    let mut rows: Vec<&BenchRow> = rows.iter().collect();
+    rows.sort_by(|a, b| b.tokens_per_sec().total_cmp(&a.tokens_per_sec()));
+    let width = rows.iter().map(|r| r.transform.len()).max().unwrap_or(0).max("transform".len());
+    let mut out = format!("{:<width$}  {:>8}  {:>10}  {:>12}\n", "transform", "tokens", "ms", "tokens/sec", width = width);
+    for r in rows {
+        out.push_str(&format!(
+            "{:<width$}  {:>8}  {:>10.2}  {:>12.0}\n",
+            r.transform,
+            r.tokens,
+            r.elapsed.as_secs_f64() * 1000.0,
+            r.tokens_per_sec(),
+            width = width
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_chunks_cover_requested_words() {
+        let chunks = bench_chunks(20);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[0].starts_with("The quick"));
+        assert!(chunks[1].starts_with(' '));
+        assert_eq!(chunks.concat().split_whitespace().count(), 20);
+    }
+
+    #[test]
+    fn test_every_transform_processes_baseline_input_within_bound() {
+        // Regression guard, deliberately generous for debug builds on slow
+        // CI machines: a release build does this in a few milliseconds.
+        let rows = run(5_000);
+        assert_eq!(rows.len(), Transform::all().len());
+        for row in &rows {
+            // Punctuation splits off as its own token.
+            assert!(row.tokens >= 5_000, "{} processed every word", row.transform);
+            assert!(
+                row.elapsed < Duration::from_secs(5),
+                "{} took {:?} for 5000 tokens",
+                row.transform,
+                row.elapsed
+            );
+        }
+        let table = report(&rows);
+        assert!(table.starts_with("transform"));
+        assert_eq!(table.lines().count(), rows.len() + 1);
+    }
+}
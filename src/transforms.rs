@@ -11,12 +11,19 @@
 //! | `reverse` | Reverses the characters of the token |
 //! | `uppercase` | Converts the token to uppercase |
 //! | `mock` | Applies alternating lower/upper case per character |
-//! | `noise` | Appends a random symbol from `* + ~ @ # $ %` |
+//! | `noise` | Appends a random symbol from `* + ~ @ # $ %` (`--noise-chars`, `--noise-count`) |
+//! | `zalgo` | Stacks random combining marks above and below each character (`--zalgo-intensity`) |
+//! | `hashnoise` | Appends noise symbols chosen by hashing the token, so equal tokens get equal noise |
+//! | `nato` | Spells each letter and digit with the NATO phonetic alphabet (`ab` → `Alpha Bravo`) |
 //! | `chaos` | Randomly selects one of the above per call |
 //! | `scramble` | Fisher-Yates shuffles the token's characters |
 //! | `delete` | Replaces the token with the empty string |
 //! | `synonym` | Substitutes the token with a static synonym, if known |
+//! | `dictionary` | Substitutes the token from a user-supplied mapping (`--dict PATH`) |
 //! | `delay:N` | Passes the token through after an N-millisecond pause |
+//!
+//! A [`WindowTransform`] such as [`Stutter`] also sees the tokens before the
+//! one it rewrites (`--window-transform`).
 
 use colored::*;
 use once_cell::sync::Lazy;
@@ -24,7 +31,186 @@ use rand::Rng;
 use std::collections::HashMap;
 use std::sync::Mutex;
 
-const NOISE_CHARS: [char; 7] = ['*', '+', '~', '@', '#', '$', '%'];
+/// Default symbols appended by [`Transform::Noise`].
+pub const NOISE_CHARS: [char; 7] = ['*', '+', '~', '@', '#', '$', '%'];
+
+/// Symbol set and count for [`Transform::Noise`] (configurable via
+/// `--noise-chars` / `--noise-count`).  Chaos's noise branch uses it too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoiseConfig {
+    chars: Vec<char>,
+    count: usize,
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        NoiseConfig { chars: NOISE_CHARS.to_vec(), count: 1 }
+    }
+}
+
+impl NoiseConfig {
+    /// Append `count` symbols, each drawn from `chars`.
+    ///
+    /// # Errors
+    /// Returns an error if `chars` is empty or `count` is zero.
+    pub fn new(chars: &str, count: usize) -> Result<Self, String> {
+        let chars: Vec<char> = chars.chars().collect();
+        if chars.is_empty() {
+            return Err("noise character set must not be empty".to_string());
+        }
+        if count == 0 {
+            return Err("noise count must be at least 1".to_string());
+        }
+        Ok(NoiseConfig { chars, count })
+    }
+
+    /// The symbols noise draws from.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// How many symbols are appended per transformed token.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// `token` followed by `count` symbols drawn from `rng`.
+    pub fn apply<R: Rng>(&self, token: &str, rng: &mut R) -> String {
+        let mut out = String::with_capacity(token.len() + self.count * 4);
+        out.push_str(token);
+        for _ in 0..self.count {
+            out.push(self.chars[rng.gen_range(0..self.chars.len())]);
+        }
+        out
+    }
+
+    /// `token` followed by `count` symbols chosen by a 32-bit FNV-1a hash of
+    /// its UTF-8 bytes, so the same token always gets the same noise.  Each
+    /// further symbol multiplies the hash by the FNV prime again; the web
+    /// UI's `hashnoise` mirror computes the same sequence.
+    pub fn apply_hashed(&self, token: &str) -> String {
+        let mut h = fnv1a_32(token.as_bytes());
+        let mut out = String::with_capacity(token.len() + self.count * 4);
+        out.push_str(token);
+        for _ in 0..self.count {
+            out.push(self.chars[h as usize % self.chars.len()]);
+            h = h.wrapping_mul(FNV_PRIME_32);
+        }
+        out
+    }
+}
+
+const FNV_OFFSET_32: u32 = 0x811c_9dc5;
+const FNV_PRIME_32: u32 = 0x0100_0193;
+
+fn fnv1a_32(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(FNV_OFFSET_32, |h, &b| (h ^ b as u32).wrapping_mul(FNV_PRIME_32))
+}
+
+/// Command-line settings of the parameterised transforms, passed to
+/// [`Transform::apply_with_options_rng`].  Each interceptor (and each web
+/// server or room store) carries its own, so runs in one process can differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformOptions {
+    /// Symbol set and count for Noise, HashNoise and Chaos's noise branches.
+    pub noise: NoiseConfig,
+    /// Combining marks Zalgo (and Chaos's zalgo branch) adds per character.
+    pub zalgo_intensity: usize,
+}
+
+impl Default for TransformOptions {
+    fn default() -> Self {
+        Self {
+            noise: NoiseConfig::default(),
+            zalgo_intensity: DEFAULT_ZALGO_INTENSITY,
+        }
+    }
+}
+
+/// Options used by the entry points that take none.
+static DEFAULT_OPTIONS: Lazy<TransformOptions> = Lazy::new(TransformOptions::default);
+
+/// Default number of combining marks [`Transform::Zalgo`] adds per character.
+pub const DEFAULT_ZALGO_INTENSITY: usize = 3;
+
+/// Combining marks drawn above the base character (U+0300..=U+0314).
+const ZALGO_ABOVE: std::ops::RangeInclusive<u32> = 0x0300..=0x0314;
+/// Combining marks drawn below the base character (U+0316..=U+0333).
+const ZALGO_BELOW: std::ops::RangeInclusive<u32> = 0x0316..=0x0333;
+
+/// Follow every non-whitespace character of `token` with `marks`
+/// combining marks, each randomly above or below.
+fn apply_zalgo<R: Rng>(token: &str, rng: &mut R, marks: usize) -> String {
+    let mut out = String::with_capacity(token.len() * (1 + marks * 2));
+    for c in token.chars() {
+        out.push(c);
+        if c.is_whitespace() {
+            continue;
+        }
+        for _ in 0..marks {
+            let range = if rng.gen_bool(0.5) { ZALGO_ABOVE } else { ZALGO_BELOW };
+            // Both ranges are assigned combining marks, so this never fails.
+            if let Some(mark) = char::from_u32(rng.gen_range(range)) {
+                out.push(mark);
+            }
+        }
+    }
+    out
+}
+
+/// Remove the combining marks [`Transform::Zalgo`] adds (the whole
+/// U+0300..=U+036F block), recovering the original characters.
+pub fn strip_combining_marks(s: &str) -> String {
+    s.chars().filter(|c| !('\u{0300}'..='\u{036F}').contains(c)).collect()
+}
+
+/// NATO phonetic words for `a`..=`z`, in order.
+pub const NATO_ALPHABET: [&str; 26] = [
+    "Alpha", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
+    "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango",
+    "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+/// Spoken forms of `0`..=`9`, in order.
+pub const NATO_DIGITS: [&str; 10] = [
+    "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine",
+];
+
+/// The phonetic word for an ASCII letter (either case) or digit.
+fn nato_word(c: char) -> Option<&'static str> {
+    match c {
+        'a'..='z' => Some(NATO_ALPHABET[(c as u8 - b'a') as usize]),
+        'A'..='Z' => Some(NATO_ALPHABET[(c as u8 - b'A') as usize]),
+        '0'..='9' => Some(NATO_DIGITS[(c as u8 - b'0') as usize]),
+        _ => None,
+    }
+}
+
+/// Replace each ASCII letter and digit of `token` with its phonetic word.
+/// Adjacent words are space-separated; every other character passes
+/// through in place, so `" a-b!"` becomes `" Alpha-Bravo!"`.
+fn apply_nato(token: &str) -> String {
+    let mut out = String::with_capacity(token.len() * 6);
+    let mut after_word = false;
+    for c in token.chars() {
+        match nato_word(c) {
+            Some(word) => {
+                if after_word {
+                    out.push(' ');
+                }
+                out.push_str(word);
+                after_word = true;
+            }
+            None => {
+                out.push(c);
+                after_word = false;
+            }
+        }
+    }
+    out
+}
 
 static SYNONYM_MAP: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     let mut m = HashMap::new();
@@ -281,6 +467,76 @@ fn synonym_lookup(token: &str) -> Option<String> {
     SYNONYM_MAP.get(lower.as_str()).map(|s| s.to_string())
 }
 
+/// User-supplied token replacement map backing [`Transform::Dictionary`].
+///
+/// Lookups ignore surrounding whitespace, so a `{"cat": "dog"}` entry turns the
+/// streamed token `" cat"` into `" dog"`.  When built case-insensitively, keys
+/// are folded to lowercase and tokens are matched on their lowercase form.
+#[derive(Debug, Clone, Default)]
+pub struct ReplacementDictionary {
+    entries: HashMap<String, String>,
+    case_sensitive: bool,
+}
+
+impl ReplacementDictionary {
+    /// Build a dictionary from an inline `from -> to` map.
+    pub fn new(map: HashMap<String, String>, case_sensitive: bool) -> Self {
+        let entries = if case_sensitive {
+            map
+        } else {
+            map.into_iter().map(|(k, v)| (k.to_lowercase(), v)).collect()
+        };
+        Self { entries, case_sensitive }
+    }
+
+    /// Parse a JSON object of `{"from": "to"}` pairs.
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if `json` is not an object of string values.
+    pub fn from_json(json: &str, case_sensitive: bool) -> Result<Self, String> {
+        let map: HashMap<String, String> = serde_json::from_str(json)
+            .map_err(|e| format!("dictionary must be a JSON object of strings: {e}"))?;
+        Ok(Self::new(map, case_sensitive))
+    }
+
+    /// Load a JSON mapping file (see [`from_json`](Self::from_json)).
+    ///
+    /// # Errors
+    /// Returns `Err(String)` if the file cannot be read or parsed.
+    pub fn from_file(path: &str, case_sensitive: bool) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read dictionary '{path}': {e}"))?;
+        Self::from_json(&content, case_sensitive)
+    }
+
+    /// Number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// `true` when the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Replacement for `token`, preserving its surrounding whitespace, or
+    /// `None` when the token is not in the map.
+    pub fn lookup(&self, token: &str) -> Option<String> {
+        let core = token.trim();
+        if core.is_empty() {
+            return None;
+        }
+        let replacement = if self.case_sensitive {
+            self.entries.get(core)
+        } else {
+            self.entries.get(&core.to_lowercase())
+        }?;
+        let start = token.len() - token.trim_start().len();
+        let end = start + core.len();
+        Some(format!("{}{}{}", &token[..start], replacement, &token[end..]))
+    }
+}
+
 /// The set of token mutation strategies available at the interception layer.
 ///
 /// Each variant describes a different way to perturb a token in the stream.
@@ -294,12 +550,18 @@ fn synonym_lookup(token: &str) -> Option<String> {
 /// | `Reverse` | Reverses the Unicode characters of the token: `"hello"` -> `"olleh"`. |
 /// | `Uppercase` | Uppercases every character: `"hello"` -> `"HELLO"`. |
 /// | `Mock` | Alternates lowercase/uppercase per character position: `"hello"` -> `"hElLo"`. |
-/// | `Noise` | Appends one random symbol from `* + ~ @ # $ %`: `"hello"` -> `"hello*"`. |
-/// | `Chaos` | Randomly picks one of Reverse, Uppercase, Mock, or Noise per token. |
+/// | `Noise` | Appends one random symbol from `* + ~ @ # $ %`: `"hello"` -> `"hello*"`.  Set and count come from [`TransformOptions::noise`]. |
+/// | `Spongebob` | Randomly cases each character, leaning uppercase: `"hello"` -> `"HeLLo"`. Reproducible with a seeded RNG. |
+/// | `Zalgo` | Adds random combining marks above/below each character ("cursed text"); `--zalgo-intensity` marks per character. |
+/// | `HashNoise` | Appends noise symbols chosen by hashing the token (same token, same noise; no seed needed). |
+/// | `Nato` | Spells letters and digits with the NATO phonetic alphabet (`ab` → `Alpha Bravo`). |
+/// | `Chaos` | Randomly picks one of Reverse, Uppercase, Mock, Noise, Spongebob, Zalgo, HashNoise, or Nato per token. |
 /// | `Scramble` | Fisher-Yates shuffles the characters: same characters, random order. |
 /// | `Delete` | Drops the token entirely, returning an empty string. |
 /// | `Synonym` | Replaces the token with a synonym from the built-in 200-entry map; passes through unchanged if no entry exists. |
+/// | `Dictionary(map)` | Replaces the token from a user-supplied [`ReplacementDictionary`]; passes through unchanged if no entry exists. |
 /// | `Delay(ms)` | Returns the token unmodified after the given delay in milliseconds. Useful for pacing experiments. |
+/// | `Identity` | Returns the token unmodified; selected tokens are still marked and counted as transformed, for baseline runs. |
 /// | `Chain(vec)` | Applies a sequence of transforms in order; label is the individual labels joined by `+`. |
 #[derive(Debug, Clone)]
 pub enum Transform {
@@ -309,9 +571,14 @@ pub enum Transform {
     Uppercase,
     /// Alternate lowercase/uppercase per character position (sPoNgEbOb case).
     Mock,
-    /// Append one random symbol from the noise character set.
+    /// Append random symbols from the noise character set ([`NoiseConfig`]).
     Noise,
-    /// Randomly select one of Reverse, Uppercase, Mock, or Noise for each token.
+    /// Randomly case each character, uppercase with probability
+    /// [`SPONGEBOB_UPPER_PROBABILITY`].  Unlike [`Transform::Mock`] the pattern
+    /// does not follow character position.
+    Spongebob,
+    /// Randomly select one of Reverse, Uppercase, Mock, Noise, Spongebob,
+    /// Zalgo, HashNoise, or Nato for each token.
     Chaos,
     /// Shuffle the characters of the token using Fisher-Yates.
     Scramble,
@@ -319,8 +586,26 @@ pub enum Transform {
     Delete,
     /// Replace the token with a built-in synonym; pass through unchanged if not found.
     Synonym,
+    /// Replace the token from a user-supplied map; pass through unchanged if not found.
+    Dictionary(ReplacementDictionary),
     /// Return the token unchanged after sleeping for the given number of milliseconds.
     Delay(u64),
+    /// Follow each character with random combining diacritical marks
+    /// ([`TransformOptions::zalgo_intensity`] per character).  Stripping the marks with
+    /// [`strip_combining_marks`] recovers the token.
+    Zalgo,
+    /// Append noise symbols (from the `--noise-chars` set, `--noise-count`
+    /// of them) chosen by hashing the token's content rather than drawing
+    /// from the RNG, so equal tokens always get equal noise.
+    HashNoise,
+    /// Spell each ASCII letter and digit as its NATO phonetic word
+    /// (`a` → `Alpha`, `7` → `Seven`), space-separated; other characters
+    /// pass through.
+    Nato,
+    /// Return the token unchanged.  Tokens are still selected, marked and
+    /// counted as transformed, so a baseline run has the same shape as a
+    /// treatment run.
+    Identity,
     /// Apply a sequence of transforms in order, chaining their effects.
     Chain(Vec<Transform>),
 }
@@ -328,8 +613,13 @@ pub enum Transform {
 impl Transform {
     /// Parse a transform name (case-insensitive) or a comma-separated chain.
     ///
-    /// Recognised single names: `reverse`, `uppercase`, `mock`, `noise`, `chaos`,
-    /// `scramble`, `delete`, `synonym`, `delay`, `delay:N` (where N is milliseconds).
+    /// Recognised single names: `reverse`, `uppercase`, `mock`, `noise`,
+    /// `spongebob` (alias `sponge`), `chaos`,
+    /// `scramble`, `delete`, `synonym`, `delay`, `delay:N` (where N is milliseconds),
+    /// `identity` (alias `none`), `zalgo`, `hashnoise` (alias `hash-noise`),
+    /// `nato`.
+    /// `dictionary` is rejected here because it needs a mapping; build
+    /// [`Transform::Dictionary`] directly or via `cli::resolve_transform`.
     ///
     /// Comma-separated input like `"reverse,uppercase"` produces a `Chain` variant.
     /// A single-element comma-separated string is unwrapped to the plain variant.
@@ -378,11 +668,17 @@ impl Transform {
             "uppercase" => Ok(Transform::Uppercase),
             "mock" => Ok(Transform::Mock),
             "noise" => Ok(Transform::Noise),
+            "spongebob" | "sponge" => Ok(Transform::Spongebob),
             "chaos" => Ok(Transform::Chaos),
             "scramble" => Ok(Transform::Scramble),
             "delete" => Ok(Transform::Delete),
             "synonym" => Ok(Transform::Synonym),
             "delay" => Ok(Transform::Delay(100)),
+            "identity" | "none" => Ok(Transform::Identity),
+            "zalgo" => Ok(Transform::Zalgo),
+            "hashnoise" | "hash-noise" => Ok(Transform::HashNoise),
+            "nato" => Ok(Transform::Nato),
+            "dictionary" => Err("the dictionary transform needs a mapping file (--dict PATH)".to_string()),
             _ => Err(format!("Unknown transform: {}", s)),
         }
     }
@@ -392,14 +688,24 @@ impl Transform {
     /// equals the transform name.  Prefer this over `apply_with_label` in hot
     /// paths to avoid per-call `thread_rng()` TLS lookups.
     pub fn apply_with_label_rng<R: Rng>(&self, token: &str, rng: &mut R) -> (String, String) {
+        self.apply_with_options_rng(token, rng, &DEFAULT_OPTIONS)
+    }
+
+    /// [`apply_with_label_rng`](Self::apply_with_label_rng) with the
+    /// parameterised transforms configured by `options` instead of their
+    /// defaults.
+    pub fn apply_with_options_rng<R: Rng>(
+        &self,
+        token: &str,
+        rng: &mut R,
+        options: &TransformOptions,
+    ) -> (String, String) {
         match self {
             Transform::Reverse => (token.chars().rev().collect(), "reverse".to_string()),
             Transform::Uppercase => (token.to_uppercase(), "uppercase".to_string()),
             Transform::Mock => (apply_mock(token), "mock".to_string()),
-            Transform::Noise => {
-                let noise_char = NOISE_CHARS[rng.gen_range(0..NOISE_CHARS.len())];
-                (format!("{}{}", token, noise_char), "noise".to_string())
-            }
+            Transform::Noise => (options.noise.apply(token, rng), "noise".to_string()),
+            Transform::Spongebob => (apply_spongebob(token, rng), "spongebob".to_string()),
             Transform::Scramble => {
                 let mut chars: Vec<char> = token.chars().collect();
                 // Fisher-Yates shuffle
@@ -415,21 +721,30 @@ impl Transform {
                 let result = synonym_lookup(token).unwrap_or_else(|| token.to_string());
                 (result, "synonym".to_string())
             }
+            Transform::Dictionary(dict) => {
+                let result = dict.lookup(token).unwrap_or_else(|| token.to_string());
+                (result, "dictionary".to_string())
+            }
             Transform::Delay(_) => (token.to_string(), "delay".to_string()),
-            Transform::Chaos => match rng.gen_range(0u8..4) {
+            Transform::Zalgo => (apply_zalgo(token, rng, options.zalgo_intensity), "zalgo".to_string()),
+            Transform::HashNoise => (options.noise.apply_hashed(token), "hashnoise".to_string()),
+            Transform::Nato => (apply_nato(token), "nato".to_string()),
+            Transform::Identity => (token.to_string(), "identity".to_string()),
+            Transform::Chaos => match rng.gen_range(0u8..8) {
                 0 => (token.chars().rev().collect(), "reverse".to_string()),
                 1 => (token.to_uppercase(), "uppercase".to_string()),
                 2 => (apply_mock(token), "mock".to_string()),
-                _ => {
-                    let noise_char = NOISE_CHARS[rng.gen_range(0..NOISE_CHARS.len())];
-                    (format!("{}{}", token, noise_char), "noise".to_string())
-                }
+                3 => (apply_spongebob(token, rng), "spongebob".to_string()),
+                4 => (apply_zalgo(token, rng, options.zalgo_intensity), "zalgo".to_string()),
+                5 => (options.noise.apply_hashed(token), "hashnoise".to_string()),
+                6 => (apply_nato(token), "nato".to_string()),
+                _ => (options.noise.apply(token, rng), "noise".to_string()),
             },
             Transform::Chain(transforms) => {
                 let mut current = token.to_string();
                 let mut labels: Vec<String> = Vec::new();
                 for t in transforms {
-                    let (next, label) = t.apply_with_label_rng(&current, rng);
+                    let (next, label) = t.apply_with_options_rng(&current, rng, options);
                     current = next;
                     labels.push(label);
                 }
@@ -438,6 +753,27 @@ impl Transform {
         }
     }
 
+    /// Replay a chaos script (see `TokenInterceptor::chaos_script`) over
+    /// `tokens`, where `tokens[i]` is the token with logical index `i`.
+    ///
+    /// Each `(index, label)` entry applies the named sub-transform to that
+    /// token; entries past the end of `tokens` or with an unknown label are
+    /// skipped, so a script can be applied to a different text.  Only the
+    /// noise, spongebob and zalgo sub-transforms draw from `rng`.
+    pub fn replay_chaos_script<R: Rng>(
+        tokens: &[String],
+        script: &[(usize, String)],
+        rng: &mut R,
+    ) -> Vec<String> {
+        let mut out = tokens.to_vec();
+        for (index, label) in script {
+            if let (Some(token), Ok(t)) = (out.get_mut(*index), Transform::from_str_loose(label)) {
+                *token = t.apply_rng(token, rng);
+            }
+        }
+        out
+    }
+
     /// Apply the transform using the provided RNG.
     pub fn apply_rng<R: Rng>(&self, token: &str, rng: &mut R) -> String {
         self.apply_with_label_rng(token, rng).0
@@ -470,6 +806,108 @@ impl Transform {
         let _ = rate;
         self.apply(token)
     }
+
+    /// One representative of every variant, in declaration order.  Variants
+    /// with parameters use the value shown in [`Transform::catalog`]:
+    /// `Dictionary` maps `happy` to `cheerful`, `Delay` waits 100 ms, and
+    /// `Chain` is `reverse,uppercase`.
+    pub fn all() -> Vec<Transform> {
+        let sample_dict = HashMap::from([("happy".to_string(), "cheerful".to_string())]);
+        vec![
+            Transform::Reverse,
+            Transform::Uppercase,
+            Transform::Mock,
+            Transform::Noise,
+            Transform::Spongebob,
+            Transform::Chaos,
+            Transform::Scramble,
+            Transform::Delete,
+            Transform::Synonym,
+            Transform::Dictionary(ReplacementDictionary::new(sample_dict, true)),
+            Transform::Delay(100),
+            Transform::Zalgo,
+            Transform::HashNoise,
+            Transform::Nato,
+            Transform::Identity,
+            Transform::Chain(vec![Transform::Reverse, Transform::Uppercase]),
+        ]
+    }
+
+    /// Name accepted by [`Transform::from_str_loose`] (for `Dictionary`, the
+    /// `--transform` value used with `--dict`).
+    pub fn name(&self) -> String {
+        match self {
+            Transform::Reverse => "reverse".to_string(),
+            Transform::Uppercase => "uppercase".to_string(),
+            Transform::Mock => "mock".to_string(),
+            Transform::Noise => "noise".to_string(),
+            Transform::Spongebob => "spongebob".to_string(),
+            Transform::Chaos => "chaos".to_string(),
+            Transform::Scramble => "scramble".to_string(),
+            Transform::Delete => "delete".to_string(),
+            Transform::Synonym => "synonym".to_string(),
+            Transform::Dictionary(_) => "dictionary".to_string(),
+            Transform::Delay(ms) => format!("delay:{}", ms),
+            Transform::Zalgo => "zalgo".to_string(),
+            Transform::HashNoise => "hashnoise".to_string(),
+            Transform::Nato => "nato".to_string(),
+            Transform::Identity => "identity".to_string(),
+            Transform::Chain(ts) => ts.iter().map(Transform::name).collect::<Vec<_>>().join(","),
+        }
+    }
+
+    /// One-line description of what the transform does to a token.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Transform::Reverse => "Reverse the characters of the token",
+            Transform::Uppercase => "Uppercase every character",
+            Transform::Mock => "Alternate lower/upper case by character position",
+            Transform::Noise => "Append random symbols (default one of * + ~ @ # $ %; see --noise-chars)",
+            Transform::Spongebob => "Randomly case each character, leaning uppercase (alias: sponge)",
+            Transform::Chaos => "Pick reverse, uppercase, mock, noise, spongebob, zalgo, hashnoise or nato at random per token",
+            Transform::Scramble => "Shuffle the characters of the token",
+            Transform::Delete => "Drop the token from the stream",
+            Transform::Synonym => "Swap in a synonym from the built-in map; unknown words pass through",
+            Transform::Dictionary(_) => "Replace tokens from a JSON map given with --dict; others pass through",
+            Transform::Delay(_) => "Pass the token through unchanged after a delay (delay:MS, default 100)",
+            Transform::Zalgo => "Stack random combining marks on each character (see --zalgo-intensity)",
+            Transform::HashNoise => "Append noise symbols picked by hashing the token, so equal tokens get equal noise",
+            Transform::Nato => "Spell letters and digits with the NATO phonetic alphabet",
+            Transform::Identity => "Pass the token through unchanged, still marked as transformed (alias: none)",
+            Transform::Chain(_) => "Apply comma-separated transforms in order, e.g. reverse,uppercase",
+        }
+    }
+
+    /// Name, description and example for every transform, for
+    /// `--list-transforms` and `GET /api/transforms`.  Random transforms use a
+    /// fixed seed so examples are stable.
+    pub fn catalog() -> Vec<TransformInfo> {
+        use rand::SeedableRng;
+        Transform::all()
+            .iter()
+            .map(|t| {
+                let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+                TransformInfo {
+                    name: t.name(),
+                    description: t.describe(),
+                    example_input: CATALOG_EXAMPLE_WORD,
+                    example_output: t.apply_rng(CATALOG_EXAMPLE_WORD, &mut rng),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sample word run through each transform in [`Transform::catalog`].
+pub const CATALOG_EXAMPLE_WORD: &str = "happy";
+
+/// Catalog entry describing one transform; see [`Transform::catalog`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TransformInfo {
+    pub name: String,
+    pub description: &'static str,
+    pub example_input: &'static str,
+    pub example_output: String,
 }
 
 /// Shared mock-case logic: alternate lower/upper per character.
@@ -487,6 +925,24 @@ fn apply_mock(token: &str) -> String {
         .collect()
 }
 
+/// Chance that [`Transform::Spongebob`] uppercases a given character.
+pub const SPONGEBOB_UPPER_PROBABILITY: f64 = 0.6;
+
+/// Case each character independently at random, uppercase with probability
+/// [`SPONGEBOB_UPPER_PROBABILITY`].
+fn apply_spongebob<R: Rng>(token: &str, rng: &mut R) -> String {
+    token
+        .chars()
+        .flat_map(|c| {
+            if rng.gen_bool(SPONGEBOB_UPPER_PROBABILITY) {
+                c.to_uppercase().collect::<Vec<_>>()
+            } else {
+                c.to_lowercase().collect::<Vec<_>>()
+            }
+        })
+        .collect()
+}
+
 /// Returns true if `ch` is a CJK ideographic character that should be its own token.
 fn is_cjk(ch: char) -> bool {
     matches!(ch,
@@ -557,6 +1013,237 @@ pub fn tokenize(text: &str) -> Vec<String> {
     tokens
 }
 
+/// Whether `token` closes a sentence: it ends in `.`, `!`, `?` or `…`.
+pub fn ends_sentence(token: &str) -> bool {
+    token.trim_end().ends_with(['.', '!', '?', '\u{2026}'])
+}
+
+/// Closing quotes and brackets that stay with the sentence they follow.
+fn is_sentence_closer(token: &str) -> bool {
+    token.chars().all(|c| matches!(c, '"' | '\'' | ')' | ']' | '\u{201D}' | '\u{2019}' | '»'))
+}
+
+/// Streaming sentence segmenter: assigns each token from [`tokenize`] to a
+/// zero-based sentence index.
+///
+/// A sentence ends at a `.`, `!` or `?` token; runs of terminators (`?!`,
+/// `...`) and closing quotes or brackets directly after them stay with the
+/// sentence they end, and the next word starts a new one.  Abbreviations
+/// such as `e.g.` are not special-cased.
+#[derive(Debug, Clone, Default)]
+pub struct SentenceTracker {
+    index: usize,
+    ended: bool,
+    spaced: bool,
+}
+
+impl SentenceTracker {
+    /// Return the sentence index of `token`, the next token of the text.
+    /// Whitespace tokens must be observed too: a quote after a space opens
+    /// the next sentence rather than closing the last one.
+    pub fn observe(&mut self, token: &str) -> usize {
+        if token.trim().is_empty() {
+            self.spaced = true;
+            return self.index;
+        }
+        let terminator = ends_sentence(token);
+        let closer = !self.spaced && is_sentence_closer(token);
+        if self.ended && !terminator && !closer {
+            self.index += 1;
+            self.ended = false;
+        }
+        if terminator {
+            self.ended = true;
+        }
+        self.spaced = false;
+        self.index
+    }
+}
+
+/// Coarse part of speech guessed by [`pos_class`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PosClass {
+    /// A function word from the built-in English stopword list.
+    Stopword,
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+    /// Punctuation, numbers and anything without letters.
+    Other,
+}
+
+/// Built-in English stopwords (articles, pronouns, prepositions,
+/// conjunctions, auxiliaries) for [`pos_class`].
+pub const STOPWORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "but", "nor", "so", "yet", "if", "then", "than", "because", "as",
+    "while", "until", "in", "on", "at", "to", "for", "of", "with", "by", "from", "up", "down", "about",
+    "into", "over", "under", "after", "before", "between", "through", "during", "without", "is", "are",
+    "was", "were", "be", "been", "being", "am", "have", "has", "had", "do", "does", "did", "will",
+    "would", "could", "should", "may", "might", "must", "shall", "can", "not", "no", "i", "me", "my",
+    "we", "us", "our", "you", "your", "he", "him", "his", "she", "her", "it", "its", "they", "them",
+    "their", "this", "that", "these", "those", "what", "which", "who", "whom", "whose", "when",
+    "where", "why", "how", "all", "each", "some", "any", "such", "there", "here", "very", "just",
+];
+
+/// Guess the part of speech of `token` from the stopword list and English
+/// suffixes: `-ly` adverbs; `-ing`, `-ed`, `-ize`, `-ise`, `-ify` verbs;
+/// `-ous`, `-ful`, `-ive`, `-able`, `-ible`, `-less`, `-ish`, `-ic`, `-al`
+/// adjectives; every other word is a noun.
+///
+/// This is a heuristic, not a tagger: it ignores context, so "running
+/// water" tags "running" as a verb and "fly" as a noun.
+pub fn pos_class(token: &str) -> PosClass {
+    let word = token.trim().trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    if !word.chars().any(char::is_alphabetic) {
+        return PosClass::Other;
+    }
+    if STOPWORDS.contains(&word.as_str()) {
+        return PosClass::Stopword;
+    }
+    // Short words ("bed", "ring", "fly") rarely carry these suffixes.
+    let suffixed = |suffixes: &[&str]| {
+        suffixes.iter().any(|suffix| word.len() >= suffix.len() + 3 && word.ends_with(suffix))
+    };
+    if suffixed(&["ly"]) {
+        PosClass::Adverb
+    } else if suffixed(&["ing", "ed", "ize", "ise", "ify"]) {
+        PosClass::Verb
+    } else if suffixed(&["ous", "ful", "ive", "able", "ible", "less", "ish", "ic", "al"]) {
+        PosClass::Adjective
+    } else {
+        PosClass::Noun
+    }
+}
+
+/// A transform that sees the tokens before the one it rewrites.
+///
+/// Registered with [`crate::TokenInterceptor::with_window_transform`], it
+/// takes the place of the interceptor's [`Transform`] for every selected
+/// token.  `context` holds up to [`window`](Self::window) preceding
+/// non-whitespace tokens of the stream, original text, oldest first.
+pub trait WindowTransform: Send + Sync {
+    /// How many preceding tokens `apply` receives.
+    fn window(&self) -> usize {
+        4
+    }
+
+    /// Rewrite `token` given the tokens before it.
+    fn apply(&self, token: &str, context: &[String]) -> String;
+}
+
+/// Window transform that stutters a token (`fox` → `fox-fox`) when the token
+/// before it is at most `max_prior_len` characters long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stutter {
+    pub max_prior_len: usize,
+}
+
+impl Default for Stutter {
+    fn default() -> Self {
+        Stutter { max_prior_len: 3 }
+    }
+}
+
+impl WindowTransform for Stutter {
+    fn window(&self) -> usize {
+        1
+    }
+
+    fn apply(&self, token: &str, context: &[String]) -> String {
+        let prior_is_short = context
+            .last()
+            .is_some_and(|prior| prior.trim().chars().count() <= self.max_prior_len);
+        let core = token.trim_start();
+        if !prior_is_short || core.is_empty() {
+            return token.to_string();
+        }
+        format!("{}-{}", token, core)
+    }
+}
+
+/// The window transform named `name` (`--window-transform`), if any.
+pub fn window_transform_by_name(name: &str) -> Option<Box<dyn WindowTransform>> {
+    match name.trim().to_lowercase().as_str() {
+        "stutter" => Some(Box::new(Stutter::default())),
+        _ => None,
+    }
+}
+
+/// One token of a [`transform_text`] result.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TextToken {
+    /// Position among the non-whitespace tokens of the input.
+    pub index: usize,
+    /// The token as it appeared in the input.
+    pub original: String,
+    /// The token after the transform (equal to `original` when untouched).
+    pub text: String,
+    /// Whether the transform was applied at this position.
+    pub transformed: bool,
+}
+
+/// Apply `transform` offline to every `interval`-th word of `text`, without
+/// calling a model.
+///
+/// `text` is split with [`tokenize`]; whitespace passes through untouched and
+/// the non-whitespace token at position `i` is transformed when
+/// `(i + 1) % interval == 0`, so the default interval of 2 matches the live
+/// stream's every-other-token behaviour.  An `interval` of 0 is treated as 1.
+/// Returns the reassembled text and the per-token breakdown.
+pub fn transform_text(
+    text: &str,
+    transform: &Transform,
+    interval: usize,
+    options: &TransformOptions,
+) -> (String, Vec<TextToken>) {
+    let interval = interval.max(1);
+    let mut rng = rand::thread_rng();
+    let mut out = String::with_capacity(text.len());
+    let mut tokens = Vec::new();
+    for token in tokenize(text) {
+        if token.trim().is_empty() {
+            out.push_str(&token);
+            continue;
+        }
+        let index = tokens.len();
+        let transformed = (index + 1) % interval == 0;
+        let result = if transformed {
+            transform.apply_with_options_rng(&token, &mut rng, options).0
+        } else {
+            token.clone()
+        };
+        out.push_str(&result);
+        tokens.push(TextToken {
+            index,
+            original: token,
+            text: result,
+            transformed,
+        });
+    }
+    (out, tokens)
+}
+
+/// Re-apply `transform` to the `original` of a token that was transformed
+/// in an earlier stream, returning the new text and its `chaos_label`:
+/// `Chaos` names its sub-transform and an emptied token is `"deleted"`, as
+/// in the live stream.
+pub fn retransform_token(
+    original: &str,
+    transform: &Transform,
+    options: &TransformOptions,
+) -> (String, Option<String>) {
+    let (text, label) = transform.apply_with_options_rng(original, &mut rand::thread_rng(), options);
+    let chaos_label = if text.is_empty() {
+        Some("deleted".to_string())
+    } else if matches!(transform, Transform::Chaos) {
+        Some(label)
+    } else {
+        None
+    };
+    (text, chaos_label)
+}
+
 /// Calculate simulated token importance (0.0 to 1.0) using a caller-supplied RNG.
 /// Identical to `calculate_token_importance` but takes an explicit RNG parameter
 /// for deterministic/seeded use.
@@ -565,6 +1252,18 @@ pub fn calculate_token_importance_rng<R: rand::Rng>(
     position: usize,
     rng: &mut R,
 ) -> f64 {
+    (heuristic_importance(token, position) + rng.gen_range(-0.1..0.1)).clamp(0.0, 1.0)
+}
+
+/// Calculate token importance (0.0 to 1.0) without random jitter, so the
+/// score is a pure function of `token` and `position`.  Used when
+/// reproducible heatmaps are required (`--deterministic-importance`).
+pub fn calculate_token_importance_deterministic(token: &str, position: usize) -> f64 {
+    heuristic_importance(token, position).clamp(0.0, 1.0)
+}
+
+/// Length, position and content-based importance score before jitter and clamping.
+fn heuristic_importance(token: &str, position: usize) -> f64 {
     let mut importance = 0.0;
 
     importance += (token.len() as f64 / 20.0).min(0.3);
@@ -618,9 +1317,7 @@ pub fn calculate_token_importance_rng<R: rand::Rng>(
         importance *= 0.1;
     }
 
-    importance += rng.gen_range(-0.1..0.1);
-
-    importance.clamp(0.0, 1.0)
+    importance
 }
 
 /// Calculate simulated token importance (0.0 to 1.0) based on length,
@@ -645,6 +1342,7 @@ pub fn apply_heatmap_color(token: &str, importance: f64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
 
     // -- Transform apply tests --
 
@@ -1033,7 +1731,7 @@ mod tests {
 
     #[test]
     fn test_transform_chaos_apply_with_label_returns_known_label() {
-        let known = ["reverse", "uppercase", "mock", "noise"];
+        let known = ["reverse", "uppercase", "mock", "noise", "spongebob", "zalgo", "hashnoise", "nato"];
         for _ in 0..50 {
             let (_text, label) = Transform::Chaos.apply_with_label("hello");
             assert!(
@@ -1056,7 +1754,7 @@ mod tests {
     fn test_transform_chaos_empty_input() {
         // Noise appends 1 char, others keep length 0; either way no panic
         let (_text, label) = Transform::Chaos.apply_with_label("");
-        let known = ["reverse", "uppercase", "mock", "noise"];
+        let known = ["reverse", "uppercase", "mock", "noise", "spongebob", "zalgo", "hashnoise", "nato"];
         assert!(known.contains(&label.as_str()));
     }
 
@@ -1066,6 +1764,7 @@ mod tests {
         assert_eq!(Transform::Uppercase.apply_with_label("hi").1, "uppercase");
         assert_eq!(Transform::Mock.apply_with_label("hi").1, "mock");
         assert_eq!(Transform::Noise.apply_with_label("hi").1, "noise");
+        assert_eq!(Transform::Spongebob.apply_with_label("hi").1, "spongebob");
     }
 
     #[test]
@@ -1098,6 +1797,22 @@ mod tests {
         assert!(results.len() >= 2, "Chaos should produce varied results");
     }
 
+    #[test]
+    fn test_replay_chaos_script_applies_labels_by_index() {
+        let tokens: Vec<String> = ["one", "two", "three"].iter().map(|t| t.to_string()).collect();
+        let script = vec![
+            (1, "reverse".to_string()),
+            (2, "uppercase".to_string()),
+            (9, "reverse".to_string()),
+            (0, "not-a-transform".to_string()),
+        ];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        assert_eq!(
+            Transform::replay_chaos_script(&tokens, &script, &mut rng),
+            vec!["one", "owt", "THREE"]
+        );
+    }
+
     #[test]
     fn test_transform_scramble_same_chars() {
         let input = "hello";
@@ -1151,6 +1866,60 @@ mod tests {
         assert_eq!(label, "synonym");
     }
 
+    // -- Dictionary transform tests --
+
+    fn dictionary(case_sensitive: bool) -> Transform {
+        let dict = ReplacementDictionary::from_json(r#"{"cat": "dog", "Paris": "Rome"}"#, case_sensitive)
+            .expect("valid dictionary");
+        Transform::Dictionary(dict)
+    }
+
+    #[test]
+    fn test_transform_dictionary_replaces_mapped_token() {
+        let t = dictionary(true);
+        assert_eq!(t.apply("cat"), "dog");
+        // Streamed tokens keep their leading space.
+        assert_eq!(t.apply(" Paris"), " Rome");
+        let (_, label) = t.apply_with_label("cat");
+        assert_eq!(label, "dictionary");
+    }
+
+    #[test]
+    fn test_transform_dictionary_passes_unmapped_token_through() {
+        let t = dictionary(true);
+        assert_eq!(t.apply(" bird"), " bird");
+        assert_eq!(t.apply(""), "");
+        // Case-sensitive: "CAT" is not "cat".
+        assert_eq!(t.apply("CAT"), "CAT");
+    }
+
+    #[test]
+    fn test_transform_dictionary_case_insensitive() {
+        let t = dictionary(false);
+        assert_eq!(t.apply("CAT"), "dog");
+        assert_eq!(t.apply(" paris"), " Rome");
+    }
+
+    #[test]
+    fn test_transform_text_every_other_word() {
+        let (text, tokens) = transform_text("the quick brown fox", &Transform::Uppercase, 2, &TransformOptions::default());
+        assert_eq!(text, "the QUICK brown FOX");
+        assert_eq!(tokens.len(), 4);
+        assert!(!tokens[0].transformed && tokens[1].transformed);
+        assert_eq!(tokens[1].original, "quick");
+
+        let (text, _) = transform_text("a b c", &Transform::Reverse, 0, &TransformOptions::default());
+        assert_eq!(text, "a b c");
+        let (text, _) = transform_text("ab cd ef", &Transform::Reverse, 3, &TransformOptions::default());
+        assert_eq!(text, "ab cd fe");
+    }
+
+    #[test]
+    fn test_transform_dictionary_needs_mapping() {
+        assert!(Transform::from_str_loose("dictionary").is_err());
+        assert!(ReplacementDictionary::from_json("[1, 2]", true).is_err());
+    }
+
     #[test]
     fn test_transform_from_str_delay_colon() {
         assert!(matches!(
@@ -1159,6 +1928,124 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_transform_identity_parses_and_passes_through() {
+        for name in ["identity", "none", "NONE"] {
+            assert!(matches!(Transform::from_str_loose(name), Ok(Transform::Identity)), "{}", name);
+        }
+        assert_eq!(
+            Transform::Identity.apply_with_label("hello"),
+            ("hello".to_string(), "identity".to_string())
+        );
+    }
+
+    #[test]
+    fn test_transform_zalgo_adds_marks_that_strip_back_to_original() {
+        assert!(matches!(Transform::from_str_loose("zalgo"), Ok(Transform::Zalgo)));
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let (text, label) = Transform::Zalgo.apply_with_label_rng("héllo wörld", &mut rng);
+        assert_eq!(label, "zalgo");
+        let marks = text.chars().filter(|c| ('\u{0300}'..='\u{036F}').contains(c)).count();
+        assert_eq!(marks, 10 * DEFAULT_ZALGO_INTENSITY);
+        assert_eq!(strip_combining_marks(&text), "héllo wörld");
+        let again = Transform::Zalgo.apply_rng("héllo wörld", &mut rand::rngs::StdRng::seed_from_u64(11));
+        assert_eq!(again, text);
+    }
+
+    #[test]
+    fn test_options_zalgo_intensity_sets_marks_per_char() {
+        let options = TransformOptions {
+            zalgo_intensity: 7,
+            ..TransformOptions::default()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let (text, _) = Transform::Zalgo.apply_with_options_rng("ab c", &mut rng, &options);
+        let marks = text.chars().filter(|c| ('\u{0300}'..='\u{036F}').contains(c)).count();
+        assert_eq!(marks, 3 * 7);
+        assert_eq!(strip_combining_marks(&text), "ab c");
+    }
+
+    #[test]
+    fn test_sentence_tracker_splits_on_terminators() {
+        let mut tracker = SentenceTracker::default();
+        let indices: Vec<(String, usize)> = tokenize("Hi there. \"Really?!\" Yes... ok")
+            .into_iter()
+            .map(|t| {
+                let i = tracker.observe(&t);
+                (t, i)
+            })
+            .filter(|(t, _)| !t.trim().is_empty())
+            .collect();
+        let sentence = |n: usize| -> String {
+            indices.iter().filter(|(_, i)| *i == n).map(|(t, _)| t.as_str()).collect()
+        };
+        assert_eq!(sentence(0), "Hithere.");
+        assert_eq!(sentence(1), "\"Really?!\"");
+        assert_eq!(sentence(2), "Yes...");
+        assert_eq!(sentence(3), "ok");
+        assert!(ends_sentence("done!") && !ends_sentence("done,"));
+    }
+
+    #[test]
+    fn test_pos_class_heuristics() {
+        assert_eq!(pos_class(" the"), PosClass::Stopword);
+        assert_eq!(pos_class("They"), PosClass::Stopword);
+        assert_eq!(pos_class("quickly"), PosClass::Adverb);
+        assert_eq!(pos_class("jumped"), PosClass::Verb);
+        assert_eq!(pos_class("running,"), PosClass::Verb);
+        assert_eq!(pos_class("dangerous"), PosClass::Adjective);
+        assert_eq!(pos_class("fox"), PosClass::Noun);
+        assert_eq!(pos_class("bed"), PosClass::Noun, "too short for the -ed rule");
+        assert_eq!(pos_class("42"), PosClass::Other);
+        assert_eq!(pos_class("."), PosClass::Other);
+    }
+
+    #[test]
+    fn test_stutter_repeats_after_short_prior_token() {
+        let stutter = Stutter::default();
+        let ctx = |prior: &str| vec![prior.to_string()];
+        assert_eq!(stutter.apply(" fox", &ctx("a")), " fox-fox");
+        assert_eq!(stutter.apply("fox", &ctx("the")), "fox-fox");
+        assert_eq!(stutter.apply("fox", &ctx("quick")), "fox");
+        assert_eq!(stutter.apply("fox", &[]), "fox", "no prior token, no stutter");
+        assert_eq!(stutter.window(), 1);
+        assert!(window_transform_by_name("Stutter").is_some());
+        assert!(window_transform_by_name("reverse").is_none());
+    }
+
+    #[test]
+    fn test_transform_hashnoise_is_keyed_to_content() {
+        assert!(matches!(Transform::from_str_loose("hashnoise"), Ok(Transform::HashNoise)));
+        let noise = NoiseConfig::default();
+        let suffix = |token: &str| noise.apply_hashed(token).chars().last().unwrap();
+        for token in ["hello", "world", "naïve", "猫"] {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(token.len() as u64);
+            let (text, label) = Transform::HashNoise.apply_with_label_rng(token, &mut rng);
+            assert_eq!(label, "hashnoise");
+            assert_eq!(text.strip_suffix(suffix(token)), Some(token));
+            assert_eq!(Transform::HashNoise.apply(token), text);
+        }
+        let words = ["the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog", "cat", "sat"];
+        let distinct: std::collections::HashSet<char> = words.iter().map(|w| suffix(w)).collect();
+        assert!(distinct.len() >= 4, "{:?}", distinct);
+        // Pinned so the JavaScript mirror in the web UI stays in step.
+        assert_eq!(fnv1a_32(b"hello"), 0x4f9f_2cab);
+        assert_eq!(noise.apply_hashed("hello"), "hello~");
+    }
+
+    #[test]
+    fn test_transform_nato_spells_letters_and_digits() {
+        assert!(matches!(Transform::from_str_loose("nato"), Ok(Transform::Nato)));
+        assert_eq!(Transform::Nato.apply("ab"), "Alpha Bravo");
+        assert_eq!(Transform::Nato.apply(" Hi7"), " Hotel India Seven");
+        assert_eq!(Transform::Nato.apply(" a-b!"), " Alpha-Bravo!");
+        assert_eq!(Transform::Nato.apply("é—"), "é—");
+        // The web UI's `nato` mirror spells lowercase input from the same tables.
+        let html = crate::web::INDEX_HTML;
+        assert!(html.contains(&format!("NATO_WORDS='{}'", NATO_ALPHABET.join(" "))));
+        assert!(html.contains(&format!("NATO_DIGITS='{}'", NATO_DIGITS.join(" "))));
+    }
+
     #[test]
     fn test_transform_from_str_delay_default() {
         assert!(matches!(
@@ -1274,6 +2161,122 @@ mod tests {
         assert_eq!(tokenize(input).join(""), input);
     }
 
+    #[test]
+    fn test_noise_config_single_char_count_two() {
+        let config = NoiseConfig::new("!", 2).expect("valid config");
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        assert_eq!(config.apply("hi", &mut rng), "hi!!");
+        assert_eq!(config.apply("", &mut rng), "!!");
+    }
+
+    #[test]
+    fn test_noise_config_draws_from_custom_set() {
+        let config = NoiseConfig::new("!?.", 3).expect("valid config");
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        for _ in 0..50 {
+            let out = config.apply("x", &mut rng);
+            assert_eq!(out.chars().count(), 4);
+            assert!(out.chars().skip(1).all(|c| "!?.".contains(c)), "got {}", out);
+        }
+    }
+
+    #[test]
+    fn test_noise_config_rejects_empty_set_and_zero_count() {
+        assert!(NoiseConfig::new("", 1).is_err());
+        assert!(NoiseConfig::new("!", 0).is_err());
+        assert_eq!(NoiseConfig::default().chars(), &NOISE_CHARS);
+        assert_eq!(NoiseConfig::default().count(), 1);
+    }
+
+    #[test]
+    fn test_options_noise_reaches_noise_hashnoise_and_chains() {
+        let options = TransformOptions {
+            noise: NoiseConfig::new("!", 2).expect("valid config"),
+            ..TransformOptions::default()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        assert_eq!(Transform::Noise.apply_with_options_rng("hi", &mut rng, &options).0, "hi!!");
+        assert_eq!(Transform::HashNoise.apply_with_options_rng("hi", &mut rng, &options).0, "hi!!");
+        let chain = Transform::from_str_loose("uppercase,noise").expect("chain");
+        assert_eq!(chain.apply_with_options_rng("hi", &mut rng, &options).0, "HI!!");
+        // Other callers keep the default set.
+        let plain = Transform::Noise.apply_rng("hi", &mut rng);
+        assert!(NOISE_CHARS.contains(&plain.chars().last().unwrap()), "{}", plain);
+    }
+
+    #[test]
+    fn test_all_covers_every_variant_with_description() {
+        // Adding a variant fails to compile here until it is numbered, and
+        // the assertion below fails until it is added to `all()`.
+        fn variant_id(t: &Transform) -> usize {
+            match t {
+                Transform::Reverse => 0,
+                Transform::Uppercase => 1,
+                Transform::Mock => 2,
+                Transform::Noise => 3,
+                Transform::Spongebob => 4,
+                Transform::Chaos => 5,
+                Transform::Scramble => 6,
+                Transform::Delete => 7,
+                Transform::Synonym => 8,
+                Transform::Dictionary(_) => 9,
+                Transform::Delay(_) => 10,
+                Transform::Zalgo => 11,
+                Transform::HashNoise => 12,
+                Transform::Nato => 13,
+                Transform::Identity => 14,
+                Transform::Chain(_) => 15,
+            }
+        }
+        let ids: Vec<usize> = Transform::all().iter().map(variant_id).collect();
+        assert_eq!(ids, (0..16).collect::<Vec<_>>());
+        for t in Transform::all() {
+            assert!(!t.describe().is_empty(), "{} has no description", t.name());
+        }
+    }
+
+    #[test]
+    fn test_catalog_names_parse_and_examples_are_stable() {
+        let catalog = Transform::catalog();
+        assert_eq!(catalog, Transform::catalog());
+        for info in &catalog {
+            if info.name != "dictionary" {
+                assert!(Transform::from_str_loose(&info.name).is_ok(), "{}", info.name);
+            }
+        }
+        let example = |name: &str| {
+            catalog
+                .iter()
+                .find(|i| i.name == name)
+                .map(|i| i.example_output.clone())
+                .unwrap_or_default()
+        };
+        assert_eq!(example("reverse"), "yppah");
+        assert_eq!(example("synonym"), "glad");
+        assert_eq!(example("dictionary"), "cheerful");
+        assert_eq!(example("reverse,uppercase"), "YPPAH");
+    }
+
+    #[test]
+    fn test_spongebob_parses_with_alias() {
+        assert!(matches!(Transform::from_str_loose("spongebob"), Ok(Transform::Spongebob)));
+        assert!(matches!(Transform::from_str_loose("Sponge"), Ok(Transform::Spongebob)));
+    }
+
+    #[test]
+    fn test_spongebob_seeded_is_reproducible_and_not_positional() {
+        use rand::SeedableRng;
+        let input = "abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz";
+        let run = |seed| Transform::Spongebob.apply_rng(input, &mut rand::rngs::StdRng::seed_from_u64(seed));
+        let out = run(7);
+        assert_eq!(out, run(7));
+        assert_eq!(out.to_lowercase(), input);
+        assert!(out.chars().any(|c| c.is_uppercase()));
+        assert!(out.chars().any(|c| c.is_lowercase()));
+        assert_ne!(out, Transform::Mock.apply(input));
+        assert_ne!(out, run(8));
+    }
+
     // -- Seeded RNG importance tests (Change 3) --
 
     #[test]
@@ -1306,6 +2309,16 @@ mod tests {
         assert!(v >= 0.0 && v <= 1.0);
     }
 
+    #[test]
+    fn test_importance_deterministic_is_repeatable() {
+        let v1 = calculate_token_importance_deterministic("algorithm", 3);
+        let v2 = calculate_token_importance_deterministic("algorithm", 3);
+        assert_eq!(v1.to_bits(), v2.to_bits());
+        // Jitter is within ±0.1 of the deterministic score.
+        let jittered = calculate_token_importance("algorithm", 3);
+        assert!((jittered - v1).abs() <= 0.1 + 1e-9);
+    }
+
     // -- Scramble and Delete extended tests (Improvement 7) --
 
     #[test]
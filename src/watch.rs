@@ -0,0 +1,223 @@
+//! Read-only terminal viewer for a collaboration room (`--watch CODE`).
+//!
+//! Connects to `/ws/CODE?spectator=1` on a running `--web` server and prints
+//! the room's live token stream with the same styling as terminal mode
+//! (`--heatmap`, `--visual`, `--show-original`).  Spectators never claim the
+//! host seat and send nothing; peer surgery and chat are noted on stderr.
+
+use futures_util::StreamExt;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::render::{self, TerminalStyle};
+use crate::TokenEvent;
+
+/// A room broadcast, as far as the viewer cares.
+#[derive(Debug, Clone)]
+pub enum WatchMessage {
+    /// A streamed token, ready to render.
+    Token(TokenEvent),
+    /// A participant edited the token with logical index `token_index`.
+    Surgery {
+        token_index: usize,
+        new_text: String,
+        editor_name: String,
+    },
+    /// A chat message.
+    Chat { author_name: String, text: String },
+    /// The host's stream finished.
+    StreamDone,
+    /// The server rejected or reported a problem with the connection.
+    Error(String),
+    /// Anything else (presence, votes, locks, ...).
+    Other,
+}
+
+/// Parse one WebSocket text frame from a room.
+///
+/// Token broadcasts are bare [`TokenEvent`] objects when the server streams
+/// to a room, and carry `"type":"token"` when a browser host relays them;
+/// both map to [`WatchMessage::Token`].
+pub fn parse_room_message(text: &str) -> WatchMessage {
+    let Ok(msg) = serde_json::from_str::<serde_json::Value>(text) else {
+        return WatchMessage::Other;
+    };
+    let str_field = |v: &serde_json::Value, key: &str| {
+        v.get(key).and_then(|s| s.as_str()).unwrap_or("").to_string()
+    };
+    match msg.get("type").and_then(|t| t.as_str()) {
+        Some("token") | None => serde_json::from_value::<TokenEvent>(msg)
+            .map(WatchMessage::Token)
+            .unwrap_or(WatchMessage::Other),
+        Some("surgery") => {
+            let edit = msg.get("edit").unwrap_or(&msg);
+            match edit.get("token_index").and_then(|i| i.as_u64()) {
+                Some(index) => WatchMessage::Surgery {
+                    token_index: index as usize,
+                    new_text: str_field(edit, "new_text"),
+                    editor_name: str_field(edit, "editor_name"),
+                },
+                None => WatchMessage::Other,
+            }
+        }
+        Some("chat") => {
+            let chat = msg.get("message").unwrap_or(&msg);
+            WatchMessage::Chat {
+                author_name: str_field(chat, "author_name"),
+                text: str_field(chat, "text"),
+            }
+        }
+        Some("stream_done") => WatchMessage::StreamDone,
+        Some("error") => WatchMessage::Error(str_field(&msg, "message")),
+        Some(_) => WatchMessage::Other,
+    }
+}
+
+/// WebSocket URL of room `code` on the server at `base` (`http://host:port`),
+/// joined as a spectator.
+pub fn spectator_ws_url(base: &str, code: &str) -> String {
+    let base = base.trim_end_matches('/');
+    let base = if let Some(rest) = base.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else if let Some(rest) = base.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if base.starts_with("ws://") || base.starts_with("wss://") {
+        base.to_string()
+    } else {
+        format!("ws://{}", base)
+    };
+    format!("{}/ws/{}?spectator=1", base, code)
+}
+
+/// Follow room `code` on the server at `base_url`, printing tokens to stdout
+/// until the server closes the connection.
+///
+/// # Errors
+///
+/// Returns an error if the connection fails or the server rejects the join
+/// (unknown room, room full, joins locked).
+pub async fn watch_room(
+    base_url: &str,
+    code: &str,
+    style: TerminalStyle,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let url = spectator_ws_url(base_url, code);
+    let (mut ws, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .map_err(|e| format!("cannot connect to {}: {}", url, e))?;
+    eprintln!("[watch] following room {} (Ctrl+C to stop)", code);
+
+    let color = colored::control::SHOULD_COLORIZE.should_colorize();
+    while let Some(frame) = ws.next().await {
+        let text = match frame? {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+        match parse_room_message(&text) {
+            WatchMessage::Token(event) if event.is_error => {
+                eprintln!("\n[watch] stream error: {}", event.text);
+            }
+            WatchMessage::Token(event) => {
+                print!(
+                    "{}",
+                    render::terminal_token(
+                        &event.text,
+                        &event.original,
+                        event.importance,
+                        event.confidence,
+                        event.transformed,
+                        style,
+                        color
+                    )
+                );
+                let _ = std::io::stdout().flush();
+            }
+            WatchMessage::Surgery { token_index, new_text, editor_name } => {
+                eprintln!("\n[watch] {} edited token #{} -> {:?}", editor_name, token_index, new_text);
+            }
+            WatchMessage::Chat { author_name, text } => {
+                eprintln!("\n[chat] {}: {}", author_name, text);
+            }
+            WatchMessage::StreamDone => println!(),
+            WatchMessage::Error(message) => return Err(message.into()),
+            WatchMessage::Other => {}
+        }
+    }
+    eprintln!("[watch] connection closed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_broadcast_parses_to_token_event() {
+        let event = TokenEvent {
+            text: "dlrow".to_string(),
+            original: "world".to_string(),
+            index: 3,
+            transformed: true,
+            importance: 0.7,
+            confidence: Some(0.9),
+            arrival_ms: Some(12),
+            ..Default::default()
+        };
+        let wire = serde_json::to_string(&event).unwrap();
+        let WatchMessage::Token(parsed) = parse_room_message(&wire) else {
+            panic!("expected a token: {}", wire);
+        };
+        assert_eq!(parsed.text, "dlrow");
+        assert_eq!(parsed.original, "world");
+        assert_eq!(parsed.index, 3);
+        assert!(parsed.transformed);
+        assert_eq!(parsed.confidence, Some(0.9));
+
+        let rendered = render::terminal_token(
+            &parsed.text,
+            &parsed.original,
+            parsed.importance,
+            parsed.confidence,
+            parsed.transformed,
+            TerminalStyle { show_original: true, ..TerminalStyle::default() },
+            false,
+        );
+        assert_eq!(rendered, "dlrow[world]");
+    }
+
+    #[test]
+    fn test_host_relayed_token_with_type_parses() {
+        let wire = r#"{"type":"token","text":"HI","original":"hi","index":0,"transformed":true,"importance":0.5}"#;
+        assert!(matches!(parse_room_message(wire), WatchMessage::Token(ev) if ev.text == "HI"));
+    }
+
+    #[test]
+    fn test_other_room_messages() {
+        let surgery = r#"{"type":"surgery","edit":{"token_index":7,"new_text":"x","old_text":"y","editor_name":"Bob"}}"#;
+        assert!(matches!(
+            parse_room_message(surgery),
+            WatchMessage::Surgery { token_index: 7, ref new_text, .. } if new_text == "x"
+        ));
+        let chat = r#"{"type":"chat","message":{"author_name":"Ann","text":"hi"}}"#;
+        assert!(matches!(parse_room_message(chat), WatchMessage::Chat { ref text, .. } if text == "hi"));
+        assert!(matches!(parse_room_message(r#"{"type":"stream_done"}"#), WatchMessage::StreamDone));
+        assert!(matches!(
+            parse_room_message(r#"{"type":"error","message":"room is full"}"#),
+            WatchMessage::Error(ref m) if m == "room is full"
+        ));
+        assert!(matches!(parse_room_message(r#"{"type":"vote_update"}"#), WatchMessage::Other));
+        assert!(matches!(parse_room_message("not json"), WatchMessage::Other));
+    }
+
+    #[test]
+    fn test_spectator_ws_url() {
+        assert_eq!(
+            spectator_ws_url("http://localhost:8888/", "SWIFT-LION-42"),
+            "ws://localhost:8888/ws/SWIFT-LION-42?spectator=1"
+        );
+        assert_eq!(spectator_ws_url("https://eot.example", "A"), "wss://eot.example/ws/A?spectator=1");
+        assert_eq!(spectator_ws_url("127.0.0.1:9000", "A"), "ws://127.0.0.1:9000/ws/A?spectator=1");
+    }
+}
@@ -9,34 +9,483 @@
 //!
 //! | Method | Path | Description |
 //! |--------|------|-------------|
-//! | `GET` | `/` | Serves the embedded single-page HTML application |
+//! | `GET` | `/` | Serves the embedded single-page HTML application (`ETag`; `304` on `If-None-Match`) |
 //! | `GET` | `/events` | SSE stream of [`crate::TokenEvent`] JSON objects |
 //! | `GET` | `/stream` | Alias for `/events` |
+//! | `POST` | `/batch-stream` | SSE run of a JSON array of prompts, tagged by `prompt_index` |
 //! | `POST` | `/room/create` | Creates a new collaboration room |
 //! | `GET` | `/ws/:code` | WebSocket endpoint for room participants |
-//! | `GET` | `/join/:code` | Serve the collaboration join page |
+//! | `GET` | `/join/:code` | Serve the collaboration join page (same page and `ETag` as `/`) |
+//! | `GET` | `/demo` | SSE replay of a built-in demo session (`?session=NAME&speed=X`; no model call) |
+//! | `GET` | `/replay/:code` | A room's recorded events as JSON (gzipped on `Accept-Encoding: gzip`) |
 //! | `POST` | `/api/config` | Update runtime configuration |
 //! | `GET` | `/api/experiments` | List stored experiments (requires `sqlite-log`) |
+//! | `GET` | `/api/preview` | Apply a transform offline to `text` (no model call) |
+//! | `POST` | `/api/load-session` | Validate an exported session bundle and return its tokens for display |
+//! | `POST` | `/api/retransform` | Re-render stored token originals with another transform, without a model call |
+//! | `POST` | `/api/ab/export` | Structured A/B experiment record: arm stats, aligned tokens, similarity, winner |
+//! | `GET` | `/api/transforms` | List transforms with descriptions and examples |
+//! | `GET` | `/api/openai/models` | OpenAI chat model ids (cached; static fallback without a key) |
+//! | `GET` | `/api/session/search` | Search a room's buffered session tokens |
+//! | `GET` | `/api/session/export` | Export a room's session as a stable-ordered JSON bundle |
+//! | `GET` | `/api/room/:code/audit` | Host-only audit log of room actions (`X-Host-Token`) |
+//! | `GET` | `/api/room/:code/archive` | Host-only archive of everything the room accumulated (`X-Host-Token`) |
 
 use colored::*;
 use serde::Serialize;
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use subtle::ConstantTimeEq;
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpListener;
-use tokio::sync::mpsc;
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
 
 use crate::cli::Args;
-use crate::collab::RoomStore;
+use crate::collab::{RoomBackend, RoomStore};
 use crate::providers::Provider;
-use crate::transforms::Transform;
+use crate::transforms::{Transform, TransformOptions};
 use crate::{TokenEvent, TokenInterceptor};
 
-/// Maximum prompt length accepted on /stream.
-const MAX_PROMPT_LEN: usize = 100_000;
+/// Default for `--sse-retry-ms`: how long browsers wait before reconnecting
+/// a dropped SSE stream.
+pub const DEFAULT_SSE_RETRY_MS: u64 = 3000;
+
+/// Default for `--render-window`: token spans the web UI keeps in each view.
+pub const DEFAULT_RENDER_WINDOW: usize = 500;
+
+/// Default for `--max-connections`: connections handled at once.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 512;
+
+/// Default for `--listen-backlog`: the same queue length
+/// `TcpListener::bind` uses.
+pub const DEFAULT_LISTEN_BACKLOG: u32 = 1024;
+
+/// Server-wide settings for the streaming endpoints, taken from [`Args`].
+#[derive(Debug, Clone)]
+struct StreamSettings {
+    /// Tokens buffered on `/stream` before the oldest is dropped (`--sse-buffer-size`).
+    sse_buffer_size: usize,
+    /// SSE `retry:` reconnection delay in ms (`--sse-retry-ms`); 0 omits it.
+    sse_retry_ms: u64,
+    /// Most `/stream` token events sent in one SSE frame (`--sse-batch`).
+    sse_batch: usize,
+    /// `/stream` emission pacing in tokens/sec (`--render-rate`), if enabled.
+    render_rate: Option<f64>,
+    /// Longest prompt, in characters, accepted by the streaming endpoints
+    /// (`--max-prompt-chars`).
+    max_prompt_chars: usize,
+    /// Milliseconds the UI shows a transformed token's original before
+    /// swapping in the transformed text (`--reveal-delay`), if enabled.
+    reveal_delay_ms: Option<u64>,
+    /// Default for the `/stream` `logprobs` param (off with `--no-logprobs`).
+    logprobs: bool,
+    /// How `/stream` reveals tokens (`--emit-granularity`); overridable per
+    /// request with `granularity=word|char`.
+    emit_granularity: EmitGranularity,
+    /// Most recent token spans the UI keeps in each view (`--render-window`);
+    /// 0 keeps them all.
+    render_window: usize,
+    /// How the UI renders each token's alternatives (`--alt-display`).
+    alt_display: AltDisplay,
+    /// EMA weight for `/stream` heatmap smoothing (`--importance-smoothing`).
+    importance_smoothing: Option<f64>,
+    /// Inline-mapping template for transformed `/stream` tokens (`--inline-mapping`).
+    inline_mapping: Option<String>,
+    /// Collapse whitespace in `/stream` token text (`--collapse-whitespace`).
+    collapse_whitespace: bool,
+    /// Unicode normalization of emitted token text (`--normalize`).
+    normalize: crate::Normalization,
+    /// Also normalize each token's original (`--normalize-original`).
+    normalize_original: bool,
+    /// Fail a stream on an unparseable provider SSE line (`--strict-parsing`).
+    strict_parsing: bool,
+    /// How long a disconnected collaborator stays away before leaving their
+    /// room (`--reconnect-grace`).
+    reconnect_grace: std::time::Duration,
+    /// Longest collaborator display name kept by `set_name` (`--max-name-chars`).
+    max_name_chars: usize,
+    /// Events kept per room recording (`--max-recording-events`).
+    max_recording_events: usize,
+    /// Slots for upstream provider requests on `/stream`, `/batch-stream`,
+    /// `/diff-stream` and `/ab-stream` (`--max-concurrency`); `None` is
+    /// unlimited.
+    upstream: Option<Arc<Semaphore>>,
+    /// Extra provider request headers (`--header`).
+    extra_headers: Vec<(String, String)>,
+    /// `--budget-usd` router shared by every request the server makes.
+    budget: crate::cli::SharedBudget,
+    /// Settings of the parameterised transforms (`--noise-chars`,
+    /// `--noise-count`, `--zalgo-intensity`), for streams, previews and room retransforms.
+    transform_options: TransformOptions,
+}
+
+impl StreamSettings {
+    fn from_args(args: &Args) -> Self {
+        Self {
+            sse_buffer_size: args.sse_buffer_size,
+            sse_retry_ms: args.sse_retry_ms,
+            sse_batch: args.sse_batch as usize,
+            render_rate: args.render_rate,
+            max_prompt_chars: args.max_prompt_chars,
+            reveal_delay_ms: args.reveal_delay.filter(|&ms| ms > 0),
+            logprobs: !args.no_logprobs,
+            emit_granularity: args.emit_granularity,
+            render_window: args.render_window,
+            alt_display: args.alt_display,
+            importance_smoothing: args.importance_smoothing,
+            inline_mapping: args.inline_mapping.clone(),
+            collapse_whitespace: args.collapse_whitespace,
+            normalize: args.normalize,
+            normalize_original: args.normalize_original,
+            strict_parsing: args.strict_parsing,
+            reconnect_grace: std::time::Duration::from_secs(args.reconnect_grace),
+            max_name_chars: args.max_name_chars,
+            max_recording_events: args.max_recording_events,
+            upstream: args.max_concurrency.map(|n| Arc::new(Semaphore::new(n as usize))),
+            extra_headers: args.headers.clone(),
+            // `main` rejects an unusable --budget-usd before serving.
+            budget: crate::cli::SharedBudget::from_args(args).unwrap_or_default(),
+            // `main` rejects unusable --noise-chars / --noise-count likewise.
+            transform_options: crate::cli::resolve_transform_options(args).unwrap_or_default(),
+        }
+    }
+
+    /// Apply the server-wide provider settings to an interceptor built by
+    /// any streaming endpoint.
+    fn configure(&self, interceptor: TokenInterceptor) -> TokenInterceptor {
+        let mut interceptor = self
+            .budget
+            .attach(interceptor)
+            .with_max_prompt_chars(self.max_prompt_chars)
+            .with_extra_headers(self.extra_headers.clone())
+            .with_transform_options(self.transform_options.clone());
+        interceptor.normalize = self.normalize;
+        interceptor.normalize_original = self.normalize_original;
+        interceptor.strict_parsing = self.strict_parsing;
+        interceptor
+    }
+
+    /// Response head for an SSE endpoint, followed by the `retry:` field so
+    /// browsers back off for `--sse-retry-ms` before reconnecting.
+    fn sse_head(&self) -> String {
+        let mut head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: {}\r\n\r\n",
+            cors_origin()
+        );
+        if self.sse_retry_ms > 0 {
+            head.push_str(&format!("retry: {}\n\n", self.sse_retry_ms));
+        }
+        head
+    }
+
+    /// Leading `/stream` SSE event carrying the server version (for citations)
+    /// and any client display settings that differ from the UI defaults.
+    fn config_event(&self) -> String {
+        let mut event = serde_json::json!({ "type": "config", "version": crate::build_info::VERSION });
+        if let Some(reveal_delay_ms) = self.reveal_delay_ms {
+            event["reveal_delay_ms"] = reveal_delay_ms.into();
+        }
+        if self.emit_granularity != EmitGranularity::Word {
+            event["emit_granularity"] = self.emit_granularity.as_str().into();
+        }
+        let noise = &self.transform_options.noise;
+        if *noise != crate::transforms::NoiseConfig::default() {
+            event["noise_chars"] = noise.chars().iter().collect::<String>().into();
+            event["noise_count"] = noise.count().into();
+        }
+        if self.render_window != DEFAULT_RENDER_WINDOW {
+            event["render_window"] = self.render_window.into();
+        }
+        if self.alt_display != AltDisplay::default() {
+            event["alt_display"] = self.alt_display.as_str().into();
+        }
+        let zalgo = self.transform_options.zalgo_intensity;
+        if zalgo != crate::transforms::DEFAULT_ZALGO_INTENSITY {
+            event["zalgo_intensity"] = zalgo.into();
+        }
+        format!("data: {}\n\n", event)
+    }
+}
+
+/// A claim on one `--max-concurrency` slot, taken before an interceptor is
+/// spawned and held until its stream finishes.
+enum UpstreamSlot {
+    /// No limit is configured.
+    Unlimited,
+    /// A slot was free.
+    Ready(OwnedSemaphorePermit),
+    /// Every slot is busy; [`UpstreamSlot::wait`] blocks until one frees up.
+    Queued(Arc<Semaphore>),
+}
+
+impl UpstreamSlot {
+    fn claim(limit: Option<&Arc<Semaphore>>) -> Self {
+        match limit {
+            None => UpstreamSlot::Unlimited,
+            Some(sem) => match Arc::clone(sem).try_acquire_owned() {
+                Ok(permit) => UpstreamSlot::Ready(permit),
+                Err(_) => UpstreamSlot::Queued(Arc::clone(sem)),
+            },
+        }
+    }
+
+    fn is_queued(&self) -> bool {
+        matches!(self, UpstreamSlot::Queued(_))
+    }
+
+    /// Resolve to the held permit (if any), waiting for a slot when queued.
+    async fn wait(self) -> Option<OwnedSemaphorePermit> {
+        match self {
+            UpstreamSlot::Unlimited => None,
+            UpstreamSlot::Ready(permit) => Some(permit),
+            UpstreamSlot::Queued(sem) => sem.acquire_owned().await.ok(),
+        }
+    }
+}
+
+/// SSE event telling the client that `side` is waiting for an upstream slot.
+fn queued_event(side: &str) -> String {
+    format!(
+        "data: {}\n\n",
+        serde_json::json!({
+            "type": "queued",
+            "side": side,
+            "message": "Waiting for a free upstream slot (--max-concurrency)",
+        })
+    )
+}
+
+/// Models for the OpenAI and Anthropic sides of `/diff-stream`.
+///
+/// `openai_model` and `anthropic_model` set each side independently.  A
+/// shared `model` only fills the side whose provider serves it (see
+/// [`Provider::for_model`]), or both sides when its family is not
+/// recognised; any side left unset uses its provider's default.
+fn diff_stream_models(params: &std::collections::HashMap<String, String>) -> (String, String) {
+    let param = |key: &str| params.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let shared = param("model");
+    let side = |provider: Provider, key: &str| {
+        let shared = shared.filter(|m| !matches!(Provider::for_model(m), Some(p) if p != provider));
+        param(key)
+            .or(shared)
+            .map(str::to_string)
+            .unwrap_or_else(|| provider.default_model().to_string())
+    };
+    (side(Provider::Openai, "openai_model"), side(Provider::Anthropic, "anthropic_model"))
+}
+
+/// The background tasks behind a two-sided stream (`/diff-stream`,
+/// `/ab-stream`): each side's interceptor and its forwarder into the merged
+/// channel.  Dropping the set aborts any task still running, so a client that
+/// disconnects early does not leave provider requests streaming unobserved.
+#[derive(Default)]
+struct SideTasks(Vec<tokio::task::JoinHandle<()>>);
+
+impl SideTasks {
+    fn spawn<F>(&mut self, task: F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.0.push(tokio::spawn(task));
+    }
+}
+
+impl Drop for SideTasks {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// Write side-tagged events from `merged_rx` as SSE until both sides finish,
+/// calling `observe` on each.  Returns `false` when the client went away
+/// first; `tasks` is aborted in that case.
+async fn forward_side_events<W: tokio::io::AsyncWrite + Unpin>(
+    stream: &mut W,
+    merged_rx: &mut mpsc::UnboundedReceiver<(&'static str, TokenEvent)>,
+    tasks: SideTasks,
+    mut observe: impl FnMut(&'static str, &TokenEvent),
+) -> bool {
+    while let Some((side, event)) = merged_rx.recv().await {
+        observe(side, &event);
+        let diff_event = DiffTokenEvent {
+            side,
+            event: &event,
+        };
+        if let Ok(json) = serde_json::to_string(&diff_event) {
+            let sse = format!("data: {}\n\n", json);
+            if stream.write_all(sse.as_bytes()).await.is_err() {
+                drop(tasks);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// How `/stream` reveals each token to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum EmitGranularity {
+    /// One event per token.
+    #[default]
+    Word,
+    /// One event per character of the transformed token, for a typewriter
+    /// effect.  The transform is still applied to the whole token.
+    Char,
+}
+
+impl EmitGranularity {
+    /// Parse `word` or `char` (case-insensitive).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "word" => Some(Self::Word),
+            "char" => Some(Self::Char),
+            _ => None,
+        }
+    }
+
+    /// Lowercase name, as accepted by [`EmitGranularity::parse`].
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Word => "word",
+            Self::Char => "char",
+        }
+    }
+}
+
+/// How the web UI shows a token's top alternatives (`--alt-display`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum AltDisplay {
+    /// Alternatives and their probabilities in the token's hover tooltip.
+    #[default]
+    Tooltip,
+    /// A mini bar chart of alternative probabilities after each token.
+    Bars,
+    /// Alternatives are not shown.
+    None,
+}
+
+impl AltDisplay {
+    /// Lowercase name, as sent in the `/stream` config event.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Tooltip => "tooltip",
+            Self::Bars => "bars",
+            Self::None => "none",
+        }
+    }
+}
+
+/// One character of a token streamed in [`EmitGranularity::Char`] mode.
+///
+/// `text` holds the single character; every other field describes the whole
+/// token, so the client appends characters with `char_index > 0` to the token
+/// started by `char_index == 0`.
+#[derive(Debug, Serialize)]
+struct CharTokenEvent {
+    #[serde(flatten)]
+    event: TokenEvent,
+    char_index: usize,
+    char_count: usize,
+}
+
+/// JSON payloads for one `/stream` token event at `granularity`.  Tokens of
+/// at most one character are always sent whole.
+fn emit_payloads(event: &TokenEvent, granularity: EmitGranularity) -> Vec<String> {
+    let char_count = event.text.chars().count();
+    if granularity == EmitGranularity::Word || char_count <= 1 {
+        return serde_json::to_string(event).into_iter().collect();
+    }
+    event
+        .text
+        .chars()
+        .enumerate()
+        .filter_map(|(char_index, c)| {
+            serde_json::to_string(&CharTokenEvent {
+                event: TokenEvent {
+                    text: c.to_string(),
+                    ..event.clone()
+                },
+                char_index,
+                char_count,
+            })
+            .ok()
+        })
+        .collect()
+}
+
+/// Groups `/stream` token payloads into SSE frames of up to `size` events
+/// (`--sse-batch`).  A lone event is sent as a bare JSON object, as without
+/// batching; several are sent as one JSON array, which the UI unpacks.
+struct SseBatcher {
+    size: usize,
+    pending: Vec<String>,
+}
+
+impl SseBatcher {
+    fn new(size: usize) -> Self {
+        Self { size: size.max(1), pending: Vec::new() }
+    }
+
+    /// Queue one serialized event; returns a frame once `size` are waiting.
+    fn push(&mut self, payload: String) -> Option<String> {
+        self.pending.push(payload);
+        (self.pending.len() >= self.size).then(|| self.frame())
+    }
+
+    /// A frame for whatever is still waiting, if anything.
+    fn flush(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| self.frame())
+    }
+
+    fn frame(&mut self) -> String {
+        let frame = if self.pending.len() == 1 {
+            format!("data: {}\n\n", self.pending[0])
+        } else {
+            format!("data: [{}]\n\n", self.pending.join(","))
+        };
+        self.pending.clear();
+        frame
+    }
+}
+
+/// SSE body reporting `message` as an error, followed by the `[DONE]` sentinel.
+fn sse_error_done(message: &str) -> String {
+    format!(
+        "data: {}\n\ndata: [DONE]\n\n",
+        serde_json::json!({ "error": message })
+    )
+}
+
+/// SSE event carrying the prompt a stream actually sent, when enrichment
+/// rewrote it, so UI exports can record it.
+fn effective_prompt_event(sent: &str) -> String {
+    format!(
+        "data: {}\n\n",
+        serde_json::json!({ "type": "effective_prompt", "effective_prompt": sent })
+    )
+}
+
+/// JSON error response with HTTP `status` reporting `message`, with the
+/// CORS header so cross-origin clients can read the error.
+fn error_response(status: &str, message: &str) -> String {
+    let body = serde_json::json!({ "error": message }).to_string();
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        cors_origin(),
+        body
+    )
+}
+
+/// `400 Bad Request` [`error_response`] reporting `message`.
+fn bad_request_response(message: &str) -> String {
+    error_response("400 Bad Request", message)
+}
 
 /// Per-IP sliding-window rate limiter for the /stream endpoint.
 /// Allows at most `MAX_REQUESTS` requests in `WINDOW_SECS` seconds per IP.
@@ -72,11 +521,43 @@ fn cors_origin() -> String {
     std::env::var("CORS_ORIGIN").unwrap_or_else(|_| "*".to_string())
 }
 
-// Default model names used when the query string omits a model parameter.
-// Centralised here so web.rs, cli.rs, and lib.rs all stay in sync.
-const DEFAULT_OPENAI_MODEL: &str = "gpt-3.5-turbo";
-const DEFAULT_ANTHROPIC_MODEL: &str = "claude-sonnet-4-6";
-const DEFAULT_MOCK_MODEL: &str = "mock-fixture-v1";
+/// Server-side SSE pacing for `--render-rate`.
+///
+/// Spaces token emission on `/stream` at most `rate` tokens per second so the
+/// UI fade-in stays smooth when a fast model delivers tokens in bursts.  Tokens
+/// are delayed, never dropped, and the provider stream keeps being consumed at
+/// full speed into the unbounded channel behind the pacer.
+struct TokenPacer {
+    interval: Option<Duration>,
+    next_at: Option<tokio::time::Instant>,
+}
+
+impl TokenPacer {
+    /// `None`, zero, or a non-finite rate disables pacing.
+    fn new(rate: Option<f64>) -> Self {
+        let interval = rate
+            .filter(|r| r.is_finite() && *r > 0.0)
+            .map(|r| Duration::from_secs_f64(1.0 / r));
+        Self { interval, next_at: None }
+    }
+
+    /// Wait until the next emission slot.  Returns immediately when pacing is
+    /// disabled or the stream has been idle for longer than one interval.
+    async fn pace(&mut self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let now = tokio::time::Instant::now();
+        let slot = match self.next_at {
+            Some(at) if at > now => {
+                tokio::time::sleep_until(at).await;
+                at
+            }
+            _ => now,
+        };
+        self.next_at = Some(slot + interval);
+    }
+}
 
 /// Wraps a `TokenEvent` with a provider-side label for diff streaming.
 #[derive(Debug, Serialize)]
@@ -86,10 +567,493 @@ struct DiffTokenEvent<'a> {
     event: &'a TokenEvent,
 }
 
+/// Per-side totals for `/diff-stream`, reported as a final `diff_summary`
+/// event before `[DONE]`.
+///
+/// The two sides are aligned by a longest-common-subsequence match over the
+/// original (pre-transform) token texts ([`crate::token_align`], shared with
+/// the A/B export), so one extra token on either side does not push every
+/// later comparison out of step.
+#[derive(Debug, Default)]
+struct DiffSummary {
+    openai: Vec<String>,
+    anthropic: Vec<String>,
+    openai_perplexity: Vec<f64>,
+    anthropic_perplexity: Vec<f64>,
+}
+
+impl DiffSummary {
+    /// Record `event` against `side` (`"openai"` or `"anthropic"`).
+    fn observe(&mut self, side: &str, event: &TokenEvent) {
+        if event.is_error {
+            return;
+        }
+        let (tokens, perplexities) = match side {
+            "openai" => (&mut self.openai, &mut self.openai_perplexity),
+            "anthropic" => (&mut self.anthropic, &mut self.anthropic_perplexity),
+            _ => return,
+        };
+        tokens.push(event.original.trim().to_string());
+        if let Some(p) = event.perplexity.filter(|p| p.is_finite()) {
+            perplexities.push(p as f64);
+        }
+    }
+
+    /// Build the `diff_summary` event.  `match_pct` is the aligned token count
+    /// as a percentage of the longer side; `mean_perplexity` is `null` for a
+    /// side that reported no logprobs.
+    fn summary(&self) -> serde_json::Value {
+        let mean = |v: &[f64]| {
+            if v.is_empty() {
+                None
+            } else {
+                Some(v.iter().sum::<f64>() / v.len() as f64)
+            }
+        };
+        let matched = crate::token_align::lcs_len(&self.openai, &self.anthropic);
+        let match_pct = crate::token_align::match_pct(matched, self.openai.len(), self.anthropic.len());
+        serde_json::json!({
+            "type": "diff_summary",
+            "openai": {
+                "token_count": self.openai.len(),
+                "mean_perplexity": mean(&self.openai_perplexity),
+            },
+            "anthropic": {
+                "token_count": self.anthropic.len(),
+                "mean_perplexity": mean(&self.anthropic_perplexity),
+            },
+            "matched": matched,
+            "match_pct": match_pct,
+        })
+    }
+}
+
+/// Most prompts accepted by a single `POST /batch-stream` request.
+const BATCH_STREAM_MAX_PROMPTS: usize = 50;
+
+/// `POST /batch-stream` body: a bare JSON array of prompts, or an object with
+/// `prompts` plus optional `transform`, `provider`, `model`, and `rate`.
+#[derive(Debug, Default, serde::Deserialize)]
+struct BatchStreamRequest {
+    prompts: Vec<String>,
+    #[serde(default)]
+    transform: Option<String>,
+    #[serde(default)]
+    provider: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    rate: Option<f64>,
+}
+
+impl BatchStreamRequest {
+    fn parse(body: &[u8]) -> Result<Self, String> {
+        let req = match serde_json::from_slice::<Vec<String>>(body) {
+            Ok(prompts) => Self {
+                prompts,
+                ..Self::default()
+            },
+            Err(_) => serde_json::from_slice::<Self>(body).map_err(|_| {
+                r#"body must be a JSON array of prompts or {"prompts":[...]}"#.to_string()
+            })?,
+        };
+        if req.prompts.is_empty() || req.prompts.len() > BATCH_STREAM_MAX_PROMPTS {
+            return Err(format!("prompts must be 1-{} items", BATCH_STREAM_MAX_PROMPTS));
+        }
+        if let Some(t) = &req.transform {
+            transform_param(t)?;
+        }
+        if let Some(p) = &req.provider {
+            p.parse::<Provider>().map_err(|e| format!("provider: {}", e))?;
+        }
+        if let Some(r) = req.rate {
+            rate_value(r)?;
+        }
+        Ok(req)
+    }
+}
+
+/// Wraps a `TokenEvent` with the index of the batch prompt that produced it.
+#[derive(Debug, Serialize)]
+struct BatchTokenEvent<'a> {
+    prompt_index: usize,
+    #[serde(flatten)]
+    event: &'a TokenEvent,
+}
+
+/// Running totals for one prompt of a `/batch-stream` run.
+#[derive(Debug, Default)]
+struct BatchPromptStats {
+    token_count: usize,
+    transformed_count: usize,
+    perplexities: Vec<f64>,
+    error: Option<String>,
+}
+
+impl BatchPromptStats {
+    fn observe(&mut self, event: &TokenEvent) {
+        if event.is_error {
+            return;
+        }
+        self.token_count += 1;
+        if event.transformed {
+            self.transformed_count += 1;
+        }
+        if let Some(p) = event.perplexity.filter(|p| p.is_finite()) {
+            self.perplexities.push(p as f64);
+        }
+    }
+
+    /// The `prompt_done` event for prompt `index` of `prompt_count`.
+    fn done_event(&self, index: usize, prompt_count: usize) -> serde_json::Value {
+        serde_json::json!({
+            "type": "prompt_done",
+            "prompt_index": index,
+            "prompt_count": prompt_count,
+            "token_count": self.token_count,
+            "transformed_count": self.transformed_count,
+            "mean_perplexity": mean_of(&self.perplexities),
+            "error": self.error,
+        })
+    }
+}
+
+fn mean_of(v: &[f64]) -> Option<f64> {
+    if v.is_empty() {
+        None
+    } else {
+        Some(v.iter().sum::<f64>() / v.len() as f64)
+    }
+}
+
+/// Final `batch_summary` event: totals across every prompt plus the
+/// per-prompt `prompt_done` events.
+fn batch_summary(stats: &[BatchPromptStats]) -> serde_json::Value {
+    let perplexities: Vec<f64> = stats.iter().flat_map(|s| s.perplexities.iter().copied()).collect();
+    serde_json::json!({
+        "type": "batch_summary",
+        "prompt_count": stats.len(),
+        "token_count": stats.iter().map(|s| s.token_count).sum::<usize>(),
+        "transformed_count": stats.iter().map(|s| s.transformed_count).sum::<usize>(),
+        "mean_perplexity": mean_of(&perplexities),
+        "failed": stats.iter().filter(|s| s.error.is_some()).count(),
+        "prompts": stats
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s.done_event(i, stats.len()))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Run each prompt of `req` through its own interceptor in turn, writing its
+/// `prompt_index`-tagged token events and a `prompt_done` event, then a final
+/// `batch_summary` event and `[DONE]`.  A failing prompt is reported in its
+/// `prompt_done` event and the batch carries on with the next one.
+async fn write_batch_stream<W: tokio::io::AsyncWrite + Unpin>(
+    out: &mut W,
+    req: &BatchStreamRequest,
+    default_provider: &Provider,
+    orchestrator: bool,
+    settings: &StreamSettings,
+) -> std::io::Result<()> {
+    let provider = req
+        .provider
+        .as_deref()
+        .and_then(|p| p.parse::<Provider>().ok())
+        .unwrap_or_else(|| default_provider.clone());
+    let model = match req.model.as_deref().filter(|m| !m.is_empty()) {
+        Some(m) => m.to_string(),
+        None => provider.default_model().to_string(),
+    };
+    // `BatchStreamRequest::parse` has already rejected bad values.
+    let transform = req
+        .transform
+        .as_deref()
+        .and_then(|t| transform_param(t).ok())
+        .unwrap_or(Transform::Reverse);
+    let rate = req.rate.and_then(|r| rate_value(r).ok()).unwrap_or(0.5);
+
+    let mut all_stats = Vec::with_capacity(req.prompts.len());
+    for (index, prompt) in req.prompts.iter().enumerate() {
+        let mut stats = BatchPromptStats::default();
+        if let Err(msg) = crate::check_prompt_length(prompt, settings.max_prompt_chars) {
+            stats.error = Some(msg);
+        } else {
+            match TokenInterceptor::new(
+                provider.clone(),
+                transform.clone(),
+                model.clone(),
+                false,
+                false,
+                orchestrator,
+            )
+            .map_err(|e| e.to_string())
+            {
+                Ok(interceptor) => {
+                    let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
+                    let mut interceptor =
+                        settings.configure(interceptor).with_rate(rate).with_logprobs(settings.logprobs);
+                    interceptor.web_tx = Some(tx);
+                    let slot = UpstreamSlot::claim(settings.upstream.as_ref());
+                    if slot.is_queued() {
+                        out.write_all(queued_event("batch").as_bytes()).await?;
+                    }
+                    let prompt = prompt.clone();
+                    let task = tokio::spawn(async move {
+                        let _permit = slot.wait().await;
+                        interceptor.intercept_stream(&prompt).await.map_err(|e| e.to_string())
+                    });
+                    while let Some(event) = rx.recv().await {
+                        stats.observe(&event);
+                        let tagged = BatchTokenEvent {
+                            prompt_index: index,
+                            event: &event,
+                        };
+                        if let Ok(json) = serde_json::to_string(&tagged) {
+                            out.write_all(format!("data: {}\n\n", json).as_bytes()).await?;
+                        }
+                    }
+                    match task.await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(msg)) => stats.error = Some(msg),
+                        Err(e) => stats.error = Some(e.to_string()),
+                    }
+                }
+                Err(msg) => stats.error = Some(msg),
+            }
+        }
+        let done = stats.done_event(index, req.prompts.len());
+        out.write_all(format!("data: {}\n\n", done).as_bytes()).await?;
+        all_stats.push(stats);
+    }
+
+    out.write_all(format!("data: {}\n\n", batch_summary(&all_stats)).as_bytes())
+        .await?;
+    out.write_all(b"data: [DONE]\n\n").await
+}
+
+/// Body of a POST request whose first bytes (`head`, headers included) were
+/// already read, topped up from `stream` until `Content-Length` bytes arrive
+/// or `max` bytes have been read.
+pub(crate) async fn read_request_body(
+    stream: &mut tokio::net::TcpStream,
+    head: &[u8],
+    max: usize,
+) -> std::io::Result<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+
+    let Some(header_end) = head.windows(4).position(|w| w == b"\r\n\r\n").map(|p| p + 4) else {
+        return Ok(Vec::new());
+    };
+    let content_length = String::from_utf8_lossy(&head[..header_end])
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())
+                .flatten()
+        })
+        .unwrap_or(0)
+        .min(max);
+    let mut body = head[header_end..].to_vec();
+    let mut chunk = vec![0u8; 8192];
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+    Ok(body)
+}
+
+/// Per-side sampling settings for `/ab-stream`, read from `max_a`/`max_b`
+/// and `temp_a`/`temp_b`.  A side without its own value keeps the
+/// interceptor default, so both sides are equal unless configured otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct AbSideConfig {
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+}
+
+impl AbSideConfig {
+    /// Parse the settings for `side` (`"a"` or `"b"`); malformed values are ignored.
+    fn from_params(params: &HashMap<String, String>, side: &str) -> Self {
+        Self {
+            max_tokens: params
+                .get(&format!("max_{side}"))
+                .and_then(|v| v.parse::<u32>().ok())
+                .filter(|&n| n > 0),
+            temperature: params
+                .get(&format!("temp_{side}"))
+                .and_then(|v| v.parse::<f32>().ok())
+                .filter(|t| t.is_finite()),
+        }
+    }
+
+    /// Apply the configured overrides to `interceptor`.
+    fn apply(self, mut interceptor: TokenInterceptor) -> TokenInterceptor {
+        if let Some(n) = self.max_tokens {
+            interceptor = interceptor.with_max_tokens(n);
+        }
+        if let Some(t) = self.temperature {
+            interceptor = interceptor.with_temperature(t);
+        }
+        interceptor
+    }
+}
+
+/// Web A/B run recorded as a `self_tune::experiment` experiment.
+///
+/// Side `a` is the control arm and side `b` the treatment arm.  Each token's
+/// perplexity (or `1 - confidence` when the provider reports no perplexity) is
+/// recorded as a sample; lower is better, matching the framework's convention.
+#[cfg(feature = "self-tune")]
+struct AbExperiment {
+    experiment: crate::self_tune::experiment::Experiment,
+}
+
+#[cfg(feature = "self-tune")]
+impl AbExperiment {
+    /// Minimum samples per arm before the significance test runs.
+    const MIN_SAMPLES: usize = 5;
+
+    fn new() -> Self {
+        use crate::self_tune::experiment::{Experiment, ExperimentSpec};
+        Self {
+            experiment: Experiment::new(ExperimentSpec {
+                name: "web-ab-stream".into(),
+                parameter: "system_prompt".into(),
+                control_value: 0.0,
+                treatment_value: 1.0,
+                traffic_split: 0.5,
+                min_samples: Self::MIN_SAMPLES,
+                ..ExperimentSpec::default()
+            }),
+        }
+    }
+
+    /// Record the metric carried by `event` against the arm for `side`.
+    fn observe(&mut self, side: &str, event: &TokenEvent) {
+        use crate::self_tune::experiment::Variant;
+        let metric = match (event.perplexity, event.confidence) {
+            (Some(p), _) => p as f64,
+            (None, Some(c)) => 1.0 - c as f64,
+            (None, None) => return,
+        };
+        let variant = if side == "a" {
+            Variant::Control
+        } else {
+            Variant::Treatment
+        };
+        self.experiment.record(variant, metric);
+    }
+
+    /// Run the significance test and build the `experiment_result` event.
+    fn verdict(&mut self) -> serde_json::Value {
+        use crate::self_tune::experiment::{ExperimentStatus, Variant};
+        self.experiment.maybe_conclude();
+        let winner = match self.experiment.status {
+            ExperimentStatus::Concluded { winner: Variant::Control } => Some("a"),
+            ExperimentStatus::Concluded { winner: Variant::Treatment } => Some("b"),
+            _ => None,
+        };
+        let p_value = self
+            .experiment
+            .last_test_result
+            .as_ref()
+            .map(|r| r.p_value)
+            .filter(|p| p.is_finite());
+        serde_json::json!({
+            "type": "experiment_result",
+            "winner": winner,
+            "p_value": p_value,
+            "significant": winner.is_some(),
+            "metric": "perplexity",
+            "samples_a": self.experiment.control.count(),
+            "samples_b": self.experiment.treatment.count(),
+        })
+    }
+}
+
 /// Embedded single-page HTML application with side-by-side, multi-transform,
 /// dependency graph, and export features.
 pub const INDEX_HTML: &str = include_str!("../static/index.html");
 
+/// Strong `ETag` for [`INDEX_HTML`]: a quoted SHA-1 of the page, computed
+/// once.  Changes whenever a rebuilt binary ships a different UI.
+static INDEX_ETAG: once_cell::sync::Lazy<String> = once_cell::sync::Lazy::new(|| {
+    use sha1::{Digest, Sha1};
+    let digest = Sha1::digest(INDEX_HTML.as_bytes());
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+});
+
+/// `true` if an `If-None-Match` header value matches `etag` (`*`, or any
+/// listed tag, compared weakly as RFC 9110 requires for this header).
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || strip_weak(tag) == strip_weak(etag))
+}
+
+/// Whether an `Accept-Encoding` value allows gzip (`gzip` or `*`, not `q=0`).
+fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|part| {
+        let mut fields = part.split(';').map(str::trim);
+        let coding = fields.next().unwrap_or("");
+        let refused = fields.any(|p| {
+            p.strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q <= 0.0)
+        });
+        (coding.eq_ignore_ascii_case("gzip") || coding == "*") && !refused
+    })
+}
+
+/// Full `/replay/CODE` response with the `{"events":[...]}` body gzipped.
+fn replay_gzip_response(events: &[crate::collab::RecordedEvent]) -> Vec<u8> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let body = serde_json::to_vec(&serde_json::json!({ "events": events })).unwrap_or_default();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let gzipped = encoder
+        .write_all(&body)
+        .and_then(|_| encoder.finish())
+        .unwrap_or_default();
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Encoding: gzip\r\nVary: Accept-Encoding\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        gzipped.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(&gzipped);
+    response
+}
+
+/// Full HTTP response for `GET /` and `GET /join/CODE`: `304 Not Modified`
+/// when the client's `If-None-Match` matches [`INDEX_ETAG`], otherwise the
+/// page with its `ETag`.  `Cache-Control: no-cache` makes browsers revalidate
+/// on every load, so a deploy is picked up immediately.
+fn index_response(if_none_match: Option<&str>) -> String {
+    let etag = INDEX_ETAG.as_str();
+    if if_none_match.is_some_and(|v| etag_matches(v, etag)) {
+        return format!(
+            "HTTP/1.1 304 Not Modified\r\nETag: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+            etag
+        );
+    }
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nETag: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n{}",
+        INDEX_HTML.len(),
+        etag,
+        INDEX_HTML,
+    )
+}
+
 /// Simple percent-decoding for URL query parameters.
 ///
 /// Accumulates decoded bytes in a staging buffer and flushes via
@@ -163,87 +1127,644 @@ pub fn parse_query(query: &str) -> std::collections::HashMap<String, String> {
 struct StreamParams {
     prompt: String,
     transform: String,
+    /// Canonical provider name (`openai`, `anthropic` or `mock`).
     provider: String,
     model: String,
     rate: f64,
     seed: Option<u64>,
     top_logprobs: u8,
+    /// Explicit `logprobs=0|1`; `None` falls back to [`StreamSettings::logprobs`].
+    logprobs: Option<bool>,
+    /// Explicit `granularity=word|char`; `None` falls back to
+    /// [`StreamSettings::emit_granularity`].
+    granularity: Option<EmitGranularity>,
     system: Option<String>,
     visual: bool,
     heatmap: bool,
 }
 
-fn parse_stream_params(query: &std::collections::HashMap<String, String>) -> StreamParams {
-    StreamParams {
-        prompt: query.get("prompt").cloned().unwrap_or_default(),
-        transform: query
+impl StreamParams {
+    /// Parse and validate the query parameters shared by `/stream`,
+    /// `/diff-stream` and `/ab-stream`.
+    ///
+    /// # Errors
+    /// Returns a message naming the parameter for an unknown provider,
+    /// transform or granularity, a non-numeric `rate`, `seed` or
+    /// `top_logprobs`, or a boolean flag that is not `1`/`0`/`true`/`false`.
+    fn from_query(query: &std::collections::HashMap<String, String>) -> Result<Self, String> {
+        let provider = match query.get("provider") {
+            Some(p) => p.parse::<Provider>().map_err(|e| format!("provider: {}", e))?.to_string(),
+            None => "openai".to_string(),
+        };
+        let transform = query
             .get("transform")
             .cloned()
-            .unwrap_or_else(|| "reverse".to_string()),
-        provider: query
-            .get("provider")
-            .cloned()
-            .unwrap_or_else(|| "openai".to_string()),
-        model: query.get("model").cloned().unwrap_or_default(),
-        rate: query
-            .get("rate")
-            .and_then(|r| r.parse::<f64>().ok())
-            .filter(|r| r.is_finite())
-            .map(|r| r.clamp(0.0, 1.0))
-            .unwrap_or(0.5),
-        seed: query.get("seed").and_then(|s| s.parse().ok()),
-        top_logprobs: query
-            .get("top_logprobs")
-            .and_then(|t| t.parse::<u8>().ok())
-            .map(|v| v.clamp(0, 20))
-            .unwrap_or(5),
-        system: query.get("system").filter(|s| !s.is_empty()).cloned(),
-        visual: query
-            .get("visual")
-            .map(|v| v == "1" || v == "true")
-            .unwrap_or(false),
-        heatmap: query
-            .get("heatmap")
-            .map(|v| v == "1" || v == "true")
-            .unwrap_or(false),
+            .unwrap_or_else(|| "reverse".to_string());
+        transform_param(&transform)?;
+        let seed = match query.get("seed") {
+            Some(s) => Some(
+                s.parse::<u64>()
+                    .map_err(|_| format!("seed: expected a non-negative integer, got '{}'", s))?,
+            ),
+            None => None,
+        };
+        let rate = match query.get("rate") {
+            Some(r) => rate_value(
+                r.parse::<f64>()
+                    .map_err(|_| format!("rate: expected a number between 0 and 1, got '{}'", r))?,
+            )?,
+            None => 0.5,
+        };
+        let top_logprobs = match query.get("top_logprobs") {
+            Some(t) => t
+                .parse::<u8>()
+                .map_err(|_| format!("top_logprobs: expected an integer from 0 to 20, got '{}'", t))?
+                .clamp(0, 20),
+            None => 5,
+        };
+        let granularity = match query.get("granularity") {
+            Some(g) => Some(
+                EmitGranularity::parse(g)
+                    .ok_or_else(|| format!("granularity: expected word or char, got '{}'", g))?,
+            ),
+            None => None,
+        };
+        Ok(StreamParams {
+            prompt: query.get("prompt").cloned().unwrap_or_default(),
+            transform,
+            provider,
+            model: query.get("model").cloned().unwrap_or_default(),
+            rate,
+            seed,
+            top_logprobs,
+            logprobs: bool_param(query, "logprobs")?,
+            granularity,
+            system: query.get("system").filter(|s| !s.is_empty()).cloned(),
+            visual: bool_param(query, "visual")?.unwrap_or(false),
+            heatmap: bool_param(query, "heatmap")?.unwrap_or(false),
+        })
     }
 }
 
-/// # HTTP API
-///
-/// ## Endpoints
-///
-/// - `GET /` — Serves the embedded single-page web UI
-///
-/// - `GET /stream?prompt=...&transform=...&provider=...&model=...&rate=...`  
-///   Server-Sent Events stream of [`TokenEvent`] JSON objects.  
-///   Each event: `data: {"text":"...","index":N,"transformed":bool,...}`
-///
-/// - `GET /diff-stream?prompt=...&transform=...`  
-///   SSE stream with two providers side-by-side; each event includes `"side":"openai"|"anthropic"`.
-///
-/// - `GET /ab-stream?prompt=...&system_a=...&system_b=...`  
-///   SSE stream for A/B experiment mode.
-///
-/// - `POST /room/create` — Creates a multiplayer room, returns `{"code":"SWIFT-LION-42","room_id":"<uuid>","ws_url":"/ws/SWIFT-LION-42"}`.
-///
-/// - `GET /join/CODE` — Returns room join HTML page.
-///
-/// - `WS /ws/CODE` — WebSocket connection for multiplayer collaboration.  
-///   **Inbound message types** (JSON):  
-///   `{"type":"set_name","name":"..."}` — Update display name (max 64 chars)  
-///   `{"type":"vote","transform":"...","dir":"up"|"down"}` — Cast a vote  
-///   `{"type":"surgery","token_index":N,"new_text":"...","old_text":"..."}` — Edit a token  
-///   `{"type":"chat","text":"...","token_index":N}` — Send a chat message  
-///   `{"type":"record_start"}` / `{"type":"record_stop"}` — Recording control  
-///   `{"type":"token",...}` — Host broadcasts a token event to guests  
-///   **Outbound event types**: `welcome`, `participant_join`, `participant_leave`,  
-///   `participant_update`, `vote_update`, `surgery`, `chat`, `record_started`,  
-///   `record_stopped`, `replay_event`, `replay_done`, `stream_done`, `pong`, `error`
-pub async fn serve(port: u16, default_args: &Args) -> Result<(), Box<dyn std::error::Error>> {
-    tracing::info!(port, "binding web UI server");
-    let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
-    tracing::info!(port, "web UI server listening");
+/// Parse a request's `transform` value, naming the parameter on error.
+fn transform_param(transform: &str) -> Result<Transform, String> {
+    Transform::from_str_loose(transform).map_err(|e| format!("transform: {}", e))
+}
+
+/// Validate a request's transform `rate`: finite, clamped into `[0, 1]`.
+fn rate_value(rate: f64) -> Result<f64, String> {
+    if rate.is_finite() {
+        Ok(rate.clamp(0.0, 1.0))
+    } else {
+        Err(format!("rate: expected a number between 0 and 1, got '{}'", rate))
+    }
+}
+
+/// Boolean query flag `key`: `1`/`true` or `0`/`false`, `None` when absent.
+fn bool_param(query: &std::collections::HashMap<String, String>, key: &str) -> Result<Option<bool>, String> {
+    match query.get(key).map(String::as_str) {
+        None => Ok(None),
+        Some("1") | Some("true") => Ok(Some(true)),
+        Some("0") | Some("false") => Ok(Some(false)),
+        Some(other) => Err(format!("{}: expected 1, 0, true or false, got '{}'", key, other.chars().take(32).collect::<String>())),
+    }
+}
+
+/// Resolve the transform for a `/stream` request: the locked transform of the
+/// first listed room that has one, otherwise `requested`.
+fn room_transform(store: &RoomStore, rooms: &[String], requested: String) -> String {
+    rooms
+        .iter()
+        .find_map(|code| crate::collab::locked_transform(store, code))
+        .unwrap_or(requested)
+}
+
+/// Parse the `/stream` `room` param: one code or a comma-separated list
+/// (`room=A,B,C`).  Blank entries and repeats are dropped; order is kept.
+fn parse_room_codes(param: Option<&str>) -> Vec<String> {
+    let mut codes: Vec<String> = Vec::new();
+    for code in param.unwrap_or("").split(',').map(str::trim) {
+        if !code.is_empty() && !codes.iter().any(|c| c == code) {
+            codes.push(code.to_string());
+        }
+    }
+    codes
+}
+
+/// Hand one `/stream` token to every room in `rooms`: retained for late
+/// joiners, broadcast (or held while paused), and recorded if recording.
+/// `is_error` notices are only broadcast, so they stay out of exports.
+fn fan_out_stream_token(store: &RoomStore, rooms: &[String], token: &serde_json::Value) {
+    if token["is_error"] == true {
+        for code in rooms {
+            crate::collab::broadcast_token(store, code, token.clone());
+        }
+        return;
+    }
+    for code in rooms {
+        crate::collab::record_session_token(store, code, token.clone());
+        crate::collab::broadcast_token(store, code, token.clone());
+        crate::collab::maybe_record(store, code, token.clone());
+    }
+}
+
+/// `true` if `id` is an acceptable `/ws/CODE?client_id=...` value: 1–64
+/// ASCII alphanumerics or dashes (a browser-generated UUID fits).
+fn valid_client_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// `POST /api/load-session`: parse an exported bundle and return its tokens
+/// with counts recomputed from them.
+fn load_session_response(body: &[u8]) -> (&'static str, String) {
+    let session = match crate::replay::ExportedSession::parse(&String::from_utf8_lossy(body)) {
+        Ok(session) => session,
+        Err(msg) => return ("400 Bad Request", serde_json::json!({ "error": msg }).to_string()),
+    };
+    let (token_count, transformed_count) = session.loaded_counts();
+    (
+        "200 OK",
+        serde_json::json!({
+            "prompt": session.prompt,
+            "transform": session.transform,
+            "token_count": token_count,
+            "transformed_count": transformed_count,
+            "recorded_counts_match": session.matches_recorded_counts(),
+            "tokens": session.tokens,
+        })
+        .to_string(),
+    )
+}
+
+/// The built-in demo session for `GET /demo`: `session` names one of
+/// [`crate::replay::BUILTIN_DEMOS`] (default: the first).  The error is the
+/// JSON body for a `404`.
+fn demo_session(params: &HashMap<String, String>) -> Result<crate::replay::ExportedSession, String> {
+    let name = params
+        .get("session")
+        .map(String::as_str)
+        .unwrap_or(crate::replay::BUILTIN_DEMOS[0].0);
+    crate::replay::ExportedSession::builtin(name).ok_or_else(|| {
+        let available: Vec<&str> = crate::replay::BUILTIN_DEMOS.iter().map(|(demo, _)| *demo).collect();
+        serde_json::json!({ "error": format!("unknown demo session: {}", name), "available": available })
+            .to_string()
+    })
+}
+
+/// Write `session`'s tokens as SSE `data:` events with their recorded
+/// timing scaled by `speed` (`0` = instant), then `[DONE]`.
+async fn write_demo_stream<W: tokio::io::AsyncWrite + Unpin>(
+    out: &mut W,
+    session: &crate::replay::ExportedSession,
+    speed: f64,
+) -> std::io::Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let records = session.replay_records();
+    let replay = tokio::spawn(async move {
+        let _ = crate::replay::Replayer::replay_to_channel_timed(records, tx, speed).await;
+    });
+    while let Some(event) = rx.recv().await {
+        let json = serde_json::to_string(&event).unwrap_or_default();
+        if let Err(e) = out.write_all(format!("data: {}\n\n", json).as_bytes()).await {
+            replay.abort();
+            return Err(e);
+        }
+    }
+    out.write_all(b"data: [DONE]\n\n").await
+}
+
+/// Build the `POST /api/ab/export` response from the UI's two A/B arms.
+fn ab_export_response(body: &[u8]) -> (&'static str, String) {
+    let error = |msg: String| ("400 Bad Request", serde_json::json!({ "error": msg }).to_string());
+    let request = match serde_json::from_slice::<crate::ab_export::AbExportRequest>(body) {
+        Ok(request) => request,
+        Err(e) => return error(format!("invalid experiment: {}", e)),
+    };
+    match crate::ab_export::AbExport::build(request) {
+        Ok(export) => ("200 OK", serde_json::to_string_pretty(&export).unwrap_or_default()),
+        Err(msg) => error(msg),
+    }
+}
+
+/// `POST /api/retransform`: re-render a finished stream with another
+/// transform, without calling the model.  The body is
+/// `{"transform":"NAME","tokens":[...]}` with the stream's token events;
+/// each token keeps its `transformed` flag (tokens without one follow the
+/// every-other alternation of [`crate::transforms::transform_text`]) and a
+/// transformed token gets a new `text` and `chaos_label` from its
+/// `original`.  Other fields pass through, so the result can replace the
+/// client's tokens as-is.
+fn retransform_response(body: &[u8], options: &TransformOptions) -> (&'static str, String) {
+    #[derive(serde::Deserialize)]
+    struct RetransformRequest {
+        transform: String,
+        tokens: Vec<serde_json::Map<String, serde_json::Value>>,
+    }
+    let error = |msg: String| ("400 Bad Request", serde_json::json!({ "error": msg }).to_string());
+    let request = match serde_json::from_slice::<RetransformRequest>(body) {
+        Ok(request) => request,
+        Err(e) => return error(format!("invalid retransform request: {}", e)),
+    };
+    let transform = match Transform::from_str_loose(&request.transform) {
+        Ok(t) => t,
+        Err(msg) => return error(msg),
+    };
+    let mut tokens = request.tokens;
+    for (position, token) in tokens.iter_mut().enumerate() {
+        let Some(original) = token.get("original").and_then(|v| v.as_str()).map(str::to_string) else {
+            return error(format!("token {} has no original", position));
+        };
+        let transformed = token
+            .get("transformed")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(position % 2 == 1);
+        let (text, chaos_label) = if transformed {
+            crate::transforms::retransform_token(&original, &transform, options)
+        } else {
+            (original, None)
+        };
+        token.insert("transformed".to_string(), serde_json::Value::Bool(transformed));
+        token.insert("text".to_string(), serde_json::Value::String(text));
+        token.insert("chaos_label".to_string(), serde_json::json!(chaos_label));
+    }
+    (
+        "200 OK",
+        serde_json::json!({ "transform": transform.name(), "tokens": tokens }).to_string(),
+    )
+}
+
+/// Build the `GET /api/preview` response: `text` run offline through
+/// `transform` at every `interval`-th word (default 2), returning the HTTP
+/// status line and JSON body.  No model is called.
+fn preview_response(
+    params: &HashMap<String, String>,
+    max_chars: usize,
+    options: &TransformOptions,
+) -> (&'static str, String) {
+    let error = |msg: String| ("400 Bad Request", serde_json::json!({ "error": msg }).to_string());
+    let text = params.get("text").map(String::as_str).unwrap_or("");
+    if let Err(msg) = crate::check_prompt_length(text, max_chars) {
+        return error(msg);
+    }
+    let transform_str = params.get("transform").map(String::as_str).unwrap_or("reverse");
+    let transform = match Transform::from_str_loose(transform_str) {
+        Ok(t) => t,
+        Err(msg) => return error(msg),
+    };
+    let interval = match params.get("interval").map(|v| v.parse::<usize>()) {
+        None => 2,
+        Some(Ok(n)) if n >= 1 => n,
+        Some(_) => return error("interval must be a positive integer".to_string()),
+    };
+    let (transformed, tokens) = crate::transforms::transform_text(text, &transform, interval, options);
+    (
+        "200 OK",
+        serde_json::json!({
+            "transform": transform_str,
+            "interval": interval,
+            "text": transformed,
+            "tokens": tokens,
+        })
+        .to_string(),
+    )
+}
+
+/// Build the `GET /api/room/CODE/audit` response for room `code`, given the
+/// `X-Host-Token` header value, returning the HTTP status line and JSON body.
+fn room_audit_response(store: &RoomStore, code: &str, host_token: &str) -> (&'static str, String) {
+    match crate::collab::audit_log(store, code, host_token) {
+        Ok(events) => (
+            "200 OK",
+            serde_json::json!({
+                "room": code,
+                "count": events.len(),
+                "events": events,
+            })
+            .to_string(),
+        ),
+        Err(crate::collab::AuditError::NotFound) => (
+            "404 Not Found",
+            serde_json::json!({"error": "room not found"}).to_string(),
+        ),
+        Err(crate::collab::AuditError::Forbidden) => (
+            "403 Forbidden",
+            serde_json::json!({"error": "host token required"}).to_string(),
+        ),
+    }
+}
+
+/// Build the `GET /api/room/CODE/archive` response for room `code`, given the
+/// `X-Host-Token` header value, returning the HTTP status line and JSON body.
+fn room_archive_response(store: &RoomStore, code: &str, host_token: &str) -> (&'static str, String) {
+    match crate::collab::room_archive(store, code, host_token) {
+        Ok(archive) => ("200 OK", archive.to_string()),
+        Err(crate::collab::AuditError::NotFound) => (
+            "404 Not Found",
+            serde_json::json!({"error": "room not found"}).to_string(),
+        ),
+        Err(crate::collab::AuditError::Forbidden) => (
+            "403 Forbidden",
+            serde_json::json!({"error": "host token required"}).to_string(),
+        ),
+    }
+}
+
+/// Run a session token search for `GET /api/session/search`, returning the
+/// HTTP status line and JSON body.
+fn session_search_response(store: &RoomStore, room: &str, q: &str) -> (&'static str, String) {
+    let query = match crate::session_search::TokenQuery::parse(q) {
+        Ok(query) => query,
+        Err(e) => {
+            return (
+                "400 Bad Request",
+                serde_json::json!({"error": e}).to_string(),
+            )
+        }
+    };
+    let Some(tokens) = crate::collab::session_tokens(store, room) else {
+        return (
+            "404 Not Found",
+            serde_json::json!({"error": "room not found"}).to_string(),
+        );
+    };
+    let matches = crate::session_search::search(&tokens, &query);
+    (
+        "200 OK",
+        serde_json::json!({
+            "room": room,
+            "query": q,
+            "total": tokens.len(),
+            "count": matches.len(),
+            "matches": matches,
+        })
+        .to_string(),
+    )
+}
+
+/// Session export bundle served by `GET /api/session/export`.
+///
+/// Fields serialize in declaration order so exported sessions diff cleanly:
+/// `room`, `token_count`, `transformed_count`, `locked_transform`, the
+/// `chaos_script`, then the large arrays `tokens`, `surgery_log`, `chat_log`,
+/// and finally the dependency `graph`.  Keys inside each token object are
+/// sorted alphabetically.
+#[derive(Serialize)]
+struct SessionExport {
+    room: String,
+    token_count: usize,
+    transformed_count: usize,
+    locked_transform: Option<String>,
+    /// `[token_index, sub_transform]` for every chaos-transformed token, in
+    /// order; replayable with `Transform::replay_chaos_script`.
+    chaos_script: Vec<(u64, String)>,
+    tokens: Vec<serde_json::Value>,
+    surgery_log: serde_json::Value,
+    chat_log: serde_json::Value,
+    graph: DependencyGraph,
+}
+
+/// Token dependency graph as drawn by the web UI's graph canvas.
+///
+/// Every token is a node; each transformed token gets an edge back to the
+/// nearest preceding untransformed token.  Edge `weight` mirrors the canvas
+/// line width (original token length, capped at 6) and `importance` its heat.
+#[derive(Debug, Serialize, PartialEq)]
+struct DependencyGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct GraphNode {
+    index: u64,
+    text: String,
+    transformed: bool,
+    importance: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct GraphEdge {
+    from: u64,
+    to: u64,
+    weight: usize,
+    importance: f64,
+}
+
+/// Compute the [`DependencyGraph`] for a recorded token array.
+fn dependency_graph(tokens: &[serde_json::Value]) -> DependencyGraph {
+    let mut nodes = Vec::with_capacity(tokens.len());
+    let mut edges = Vec::new();
+    let mut last_even: Option<u64> = None;
+    for (pos, t) in tokens.iter().enumerate() {
+        let index = t.get("index").and_then(|v| v.as_u64()).unwrap_or(pos as u64);
+        let text = t.get("text").and_then(|v| v.as_str()).unwrap_or("");
+        let original = t.get("original").and_then(|v| v.as_str()).unwrap_or(text);
+        let transformed = t.get("transformed").and_then(|v| v.as_bool()).unwrap_or(false);
+        let importance = t.get("importance").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        if transformed {
+            if let Some(from) = last_even {
+                edges.push(GraphEdge {
+                    from,
+                    to: index,
+                    weight: original.chars().count().min(6),
+                    importance,
+                });
+            }
+        } else {
+            last_even = Some(index);
+        }
+        nodes.push(GraphNode {
+            index,
+            text: text.to_string(),
+            transformed,
+            importance,
+        });
+    }
+    DependencyGraph { nodes, edges }
+}
+
+/// The chaos script of a recorded token array: `(index, chaos_label)` for each
+/// token with a sub-transform label (deleted tokens carry none).
+fn chaos_script(tokens: &[serde_json::Value]) -> Vec<(u64, String)> {
+    tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(pos, t)| {
+            let label = t.get("chaos_label")?.as_str()?;
+            if label == "deleted" {
+                return None;
+            }
+            let index = t.get("index").and_then(|v| v.as_u64()).unwrap_or(pos as u64);
+            Some((index, label.to_string()))
+        })
+        .collect()
+}
+
+/// Build the `GET /api/session/export` response for `room`, returning the HTTP
+/// status line and JSON body (pretty-printed when `pretty` is set).
+fn session_export_response(store: &RoomStore, room: &str, pretty: bool) -> (&'static str, String) {
+    let Some(tokens) = crate::collab::session_tokens(store, room) else {
+        return (
+            "404 Not Found",
+            serde_json::json!({"error": "room not found"}).to_string(),
+        );
+    };
+    let snapshot = crate::collab::room_state_snapshot(store, room);
+    let export = SessionExport {
+        room: room.to_string(),
+        token_count: tokens.len(),
+        transformed_count: tokens
+            .iter()
+            .filter(|t| t.get("transformed").and_then(|v| v.as_bool()).unwrap_or(false))
+            .count(),
+        locked_transform: crate::collab::locked_transform(store, room),
+        chaos_script: chaos_script(&tokens),
+        graph: dependency_graph(&tokens),
+        tokens,
+        surgery_log: snapshot["surgery_log"].clone(),
+        chat_log: snapshot["chat_log"].clone(),
+    };
+    let body = if pretty {
+        serde_json::to_string_pretty(&export)
+    } else {
+        serde_json::to_string(&export)
+    };
+    match body {
+        Ok(body) => ("200 OK", body),
+        Err(e) => (
+            "500 Internal Server Error",
+            serde_json::json!({"error": e.to_string()}).to_string(),
+        ),
+    }
+}
+
+/// # HTTP API
+///
+/// ## Endpoints
+///
+/// - `GET /` — Serves the embedded single-page web UI
+///
+/// - `GET /stream?prompt=...&transform=...&provider=...&model=...&rate=...`  
+///   Server-Sent Events stream of [`TokenEvent`] JSON objects.  
+///   Each event: `data: {"text":"...","index":N,"transformed":bool,...}`
+///   `logprobs=0` skips OpenAI log probabilities (no confidence/perplexity data).
+///   `room=CODE` (or `room=A,B,C`) also broadcasts each token to those
+///   collaboration rooms; an unknown code is a `404`.
+///
+/// - `GET /diff-stream?prompt=...&transform=...`  
+///   SSE stream with two providers side-by-side; each event includes `"side":"openai"|"anthropic"`.
+///   `openai_model`/`anthropic_model` pick each side's model; a shared `model`
+///   applies only to the provider that serves it.
+///   A final `{"type":"diff_summary","openai":{...},"anthropic":{...},"matched":N,"match_pct":P}`
+///   event precedes `[DONE]`.
+///
+/// - `GET /ab-stream?prompt=...&system_a=...&system_b=...`  
+///   SSE stream for A/B experiment mode.  Optional `max_a`/`max_b` and
+///   `temp_a`/`temp_b` set each side's output-token cap and temperature
+///   independently.  With the `self-tune` feature the two
+///   sides are recorded as experiment arms and a final
+///   `{"type":"experiment_result","winner":"a","p_value":...}` event precedes `[DONE]`.
+///
+///   Every SSE endpoint opens with a `retry: N` field (`--sse-retry-ms`, default
+///   3000; `0` omits it) so browsers back off before reconnecting.
+///
+///   With `--max-concurrency N`, each `/diff-stream` and `/ab-stream` side takes
+///   one of N server-wide upstream slots before calling its provider; a side that
+///   has to wait is announced with `{"type":"queued","side":"..."}`.
+///
+/// - `GET /api/session/search?q=...&room=CODE` — Token indices from the room's
+///   most recent stream matching `q` (substring, or `perplexity:2..5` /
+///   `confidence:<0.3` ranges; see [`crate::session_search`]).
+///
+/// - `GET /api/session/export?room=CODE[&pretty=1]` — Session export bundle
+///   (see [`SessionExport`] for the field order); compact unless `pretty=1`.
+///
+/// - `GET /api/preview?text=...&transform=...&interval=N` — `{"text":...,"tokens":[...]}`
+///   with `text` transformed offline at every `N`th word (default 2); no model is called.
+///
+/// - `GET /demo?session=NAME[&speed=X]` — SSE replay of a built-in demo
+///   session (`recursion`, the default, or `haiku`): one [`TokenEvent`] per
+///   `data:` event at its recorded `arrival_ms`, scaled by `speed` (default
+///   `1`, `0` = instant), then `[DONE]`.  `404` lists the available names.
+///   No model is called.
+///
+/// - `POST /api/load-session` — Body is an exported session bundle (the web
+///   UI's Export JSON or `/api/session/export`).  Returns
+///   `{"token_count":N,"transformed_count":M,"recorded_counts_match":bool,"tokens":[...]}`
+///   with counts taken from the tokens themselves; `400` when the body is
+///   not a session export.  No model is called.
+///
+/// - `POST /api/retransform` — Body is `{"transform":"NAME","tokens":[...]}`
+///   with the token events of a finished stream.  Returns
+///   `{"transform":"NAME","tokens":[...]}` with each transformed token's
+///   `text` and `chaos_label` recomputed from its `original`; `400` for a
+///   malformed body, unknown transform or token without `original`.  No
+///   model is called.
+///
+/// - `POST /api/ab/export` — Body is `{"prompt":...,"system_a":...,"system_b":...,
+///   "tokens_a":[...],"tokens_b":[...],"criterion":"perplexity"|"confidence"}`
+///   as streamed by `/ab-stream`.  Returns the [`crate::ab_export::AbExport`]
+///   record; `400` for a malformed body or unknown criterion.
+///
+/// - `GET /api/transforms` — `[{"name":...,"description":...,"example_input":...,"example_output":...}]`
+///   for every transform (see [`Transform::catalog`]).
+///
+/// - `GET /api/room/CODE/audit` — `{"room":...,"count":N,"events":[...]}` audit
+///   log of joins, leaves, edits, chats, votes, locks and pauses, oldest first.
+///   Requires the `X-Host-Token` header returned by `/room/create`; 403 otherwise.
+///
+/// - `GET /api/room/CODE/archive` — downloadable JSON bundle of the room's
+///   tokens, recording, surgery log, chat, annotations, votes and participants
+///   with join/leave times (see [`crate::collab::room_archive`]).  Same
+///   `X-Host-Token` requirement as the audit log.
+///
+/// - `GET /api/version` — `{"name":...,"version":...,"git_commit":...,"features":[...]}`
+///   build metadata (see [`crate::build_info`]).
+///
+/// - `GET /api/openai/models` — `["gpt-4o",...]` chat models from OpenAI's
+///   `/v1/models`, cached for ten minutes; the static known-model list when
+///   `OPENAI_API_KEY` is unset or the call fails (see [`crate::model_list`]).
+///
+/// - `POST /batch-stream` — Body is a JSON array of prompts (or `{"prompts":[...],...}`).
+///   SSE stream of token events tagged with `prompt_index`, a `prompt_done` event per
+///   prompt, and a final `batch_summary` event before `[DONE]`.
+///
+/// - `POST /room/create` — Creates a multiplayer room, returns `{"code":"SWIFT-LION-42","room_id":"<uuid>","ws_url":"/ws/SWIFT-LION-42"}`.
+///
+/// - `GET /join/CODE` — Returns room join HTML page.
+///
+/// - `WS /ws/CODE[?client_id=ID][&spectator=1]` — WebSocket connection for multiplayer
+///   collaboration.  `spectator=1` joins as a guest even when the host seat is free.
+///   A stable `client_id` (the UI stores a UUID in `localStorage`) keeps the
+///   participant's color across reconnects.  
+///   **Inbound message types** (JSON):  
+///   `{"type":"set_name","name":"..."}` — Update display name (tags and control characters
+///   stripped, at most `--max-name-chars`, default 32; an empty result is an `error`); guests
+///   otherwise keep a generated `AdjectiveAnimal` name  
+///   `{"type":"vote","transform":"...","dir":"up"|"down"}` — Cast a vote  
+///   `{"type":"surgery","token_index":N,"new_text":"...","old_text":"..."}` — Edit a token  
+///   `{"type":"chat","text":"...","token_index":N}` — Send a chat message  
+///   `{"type":"annotate","token_index":N,"note":"..."}` — Attach a note to a token (stored in
+///   the room state, capped per room)  
+///   `{"type":"record_start"}` / `{"type":"record_stop"}` — Recording control  
+///   `{"type":"request_state"}` — Reply (to the sender only) with `{"type":"room_state","room_state":{...}}`  
+///   `{"type":"token",...}` — Host broadcasts a token event to guests  
+///   `{"type":"lock_transform","transform":"reverse"}` — Host locks the room transform (`null` unlocks);
+///   `/stream?room=CODE` then ignores the client's `transform` param  
+///   `{"type":"pause"}` / `{"type":"resume"}` — Host holds stream tokens for the room; resume
+///   flushes them in order  
+///   **Outbound event types**: `welcome`, `participant_join`, `participant_leave`,  
+///   `participant_update`, `vote_update`, `surgery`, `chat`, `annotation`, `transform_lock`, `room_lock`, `stream_pause`,  
+///   `record_started`, `record_stopped`, `replay_event`, `replay_done`, `stream_done`, `pong`, `error`  
+///   A dropped participant that connected with `client_id` is sent as `participant_update` with
+///   `"away":true`, and only as `participant_leave` if it does not reconnect within
+///   `--reconnect-grace`; reconnecting resumes the same participant.  
+///   **Close codes** on rejected joins: `4004` room not found, `4029` room full,
+///   `4003` room locked (`{"type":"lock_room","locked":true}` from the host)
+pub async fn serve(port: u16, default_args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    tracing::info!(port, "binding web UI server");
+    let listener = bind_listener(port, default_args.listen_backlog)?;
+    tracing::info!(port, "web UI server listening");
 
     eprintln!(
         "{}",
@@ -274,9 +1795,34 @@ pub async fn serve(port: u16, default_args: &Args) -> Result<(), Box<dyn std::er
     let default_provider = default_args.provider.clone();
     let orchestrator = default_args.orchestrator;
     let api_key: Option<String> = default_args.api_key.clone();
-    let sse_buffer_size = default_args.sse_buffer_size;
-
-    let room_store = crate::collab::new_room_store();
+    let settings = StreamSettings::from_args(default_args);
+
+    // Shared room backend: mirror rooms to Redis and relay broadcasts from
+    // other instances into the local store.
+    #[cfg(feature = "redis-backing")]
+    let room_backend: Option<Arc<dyn RoomBackend>> = match default_args.redis_url.as_deref() {
+        Some(url) => match crate::collab_redis::RedisRoomBackend::connect(url) {
+            Ok(backend) => {
+                eprintln!("{}", format!("  Redis room store active → {url}").bright_cyan());
+                Some(Arc::new(backend))
+            }
+            Err(e) => {
+                eprintln!("  Redis room store init failed: {e}; rooms stay local");
+                None
+            }
+        },
+        None => None,
+    };
+    #[cfg(not(feature = "redis-backing"))]
+    let room_backend: Option<Arc<dyn RoomBackend>> = None;
+    let room_store = match room_backend {
+        Some(backend) => crate::collab::new_room_store_with_backend(backend),
+        None => crate::collab::new_room_store(),
+    }
+    .with_transform_options(settings.transform_options.clone());
+    if let Err(e) = crate::collab::spawn_remote_relay(&room_store) {
+        eprintln!("  Room backend subscribe failed: {e}; cross-instance broadcasts disabled");
+    }
     let rate_limiter = new_rate_limiter();
 
     // Background task: evict idle rooms every 5 minutes; evict abandoned rooms every minute.
@@ -336,19 +1882,111 @@ pub async fn serve(port: u16, default_args: &Args) -> Result<(), Box<dyn std::er
         }
     }
 
-    loop {
-        let (stream, addr) = listener.accept().await?;
+    accept_connections(listener, default_args.max_connections as usize, move |stream, addr| {
         let provider = default_provider.clone();
         let store = room_store.clone();
         let conn_api_key = api_key.clone();
         let limiter = rate_limiter.clone();
         let peer_ip = addr.ip();
-        let buf_sz = sse_buffer_size;
-        tokio::spawn(async move {
-            if let Err(e) = handle_connection(stream, provider, orchestrator, store, conn_api_key, limiter, peer_ip, buf_sz).await {
+        let settings = settings.clone();
+        async move {
+            if let Err(e) = handle_connection(stream, provider, orchestrator, store, conn_api_key, limiter, peer_ip, settings).await {
                 eprintln!("  connection error: {}", e);
             }
-        });
+        }
+    })
+    .await?;
+    Ok(())
+}
+
+/// Bind `127.0.0.1:port` with a `listen(2)` queue of `backlog` connections.
+fn bind_listener(port: u16, backlog: u32) -> std::io::Result<TcpListener> {
+    let socket = TcpSocket::new_v4()?;
+    #[cfg(not(windows))]
+    socket.set_reuseaddr(true)?;
+    socket.bind(SocketAddr::from(([127, 0, 0, 1], port)))?;
+    socket.listen(backlog)
+}
+
+/// Response for a connection turned away at `--max-connections`.
+fn at_capacity_response() -> String {
+    let body = r#"{"error":"server at capacity, retry shortly"}"#;
+    format!(
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\nRetry-After: 1\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Connections turned away at once by [`reject_at_capacity`]; beyond it an
+/// over-capacity connection is closed without a reply.
+const MAX_REJECTING: usize = 64;
+
+/// How long a turned-away connection gets to send its request head, and
+/// afterwards to finish sending before the socket is closed.
+const REJECT_READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Answer a connection turned away at `--max-connections`: read its request
+/// head, write [`at_capacity_response`], then half-close and drain until the
+/// client hangs up.  Closing with unread input makes the kernel send a reset,
+/// which the client sees instead of the 503.
+async fn reject_at_capacity(mut stream: TcpStream) {
+    use tokio::io::AsyncReadExt;
+    let mut head: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 1024];
+    let _ = tokio::time::timeout(REJECT_READ_TIMEOUT, async {
+        while head.len() < 8192 && !head.windows(4).any(|w| w == b"\r\n\r\n") {
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => head.extend_from_slice(&chunk[..n]),
+            }
+        }
+    })
+    .await;
+    if stream.write_all(at_capacity_response().as_bytes()).await.is_err() {
+        return;
+    }
+    let _ = stream.shutdown().await;
+    let _ = tokio::time::timeout(REJECT_READ_TIMEOUT, async {
+        while matches!(stream.read(&mut chunk).await, Ok(n) if n > 0) {}
+    })
+    .await;
+}
+
+/// Accept connections forever, running `handle` for each on its own task
+/// while at most `max_connections` are in flight.  A connection arriving at
+/// capacity is answered by [`reject_at_capacity`] on a small, separately
+/// capped task, so a flood cannot grow the number of tasks without bound.
+async fn accept_connections<F, Fut>(listener: TcpListener, max_connections: usize, mut handle: F) -> std::io::Result<()>
+where
+    F: FnMut(TcpStream, SocketAddr) -> Fut,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    let slots = Arc::new(Semaphore::new(max_connections));
+    let rejecting = Arc::new(Semaphore::new(MAX_REJECTING));
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        match Arc::clone(&slots).try_acquire_owned() {
+            Ok(permit) => {
+                let task = handle(stream, addr);
+                tokio::spawn(async move {
+                    task.await;
+                    drop(permit);
+                });
+            }
+            Err(_) => match Arc::clone(&rejecting).try_acquire_owned() {
+                Ok(permit) => {
+                    tracing::warn!(%addr, max_connections, "connection limit reached; answering 503");
+                    tokio::spawn(async move {
+                        reject_at_capacity(stream).await;
+                        drop(permit);
+                    });
+                }
+                Err(_) => {
+                    tracing::warn!(%addr, max_connections, "connection limit reached; dropping connection");
+                }
+            },
+        }
     }
 }
 
@@ -360,7 +1998,7 @@ async fn handle_connection(
     api_key: Option<String>,
     limiter: RateLimiter,
     peer_ip: IpAddr,
-    sse_buffer_size: usize,
+    settings: StreamSettings,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     use tokio::io::AsyncReadExt;
 
@@ -377,19 +2015,30 @@ async fn handle_connection(
             .nth(1)
             .unwrap_or("/")
             .to_string();
-        if let Some(code) = ws_path.strip_prefix("/ws/") {
+        if let Some(rest) = ws_path.strip_prefix("/ws/") {
+            let (code, ws_query) = rest.split_once('?').unwrap_or((rest, ""));
             let code = code.to_string();
+            let mut ws_params = parse_query(ws_query);
+            let client_id = ws_params
+                .remove("client_id")
+                .filter(|id| valid_client_id(id));
+            // Spectators (`--watch`) never take the host seat, even if they
+            // connect before the host does.
+            let spectator = ws_params.get("spectator").is_some_and(|v| v == "1");
+            // Rooms created on another instance are pulled in from the shared backend.
+            crate::collab::hydrate_room(&store, &code);
             // is_host = true only for the first connection (host_id not yet assigned).
             // room_exists=true after /room/create, so "!room_exists" was always false,
             // meaning every client was treated as a guest.  Check host_id instead.
-            let is_host = store
+            let is_host = !spectator && store
                 .lock()
                 .map(|s| s.get(&code).map(|r| r.host_id.is_empty()).unwrap_or(false))
                 .unwrap_or(false);
 
             match tokio_tungstenite::accept_async(stream).await {
                 Ok(ws_stream) => {
-                    crate::collab::handle_ws(ws_stream, store, code, is_host).await;
+                    crate::collab::handle_ws(ws_stream, store, code, is_host, client_id, settings.reconnect_grace, settings.max_name_chars)
+                        .await;
                 }
                 Err(e) => {
                     eprintln!("  WS handshake error: {}", e);
@@ -447,14 +2096,22 @@ async fn handle_connection(
         }
     }
 
+    let if_none_match = req
+        .headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("if-none-match"))
+        .and_then(|h| std::str::from_utf8(h.value).ok())
+        .map(str::to_string);
+    let gzip_ok = req
+        .headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("accept-encoding"))
+        .and_then(|h| std::str::from_utf8(h.value).ok())
+        .is_some_and(accepts_gzip);
+
     match path {
         "/" => {
-            let response = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                INDEX_HTML.len(),
-                INDEX_HTML,
-            );
-            stream.write_all(response.as_bytes()).await?;
+            stream.write_all(index_response(if_none_match.as_deref()).as_bytes()).await?;
         }
         "/stream" => {
             // Rate limiting: max RATE_LIMIT_MAX requests per IP per RATE_LIMIT_WINDOW.
@@ -469,24 +2126,46 @@ async fn handle_connection(
             }
 
             let params = parse_query(query_str);
-            let sp = parse_stream_params(&params);
+            let sp = match StreamParams::from_query(&params) {
+                Ok(sp) => sp,
+                Err(msg) => {
+                    stream.write_all(bad_request_response(&msg).as_bytes()).await?;
+                    return Ok(());
+                }
+            };
 
             // Guard against oversized prompts.
-            if sp.prompt.len() > MAX_PROMPT_LEN {
-                let body = r#"{"error":"Prompt exceeds maximum length"}"#;
-                let response = format!(
-                    "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                    body.len(), body
-                );
-                stream.write_all(response.as_bytes()).await?;
+            if let Err(msg) = crate::check_prompt_length(&sp.prompt, settings.max_prompt_chars) {
+                stream.write_all(bad_request_response(&msg).as_bytes()).await?;
                 return Ok(());
             }
 
+            // One LLM call can feed several rooms (`room=A,B,C`); all must exist.
+            let stream_rooms = parse_room_codes(params.get("room").map(String::as_str));
+            for code in &stream_rooms {
+                crate::collab::hydrate_room(&store, code);
+            }
+            let missing: Vec<&str> = stream_rooms
+                .iter()
+                .filter(|code| !crate::collab::room_exists(&store, code))
+                .map(String::as_str)
+                .collect();
+            if !missing.is_empty() {
+                let response = error_response("404 Not Found", &format!("room not found: {}", missing.join(", ")));
+                stream.write_all(response.as_bytes()).await?;
+                return Ok(());
+            }
             let prompt = sp.prompt;
-            let transform_str = sp.transform;
+            // A host-locked room transform overrides whatever the client asked for.
+            let transform_str = room_transform(&store, &stream_rooms, sp.transform);
             let rate = sp.rate;
             let seed = sp.seed;
             let top_logprobs = sp.top_logprobs;
+            let logprobs = sp.logprobs.unwrap_or(settings.logprobs);
+            let settings = StreamSettings {
+                emit_granularity: sp.granularity.unwrap_or(settings.emit_granularity),
+                ..settings
+            };
             let system = sp.system;
             let visual = sp.visual;
             let provider_str = if sp.provider == "openai" {
@@ -497,30 +2176,19 @@ async fn handle_connection(
             let model_input = sp.model;
             let heatmap = sp.heatmap;
 
-            let provider = match provider_str.as_str() {
-                "anthropic" => Provider::Anthropic,
-                _ => Provider::Openai,
-            };
+            let provider = provider_str.parse::<Provider>().unwrap_or(Provider::Openai);
 
             let model = if model_input.is_empty() {
-                match provider {
-                    Provider::Openai => DEFAULT_OPENAI_MODEL.to_string(),
-                    Provider::Anthropic => DEFAULT_ANTHROPIC_MODEL.to_string(),
-                    Provider::Mock => DEFAULT_MOCK_MODEL.to_string(),
-                }
+                provider.default_model().to_string()
             } else {
                 model_input
             };
 
             let transform = Transform::from_str_loose(&transform_str).unwrap_or(Transform::Reverse);
-            let stream_room_code = params.get("room").cloned();
 
             // SSE headers
-            let headers = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: {}\r\n\r\n",
-                cors_origin()
-            );
-            stream.write_all(headers.as_bytes()).await?;
+            stream.write_all(settings.sse_head().as_bytes()).await?;
+            stream.write_all(settings.config_event().as_bytes()).await?;
 
             // Create channel for token events.
             let (tx, mut rx) = mpsc::unbounded_channel::<TokenEvent>();
@@ -538,14 +2206,18 @@ async fn handle_connection(
             // to satisfy Send bounds on the spawned task.
             let interceptor_result = interceptor_result.map_err(|e| e.to_string());
             let mut interceptor = match interceptor_result {
-                Ok(mut i) => {
-                    i = i.with_rate(rate);
+                Ok(i) => {
+                    let mut i = settings.configure(i).with_rate(rate);
                     if let Some(s) = seed {
                         i = i.with_seed(s);
                     }
                     i.top_logprobs = top_logprobs;
+                    i.logprobs = logprobs;
                     i.system_prompt = system;
                     i.web_tx = Some(tx);
+                    i.importance_smoothing = settings.importance_smoothing;
+                    i.inline_mapping = settings.inline_mapping.clone();
+                    i.collapse_whitespace = settings.collapse_whitespace;
                     i
                 }
                 Err(msg) => {
@@ -558,10 +2230,17 @@ async fn handle_connection(
                 }
             };
 
+            let slot = UpstreamSlot::claim(settings.upstream.as_ref());
+            if slot.is_queued() {
+                stream.write_all(queued_event("stream").as_bytes()).await?;
+            }
+
             // Spawn the LLM streaming in background
             let prompt_clone = prompt.clone();
             let stream_task = tokio::spawn(async move {
-                let _ = interceptor.intercept_stream(&prompt_clone).await;
+                let _permit = slot.wait().await;
+                interceptor.intercept_stream(&prompt_clone).await.map_err(|e| e.to_string())?;
+                Ok::<_, String>(interceptor.rewritten_prompt(&prompt_clone).map(str::to_string))
             });
 
             // Forward token events as SSE with bounded backpressure buffer.
@@ -573,17 +2252,26 @@ async fn handle_connection(
             let mut token_buffer: std::collections::VecDeque<TokenEvent> =
                 std::collections::VecDeque::new();
             let mut overflow_emitted = false;
+            let mut pacer = TokenPacer::new(settings.render_rate);
+            let mut batcher = SseBatcher::new(settings.sse_batch);
+
+            let mut percentiles = crate::perplexity_percentile::RollingPercentile::default();
+
+            for code in &stream_rooms {
+                crate::collab::clear_session_tokens(&store, code);
+            }
 
-            while let Some(event) = rx.recv().await {
-                if let Some(ref code) = stream_room_code {
+            while let Some(mut event) = rx.recv().await {
+                // Live percentile against the recent window; later tokens are unknown.
+                percentiles.annotate(&mut event);
+                if !stream_rooms.is_empty() {
                     if let Ok(token_val) = serde_json::to_value(&event) {
-                        crate::collab::broadcast(&store, code, token_val.clone());
-                        crate::collab::maybe_record(&store, code, token_val);
+                        fan_out_stream_token(&store, &stream_rooms, &token_val);
                     }
                 }
 
                 // Buffer management: drop oldest when full, emit overflow sentinel once
-                if token_buffer.len() >= sse_buffer_size {
+                if token_buffer.len() >= settings.sse_buffer_size {
                     token_buffer.pop_front(); // drop oldest
                     if !overflow_emitted {
                         let sentinel =
@@ -599,25 +2287,46 @@ async fn handle_connection(
                 }
                 token_buffer.push_back(event);
 
-                // Drain the buffer and write events
-                while let Some(buffered) = token_buffer.pop_front() {
-                    if let Ok(json) = serde_json::to_string(&buffered) {
-                        let sse = format!("data: {}\n\n", json);
+                // Drain the buffer and write events; a partial batch goes out
+                // as soon as no further event is already waiting.
+                'drain: while let Some(buffered) = token_buffer.pop_front() {
+                    for json in emit_payloads(&buffered, settings.emit_granularity) {
+                        pacer.pace().await;
+                        let Some(sse) = batcher.push(json) else { continue };
                         if stream.write_all(sse.as_bytes()).await.is_err() {
                             client_disconnected = true;
-                            break;
+                            break 'drain;
                         }
                     }
                 }
+                if !client_disconnected && rx.is_empty() {
+                    if let Some(sse) = batcher.flush() {
+                        client_disconnected = stream.write_all(sse.as_bytes()).await.is_err();
+                    }
+                }
                 if client_disconnected {
                     break;
                 }
             }
+            if let Some(sse) = batcher.flush().filter(|_| !client_disconnected) {
+                client_disconnected = stream.write_all(sse.as_bytes()).await.is_err();
+            }
 
             if client_disconnected {
                 stream_task.abort();
             } else {
-                let _ = stream_task.await;
+                match stream_task.await {
+                    // Provider failures (e.g. Anthropic overloaded_error) reach the
+                    // UI as an error event rather than an empty stream.
+                    Ok(Err(msg)) => {
+                        let _ = stream.write_all(sse_error_done(&msg).as_bytes()).await;
+                        return Ok(());
+                    }
+                    Ok(Ok(Some(sent))) => {
+                        let _ = stream.write_all(effective_prompt_event(&sent).as_bytes()).await;
+                    }
+                    _ => {}
+                }
             }
 
             // Send done signal
@@ -625,37 +2334,29 @@ async fn handle_connection(
         }
         "/diff-stream" => {
             let params = parse_query(query_str);
-            let prompt = params.get("prompt").cloned().unwrap_or_default();
-            let transform_str = params
-                .get("transform")
-                .cloned()
-                .unwrap_or_else(|| "reverse".to_string());
-            let model_input = params.get("model").cloned().unwrap_or_default();
-            let heatmap = params.get("heatmap").is_some_and(|v| v == "1");
-
-            let transform = Transform::from_str_loose(&transform_str).unwrap_or(Transform::Reverse);
-
-            let openai_model = if model_input.is_empty() {
-                DEFAULT_OPENAI_MODEL.to_string()
-            } else {
-                model_input.clone()
-            };
-            let anthropic_model = if model_input.is_empty() {
-                DEFAULT_ANTHROPIC_MODEL.to_string()
-            } else {
-                model_input.clone()
+            let sp = match StreamParams::from_query(&params) {
+                Ok(sp) => sp,
+                Err(msg) => {
+                    stream.write_all(bad_request_response(&msg).as_bytes()).await?;
+                    return Ok(());
+                }
             };
+            let prompt = sp.prompt;
+            let heatmap = sp.heatmap;
+            let transform = transform_param(&sp.transform).unwrap_or(Transform::Reverse);
+            let (openai_model, anthropic_model) = diff_stream_models(&params);
 
             // SSE headers
-            let headers = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: {}\r\n\r\n",
-                cors_origin()
-            );
-            stream.write_all(headers.as_bytes()).await?;
+            stream.write_all(settings.sse_head().as_bytes()).await?;
+            if let Err(msg) = crate::check_prompt_length(&prompt, settings.max_prompt_chars) {
+                stream.write_all(sse_error_done(&msg).as_bytes()).await?;
+                return Ok(());
+            }
 
             // Merged channel: (side, event)
             let (merged_tx, mut merged_rx) =
                 mpsc::unbounded_channel::<(&'static str, TokenEvent)>();
+            let mut tasks = SideTasks::default();
 
             // Spawn OpenAI side
             let openai_result = TokenInterceptor::new(
@@ -666,16 +2367,22 @@ async fn handle_connection(
                 heatmap,
                 orchestrator,
             )
+            .map(|i| settings.configure(i))
             .map_err(|e| e.to_string());
             if let Ok(mut oai) = openai_result {
                 let (tx_oai, mut rx_oai) = mpsc::unbounded_channel::<TokenEvent>();
                 oai.web_tx = Some(tx_oai);
                 let prompt_o = prompt.clone();
-                tokio::spawn(async move {
+                let slot = UpstreamSlot::claim(settings.upstream.as_ref());
+                if slot.is_queued() {
+                    stream.write_all(queued_event("openai").as_bytes()).await?;
+                }
+                tasks.spawn(async move {
+                    let _permit = slot.wait().await;
                     let _ = oai.intercept_stream(&prompt_o).await;
                 });
                 let mtx = merged_tx.clone();
-                tokio::spawn(async move {
+                tasks.spawn(async move {
                     while let Some(ev) = rx_oai.recv().await {
                         let _ = mtx.send(("openai", ev));
                     }
@@ -691,16 +2398,22 @@ async fn handle_connection(
                 heatmap,
                 orchestrator,
             )
+            .map(|i| settings.configure(i))
             .map_err(|e| e.to_string());
             if let Ok(mut ant) = anthropic_result {
                 let (tx_ant, mut rx_ant) = mpsc::unbounded_channel::<TokenEvent>();
                 ant.web_tx = Some(tx_ant);
                 let prompt_a = prompt.clone();
-                tokio::spawn(async move {
+                let slot = UpstreamSlot::claim(settings.upstream.as_ref());
+                if slot.is_queued() {
+                    stream.write_all(queued_event("anthropic").as_bytes()).await?;
+                }
+                tasks.spawn(async move {
+                    let _permit = slot.wait().await;
                     let _ = ant.intercept_stream(&prompt_a).await;
                 });
                 let mtx = merged_tx.clone();
-                tokio::spawn(async move {
+                tasks.spawn(async move {
                     while let Some(ev) = rx_ant.recv().await {
                         let _ = mtx.send(("anthropic", ev));
                     }
@@ -711,34 +2424,29 @@ async fn handle_connection(
             drop(merged_tx);
 
             // Forward merged events as SSE with side tag
-            while let Some((side, event)) = merged_rx.recv().await {
-                let diff_event = DiffTokenEvent {
-                    side,
-                    event: &event,
-                };
-                if let Ok(json) = serde_json::to_string(&diff_event) {
-                    let sse = format!("data: {}\n\n", json);
-                    if stream.write_all(sse.as_bytes()).await.is_err() {
-                        break;
-                    }
-                }
-            }
-
+            let mut summary = DiffSummary::default();
+            forward_side_events(&mut stream, &mut merged_rx, tasks, |side, event| {
+                summary.observe(side, event)
+            })
+            .await;
+
+            let _ = stream
+                .write_all(format!("data: {}\n\n", summary.summary()).as_bytes())
+                .await;
             let _ = stream.write_all(b"data: [DONE]\n\n").await;
         }
         "/ab-stream" => {
             // A/B Experiment: same prompt sent to provider with two different system prompts
             let params = parse_query(query_str);
-            let prompt = params.get("prompt").cloned().unwrap_or_default();
-            let transform_str = params
-                .get("transform")
-                .cloned()
-                .unwrap_or_else(|| "reverse".to_string());
-            let provider_str = params
-                .get("provider")
-                .cloned()
-                .unwrap_or_else(|| default_provider.to_string());
-            let model_input = params.get("model").cloned().unwrap_or_default();
+            let sp = match StreamParams::from_query(&params) {
+                Ok(sp) => sp,
+                Err(msg) => {
+                    stream.write_all(bad_request_response(&msg).as_bytes()).await?;
+                    return Ok(());
+                }
+            };
+            let prompt = sp.prompt;
+            let model_input = sp.model;
             let sys_a = params
                 .get("sys_a")
                 .cloned()
@@ -748,29 +2456,30 @@ async fn handle_connection(
                 .cloned()
                 .unwrap_or_else(|| "You are a technical writer. Be precise.".to_string());
 
-            let ab_provider = match provider_str.as_str() {
-                "anthropic" => Provider::Anthropic,
-                _ => Provider::Openai,
+            let config_a = AbSideConfig::from_params(&params, "a");
+            let config_b = AbSideConfig::from_params(&params, "b");
+
+            let ab_provider = if params.contains_key("provider") {
+                sp.provider.parse::<Provider>().unwrap_or(Provider::Openai)
+            } else {
+                default_provider.clone()
             };
-            let transform = Transform::from_str_loose(&transform_str).unwrap_or(Transform::Reverse);
+            let transform = transform_param(&sp.transform).unwrap_or(Transform::Reverse);
             let model = if model_input.is_empty() {
-                match ab_provider {
-                    Provider::Openai => DEFAULT_OPENAI_MODEL.to_string(),
-                    Provider::Anthropic => DEFAULT_ANTHROPIC_MODEL.to_string(),
-                    Provider::Mock => DEFAULT_MOCK_MODEL.to_string(),
-                }
+                ab_provider.default_model().to_string()
             } else {
                 model_input
             };
 
-            let headers = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: {}\r\n\r\n",
-                cors_origin()
-            );
-            stream.write_all(headers.as_bytes()).await?;
+            stream.write_all(settings.sse_head().as_bytes()).await?;
+            if let Err(msg) = crate::check_prompt_length(&prompt, settings.max_prompt_chars) {
+                stream.write_all(sse_error_done(&msg).as_bytes()).await?;
+                return Ok(());
+            }
 
             let (merged_tx, mut merged_rx) =
                 mpsc::unbounded_channel::<(&'static str, TokenEvent)>();
+            let mut tasks = SideTasks::default();
 
             // Side A
             let a_result = TokenInterceptor::new(
@@ -781,17 +2490,23 @@ async fn handle_connection(
                 false,
                 orchestrator,
             )
+            .map(|i| config_a.apply(settings.configure(i)))
             .map_err(|e| e.to_string());
             if let Ok(mut side_a) = a_result {
                 let (tx_a, mut rx_a) = mpsc::unbounded_channel::<TokenEvent>();
                 side_a.web_tx = Some(tx_a);
                 side_a.system_prompt = Some(sys_a);
                 let prompt_a = prompt.clone();
-                tokio::spawn(async move {
+                let slot = UpstreamSlot::claim(settings.upstream.as_ref());
+                if slot.is_queued() {
+                    stream.write_all(queued_event("a").as_bytes()).await?;
+                }
+                tasks.spawn(async move {
+                    let _permit = slot.wait().await;
                     let _ = side_a.intercept_stream(&prompt_a).await;
                 });
                 let mtx = merged_tx.clone();
-                tokio::spawn(async move {
+                tasks.spawn(async move {
                     while let Some(ev) = rx_a.recv().await {
                         let _ = mtx.send(("a", ev));
                     }
@@ -801,17 +2516,23 @@ async fn handle_connection(
             // Side B
             let b_result =
                 TokenInterceptor::new(ab_provider, transform, model, true, false, orchestrator)
+                    .map(|i| config_b.apply(settings.configure(i)))
                     .map_err(|e| e.to_string());
             if let Ok(mut side_b) = b_result {
                 let (tx_b, mut rx_b) = mpsc::unbounded_channel::<TokenEvent>();
                 side_b.web_tx = Some(tx_b);
                 side_b.system_prompt = Some(sys_b);
                 let prompt_b = prompt.clone();
-                tokio::spawn(async move {
+                let slot = UpstreamSlot::claim(settings.upstream.as_ref());
+                if slot.is_queued() {
+                    stream.write_all(queued_event("b").as_bytes()).await?;
+                }
+                tasks.spawn(async move {
+                    let _permit = slot.wait().await;
                     let _ = side_b.intercept_stream(&prompt_b).await;
                 });
                 let mtx = merged_tx.clone();
-                tokio::spawn(async move {
+                tasks.spawn(async move {
                     while let Some(ev) = rx_b.recv().await {
                         let _ = mtx.send(("b", ev));
                     }
@@ -820,17 +2541,19 @@ async fn handle_connection(
 
             drop(merged_tx);
 
-            while let Some((side, event)) = merged_rx.recv().await {
-                let diff_event = DiffTokenEvent {
-                    side,
-                    event: &event,
-                };
-                if let Ok(json) = serde_json::to_string(&diff_event) {
-                    let sse = format!("data: {}\n\n", json);
-                    if stream.write_all(sse.as_bytes()).await.is_err() {
-                        break;
-                    }
-                }
+            #[cfg(feature = "self-tune")]
+            let mut ab_experiment = AbExperiment::new();
+
+            forward_side_events(&mut stream, &mut merged_rx, tasks, |_side, _event| {
+                #[cfg(feature = "self-tune")]
+                ab_experiment.observe(_side, _event);
+            })
+            .await;
+
+            #[cfg(feature = "self-tune")]
+            {
+                let verdict = ab_experiment.verdict();
+                let _ = stream.write_all(format!("data: {}\n\n", verdict).as_bytes()).await;
             }
 
             let _ = stream.write_all(b"data: [DONE]\n\n").await;
@@ -845,9 +2568,23 @@ async fn handle_connection(
                 stream.write_all(response.as_bytes()).await?;
                 return Ok(());
             }
-            let code = crate::collab::create_room(&store);
+            // A repeated create with the same Idempotency-Key (e.g. a
+            // double-clicked "Host Session") returns the first room's code.
+            let idempotency_key = req
+                .headers
+                .iter()
+                .find(|h| h.name.eq_ignore_ascii_case("idempotency-key"))
+                .and_then(|h| std::str::from_utf8(h.value).ok())
+                .map(str::trim)
+                .filter(|k| valid_client_id(k));
+            let code = crate::collab::create_room_idempotent(&store, idempotency_key);
+            crate::collab::set_recording_cap(&store, &code, settings.max_recording_events);
             let room_id = uuid::Uuid::new_v4().to_string();
-            let body = format!(r#"{{"code":"{}","room_id":"{}","ws_url":"/ws/{}"}}"#, code, room_id, code);
+            let host_token = crate::collab::host_token(&store, &code).unwrap_or_default();
+            let body = format!(
+                r#"{{"code":"{}","room_id":"{}","ws_url":"/ws/{}","host_token":"{}"}}"#,
+                code, room_id, code, host_token
+            );
             let response = format!(
                 "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
                 body.len(),
@@ -856,12 +2593,7 @@ async fn handle_connection(
             stream.write_all(response.as_bytes()).await?;
         }
         path if path.starts_with("/join/") => {
-            let response = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                INDEX_HTML.len(),
-                INDEX_HTML
-            );
-            stream.write_all(response.as_bytes()).await?;
+            stream.write_all(index_response(if_none_match.as_deref()).as_bytes()).await?;
         }
         path if path.starts_with("/replay/") => {
             let code = path.strip_prefix("/replay/").unwrap_or("");
@@ -885,6 +2617,9 @@ async fn handle_connection(
                     );
                     stream.write_all(response.as_bytes()).await?;
                 }
+                Ok(events) if gzip_ok => {
+                    stream.write_all(&replay_gzip_response(&events)).await?;
+                }
                 Ok(events) => {
                     // Write JSON in chunks: prefix, each event, suffix.
                     // Use chunked transfer encoding to avoid buffering the full response.
@@ -910,6 +2645,181 @@ async fn handle_connection(
                 }
             }
         }
+        "/api/session/search" => {
+            let params = parse_query(query_str);
+            let room = params.get("room").cloned().unwrap_or_default();
+            let (status, body) = session_search_response(
+                &store,
+                &room,
+                params.get("q").map(String::as_str).unwrap_or(""),
+            );
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/api/session/export" => {
+            let params = parse_query(query_str);
+            let room = params.get("room").cloned().unwrap_or_default();
+            let pretty = matches!(params.get("pretty").map(String::as_str), Some("1" | "true"));
+            let (status, body) = session_export_response(&store, &room, pretty);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/api/preview" => {
+            let (status, body) = preview_response(&parse_query(query_str), settings.max_prompt_chars, &settings.transform_options);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/demo" => {
+            // GET /demo?session=NAME: replay a compiled-in session over SSE,
+            // so the UI can be tried without an API key.
+            let params = parse_query(query_str);
+            match demo_session(&params) {
+                Ok(session) => {
+                    let speed = params
+                        .get("speed")
+                        .and_then(|v| v.parse::<f64>().ok())
+                        .filter(|v| v.is_finite())
+                        .unwrap_or(1.0);
+                    stream.write_all(settings.sse_head().as_bytes()).await?;
+                    write_demo_stream(&mut stream, &session, speed).await?;
+                }
+                Err(body) => {
+                    let response = format!(
+                        "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).await?;
+                }
+            }
+        }
+        "/api/load-session" => {
+            let body = read_request_body(&mut stream, &buf[..n], 16 << 20).await?;
+            let (status, body) = load_session_response(&body);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/api/retransform" => {
+            let body = read_request_body(&mut stream, &buf[..n], 16 << 20).await?;
+            let (status, body) = retransform_response(&body, &settings.transform_options);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/api/ab/export" => {
+            let body = read_request_body(&mut stream, &buf[..n], 16 << 20).await?;
+            let (status, body) = ab_export_response(&body);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/api/transforms" => {
+            let body = serde_json::to_string(&Transform::catalog()).unwrap_or_else(|_| "[]".to_string());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/api/version" => {
+            let body = crate::build_info::version_json().to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        "/api/openai/models" => {
+            let api_key = std::env::var("OPENAI_API_KEY").ok();
+            let models = crate::model_list::OPENAI_MODEL_CACHE
+                .openai_models(api_key.as_deref())
+                .await;
+            let body = serde_json::to_string(&models).unwrap_or_else(|_| "[]".to_string());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        path if path.starts_with("/api/room/") && path.ends_with("/archive") => {
+            let code = path
+                .strip_prefix("/api/room/")
+                .and_then(|rest| rest.strip_suffix("/archive"))
+                .unwrap_or("");
+            let host_token = req
+                .headers
+                .iter()
+                .find(|h| h.name.eq_ignore_ascii_case("x-host-token"))
+                .and_then(|h| std::str::from_utf8(h.value).ok())
+                .map(str::trim)
+                .unwrap_or("");
+            let (status, body) = room_archive_response(&store, code, host_token);
+            let disposition = if status == "200 OK" {
+                format!("Content-Disposition: attachment; filename=\"room-{}-archive.json\"\r\n", code)
+            } else {
+                String::new()
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                disposition,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
+        path if path.starts_with("/api/room/") && path.ends_with("/audit") => {
+            let code = path
+                .strip_prefix("/api/room/")
+                .and_then(|rest| rest.strip_suffix("/audit"))
+                .unwrap_or("");
+            let host_token = req
+                .headers
+                .iter()
+                .find(|h| h.name.eq_ignore_ascii_case("x-host-token"))
+                .and_then(|h| std::str::from_utf8(h.value).ok())
+                .map(str::trim)
+                .unwrap_or("");
+            let (status, body) = room_audit_response(&store, code, host_token);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await?;
+        }
         "/api/experiments" => {
             // Returns stored experiment runs from the SQLite log when the
             // sqlite-log feature is enabled and a --log-db path is provided.
@@ -972,23 +2882,13 @@ async fn handle_connection(
             let req: BatchRequest = match serde_json::from_slice(body_bytes) {
                 Ok(r) => r,
                 Err(_) => {
-                    let body = r#"{"error":"Invalid JSON body"}"#;
-                    let response = format!(
-                        "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                        body.len(), body
-                    );
-                    stream.write_all(response.as_bytes()).await?;
+                    stream.write_all(bad_request_response("Invalid JSON body").as_bytes()).await?;
                     return Ok(());
                 }
             };
 
             if req.prompts.is_empty() || req.prompts.len() > 10 {
-                let body = r#"{"error":"prompts must be 1-10 items"}"#;
-                let response = format!(
-                    "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                    body.len(), body
-                );
-                stream.write_all(response.as_bytes()).await?;
+                stream.write_all(bad_request_response("prompts must be 1-10 items").as_bytes()).await?;
                 return Ok(());
             }
 
@@ -997,10 +2897,15 @@ async fn handle_connection(
             } else {
                 req.transform
             };
-            let transform = Transform::from_str_loose(&transform_str).unwrap_or(Transform::Reverse);
-            let rate = req.rate.clamp(0.0, 1.0);
+            let (transform, rate) = match (transform_param(&transform_str), rate_value(req.rate)) {
+                (Ok(transform), Ok(rate)) => (transform, rate),
+                (Err(msg), _) | (_, Err(msg)) => {
+                    stream.write_all(bad_request_response(&msg).as_bytes()).await?;
+                    return Ok(());
+                }
+            };
             let model = if req.model.is_empty() {
-                DEFAULT_MOCK_MODEL.to_string()
+                Provider::Mock.default_model().to_string()
             } else {
                 req.model
             };
@@ -1041,6 +2946,29 @@ async fn handle_connection(
             );
             stream.write_all(response.as_bytes()).await?;
         }
+        "/batch-stream" => {
+            // POST /batch-stream: run a small dataset of prompts sequentially
+            // over one SSE connection (web counterpart of the CLI batch mode).
+            if !rate_limit_check(&limiter, peer_ip) {
+                let body = r#"{"error":"Too Many Requests"}"#;
+                let response = format!(
+                    "HTTP/1.1 429 Too Many Requests\r\nContent-Type: application/json\r\nContent-Length: {}\r\nRetry-After: 60\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                );
+                stream.write_all(response.as_bytes()).await?;
+                return Ok(());
+            }
+            let body = read_request_body(&mut stream, &buf[..n], 1 << 20).await?;
+            let req = match BatchStreamRequest::parse(&body) {
+                Ok(req) => req,
+                Err(msg) => {
+                    stream.write_all(bad_request_response(&msg).as_bytes()).await?;
+                    return Ok(());
+                }
+            };
+            stream.write_all(settings.sse_head().as_bytes()).await?;
+            write_batch_stream(&mut stream, &req, &default_provider, orchestrator, &settings).await?;
+        }
         _ => {
             let response =
                 "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\nConnection: close\r\n\r\nNot Found";
@@ -1105,6 +3033,33 @@ mod tests {
 
     // -- parse_query tests --
 
+    #[test]
+    fn test_diff_stream_models_per_provider() {
+        let models = |q: &str| diff_stream_models(&parse_query(q));
+        assert_eq!(
+            models("prompt=hi&openai_model=gpt-4o&anthropic_model=claude-opus-4-6"),
+            ("gpt-4o".to_string(), "claude-opus-4-6".to_string())
+        );
+        // A shared model for one provider leaves the other side on its default.
+        assert_eq!(
+            models("prompt=hi&model=gpt-4o"),
+            ("gpt-4o".to_string(), Provider::Anthropic.default_model().to_string())
+        );
+        assert_eq!(
+            models("model=claude-opus-4-6&openai_model=gpt-4.1"),
+            ("gpt-4.1".to_string(), "claude-opus-4-6".to_string())
+        );
+        assert_eq!(
+            models("prompt=hi"),
+            (
+                Provider::Openai.default_model().to_string(),
+                Provider::Anthropic.default_model().to_string()
+            )
+        );
+        // An unrecognised family still applies to both sides.
+        assert_eq!(models("model=custom"), ("custom".to_string(), "custom".to_string()));
+    }
+
     #[test]
     fn test_parse_query_basic() {
         let params = parse_query("prompt=hello+world&transform=reverse&heatmap=1");
@@ -1158,6 +3113,35 @@ mod tests {
         assert_eq!(params.get("q").map(|s| s.as_str()), Some("a+b=c"));
     }
 
+    // -- INDEX_HTML ETag --
+
+    #[test]
+    fn test_index_response_honors_if_none_match() {
+        let etag = INDEX_ETAG.as_str();
+        assert!(etag.starts_with('"') && etag.ends_with('"') && etag.len() == 42);
+
+        let not_modified = index_response(Some(etag));
+        assert!(not_modified.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        assert!(not_modified.contains(&format!("ETag: {}\r\n", etag)));
+        assert!(not_modified.ends_with("\r\n\r\n"), "304 has no body");
+        assert!(index_response(Some(&format!("\"stale\", W/{}", etag))).starts_with("HTTP/1.1 304"));
+
+        for header in [Some("\"0000\""), None] {
+            let ok = index_response(header);
+            assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(ok.contains(&format!("ETag: {}\r\n", etag)));
+            assert!(ok.ends_with(INDEX_HTML));
+        }
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("*", "\"a\""));
+        assert!(etag_matches(" \"b\" , \"a\"", "\"a\""));
+        assert!(etag_matches("W/\"a\"", "\"a\""));
+        assert!(!etag_matches("\"b\"", "\"a\""));
+    }
+
     // -- INDEX_HTML structure tests --
 
     #[test]
@@ -1171,6 +3155,21 @@ mod tests {
         assert!(INDEX_HTML.contains("<title>Every Other Token</title>"));
     }
 
+    #[test]
+    fn test_index_html_heatmap_toggle_recolors_rendered_tokens() {
+        // Tokens carry their importance so the toggle can re-derive heat classes.
+        assert!(INDEX_HTML.contains("s.dataset.importance=importance"));
+        assert!(INDEX_HTML.contains("$('#heatmap').onchange=function(){applyHeatmap(this.checked);}"));
+        let apply = INDEX_HTML
+            .split("function applyHeatmap(on){")
+            .nth(1)
+            .and_then(|rest| rest.split("\n}\n").next())
+            .expect("applyHeatmap defined");
+        assert!(apply.contains("querySelectorAll('.token[data-importance]')"));
+        assert!(apply.contains("classList.remove('heat-'+h)"));
+        assert!(apply.contains("if(on)el.classList.add('heat-'+heatLevel("));
+    }
+
     #[test]
     fn test_index_html_has_dark_theme() {
         assert!(INDEX_HTML.contains("background:#0d1117"));
@@ -1428,15 +3427,8 @@ mod tests {
             text: "hello".to_string(),
             original: "hello".to_string(),
             index: 0,
-            transformed: false,
             importance: 0.5,
-            chaos_label: None,
-            provider: None,
-            confidence: None,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let diff = DiffTokenEvent {
             side: "openai",
@@ -1457,12 +3449,7 @@ mod tests {
             transformed: true,
             importance: 0.7,
             chaos_label: Some("reverse".to_string()),
-            provider: None,
-            confidence: None,
-            perplexity: None,
-            alternatives: vec![],
-            is_error: false,
-            arrival_ms: None,
+            ..Default::default()
         };
         let diff = DiffTokenEvent {
             side: "anthropic",
@@ -1682,9 +3669,16 @@ mod tests {
         assert!(INDEX_HTML.contains("participant_leave"));
     }
 
+    #[test]
+    fn test_index_html_dims_away_participants() {
+        assert!(INDEX_HTML.contains(".p-item.away"));
+        assert!(INDEX_HTML.contains("setPAway(el,m.participant.away)"));
+    }
+
     #[test]
     fn test_index_html_has_surgery_peer_handler() {
         assert!(INDEX_HTML.contains("applyPeerSurgery"));
+        assert!(INDEX_HTML.contains(".token[data-idx=\"'+edit.token_index+'\"]"));
     }
 
     #[test]
@@ -1723,11 +3717,333 @@ mod tests {
         assert!(!origin.is_empty());
     }
 
-    // -- New: MAX_PROMPT_LEN constant --
+    // -- Prompt length limit --
+
+    #[test]
+    fn test_stream_settings_from_args() {
+        use clap::Parser;
+        let args = Args::parse_from(["eot", "p", "--max-prompt-chars", "64", "--render-rate", "5"]);
+        let settings = StreamSettings::from_args(&args);
+        assert_eq!(settings.max_prompt_chars, 64);
+        assert_eq!(settings.render_rate, Some(5.0));
+        assert_eq!(settings.sse_buffer_size, 1000);
+        assert_eq!(
+            settings.reconnect_grace,
+            std::time::Duration::from_secs(crate::collab::DEFAULT_RECONNECT_GRACE_SECS)
+        );
+    }
+
+    #[test]
+    fn test_stream_settings_configure_applies_server_flags() {
+        use clap::Parser;
+        let args = Args::parse_from([
+            "eot", "p", "--max-prompt-chars", "64", "--header", "OpenAI-Organization: org-9",
+            "--normalize", "nfd", "--normalize-original", "--strict-parsing",
+        ]);
+        let interceptor = StreamSettings::from_args(&args).configure(
+            TokenInterceptor::new(Provider::Mock, Transform::Reverse, "mock".to_string(), false, false, false)
+                .expect("mock interceptor"),
+        );
+        assert_eq!(interceptor.max_prompt_chars, 64);
+        assert_eq!(
+            interceptor.extra_headers,
+            vec![("OpenAI-Organization".to_string(), "org-9".to_string())]
+        );
+        assert_eq!(interceptor.normalize, crate::Normalization::Nfd);
+        assert!(interceptor.normalize_original);
+        assert!(interceptor.strict_parsing);
+    }
+
+    #[test]
+    fn test_config_event_carries_reveal_delay() {
+        use clap::Parser;
+        let parse = |args: &Args| -> serde_json::Value {
+            let event = StreamSettings::from_args(args).config_event();
+            serde_json::from_str(event.trim().strip_prefix("data: ").expect("sse data")).expect("json")
+        };
+        let json = parse(&Args::parse_from(["eot", "p", "--reveal-delay", "350"]));
+        assert_eq!(json["type"], "config");
+        assert_eq!(json["reveal_delay_ms"], 350);
+
+        let json = parse(&Args::parse_from(["eot", "p"]));
+        assert!(json.get("reveal_delay_ms").is_none());
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_config_event_carries_render_window() {
+        use clap::Parser;
+        let parse = |args: &Args| -> serde_json::Value {
+            let event = StreamSettings::from_args(args).config_event();
+            serde_json::from_str(event.trim().strip_prefix("data: ").expect("sse data")).expect("json")
+        };
+        assert_eq!(parse(&Args::parse_from(["eot", "p", "--render-window", "200"]))["render_window"], 200);
+        assert_eq!(parse(&Args::parse_from(["eot", "p", "--render-window", "0"]))["render_window"], 0);
+        assert!(parse(&Args::parse_from(["eot", "p"])).get("render_window").is_none());
+    }
+
+    #[test]
+    fn test_config_event_carries_alt_display() {
+        use clap::Parser;
+        let parse = |args: &Args| -> serde_json::Value {
+            let event = StreamSettings::from_args(args).config_event();
+            serde_json::from_str(event.trim().strip_prefix("data: ").expect("sse data")).expect("json")
+        };
+        assert_eq!(parse(&Args::parse_from(["eot", "p", "--alt-display", "bars"]))["alt_display"], "bars");
+        assert_eq!(parse(&Args::parse_from(["eot", "p", "--alt-display", "none"]))["alt_display"], "none");
+        assert!(parse(&Args::parse_from(["eot", "p"])).get("alt_display").is_none());
+        assert!(Args::try_parse_from(["eot", "p", "--alt-display", "pie"]).is_err());
+    }
+
+    #[test]
+    fn test_index_html_reports_stream_errors() {
+        assert!(INDEX_HTML.contains("if(tk.error){showNotice('Stream failed: '+tk.error,'error');return;}"));
+    }
+
+    #[test]
+    fn test_index_html_branches_on_alt_display() {
+        assert!(INDEX_HTML.contains("altDisplay=tk.alt_display||'tooltip'"));
+        assert!(INDEX_HTML.contains("function showAlternatives("));
+        assert!(INDEX_HTML.contains("altDisplay==='none'"));
+        assert!(INDEX_HTML.contains("altDisplay==='bars'"));
+    }
+
+    #[test]
+    fn test_index_html_trims_rendered_tokens_to_window() {
+        assert!(INDEX_HTML.contains("renderWindow=tk.render_window"));
+        assert!(INDEX_HTML.contains("function trimRendered("));
+        assert!(INDEX_HTML.contains("trimRendered($('#v-single'))"));
+        assert!(!INDEX_HTML.contains("MAX_DOM_TOKENS"));
+    }
+
+    #[test]
+    fn test_char_granularity_emits_one_event_per_char() {
+        let event = TokenEvent {
+            text: "olleh".to_string(),
+            original: "hello".to_string(),
+            index: 3,
+            transformed: true,
+            importance: 0.5,
+            ..Default::default()
+        };
+        let payloads = emit_payloads(&event, EmitGranularity::Char);
+        assert_eq!(payloads.len(), 5);
+        let parsed: Vec<serde_json::Value> = payloads
+            .iter()
+            .map(|p| serde_json::from_str(p).expect("json"))
+            .collect();
+        let text: String = parsed.iter().filter_map(|v| v["text"].as_str()).collect();
+        assert_eq!(text, "olleh");
+        for (i, v) in parsed.iter().enumerate() {
+            assert_eq!(v["char_index"], i);
+            assert_eq!(v["char_count"], 5);
+            assert_eq!(v["original"], "hello");
+            assert_eq!(v["transformed"], true);
+            assert_eq!(v["index"], 3);
+        }
+
+        let word = emit_payloads(&event, EmitGranularity::Word);
+        assert_eq!(word.len(), 1);
+        assert!(!word[0].contains("char_index"));
+    }
+
+    #[test]
+    fn test_index_html_handles_char_granularity() {
+        assert!(INDEX_HTML.contains("id=\"typewriter\""));
+        assert!(INDEX_HTML.contains("&granularity=char"));
+        assert!(INDEX_HTML.contains("tk.char_index>0&&charTok"));
+    }
+
+    #[test]
+    fn test_emit_granularity_param_and_config_event() {
+        use clap::Parser;
+        let sp = StreamParams::from_query(&parse_query("prompt=x&granularity=CHAR")).expect("valid params");
+        assert_eq!(sp.granularity, Some(EmitGranularity::Char));
+        assert_eq!(
+            StreamParams::from_query(&parse_query("granularity=bogus")).err().as_deref(),
+            Some("granularity: expected word or char, got 'bogus'")
+        );
+        let args = Args::parse_from(["eot", "p", "--emit-granularity", "char"]);
+        let event = StreamSettings::from_args(&args).config_event();
+        assert!(event.contains(r#""emit_granularity":"char""#), "{event}");
+        let default = StreamSettings::from_args(&Args::parse_from(["eot", "p"])).config_event();
+        assert!(!default.contains("emit_granularity"));
+    }
+
+    #[test]
+    fn test_sse_head_starts_body_with_retry_directive() {
+        use clap::Parser;
+        let head = StreamSettings::from_args(&Args::parse_from(["eot", "p"])).sse_head();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n"));
+        let (_, body) = head.split_once("\r\n\r\n").expect("header terminator");
+        assert_eq!(body, "retry: 3000\n\n");
+
+        let args = Args::parse_from(["eot", "p", "--sse-retry-ms", "750"]);
+        assert!(StreamSettings::from_args(&args).sse_head().ends_with("\r\n\r\nretry: 750\n\n"));
+        let args = Args::parse_from(["eot", "p", "--sse-retry-ms", "0"]);
+        assert!(!StreamSettings::from_args(&args).sse_head().contains("retry:"));
+    }
+
+    #[test]
+    fn test_index_html_escapes_peer_strings() {
+        // Names, toasts, chat and annotations are set as text, never markup.
+        for sink in [
+            "nm.textContent=p.name",
+            "el.querySelector('.p-name').textContent=m.participant.name",
+            "t.textContent=text",
+            "author.textContent=m.author_name",
+            "body.textContent=m.text",
+            "sp.title=notes.map(a=>a.author_name+': '+a.note)",
+        ] {
+            assert!(INDEX_HTML.contains(sink), "missing text sink: {sink}");
+        }
+        // Token text relayed by a room host is escaped in innerHTML lists.
+        assert!(INDEX_HTML.contains(r#""${escHtml(t.original)}""#));
+        assert!(INDEX_HTML.contains("sp.style.color=safeCssColor(edit.editor_color)"));
+        for line in INDEX_HTML.lines().filter(|l| l.contains("innerHTML")) {
+            for field in [".name", "author_", ".note", "m.text", "editor_"] {
+                assert!(!line.contains(field), "peer field {field} reaches innerHTML: {line}");
+            }
+        }
+    }
 
     #[test]
-    fn test_max_prompt_len_is_100k() {
-        assert_eq!(MAX_PROMPT_LEN, 100_000);
+    fn test_index_html_noise_mirrors_config_event() {
+        assert!(INDEX_HTML.contains("noiseChars=tk.noise_chars||'*+~@#$%'"));
+        assert!(INDEX_HTML.contains("i<noiseCount"));
+    }
+
+    #[test]
+    fn test_index_html_has_reveal_logic() {
+        assert!(INDEX_HTML.contains("tk.type==='config'"));
+        assert!(INDEX_HTML.contains("revealDelayMs=tk.reveal_delay_ms||0"));
+        assert!(INDEX_HTML.contains("function revealAfterDelay("));
+        assert!(INDEX_HTML.contains(".token.reveal-pending"));
+        // A span rewritten while its reveal is pending keeps the new text.
+        assert!(INDEX_HTML.contains("const obs=new MutationObserver(settle);"));
+    }
+
+    #[test]
+    fn test_sse_error_done_reports_prompt_too_long() {
+        let msg = crate::check_prompt_length("abcdef", 5).expect_err("over limit");
+        let sse = sse_error_done(&msg);
+        assert!(sse.starts_with("data: {\"error\":\"prompt too long"));
+        assert!(sse.ends_with("data: [DONE]\n\n"));
+        assert!(crate::check_prompt_length("abcde", 5).is_ok());
+    }
+
+    fn diff_token(original: &str, perplexity: Option<f32>) -> TokenEvent {
+        TokenEvent {
+            text: original.to_string(),
+            original: original.to_string(),
+            index: 0,
+            importance: 0.5,
+            perplexity,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sse_batcher_groups_events_and_keeps_count() {
+        use clap::Parser;
+        let settings = StreamSettings::from_args(&Args::parse_from(["eot", "p", "--sse-batch", "3"]));
+        let mut batcher = SseBatcher::new(settings.sse_batch);
+        let words = ["The", " quick", " brown", " fox", " jumps", " over", " it"];
+        let mut frames: Vec<String> = words
+            .iter()
+            .flat_map(|w| emit_payloads(&diff_token(w, None), EmitGranularity::Word))
+            .filter_map(|json| batcher.push(json))
+            .collect();
+        frames.extend(batcher.flush());
+        assert!(batcher.flush().is_none());
+
+        let mut texts = Vec::new();
+        let mut sizes = Vec::new();
+        for frame in &frames {
+            let data = frame.strip_prefix("data: ").and_then(|f| f.strip_suffix("\n\n")).expect("sse frame");
+            let events = match serde_json::from_str::<serde_json::Value>(data).expect("json") {
+                serde_json::Value::Array(events) => events,
+                single => vec![single],
+            };
+            sizes.push(events.len());
+            texts.extend(events.iter().map(|e| e["text"].as_str().unwrap_or("").to_string()));
+        }
+        assert_eq!(sizes, vec![3, 3, 1]);
+        assert_eq!(texts, words);
+        // The last, lone event is a bare object, exactly as without batching.
+        assert!(frames[2].starts_with("data: {"));
+
+        let mut unbatched = SseBatcher::new(StreamSettings::from_args(&Args::parse_from(["eot", "p"])).sse_batch);
+        assert_eq!(unbatched.push("{}".to_string()).as_deref(), Some("data: {}\n\n"));
+        assert!(Args::try_parse_from(["eot", "p", "--sse-batch", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_index_html_unpacks_batched_frames() {
+        assert!(INDEX_HTML.contains("(Array.isArray(msg)?msg:[msg]).forEach(onStreamEvent)"));
+    }
+
+    #[test]
+    fn test_diff_summary_carries_counts_and_match_pct() {
+        let mut summary = DiffSummary::default();
+        for (t, p) in [("The", 1.0), (" quick", 3.0), (" brown", 2.0), (" fox", 2.0)] {
+            summary.observe("openai", &diff_token(t, Some(p)));
+        }
+        // Anthropic inserts an extra token; alignment still matches the rest.
+        for t in ["The", " very", " quick", " brown", " fox"] {
+            summary.observe("anthropic", &diff_token(t, None));
+        }
+        let v = summary.summary();
+        assert_eq!(v["type"], "diff_summary");
+        assert_eq!(v["openai"]["token_count"], 4);
+        assert_eq!(v["anthropic"]["token_count"], 5);
+        assert_eq!(v["matched"], 4);
+        assert_eq!(v["match_pct"], 80.0);
+        assert_eq!(v["openai"]["mean_perplexity"], 2.0);
+        assert!(v["anthropic"]["mean_perplexity"].is_null());
+    }
+
+    #[test]
+    fn test_diff_summary_empty_and_errors() {
+        let mut summary = DiffSummary::default();
+        let mut err = diff_token("[error]", None);
+        err.is_error = true;
+        summary.observe("openai", &err);
+        let v = summary.summary();
+        assert_eq!(v["openai"]["token_count"], 0);
+        assert_eq!(v["match_pct"], 0.0);
+    }
+
+    #[test]
+    fn test_ab_sides_receive_distinct_params() {
+        let params = parse_query("prompt=hi&max_a=64&max_b=256&temp_a=0.2&temp_b=1.1");
+        let a = AbSideConfig::from_params(&params, "a");
+        let b = AbSideConfig::from_params(&params, "b");
+        assert_eq!(a, AbSideConfig { max_tokens: Some(64), temperature: Some(0.2) });
+        assert_eq!(b, AbSideConfig { max_tokens: Some(256), temperature: Some(1.1) });
+
+        let mk = || {
+            TokenInterceptor::new(
+                Provider::Mock,
+                Transform::Reverse,
+                "mock".to_string(),
+                true,
+                false,
+                false,
+            )
+            .expect("mock interceptor")
+        };
+        let side_a = a.apply(mk());
+        let side_b = b.apply(mk());
+        assert_eq!((side_a.max_tokens, side_a.temperature), (Some(64), 0.2));
+        assert_eq!((side_b.max_tokens, side_b.temperature), (Some(256), 1.1));
+    }
+
+    #[test]
+    fn test_ab_sides_default_to_equal() {
+        let params = parse_query("prompt=hi&max_a=abc&temp_b=NaN");
+        assert_eq!(AbSideConfig::from_params(&params, "a"), AbSideConfig::default());
+        assert_eq!(AbSideConfig::from_params(&params, "b"), AbSideConfig::default());
     }
 
     // -- New: batch endpoint parsing logic --
@@ -2110,12 +4426,12 @@ mod tests {
         let _ = parse_query("");
     }
 
-    // -- parse_stream_params tests (item 13) --
+    // -- StreamParams::from_query tests (item 13) --
 
     #[test]
     fn test_parse_stream_params_defaults() {
         let params = parse_query("");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.prompt, "");
         assert_eq!(sp.transform, "reverse");
         assert_eq!(sp.provider, "openai");
@@ -2131,80 +4447,250 @@ mod tests {
     #[test]
     fn test_parse_stream_params_seed_parsed() {
         let params = parse_query("seed=42");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.seed, Some(42));
     }
 
     #[test]
     fn test_parse_stream_params_visual_flag_one() {
         let params = parse_query("visual=1");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert!(sp.visual);
     }
 
     #[test]
     fn test_parse_stream_params_visual_flag_true() {
         let params = parse_query("visual=true");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert!(sp.visual);
     }
 
     #[test]
     fn test_parse_stream_params_rate_parsed() {
         let params = parse_query("rate=0.8");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert!((sp.rate - 0.8).abs() < 1e-9);
     }
 
     #[test]
     fn test_parse_stream_params_system_prompt() {
         let params = parse_query("system=Be+concise");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.system.as_deref(), Some("Be concise"));
     }
 
     #[test]
     fn test_parse_stream_params_empty_system_is_none() {
         let params = parse_query("system=");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.system, None);
     }
 
     #[test]
     fn test_parse_stream_params_rate_negative_clamped() {
         let params = parse_query("rate=-1");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.rate, 0.0);
     }
 
     #[test]
     fn test_parse_stream_params_rate_over_one_clamped() {
         let params = parse_query("rate=2.0");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.rate, 1.0);
     }
 
     #[test]
-    fn test_parse_stream_params_rate_nan_uses_default() {
+    fn test_parse_stream_params_rate_nan_rejected() {
         let params = parse_query("rate=nan");
-        let sp = parse_stream_params(&params);
-        assert_eq!(sp.rate, 0.5);
+        assert!(StreamParams::from_query(&params).is_err());
+    }
+
+    #[test]
+    fn test_batch_stream_request_parse() {
+        let req = BatchStreamRequest::parse(br#"["a", "b"]"#).expect("bare array");
+        assert_eq!(req.prompts, vec!["a", "b"]);
+        let req = BatchStreamRequest::parse(br#"{"prompts":["a"],"provider":"mock","rate":1.0}"#)
+            .expect("object form");
+        assert_eq!(req.provider.as_deref(), Some("mock"));
+        assert_eq!(req.rate, Some(1.0));
+        assert!(BatchStreamRequest::parse(b"[]").is_err());
+        assert!(BatchStreamRequest::parse(b"not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_demo_streams_builtin_session_in_order_then_done() {
+        let session = demo_session(&parse_query("session=haiku")).expect("built-in demo");
+        let mut out: Vec<u8> = Vec::new();
+        write_demo_stream(&mut out, &session, 0.0).await.expect("write");
+        let text = String::from_utf8(out).expect("utf-8");
+        assert!(text.ends_with("data: [DONE]\n\n"));
+        let events: Vec<TokenEvent> = text
+            .split("\n\n")
+            .filter_map(|chunk| chunk.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).expect("token event"))
+            .collect();
+        assert_eq!(events.len(), session.tokens.len());
+        assert!(events.iter().enumerate().all(|(i, e)| e.index == i));
+        let streamed: String = events.iter().map(|e| e.original.as_str()).collect();
+        assert_eq!(streamed, session.tokens.iter().map(|t| t.original.as_str()).collect::<String>());
+
+        assert_eq!(
+            demo_session(&HashMap::new()).expect("default demo").prompt,
+            crate::replay::ExportedSession::builtin(crate::replay::BUILTIN_DEMOS[0].0).unwrap().prompt
+        );
+        let err = demo_session(&parse_query("session=nope")).unwrap_err();
+        assert!(err.contains("unknown demo session") && err.contains("recursion"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_stream_waits_for_upstream_slot() {
+        let req = BatchStreamRequest::parse(br#"{"prompts":["only prompt"],"provider":"mock"}"#).expect("request");
+        let settings = StreamSettings::from_args(&{
+            use clap::Parser;
+            Args::parse_from(["eot", "p", "--max-concurrency", "1"])
+        });
+        let held = Arc::clone(settings.upstream.as_ref().expect("limit"))
+            .try_acquire_owned()
+            .expect("free slot");
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            drop(held);
+        });
+        let mut out: Vec<u8> = Vec::new();
+        write_batch_stream(&mut out, &req, &Provider::Openai, false, &settings)
+            .await
+            .expect("write");
+        let text = String::from_utf8(out).expect("utf-8");
+        let queued = text.find(r#""type":"queued""#).expect("queued event");
+        let first_token = text.find(r#""prompt_index":0"#).expect("token event");
+        assert!(queued < first_token, "{text}");
+        assert_eq!(settings.upstream.as_ref().expect("limit").available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_stream_tags_prompt_indices_and_summarizes() {
+        let req = BatchStreamRequest::parse(br#"{"prompts":["first prompt","second prompt"],"provider":"mock"}"#)
+            .expect("request");
+        let settings = StreamSettings::from_args(&{
+            use clap::Parser;
+            Args::parse_from(["eot", "p"])
+        });
+        let mut out: Vec<u8> = Vec::new();
+        write_batch_stream(&mut out, &req, &Provider::Openai, false, &settings)
+            .await
+            .expect("write");
+        let text = String::from_utf8(out).expect("utf-8");
+        let events: Vec<serde_json::Value> = text
+            .split("\n\n")
+            .filter_map(|chunk| chunk.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).expect("json event"))
+            .collect();
+        assert!(text.trim_end().ends_with("data: [DONE]"));
+
+        let tokens_for = |i: usize| {
+            events
+                .iter()
+                .filter(|e| e.get("type").is_none() && e["prompt_index"] == i)
+                .count()
+        };
+        assert!(tokens_for(0) > 0, "no tokens for prompt 0: {text}");
+        assert!(tokens_for(1) > 0, "no tokens for prompt 1: {text}");
+
+        let summary = events.last().expect("summary");
+        assert_eq!(summary["type"], "batch_summary");
+        assert_eq!(summary["prompt_count"], 2);
+        assert_eq!(summary["token_count"], tokens_for(0) + tokens_for(1));
+        assert_eq!(summary["failed"], 0);
+        assert_eq!(summary["prompts"][1]["prompt_index"], 1);
+    }
+
+    #[test]
+    fn test_parse_stream_params_logprobs() {
+        assert_eq!(StreamParams::from_query(&parse_query("logprobs=0")).expect("valid params").logprobs, Some(false));
+        assert_eq!(StreamParams::from_query(&parse_query("logprobs=true")).expect("valid params").logprobs, Some(true));
+        assert_eq!(StreamParams::from_query(&parse_query("prompt=x")).expect("valid params").logprobs, None);
     }
 
     #[test]
     fn test_parse_stream_params_top_logprobs_clamped_to_20() {
         let params = parse_query("top_logprobs=255");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.top_logprobs, 20);
     }
 
     #[test]
     fn test_parse_stream_params_top_logprobs_valid_unchanged() {
         let params = parse_query("top_logprobs=10");
-        let sp = parse_stream_params(&params);
+        let sp = StreamParams::from_query(&params).expect("valid params");
         assert_eq!(sp.top_logprobs, 10);
     }
 
+    #[test]
+    fn test_stream_params_typed_values() {
+        let sp = StreamParams::from_query(&parse_query(
+            "provider=Anthropic&transform=chain:reverse,uppercase&heatmap=true&visual=0&logprobs=1&seed=7",
+        ))
+        .expect("valid params");
+        assert_eq!(sp.provider, "anthropic");
+        assert_eq!(sp.transform, "chain:reverse,uppercase");
+        assert!(sp.heatmap);
+        assert!(!sp.visual);
+        assert_eq!(sp.logprobs, Some(true));
+        assert_eq!(sp.seed, Some(7));
+        let sp = StreamParams::from_query(&parse_query("provider=mock")).expect("valid params");
+        assert_eq!(sp.provider, "mock");
+    }
+
+    #[test]
+    fn test_stream_params_reject_bad_values() {
+        let err = |q: &str| StreamParams::from_query(&parse_query(q)).err().unwrap_or_default();
+        assert!(err("provider=gemini").starts_with("provider: unknown provider: 'gemini'"), "{}", err("provider=gemini"));
+        assert!(err("transform=sideways").starts_with("transform: "), "{}", err("transform=sideways"));
+        assert_eq!(err("heatmap=yes"), "heatmap: expected 1, 0, true or false, got 'yes'");
+        assert_eq!(err("visual=on"), "visual: expected 1, 0, true or false, got 'on'");
+        assert_eq!(err("logprobs=2"), "logprobs: expected 1, 0, true or false, got '2'");
+        assert_eq!(err("seed=abc"), "seed: expected a non-negative integer, got 'abc'");
+        assert_eq!(err("rate=half"), "rate: expected a number between 0 and 1, got 'half'");
+        assert_eq!(err("rate=inf"), "rate: expected a number between 0 and 1, got 'inf'");
+        assert_eq!(err("top_logprobs=-1"), "top_logprobs: expected an integer from 0 to 20, got '-1'");
+        assert_eq!(err("top_logprobs=lots"), "top_logprobs: expected an integer from 0 to 20, got 'lots'");
+        assert_eq!(err("granularity=line"), "granularity: expected word or char, got 'line'");
+    }
+
+    #[test]
+    fn test_effective_prompt_event_and_ui_export() {
+        let event = effective_prompt_event("enriched \"prompt\"");
+        let data = event.strip_prefix("data: ").and_then(|e| e.strip_suffix("\n\n")).expect("sse frame");
+        let v: serde_json::Value = serde_json::from_str(data).expect("json");
+        assert_eq!(v["type"], "effective_prompt");
+        assert_eq!(v["effective_prompt"], "enriched \"prompt\"");
+        assert!(INDEX_HTML.contains("if(tk.type==='effective_prompt'){streamEffectivePrompt=tk.effective_prompt;return;}"));
+        assert!(INDEX_HTML.contains("effective_prompt:streamEffectivePrompt||undefined"));
+    }
+
+    #[test]
+    fn test_error_responses_carry_cors_header() {
+        let response = bad_request_response("rate: nope");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.contains(&format!("Access-Control-Allow-Origin: {}\r\n", cors_origin())));
+        assert!(response.ends_with(r#"{"error":"rate: nope"}"#));
+
+        let response = error_response("404 Not Found", "room not found: ABC123");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(response.contains(&format!("Access-Control-Allow-Origin: {}\r\n", cors_origin())));
+    }
+
+    #[test]
+    fn test_batch_stream_request_rejects_bad_values() {
+        let err = |body: &[u8]| BatchStreamRequest::parse(body).err().unwrap_or_default();
+        assert!(err(br#"{"prompts":["a"],"transform":"sideways"}"#).starts_with("transform: "));
+        assert!(err(br#"{"prompts":["a"],"provider":"gemini"}"#).starts_with("provider: "));
+        assert!(BatchStreamRequest::parse(br#"{"prompts":["a"],"transform":"uppercase","provider":"mock","rate":2}"#).is_ok());
+    }
+
     // -- url_decode UTF-8 multi-byte (item 1) --
 
     #[test]
@@ -2285,6 +4771,38 @@ mod tests {
         assert!(rate_limit_check(&limiter, ip), "should be allowed after window expiry");
     }
 
+    #[test]
+    fn test_accepts_gzip() {
+        assert!(accepts_gzip("gzip"));
+        assert!(accepts_gzip("br, GZIP;q=0.8, deflate"));
+        assert!(accepts_gzip("*"));
+        assert!(!accepts_gzip("gzip;q=0"));
+        assert!(!accepts_gzip("deflate, br"));
+        assert!(!accepts_gzip(""));
+    }
+
+    #[test]
+    fn test_replay_gzip_response_sets_content_encoding() {
+        use std::io::Read;
+        let events = vec![
+            crate::collab::RecordedEvent { offset_ms: 0, payload: serde_json::json!({"type": "chat", "text": "hi"}) },
+            crate::collab::RecordedEvent { offset_ms: 40, payload: serde_json::json!({"type": "stream_done"}) },
+        ];
+        let response = replay_gzip_response(&events);
+        let split = response.windows(4).position(|w| w == b"\r\n\r\n").expect("header end");
+        let head = std::str::from_utf8(&response[..split]).unwrap();
+        let body = &response[split + 4..];
+        assert!(head.contains("\r\nContent-Encoding: gzip\r\n"), "{head}");
+        assert!(head.contains("\r\nVary: Accept-Encoding\r\n"));
+        assert!(head.contains(&format!("Content-Length: {}\r\n", body.len())));
+
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(body).read_to_string(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["events"][0]["payload"]["text"], "hi");
+        assert_eq!(value["events"][1]["offset_ms"], 40);
+    }
+
     // -- Item 7: replay response prefix --
     #[test]
     fn test_replay_response_prefix() {
@@ -2300,4 +4818,734 @@ mod tests {
         assert_eq!(result, input);
         assert_eq!(result.len(), 100);
     }
+
+    // -- A/B experiment verdict --
+
+    #[cfg(feature = "self-tune")]
+    fn perplexity_event(perplexity: f32) -> crate::TokenEvent {
+        crate::TokenEvent {
+            text: "t".to_string(),
+            original: "t".to_string(),
+            index: 0,
+            importance: 0.5,
+            perplexity: Some(perplexity),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "self-tune")]
+    #[test]
+    fn test_ab_experiment_records_each_side_as_arm() {
+        let mut exp = AbExperiment::new();
+        exp.observe("a", &perplexity_event(2.0));
+        exp.observe("b", &perplexity_event(3.0));
+        exp.observe("b", &perplexity_event(3.5));
+        let verdict = exp.verdict();
+        assert_eq!(verdict["type"], "experiment_result");
+        assert_eq!(verdict["samples_a"], 1);
+        assert_eq!(verdict["samples_b"], 2);
+        assert!(verdict["winner"].is_null());
+    }
+
+    #[cfg(feature = "self-tune")]
+    #[test]
+    fn test_ab_experiment_declares_significant_winner() {
+        let mut exp = AbExperiment::new();
+        for i in 0..20 {
+            let jitter = (i % 3) as f32 * 0.05;
+            exp.observe("a", &perplexity_event(1.2 + jitter));
+            exp.observe("b", &perplexity_event(4.0 + jitter));
+        }
+        let verdict = exp.verdict();
+        assert_eq!(verdict["winner"], "a");
+        assert_eq!(verdict["significant"], true);
+        assert!(verdict["p_value"].as_f64().expect("p_value") < 0.05);
+    }
+
+    #[cfg(feature = "self-tune")]
+    #[test]
+    fn test_ab_experiment_ignores_events_without_metrics() {
+        let mut exp = AbExperiment::new();
+        let mut ev = perplexity_event(1.0);
+        ev.perplexity = None;
+        exp.observe("a", &ev);
+        assert_eq!(exp.verdict()["samples_a"], 0);
+    }
+
+    #[test]
+    fn test_index_html_handles_experiment_result() {
+        assert!(INDEX_HTML.contains("experiment_result"));
+        assert!(INDEX_HTML.contains("renderExpVerdict"));
+    }
+
+    // -- room transform lock --
+
+    #[test]
+    fn test_room_transform_overrides_guest_param_when_locked() {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        assert!(crate::collab::set_transform_lock(&store, &code, Some("uppercase".to_string())));
+        let t = room_transform(&store, std::slice::from_ref(&code), "reverse".to_string());
+        assert_eq!(t, "uppercase");
+    }
+
+    #[test]
+    fn test_room_transform_uses_param_when_unlocked() {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        assert_eq!(room_transform(&store, std::slice::from_ref(&code), "mock".to_string()), "mock");
+        crate::collab::set_transform_lock(&store, &code, Some("noise".to_string()));
+        crate::collab::set_transform_lock(&store, &code, None);
+        assert_eq!(room_transform(&store, std::slice::from_ref(&code), "mock".to_string()), "mock");
+    }
+
+    #[test]
+    fn test_room_transform_without_room_uses_param() {
+        let store = crate::collab::new_room_store();
+        assert_eq!(room_transform(&store, &[], "delete".to_string()), "delete");
+        assert_eq!(
+            room_transform(&store, &["NO-SUCH-00".to_string()], "delete".to_string()),
+            "delete"
+        );
+    }
+
+    #[test]
+    fn test_parse_room_codes() {
+        assert!(parse_room_codes(None).is_empty());
+        assert!(parse_room_codes(Some("")).is_empty());
+        assert_eq!(parse_room_codes(Some("A")), vec!["A"]);
+        assert_eq!(parse_room_codes(Some("A, B,,A,C ")), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_stream_token_reaches_every_listed_room() {
+        let store = crate::collab::new_room_store();
+        let a = crate::collab::create_room(&store);
+        let b = crate::collab::create_room(&store);
+        let c = crate::collab::create_room(&store);
+        let (_, mut rx_a) = crate::collab::join_room(&store, &a, "in-a", false).unwrap();
+        let (_, mut rx_b) = crate::collab::join_room(&store, &b, "in-b", false).unwrap();
+        let (_, mut rx_c) = crate::collab::join_room(&store, &c, "in-c", false).unwrap();
+        let rooms = parse_room_codes(Some(&format!("{},{}", a, b)));
+
+        let token = serde_json::json!({"text": "HELLO", "original": "hello", "index": 0});
+        fan_out_stream_token(&store, &rooms, &token);
+
+        assert_eq!(rx_a.try_recv().unwrap()["text"], "HELLO");
+        assert_eq!(rx_b.try_recv().unwrap()["text"], "HELLO");
+        assert!(rx_c.try_recv().is_err(), "unlisted room must not receive the token");
+        assert_eq!(crate::collab::session_tokens(&store, &a).unwrap().len(), 1);
+        assert_eq!(crate::collab::session_tokens(&store, &b).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_stream_notice_is_broadcast_but_not_retained() {
+        let store = crate::collab::new_room_store();
+        let a = crate::collab::create_room(&store);
+        let (_, mut rx_a) = crate::collab::join_room(&store, &a, "in-a", false).unwrap();
+        let notice = serde_json::json!({"text": "[tool call] lookup", "index": 0, "is_error": true});
+        fan_out_stream_token(&store, std::slice::from_ref(&a), &notice);
+        assert_eq!(rx_a.try_recv().unwrap()["is_error"], true);
+        assert!(crate::collab::session_tokens(&store, &a).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_html_shows_error_events_as_notices() {
+        assert!(INDEX_HTML.contains("if(tk.is_error){showNotice(tk.text,'warning');return;}"));
+        assert!(INDEX_HTML.contains("if(m.is_error){if(!amHost)showNotice(m.text,'warning');break;}"));
+    }
+
+    #[test]
+    fn test_room_transform_uses_first_locked_room() {
+        let store = crate::collab::new_room_store();
+        let a = crate::collab::create_room(&store);
+        let b = crate::collab::create_room(&store);
+        crate::collab::set_transform_lock(&store, &b, Some("uppercase".to_string()));
+        assert_eq!(room_transform(&store, &[a, b], "reverse".to_string()), "uppercase");
+        assert!(!crate::collab::room_exists(&store, "NO-SUCH-00"));
+    }
+
+    #[test]
+    fn test_index_html_has_transform_lock_controls() {
+        assert!(INDEX_HTML.contains("lock_transform"));
+        assert!(INDEX_HTML.contains("transform_lock"));
+    }
+
+    #[test]
+    fn test_valid_client_id() {
+        assert!(valid_client_id("3f2b8c1e-9d4a-4f7b-a1c2-0e5d6f7a8b9c"));
+        assert!(!valid_client_id(""));
+        assert!(!valid_client_id("has space"));
+        assert!(!valid_client_id(&"a".repeat(65)));
+    }
+
+    #[test]
+    fn test_index_html_sends_client_id_on_join() {
+        assert!(INDEX_HTML.contains("eot-client-id"));
+        assert!(INDEX_HTML.contains("client_id="));
+    }
+
+    #[test]
+    fn test_index_html_highlights_top_decile() {
+        assert!(INDEX_HTML.contains("perplexity_percentile"));
+        assert!(INDEX_HTML.contains("top-decile"));
+    }
+
+    #[test]
+    fn test_index_html_has_pause_controls() {
+        assert!(INDEX_HTML.contains("btn-pause"));
+        assert!(INDEX_HTML.contains("stream_pause"));
+        assert!(INDEX_HTML.contains("Paused by host"));
+    }
+
+    #[test]
+    fn test_index_html_maps_ws_close_codes() {
+        assert!(INDEX_HTML.contains("wsCloseMessage"));
+        assert!(INDEX_HTML.contains("4004"));
+        assert!(INDEX_HTML.contains("4029"));
+        assert!(INDEX_HTML.contains("4003"));
+    }
+
+    // -- session search --
+
+    fn search_store() -> (RoomStore, String) {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        for (i, (text, ppl)) in [("alpha", 1.5), ("beta", 3.0), ("alphabet", 6.0)].iter().enumerate() {
+            crate::collab::record_session_token(
+                &store,
+                &code,
+                serde_json::json!({"index": i, "original": text, "perplexity": ppl}),
+            );
+        }
+        (store, code)
+    }
+
+    #[test]
+    fn test_preview_applies_each_builtin_transform() {
+        let preview = |transform: &str| -> serde_json::Value {
+            let params = parse_query(&format!("text=hello+world+foo+bar&transform={transform}"));
+            let (status, body) = preview_response(&params, 32_000, &TransformOptions::default());
+            assert_eq!(status, "200 OK", "{transform}: {body}");
+            serde_json::from_str(&body).expect("json")
+        };
+        let expected = [
+            ("reverse", "hello dlrow foo rab"),
+            ("uppercase", "hello WORLD foo BAR"),
+            ("mock", "hello wOrLd foo bAr"),
+            ("delete", "hello  foo "),
+            ("delay", "hello world foo bar"),
+        ];
+        for (name, text) in expected {
+            let v = preview(name);
+            assert_eq!(v["text"], text, "transform {name}");
+            assert_eq!(v["tokens"][1]["original"], "world");
+            assert_eq!(v["tokens"][1]["transformed"], true);
+            assert_eq!(v["tokens"][0]["transformed"], false);
+        }
+        // Randomised transforms: check the shape rather than the exact text.
+        let noise = preview("noise");
+        let word = noise["tokens"][1]["text"].as_str().expect("text");
+        assert!(word.starts_with("world") && word.chars().count() == 6);
+        let scramble = preview("scramble");
+        let mut chars: Vec<char> = scramble["tokens"][1]["text"].as_str().expect("text").chars().collect();
+        chars.sort_unstable();
+        assert_eq!(chars, vec!['d', 'l', 'o', 'r', 'w']);
+        let chaos = preview("chaos");
+        assert_eq!(chaos["tokens"][0]["text"], "hello");
+        assert_ne!(chaos["tokens"][1]["text"], "world");
+        assert_eq!(preview("synonym")["tokens"][0]["text"], "hello");
+    }
+
+    #[test]
+    fn test_index_html_has_preview_button() {
+        assert!(INDEX_HTML.contains("id=\"btn-preview\""));
+        assert!(INDEX_HTML.contains("/api/preview?text="));
+    }
+
+    #[test]
+    fn test_preview_rejects_bad_input() {
+        let bad = |q: &str| preview_response(&parse_query(q), 10, &TransformOptions::default()).0;
+        assert_eq!(bad("text=hi&transform=bogus"), "400 Bad Request");
+        assert_eq!(bad("text=hi&interval=0"), "400 Bad Request");
+        assert_eq!(bad("text=this+is+far+too+long"), "400 Bad Request");
+        let (_, body) = preview_response(&parse_query("text=a+b+c&transform=uppercase&interval=1"), 10, &TransformOptions::default());
+        assert!(body.contains(r#""text":"A B C""#), "{body}");
+    }
+
+    #[test]
+    fn test_session_search_substring() {
+        let (store, code) = search_store();
+        let (status, body) = session_search_response(&store, &code, "alpha");
+        assert_eq!(status, "200 OK");
+        let v: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(v["matches"], serde_json::json!([0, 2]));
+        assert_eq!(v["total"], 3);
+    }
+
+    #[test]
+    fn test_session_search_perplexity_range() {
+        let (store, code) = search_store();
+        let (_, body) = session_search_response(&store, &code, "perplexity:2..10");
+        let v: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(v["matches"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_session_search_bad_query_is_400() {
+        let (store, code) = search_store();
+        assert_eq!(session_search_response(&store, &code, "ppl:x..y").0, "400 Bad Request");
+        assert_eq!(session_search_response(&store, &code, "").0, "400 Bad Request");
+    }
+
+    #[test]
+    fn test_session_search_unknown_room_is_404() {
+        let store = crate::collab::new_room_store();
+        assert_eq!(session_search_response(&store, "NOPE", "a").0, "404 Not Found");
+    }
+
+    #[test]
+    fn test_room_audit_response_status_codes() {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        let token = crate::collab::host_token(&store, &code).expect("token");
+        crate::collab::join_room(&store, &code, "Host", true).expect("join");
+        crate::collab::set_room_paused(&store, &code, true);
+        let (status, body) = room_audit_response(&store, &code, &token);
+        assert_eq!(status, "200 OK");
+        let v: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(v["count"], 2);
+        assert_eq!(v["events"][0]["event"], "join");
+        assert_eq!(v["events"][1]["event"], "pause");
+        assert_eq!(room_audit_response(&store, &code, "guess").0, "403 Forbidden");
+        assert_eq!(room_audit_response(&store, "NOPE", &token).0, "404 Not Found");
+    }
+
+    #[test]
+    fn test_room_archive_response_status_codes() {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        let token = crate::collab::host_token(&store, &code).expect("token");
+        crate::collab::join_room(&store, &code, "Host", true).expect("join");
+        let (status, body) = room_archive_response(&store, &code, &token);
+        assert_eq!(status, "200 OK");
+        let v: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(v["participants"][0]["name"], "Host");
+        assert_eq!(room_archive_response(&store, &code, "guess").0, "403 Forbidden");
+        assert_eq!(room_archive_response(&store, "NOPE", &token).0, "404 Not Found");
+    }
+
+    #[test]
+    fn test_session_export_keys_in_documented_order() {
+        let (store, code) = search_store();
+        let (status, body) = session_export_response(&store, &code, false);
+        assert_eq!(status, "200 OK");
+        let keys = [
+            "\"room\"",
+            "\"token_count\"",
+            "\"transformed_count\"",
+            "\"locked_transform\"",
+            "\"chaos_script\"",
+            "\"tokens\"",
+            "\"surgery_log\"",
+            "\"chat_log\"",
+            "\"graph\"",
+        ];
+        let positions: Vec<usize> = keys
+            .iter()
+            .map(|k| body.find(k).unwrap_or_else(|| panic!("missing {k} in {body}")))
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "keys out of order: {body}");
+        assert!(!body.contains('\n'));
+    }
+
+    #[test]
+    fn test_session_export_chaos_script() {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        for token in [
+            serde_json::json!({"index": 0, "text": "a", "transformed": false}),
+            serde_json::json!({"index": 1, "text": "B", "transformed": true, "chaos_label": "uppercase"}),
+            serde_json::json!({"index": 3, "text": "dc", "transformed": true, "chaos_label": "reverse"}),
+        ] {
+            crate::collab::record_session_token(&store, &code, token);
+        }
+        let (_, body) = session_export_response(&store, &code, false);
+        let v: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(v["chaos_script"], serde_json::json!([[1, "uppercase"], [3, "reverse"]]));
+    }
+
+    #[tokio::test]
+    async fn test_connections_beyond_cap_get_503() {
+        use tokio::io::AsyncReadExt;
+        let listener = bind_listener(0, 16).expect("bind");
+        let addr = listener.local_addr().unwrap();
+        // Handlers hold their connection until the test hands out a permit.
+        let release = Arc::new(Semaphore::new(0));
+        let handler_release = Arc::clone(&release);
+        tokio::spawn(accept_connections(listener, 2, move |mut stream, _| {
+            let release = Arc::clone(&handler_release);
+            async move {
+                let _ = release.acquire().await.map(|p| p.forget());
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+            }
+        }));
+
+        let read_all = |mut stream: tokio::net::TcpStream| async move {
+            let mut out = String::new();
+            let _ = tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut out)).await;
+            out
+        };
+        let held: Vec<_> = futures_util::future::join_all((0..2).map(|_| TcpStream::connect(addr)))
+            .await
+            .into_iter()
+            .map(|c| c.expect("connect"))
+            .collect();
+        // Connections are accepted in arrival order, so the held pair has
+        // both slots by the time these are accepted.
+        for _ in 0..3 {
+            let mut client = TcpStream::connect(addr).await.expect("connect");
+            // A request with a body still unread when the 503 is written
+            // must not turn the close into a reset.
+            client
+                .write_all(b"POST /batch-stream HTTP/1.1\r\nContent-Length: 5\r\n\r\n")
+                .await
+                .expect("send head");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let _ = client.write_all(b"[\"a\"]").await;
+            let refused = read_all(client).await;
+            assert!(refused.starts_with("HTTP/1.1 503 Service Unavailable"), "{}", refused);
+            assert!(refused.contains("Retry-After: 1"));
+        }
+        let silent = read_all(TcpStream::connect(addr).await.expect("connect")).await;
+        assert!(silent.starts_with("HTTP/1.1 503"), "a client that sends nothing still gets the 503: {}", silent);
+
+        release.add_permits(2);
+        for stream in held {
+            assert!(read_all(stream).await.starts_with("HTTP/1.1 200 OK"));
+        }
+        // The slot is freed just after the handler's response is read.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let accepted = TcpStream::connect(addr).await.expect("connect");
+        release.add_permits(1);
+        assert!(read_all(accepted).await.starts_with("HTTP/1.1 200 OK"), "a freed slot is reused");
+    }
+
+    #[test]
+    fn test_ab_export_response_reports_both_arms() {
+        let body = serde_json::json!({
+            "prompt": "p", "system_a": "A", "system_b": "B", "criterion": "confidence",
+            "tokens_a": [{"text": "x", "original": "x", "index": 0, "transformed": false, "importance": 0.5, "confidence": 0.9}],
+            "tokens_b": [{"text": "y", "original": "y", "index": 0, "transformed": true, "importance": 0.5, "confidence": 0.4}],
+        });
+        let (status, body) = ab_export_response(body.to_string().as_bytes());
+        assert_eq!(status, "200 OK", "{body}");
+        let export: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(export["arm_a"]["system_prompt"], "A");
+        assert_eq!(export["arm_b"]["transformed_count"], 1);
+        assert_eq!(export["winner"], "a");
+        assert_eq!(export["matched"], 0);
+        assert_eq!(ab_export_response(b"{\"tokens_a\": []}").0, "400 Bad Request");
+        assert!(INDEX_HTML.contains("fetch('/api/ab/export'"));
+    }
+
+    #[test]
+    fn test_retransform_response_applies_new_transform_per_token() {
+        let body = serde_json::json!({
+            "transform": "uppercase",
+            "tokens": [
+                {"text": "hello", "original": "hello", "index": 0, "transformed": false, "confidence": 0.9},
+                {"text": "dlrow", "original": "world", "index": 1, "transformed": true, "confidence": 0.4},
+                {"text": "again", "original": "again", "index": 2, "transformed": false},
+                {"text": "ffo", "original": "off", "index": 3, "transformed": true},
+            ],
+        });
+        let (status, body) = retransform_response(body.to_string().as_bytes(), &TransformOptions::default());
+        assert_eq!(status, "200 OK", "{body}");
+        let result: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(result["transform"], "uppercase");
+        let texts: Vec<&str> = result["tokens"]
+            .as_array()
+            .expect("tokens")
+            .iter()
+            .map(|t| t["text"].as_str().expect("text"))
+            .collect();
+        assert_eq!(texts, ["hello", "WORLD", "again", "OFF"]);
+        assert_eq!(result["tokens"][1]["confidence"], 0.4, "other fields pass through");
+
+        // Bare originals follow every-other alternation.
+        let (_, body) = retransform_response(
+            br#"{"transform":"reverse","tokens":[{"original":"ab"},{"original":"cd"}]}"#,
+            &TransformOptions::default(),
+        );
+        let result: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(result["tokens"][0]["text"], "ab");
+        assert_eq!(result["tokens"][1]["text"], "dc");
+        assert_eq!(result["tokens"][1]["transformed"], true);
+
+        assert_eq!(retransform_response(br#"{"transform":"bogus","tokens":[]}"#, &TransformOptions::default()).0, "400 Bad Request");
+        assert_eq!(retransform_response(br#"{"transform":"reverse","tokens":[{"text":"x"}]}"#, &TransformOptions::default()).0, "400 Bad Request");
+        assert!(INDEX_HTML.contains("fetch('/api/retransform'"));
+    }
+
+    #[test]
+    fn test_load_session_round_trips_an_export() {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        for (i, (text, original)) in [("The", "The"), ("tac", "cat"), ("sat", "sat"), ("no", "on")].iter().enumerate() {
+            crate::collab::record_session_token(
+                &store,
+                &code,
+                serde_json::json!({
+                    "index": i, "text": text, "original": original,
+                    "transformed": text != original, "importance": 0.5, "confidence": 0.8,
+                }),
+            );
+        }
+        let (_, exported) = session_export_response(&store, &code, false);
+        let recorded: serde_json::Value = serde_json::from_str(&exported).expect("json");
+
+        let (status, body) = load_session_response(exported.as_bytes());
+        assert_eq!(status, "200 OK", "{body}");
+        let loaded: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(loaded["token_count"], recorded["token_count"]);
+        assert_eq!(loaded["transformed_count"], recorded["transformed_count"]);
+        assert_eq!(loaded["transformed_count"], 2);
+        assert_eq!(loaded["recorded_counts_match"], true);
+        assert_eq!(loaded["tokens"][1]["original"], "cat");
+
+        assert_eq!(load_session_response(b"{\"tokens\": 3}").0, "400 Bad Request");
+        assert_eq!(load_session_response(b"not json").0, "400 Bad Request");
+    }
+
+    #[test]
+    fn test_session_export_pretty() {
+        let (store, code) = search_store();
+        let (_, body) = session_export_response(&store, &code, true);
+        assert!(body.contains("\n  \"room\""));
+        let v: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(v["token_count"], 3);
+    }
+
+    #[test]
+    fn test_session_export_graph_links_transformed_to_predecessor() {
+        let store = crate::collab::new_room_store();
+        let code = crate::collab::create_room(&store);
+        for (i, (text, original, transformed)) in [
+            ("The", "The", false),
+            ("ehT", "The", true),
+            ("quick", "quick", false),
+            ("kciuq", "quick", true),
+        ]
+        .iter()
+        .enumerate()
+        {
+            crate::collab::record_session_token(
+                &store,
+                &code,
+                serde_json::json!({
+                    "index": i, "text": text, "original": original,
+                    "transformed": transformed, "importance": 0.5,
+                }),
+            );
+        }
+        let (_, body) = session_export_response(&store, &code, false);
+        let v: serde_json::Value = serde_json::from_str(&body).expect("json");
+        assert_eq!(v["graph"]["nodes"].as_array().map(|a| a.len()), Some(4));
+        assert_eq!(v["graph"]["nodes"][1]["text"], "ehT");
+        assert_eq!(
+            v["graph"]["edges"],
+            serde_json::json!([
+                {"from": 0, "to": 1, "weight": 3, "importance": 0.5},
+                {"from": 2, "to": 3, "weight": 5, "importance": 0.5},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_dependency_graph_skips_leading_transformed_token() {
+        let tokens = vec![
+            serde_json::json!({"index": 0, "text": "x", "transformed": true}),
+            serde_json::json!({"index": 1, "text": "longword", "transformed": false}),
+            serde_json::json!({"index": 2, "text": "y", "original": "longword", "transformed": true}),
+        ];
+        let graph = dependency_graph(&tokens);
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(
+            graph.edges,
+            vec![GraphEdge { from: 1, to: 2, weight: 6, importance: 0.0 }]
+        );
+    }
+
+    #[test]
+    fn test_index_html_uses_transform_catalog_for_tooltips() {
+        assert!(INDEX_HTML.contains("fetch('/api/transforms')"));
+        let body = serde_json::to_value(Transform::catalog()).unwrap();
+        let names: Vec<&str> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|t| t["name"].as_str())
+            .collect();
+        for option in ["reverse", "uppercase", "mock", "noise", "spongebob", "chaos"] {
+            assert!(names.contains(&option), "{} missing from catalog", option);
+        }
+    }
+
+    #[test]
+    fn test_index_html_shows_version_from_api() {
+        assert!(INDEX_HTML.contains("fetch('/api/version')"));
+        assert!(INDEX_HTML.contains("id=\"app-version\""));
+        assert!(!INDEX_HTML.contains("v4.0.0"));
+    }
+
+    #[test]
+    fn test_index_html_imports_through_load_session() {
+        assert!(INDEX_HTML.contains("fetch('/api/load-session'"));
+    }
+
+    #[test]
+    fn test_index_html_suggests_models_from_api() {
+        assert!(INDEX_HTML.contains("fetch('/api/openai/models')"));
+        assert!(INDEX_HTML.contains("list=\"model-list\""));
+    }
+
+    #[test]
+    fn test_index_html_exports_graph_data() {
+        assert!(INDEX_HTML.contains("graph:buildGraphData("));
+    }
+
+    #[test]
+    fn test_session_export_unknown_room_is_404() {
+        let store = crate::collab::new_room_store();
+        assert_eq!(session_export_response(&store, "NOPE", false).0, "404 Not Found");
+    }
+
+    #[test]
+    fn test_index_html_has_session_search_box() {
+        assert!(INDEX_HTML.contains("/api/session/search"));
+        assert!(INDEX_HTML.contains("id=\"session-search\""));
+        // Solo sessions honour the advertised ppl:/conf: ranges, not just substrings
+        assert!(INDEX_HTML.contains("function parseTokenQuery(raw)"));
+        assert!(INDEX_HTML.contains("matches=allTokens.filter(pred)"));
+        // The research box must not collide with the live-view filter's id
+        assert_eq!(INDEX_HTML.matches("id=\"token-search\"").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_slot_queues_until_permit_released() {
+        let limit = Arc::new(Semaphore::new(1));
+        let first = UpstreamSlot::claim(Some(&limit));
+        assert!(!first.is_queued());
+        let first_permit = first.wait().await;
+        assert!(first_permit.is_some());
+
+        let second = UpstreamSlot::claim(Some(&limit));
+        assert!(second.is_queued());
+        let waiter = tokio::spawn(second.wait());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished(), "second stream must wait for the first");
+
+        drop(first_permit);
+        let second_permit = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("released permit wakes the waiter")
+            .unwrap();
+        assert!(second_permit.is_some());
+        assert_eq!(limit.available_permits(), 0);
+    }
+
+    #[test]
+    fn test_upstream_slot_unlimited_and_queued_event() {
+        use clap::Parser;
+        assert!(!UpstreamSlot::claim(None).is_queued());
+        let event = queued_event("anthropic");
+        let json: serde_json::Value =
+            serde_json::from_str(event.trim_start_matches("data: ").trim_end()).unwrap();
+        assert_eq!(json["type"], "queued");
+        assert_eq!(json["side"], "anthropic");
+        let args = Args::parse_from(["eot", "p", "--max-concurrency", "2"]);
+        let limit = StreamSettings::from_args(&args).upstream.expect("limit configured");
+        assert_eq!(limit.available_permits(), 2);
+        assert!(StreamSettings::from_args(&Args::parse_from(["eot", "p"])).upstream.is_none());
+    }
+
+    /// Spawn sides `a` and `b`, each sending one event and then waiting on
+    /// `gate`; `completed` counts the ones that ran to the end.
+    fn gated_side_tasks(
+        gate: &Arc<Semaphore>,
+        completed: &Arc<std::sync::atomic::AtomicUsize>,
+    ) -> (SideTasks, mpsc::UnboundedReceiver<(&'static str, TokenEvent)>) {
+        let (merged_tx, merged_rx) = mpsc::unbounded_channel();
+        let mut tasks = SideTasks::default();
+        for side in ["a", "b"] {
+            let (tx, gate, completed) = (merged_tx.clone(), Arc::clone(gate), Arc::clone(completed));
+            tasks.spawn(async move {
+                let _ = tx.send((side, diff_token("tok", None)));
+                let _permit = gate.acquire().await;
+                completed.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            });
+        }
+        (tasks, merged_rx)
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_aborts_both_side_tasks() {
+        let gate = Arc::new(Semaphore::new(0));
+        let completed = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (tasks, mut merged_rx) = gated_side_tasks(&gate, &completed);
+        let (client, mut sink) = tokio::io::duplex(1024);
+        drop(client);
+
+        let connected = forward_side_events(&mut sink, &mut merged_rx, tasks, |_, _| {}).await;
+        assert!(!connected);
+        // Had the sides not been aborted they would now finish.
+        gate.add_permits(2);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(completed.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connected_client_lets_side_tasks_finish() {
+        let gate = Arc::new(Semaphore::new(2));
+        let completed = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (tasks, mut merged_rx) = gated_side_tasks(&gate, &completed);
+        let mut seen = Vec::new();
+        let connected = forward_side_events(&mut tokio::io::sink(), &mut merged_rx, tasks, |side, _| {
+            seen.push(side)
+        })
+        .await;
+        assert!(connected);
+        seen.sort_unstable();
+        assert_eq!(seen, ["a", "b"]);
+        assert_eq!(completed.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_token_pacer_spaces_emissions() {
+        // 20 tokens/sec → one slot every 50 ms.
+        let mut pacer = TokenPacer::new(Some(20.0));
+        let mut stamps = Vec::new();
+        for _ in 0..5 {
+            pacer.pace().await;
+            stamps.push(tokio::time::Instant::now());
+        }
+        for pair in stamps.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap >= Duration::from_millis(45), "gap too short: {:?}", gap);
+            assert!(gap < Duration::from_millis(150), "gap too long: {:?}", gap);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_pacer_disabled_by_default() {
+        for rate in [None, Some(0.0), Some(f64::NAN)] {
+            let mut pacer = TokenPacer::new(rate);
+            let start = tokio::time::Instant::now();
+            for _ in 0..100 {
+                pacer.pace().await;
+            }
+            assert!(start.elapsed() < Duration::from_millis(20));
+        }
+    }
 }
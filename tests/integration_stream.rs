@@ -14,7 +14,7 @@ fn test_args(port: u16) -> Args {
     Args {
         prompt: "test".to_string(),
         transform: "reverse".to_string(),
-        model: "mock-fixture-v1".to_string(),
+        model: Some("mock-fixture-v1".to_string()),
         provider: Provider::Mock,
         visual: false,
         heatmap: false,
@@ -24,8 +24,14 @@ fn test_args(port: u16) -> Args {
         research: false,
         runs: 1,
         output: "research_output.json".to_string(),
+        tag: None,
+        notes: None,
+        temp_sweep: Vec::new(),
         system_a: None,
         top_logprobs: 0,
+        no_logprobs: false,
+        no_logprobs_fallback: false,
+        strict_parsing: false,
         system_b: None,
         db: None,
         significance: false,
@@ -34,8 +40,10 @@ fn test_args(port: u16) -> Args {
         heatmap_sort_by: "position".to_string(),
         record: None,
         replay: None,
+        replay_export: None,
         rate: None,
         seed: None,
+        start_index: 0,
         log_db: None,
         baseline: false,
         prompt_file: None,
@@ -45,20 +53,90 @@ fn test_args(port: u16) -> Args {
         rate_range: None,
         dry_run: false,
         template: None,
+        vars: vec![],
+        vars_file: None,
+        preprocess: vec![],
+        preprocess_max_chars: None,
+        style_instruction: None,
         min_confidence: None,
+        importance_smoothing: None,
+        select: every_other_token::TransformSelector::Alternating,
+        pos_filter: None,
+        granularity: every_other_token::Granularity::Token,
         format: "json".to_string(),
         collapse_window: 5,
         orchestrator_url: "http://localhost:3000".to_string(),
         max_retries: 3,
         anthropic_max_tokens: 4096,
         synonym_file: None,
+        dict: None,
+        dict_ignore_case: false,
         api_key: None,
         replay_speed: 1.0,
         timeout: 120,
+        headers: Vec::new(),
         export_timeseries: None,
         json_schema: false,
         list_models: None,
+        mcp_server: false,
+        list_transforms: false,
+        bench_transforms: false,
+        doctor: false,
+        watch: None,
+        watch_url: "http://localhost:8888".to_string(),
         validate_config: false,
+        sse_buffer_size: 1000,
+        sse_retry_ms: every_other_token::web::DEFAULT_SSE_RETRY_MS,
+        sse_batch: 1,
+        render_rate: None,
+        reveal_delay: None,
+        max_prompt_chars: 32_000,
+        tee: None,
+        show_original: false,
+        transform_prompt: false,
+        color_by: every_other_token::render::ColorBy::Importance,
+        show_enriched: false,
+        noise_chars: None,
+        noise_count: 1,
+        zalgo_intensity: 3,
+        word_boundaries: false,
+        window_transform: None,
+        normalize: every_other_token::Normalization::None,
+        normalize_original: false,
+        inline_mapping: None,
+        collapse_whitespace: false,
+        flush_policy: every_other_token::FlushPolicy::Token,
+        debug_raw: false,
+        debug_raw_file: None,
+        emit_granularity: every_other_token::web::EmitGranularity::Word,
+        render_window: every_other_token::web::DEFAULT_RENDER_WINDOW,
+        alt_display: every_other_token::web::AltDisplay::Tooltip,
+        reconnect_grace: every_other_token::collab::DEFAULT_RECONNECT_GRACE_SECS,
+        max_name_chars: every_other_token::collab::DEFAULT_MAX_NAME_CHARS,
+        max_recording_events: every_other_token::collab::DEFAULT_RECORDING_CAP,
+        max_concurrency: None,
+        max_connections: every_other_token::web::DEFAULT_MAX_CONNECTIONS,
+        listen_backlog: every_other_token::web::DEFAULT_LISTEN_BACKLOG,
+        no_color: false,
+        deterministic_importance: false,
+        batch: None,
+        export_logprobs: None,
+        compare: None,
+        batch_tokens: None,
+        quality: false,
+        similarity: None,
+        diversity_filter: false,
+        importance: None,
+        chunk: None,
+        stats: false,
+        benchmark: false,
+        context_budget: 4096,
+        vocab_stats: false,
+        #[cfg(feature = "helix-bridge")]
+        helix_url: None,
+        #[cfg(feature = "redis-backing")]
+        redis_url: None,
+        budget_usd: None,
     }
 }
 
@@ -16,13 +16,9 @@ fn make_event(idx: usize, confidence: Option<f32>) -> TokenEvent {
         index: idx,
         transformed: idx % 2 == 0,
         importance: 0.5,
-        chaos_label: None,
-        provider: None,
         confidence,
         perplexity: confidence.map(|c| 1.0 / c.max(0.01)),
-        alternatives: vec![],
-        is_error: false,
-        arrival_ms: None,
+        ..Default::default()
     }
 }
 